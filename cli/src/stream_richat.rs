@@ -27,6 +27,7 @@ use {
     solana_sdk::{
         clock::Slot,
         message::{LegacyMessage, Message, SanitizedMessage},
+        pubkey::Pubkey,
         transaction::SanitizedTransaction,
     },
     std::{collections::HashSet, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration},
@@ -53,6 +54,19 @@ pub struct ArgsAppStreamRichat {
     #[clap(long)]
     disable_entries: bool,
 
+    /// Stream only these accounts
+    #[clap(long)]
+    accounts: Vec<String>,
+
+    /// Stream only accounts owned by these programs
+    #[clap(long)]
+    owners: Vec<String>,
+
+    /// Stream only accounts and transactions matched by the expression,
+    /// e.g. `account.owner == <PUBKEY> && account.data[0] == 3`
+    #[clap(long)]
+    expression: Option<String>,
+
     /// Subscribe on stream from slot
     #[clap(long)]
     replay_from_slot: Option<Slot>,
@@ -71,6 +85,18 @@ pub struct ArgsAppStreamRichat {
 }
 
 impl ArgsAppStreamRichat {
+    fn decode_pubkeys(pubkeys: &[String]) -> anyhow::Result<Vec<Vec<u8>>> {
+        pubkeys
+            .iter()
+            .map(|pubkey| {
+                pubkey
+                    .parse::<Pubkey>()
+                    .map(|pubkey| pubkey.to_bytes().to_vec())
+                    .with_context(|| format!("invalid pubkey: {pubkey}"))
+            })
+            .collect()
+    }
+
     async fn subscribe(
         self,
         replay_from_slot: Option<Slot>,
@@ -79,6 +105,9 @@ impl ArgsAppStreamRichat {
             disable_accounts: self.disable_accounts,
             disable_transactions: self.disable_transactions,
             disable_entries: self.disable_entries,
+            accounts: Self::decode_pubkeys(&self.accounts)?,
+            owners: Self::decode_pubkeys(&self.owners)?,
+            expression: self.expression,
         };
         let x_token = self.x_token.map(|xt| xt.into_bytes());
         match self.action {
@@ -164,6 +193,10 @@ struct ArgsAppStreamQuic {
 
     #[clap(long)]
     cert: Option<PathBuf>,
+
+    /// Receive slot updates as unreliable datagrams
+    #[clap(long)]
+    slot_datagrams: bool,
 }
 
 impl ArgsAppStreamQuic {
@@ -181,7 +214,8 @@ impl ArgsAppStreamQuic {
             .set_server_name(self.server_name.clone())
             .set_recv_streams(self.recv_streams)
             .set_max_backlog(self.max_backlog)
-            .set_x_token(x_token);
+            .set_x_token(x_token)
+            .set_slot_datagrams(self.slot_datagrams);
 
         let client = if self.insecure {
             builder.insecure().connect(self.endpoint.clone()).await
@@ -262,6 +296,10 @@ struct ArgsAppStreamGrpc {
     /// Max message size before decoding
     #[clap(long, default_value_t = 64 * 1024 * 1024)]
     max_decoding_message_size: usize,
+
+    /// Receive messages in batches
+    #[clap(long, default_value_t = false)]
+    batch: bool,
 }
 
 impl ArgsAppStreamGrpc {
@@ -319,6 +357,7 @@ impl ArgsAppStreamGrpc {
         x_token: Option<Vec<u8>>,
     ) -> anyhow::Result<SubscribeStreamInput> {
         let endpoint = self.endpoint.clone();
+        let batch = self.batch;
         let mut client = self.connect(x_token).await.context("failed to connect")?;
         info!("connected to {endpoint} over gRPC");
 
@@ -329,6 +368,7 @@ impl ArgsAppStreamGrpc {
             .subscribe_richat(GrpcSubscribeRequest {
                 replay_from_slot,
                 filter: Some(filter),
+                batch,
             })
             .await
             .context("failed to subscribe")?;
@@ -376,6 +416,9 @@ fn convert_prost_to_raw(msg: &SubscribeUpdate) -> anyhow::Result<Option<Vec<u8>>
                     write_version: account.write_version,
                     txn: txn.as_ref(),
                 },
+                original_data_len: None,
+                is_startup: false,
+                owner_changed: false,
             };
             msg.encode_with_timestamp(ProtobufEncoder::Raw, created_at)
         }
@@ -432,6 +475,7 @@ fn convert_prost_to_raw(msg: &SubscribeUpdate) -> anyhow::Result<Option<Vec<u8>>
                     transaction_status_meta: &transaction_status_meta,
                     index: tx.index as usize,
                 },
+                include_loaded_addresses: true,
             };
             msg.encode_with_timestamp(ProtobufEncoder::Raw, created_at)
         }
@@ -466,6 +510,7 @@ fn convert_prost_to_raw(msg: &SubscribeUpdate) -> anyhow::Result<Option<Vec<u8>>
                     executed_transaction_count: meta.executed_transaction_count,
                     entry_count: meta.entries_count,
                 },
+                counts: None,
             };
             msg.encode_with_timestamp(ProtobufEncoder::Raw, created_at)
         }
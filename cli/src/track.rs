@@ -165,6 +165,9 @@ impl ConfigSourceRichatPluginAgave {
             disable_accounts: !accounts_enabled,
             disable_transactions: !transactions_enabled,
             disable_entries: false,
+            accounts: Vec::new(),
+            owners: Vec::new(),
+            expression: None,
         });
 
         let stream = match self {
@@ -176,6 +179,7 @@ impl ConfigSourceRichatPluginAgave {
                 let request = GrpcSubscribeRequest {
                     replay_from_slot: None,
                     filter,
+                    batch: false,
                 };
 
                 let stream = config.connect().await?.subscribe_richat(request).await?;
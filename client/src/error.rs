@@ -4,6 +4,7 @@ use {
         QuicSubscribeClose, QuicSubscribeCloseError, QuicSubscribeResponse,
         QuicSubscribeResponseError,
     },
+    richat_shared::transports::DisconnectReason,
     std::io,
     thiserror::Error,
     tokio::io::{AsyncRead, AsyncReadExt},
@@ -35,12 +36,14 @@ pub enum SubscribeError {
     XTokenRequired,
     #[error("x-token invalid")]
     XTokenInvalid,
+    #[error("invalid filter expression: {0}")]
+    InvalidExpression(String),
 }
 
 impl SubscribeError {
     pub(crate) async fn parse_quic_response<R: AsyncRead + Unpin>(
         recv: &mut R,
-    ) -> Result<String, Self> {
+    ) -> Result<QuicSubscribeResponse, Self> {
         let size = recv.read_u64().await?;
         let mut buf = vec![0; size as usize];
         recv.read_exact(buf.as_mut_slice()).await?;
@@ -61,10 +64,13 @@ impl SubscribeError {
                 }
                 Ok(QuicSubscribeResponseError::XTokenRequired) => SubscribeError::XTokenRequired,
                 Ok(QuicSubscribeResponseError::XTokenInvalid) => SubscribeError::XTokenInvalid,
+                Ok(QuicSubscribeResponseError::InvalidExpression) => {
+                    SubscribeError::InvalidExpression(response.error_message().to_owned())
+                }
                 Err(_error) => SubscribeError::Unknown(error),
             })
         } else {
-            Ok(response.version)
+            Ok(response)
         }
     }
 }
@@ -77,6 +83,8 @@ pub enum ReceiveError {
     QuicRecv(#[from] quinn::ReadExactError),
     #[error("failed to decode response: {0}")]
     Decode(#[from] DecodeError),
+    #[error("failed to decompress response: {0}")]
+    Decompress(io::Error),
     #[error("stream failed: {0}")]
     Status(#[from] tonic::Status),
     #[error("unknown close error: {0}")]
@@ -105,4 +113,14 @@ impl ReceiveError {
             false
         }
     }
+
+    /// Reason of stream close reported by the server, if any
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        match self {
+            Self::Status(status) => DisconnectReason::from_status(status),
+            Self::Lagged => Some(DisconnectReason::GapDetected),
+            Self::Closed => Some(DisconnectReason::ServerShutdown),
+            _ => None,
+        }
+    }
 }
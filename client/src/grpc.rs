@@ -21,7 +21,7 @@ use {
             IsBlockhashValidResponse, PingRequest, PongResponse, SubscribeReplayInfoRequest,
             SubscribeReplayInfoResponse, SubscribeRequest,
         },
-        richat::{GrpcSubscribeRequest, SubscribeAccountsRequest},
+        richat::{GrpcSubscribeBatch, GrpcSubscribeRequest, SubscribeAccountsRequest},
     },
     richat_shared::{
         config::{deserialize_humansize_usize, deserialize_maybe_x_token},
@@ -29,12 +29,12 @@ use {
     },
     serde::Deserialize,
     std::{
-        collections::HashMap,
+        collections::{HashMap, VecDeque},
         fmt, io,
         marker::PhantomData,
         path::PathBuf,
         pin::Pin,
-        task::{Context, Poll},
+        task::{Context, Poll, ready},
         time::Duration,
     },
     thiserror::Error,
@@ -408,9 +408,7 @@ impl<F: Interceptor> GrpcClient<F> {
     > {
         let (subscribe_tx, subscribe_rx) = mpsc::unbounded();
         let response: Response<Streaming<Vec<u8>>> = self.geyser.subscribe(subscribe_rx).await?;
-        let stream = GrpcClientStream {
-            stream: response.into_inner(),
-        };
+        let stream = GrpcClientStream::new(response.into_inner(), false);
         Ok((subscribe_tx, stream))
     }
 
@@ -438,9 +436,7 @@ impl<F: Interceptor> GrpcClient<F> {
         let (subscribe_tx, subscribe_rx) = mpsc::unbounded();
         let response: Response<Streaming<Vec<u8>>> =
             self.geyser.subscribe_accounts(subscribe_rx).await?;
-        let stream = GrpcClientStream {
-            stream: response.into_inner(),
-        };
+        let stream = GrpcClientStream::new(response.into_inner(), false);
         Ok((subscribe_tx, stream))
     }
 
@@ -449,14 +445,14 @@ impl<F: Interceptor> GrpcClient<F> {
         &mut self,
         request: GrpcSubscribeRequest,
     ) -> Result<GrpcClientStream, Status> {
+        let batched = request.batch;
         let (mut tx, rx) = mpsc::unbounded();
         tx.send(request)
             .await
             .expect("failed to send to unbounded channel");
 
         let response: Response<Streaming<Vec<u8>>> = self.geyser.subscribe_richat(rx).await?;
-        let stream = response.into_inner();
-        Ok(GrpcClientStream { stream })
+        Ok(GrpcClientStream::new(response.into_inner(), batched))
     }
 
     // RPC calls
@@ -612,10 +608,20 @@ pin_project! {
     pub struct GrpcClientStream {
         #[pin]
         stream: Streaming<Vec<u8>>,
+        batched: bool,
+        pending: VecDeque<Vec<u8>>,
     }
 }
 
 impl GrpcClientStream {
+    const fn new(stream: Streaming<Vec<u8>>, batched: bool) -> Self {
+        Self {
+            stream,
+            batched,
+            pending: VecDeque::new(),
+        }
+    }
+
     pub fn into_parsed(self) -> SubscribeStream {
         SubscribeStream::new(self.boxed())
     }
@@ -631,7 +637,24 @@ impl Stream for GrpcClientStream {
     type Item = Result<Vec<u8>, ReceiveError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let me = self.project();
-        me.stream.poll_next(cx).map_err(Into::into)
+        let mut me = self.project();
+        if !*me.batched {
+            return me.stream.poll_next(cx).map_err(Into::into);
+        }
+
+        loop {
+            if let Some(message) = me.pending.pop_front() {
+                return Poll::Ready(Some(Ok(message)));
+            }
+
+            match ready!(me.stream.as_mut().poll_next(cx)) {
+                Some(Ok(data)) => match GrpcSubscribeBatch::decode(data.as_slice()) {
+                    Ok(batch) => me.pending.extend(batch.messages),
+                    Err(error) => return Poll::Ready(Some(Err(error.into()))),
+                },
+                Some(Err(error)) => return Poll::Ready(Some(Err(error.into()))),
+                None => return Poll::Ready(None),
+            }
+        }
     }
 }
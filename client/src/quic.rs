@@ -9,7 +9,7 @@ use {
         stream::{Stream, StreamExt},
     },
     pin_project_lite::pin_project,
-    prost::Message,
+    prost::{Message, bytes::Bytes},
     quinn::{
         ClientConfig, ConnectError, Connection, ConnectionError, Endpoint, RecvStream,
         TransportConfig, VarInt,
@@ -148,6 +148,7 @@ pub struct ConfigQuicClient {
     pub cert: Option<PathBuf>,
     #[serde(deserialize_with = "deserialize_maybe_x_token")]
     pub x_token: Option<Vec<u8>>,
+    pub slot_datagrams: bool,
 }
 
 impl Default for ConfigQuicClient {
@@ -164,6 +165,7 @@ impl Default for ConfigQuicClient {
             insecure: false,
             cert: None,
             x_token: None,
+            slot_datagrams: false,
         }
     }
 }
@@ -178,7 +180,8 @@ impl ConfigQuicClient {
             .set_server_name(self.server_name.clone())
             .set_recv_streams(self.recv_streams)
             .set_max_backlog(self.max_backlog)
-            .set_x_token(self.x_token);
+            .set_x_token(self.x_token)
+            .set_slot_datagrams(self.slot_datagrams);
 
         if self.insecure {
             builder.insecure().connect(self.endpoint.clone()).await
@@ -201,6 +204,7 @@ pub struct QuicClientBuilder {
     pub recv_streams: u32,
     pub max_backlog: Option<u32>,
     pub x_token: Option<Vec<u8>>,
+    pub slot_datagrams: bool,
 }
 
 impl Default for QuicClientBuilder {
@@ -215,6 +219,7 @@ impl Default for QuicClientBuilder {
             recv_streams: config.recv_streams,
             max_backlog: config.max_backlog,
             x_token: config.x_token,
+            slot_datagrams: config.slot_datagrams,
         }
     }
 }
@@ -277,6 +282,13 @@ impl QuicClientBuilder {
         Self { x_token, ..self }
     }
 
+    pub fn set_slot_datagrams(self, slot_datagrams: bool) -> Self {
+        Self {
+            slot_datagrams,
+            ..self
+        }
+    }
+
     pub const fn insecure(self) -> QuicClientBuilderInsecure {
         QuicClientBuilderInsecure { builder: self }
     }
@@ -336,6 +348,7 @@ impl QuicClientBuilder {
             recv_streams: self.recv_streams,
             max_backlog: self.max_backlog,
             x_token: self.x_token,
+            slot_datagrams: self.slot_datagrams,
         })
     }
 }
@@ -418,6 +431,7 @@ pub struct QuicClient {
     recv_streams: u32,
     max_backlog: Option<u32>,
     x_token: Option<Vec<u8>>,
+    slot_datagrams: bool,
 }
 
 impl QuicClient {
@@ -436,6 +450,7 @@ impl QuicClient {
             max_backlog: self.max_backlog,
             replay_from_slot,
             filter,
+            slot_datagrams: self.slot_datagrams,
         }
         .encode_to_vec();
 
@@ -444,7 +459,7 @@ impl QuicClient {
         send.write_all(&message).await?;
         send.flush().await?;
 
-        let version = SubscribeError::parse_quic_response(&mut recv).await?;
+        let response = SubscribeError::parse_quic_response(&mut recv).await?;
 
         let mut readers = Vec::with_capacity(self.recv_streams as usize);
         for _ in 0..self.recv_streams {
@@ -454,9 +469,14 @@ impl QuicClient {
             });
         }
 
+        let datagrams = response
+            .slot_datagrams
+            .then(|| QuicClient::recv_datagram(self.conn.clone()));
+
         Ok(QuicClientStream {
             conn: self.conn,
-            version,
+            version: response.version,
+            datagrams,
             messages: HashMap::default(),
             msg_id: 0,
             readers,
@@ -464,6 +484,10 @@ impl QuicClient {
         })
     }
 
+    fn recv_datagram(conn: Connection) -> BoxFuture<'static, Result<Bytes, ConnectionError>> {
+        async move { conn.read_datagram().await }.boxed()
+    }
+
     async fn recv(mut stream: RecvStream) -> Result<(RecvStream, u64, Vec<u8>), ReceiveError> {
         let msg_id = stream.read_u64().await?;
         let error = msg_id == u64::MAX;
@@ -491,6 +515,8 @@ pin_project! {
     pub struct QuicClientStream {
         conn: Connection,
         version: String,
+        // slot updates received as datagrams, not ordered with messages from streams
+        datagrams: Option<BoxFuture<'static, Result<Bytes, ConnectionError>>>,
         messages: HashMap<u64, Vec<u8>, RandomState>,
         msg_id: u64,
         #[pin]
@@ -523,6 +549,19 @@ impl Stream for QuicClientStream {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut me = self.project();
 
+        if let Some(datagrams) = me.datagrams.as_mut() {
+            if let Poll::Ready(result) = datagrams.as_mut().poll(cx) {
+                match result {
+                    Ok(msg) => {
+                        *datagrams = QuicClient::recv_datagram(me.conn.clone());
+                        return Poll::Ready(Some(Ok(msg.to_vec())));
+                    }
+                    // connection errors are reported by streams
+                    Err(_error) => *me.datagrams = None,
+                }
+            }
+        }
+
         if let Some(msg) = me.messages.remove(me.msg_id) {
             *me.msg_id += 1;
             return Poll::Ready(Some(Ok(msg)));
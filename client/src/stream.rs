@@ -2,10 +2,14 @@ use {
     crate::error::ReceiveError,
     futures::stream::{BoxStream, Stream},
     pin_project_lite::pin_project,
-    prost::Message,
-    richat_proto::geyser::SubscribeUpdate,
+    prost::{Message, encoding},
+    richat_proto::{
+        geyser::SubscribeUpdate,
+        richat::{SubscribeUpdateCompressed, subscribe_update_compressed::Data},
+    },
     std::{
         fmt,
+        io::Read,
         pin::Pin,
         task::{Context, Poll, ready},
     },
@@ -13,6 +17,36 @@ use {
 
 type InputStream = BoxStream<'static, Result<Vec<u8>, ReceiveError>>;
 
+/// Messages of the plugin `zstd` and `lz4` encoders are `SubscribeUpdateCompressed`, compression is
+/// the tag of the first field, that tag is never used by `SubscribeUpdate`
+fn is_compressed(mut slice: &[u8]) -> bool {
+    matches!(
+        encoding::decode_key(&mut slice),
+        Ok((106 | 107, encoding::WireType::LengthDelimited))
+    )
+}
+
+fn decode(slice: &[u8]) -> Result<SubscribeUpdate, ReceiveError> {
+    if !is_compressed(slice) {
+        return SubscribeUpdate::decode(slice).map_err(Into::into);
+    }
+
+    let data = match SubscribeUpdateCompressed::decode(slice)?.data {
+        Some(Data::Zstd(data)) => {
+            zstd::decode_all(data.as_slice()).map_err(ReceiveError::Decompress)?
+        }
+        Some(Data::Lz4(data)) => {
+            let mut decoded = Vec::new();
+            lz4_flex::frame::FrameDecoder::new(data.as_slice())
+                .read_to_end(&mut decoded)
+                .map_err(ReceiveError::Decompress)?;
+            decoded
+        }
+        None => Vec::new(),
+    };
+    SubscribeUpdate::decode(data.as_slice()).map_err(Into::into)
+}
+
 pin_project! {
     pub struct SubscribeStream {
         stream: InputStream,
@@ -37,7 +71,7 @@ impl Stream for SubscribeStream {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut me = self.project();
         Poll::Ready(match ready!(Pin::new(&mut me.stream).poll_next(cx)) {
-            Some(Ok(slice)) => Some(SubscribeUpdate::decode(slice.as_slice()).map_err(Into::into)),
+            Some(Ok(slice)) => Some(decode(slice.as_slice())),
             Some(Err(error)) => Some(Err(error)),
             None => None,
         })
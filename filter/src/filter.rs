@@ -808,6 +808,7 @@ impl FilteredUpdate<'_> {
                     buffer,
                     account_offset: _,
                     range,
+                    original_data_len: _,
                 } => SubscribeUpdateMessageLimited {
                     filters: &self.filters,
                     update: UpdateOneofLimitedEncode::Account(if data_slices.is_empty() {
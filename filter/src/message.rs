@@ -12,6 +12,7 @@ use {
             SlotStatus, SubscribeUpdate, SubscribeUpdateAccountInfo, SubscribeUpdateBlockMeta,
             SubscribeUpdateEntry, SubscribeUpdateTransactionInfo, subscribe_update::UpdateOneof,
         },
+        richat::SubscribeUpdateExtensions,
         solana::storage::confirmed_block::{Transaction, TransactionError, TransactionStatusMeta},
     },
     serde::{Deserialize, Serialize},
@@ -245,6 +246,7 @@ impl MessageParserLimited {
                         buffer: data,
                         account_offset: message.account + range.start,
                         range,
+                        original_data_len: update.original_data_len,
                     })
                 }
                 UpdateOneofLimitedDecode::Transaction(range) => {
@@ -378,6 +380,8 @@ impl MessageParserProst {
                         is_startup: message.is_startup,
                         created_at,
                         size: PUBKEY_BYTES + PUBKEY_BYTES + encoded_len + 20,
+                        original_data_len: SubscribeUpdateExtensions::decode(data.deref())?
+                            .original_data_len,
                     })
                 }
                 UpdateOneof::Transaction(message) => {
@@ -449,6 +453,7 @@ impl MessageParserProst {
                                 is_startup: false,
                                 created_at,
                                 size: PUBKEY_BYTES + PUBKEY_BYTES + encoded_len + 32,
+                                original_data_len: None,
                             }))
                         })
                         .collect::<Result<_, MessageParseError>>()?;
@@ -632,6 +637,7 @@ pub enum MessageAccount {
         buffer: Vec<u8>,
         account_offset: usize,
         range: Range<usize>,
+        original_data_len: Option<u64>,
     },
     Prost {
         pubkey: Pubkey,
@@ -641,6 +647,7 @@ pub enum MessageAccount {
         is_startup: bool,
         created_at: Timestamp,
         size: usize,
+        original_data_len: Option<u64>,
     },
 }
 
@@ -694,6 +701,18 @@ impl MessageAccount {
         }
     }
 
+    /// Account data length before slicing by the plugin, `None` if data was not sliced
+    pub const fn original_data_len(&self) -> Option<u64> {
+        match self {
+            Self::Limited {
+                original_data_len, ..
+            } => *original_data_len,
+            Self::Prost {
+                original_data_len, ..
+            } => *original_data_len,
+        }
+    }
+
     pub fn txn_signature(&self) -> Option<&[u8]> {
         match self {
             MessageAccount::Limited {
@@ -48,6 +48,8 @@ pub trait LimitedDecode: Default {
 pub struct SubscribeUpdateLimitedDecode {
     pub update_oneof: Option<UpdateOneofLimitedDecode>,
     pub created_at: Option<Timestamp>,
+    /// `richat.SubscribeUpdateExtensions.original_data_len` appended by the plugin
+    pub original_data_len: Option<u64>,
 }
 
 impl LimitedDecode for SubscribeUpdateLimitedDecode {
@@ -59,9 +61,9 @@ impl LimitedDecode for SubscribeUpdateLimitedDecode {
         buf_len: usize,
     ) -> Result<(), DecodeError> {
         const STRUCT_NAME: &str = "SubscribeUpdateLimitedDecode";
-        check_wire_type(WireType::LengthDelimited, wire_type)?;
         match tag {
             1u32 => {
+                check_wire_type(WireType::LengthDelimited, wire_type)?;
                 let len = decode_varint(buf)? as usize;
                 if len > buf.remaining() {
                     return Err(decode_error(
@@ -74,6 +76,7 @@ impl LimitedDecode for SubscribeUpdateLimitedDecode {
             }
             #[allow(clippy::manual_range_patterns)]
             2u32 | 3u32 | 4u32 | 10u32 | 5u32 | 6u32 | 9u32 | 7u32 | 8u32 => {
+                check_wire_type(WireType::LengthDelimited, wire_type)?;
                 let value = &mut self.update_oneof;
                 UpdateOneofLimitedDecode::merge(value, tag, buf, buf_len).map_err(|mut error| {
                     error.push(STRUCT_NAME, "update_oneof");
@@ -83,7 +86,7 @@ impl LimitedDecode for SubscribeUpdateLimitedDecode {
             11u32 => {
                 let value = &mut self.created_at;
                 encoding::message::merge(
-                    WireType::LengthDelimited,
+                    wire_type,
                     value.get_or_insert_with(Default::default),
                     buf,
                     DecodeContext::default(),
@@ -93,12 +96,21 @@ impl LimitedDecode for SubscribeUpdateLimitedDecode {
                     error
                 })
             }
-            _ => encoding::skip_field(
-                WireType::LengthDelimited,
-                tag,
-                buf,
-                DecodeContext::default(),
-            ),
+            100u32 => {
+                let value = &mut self.original_data_len;
+                encoding::uint64::merge(
+                    wire_type,
+                    value.get_or_insert_with(Default::default),
+                    buf,
+                    DecodeContext::default(),
+                )
+                .map_err(|mut error| {
+                    error.push(STRUCT_NAME, "original_data_len");
+                    error
+                })
+            }
+            // other extensions of the plugin, see `richat.SubscribeUpdateExtensions`
+            _ => encoding::skip_field(wire_type, tag, buf, DecodeContext::default()),
         }
     }
 }
@@ -3,17 +3,20 @@ use {
     std::net::{IpAddr, Ipv4Addr, SocketAddr},
 };
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct ConfigMetrics {
     /// Endpoint of Prometheus service
     pub endpoint: SocketAddr,
+    /// Optional prefix prepended to names of all metrics, e.g. `richat_`
+    pub namespace: Option<String>,
 }
 
 impl Default for ConfigMetrics {
     fn default() -> Self {
         Self {
             endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10123),
+            namespace: None,
         }
     }
 }
@@ -6,7 +6,7 @@ pub use config::ConfigMetrics;
 mod macros;
 
 mod recorder;
-pub use recorder::MaybeRecorder;
+pub use recorder::{MaybeRecorder, PrefixedRecorder};
 
 mod server;
 pub use server::spawn_server;
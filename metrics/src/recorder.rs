@@ -66,3 +66,76 @@ impl<R: Recorder> Recorder for MaybeRecorder<R> {
         }
     }
 }
+
+/// Recorder wrapper which prepends optional prefix to every metric name
+pub struct PrefixedRecorder<R> {
+    recorder: R,
+    prefix: Option<String>,
+}
+
+impl<R> fmt::Debug for PrefixedRecorder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrefixedRecorder")
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R> PrefixedRecorder<R> {
+    pub fn new(recorder: R, prefix: Option<String>) -> Self {
+        Self {
+            recorder,
+            prefix: prefix.filter(|prefix| !prefix.is_empty()),
+        }
+    }
+
+    pub const fn inner(&self) -> &R {
+        &self.recorder
+    }
+
+    fn key_name(&self, key_name: KeyName) -> KeyName {
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}{}", key_name.as_str()).into(),
+            None => key_name,
+        }
+    }
+
+    fn key(&self, key: &Key) -> Key {
+        match &self.prefix {
+            Some(prefix) => Key::from_parts(
+                format!("{prefix}{}", key.name()),
+                key.labels().cloned().collect::<Vec<_>>(),
+            ),
+            None => key.clone(),
+        }
+    }
+}
+
+impl<R: Recorder> Recorder for PrefixedRecorder<R> {
+    fn describe_counter(&self, key_name: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.recorder
+            .describe_counter(self.key_name(key_name), unit, description)
+    }
+
+    fn describe_gauge(&self, key_name: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.recorder
+            .describe_gauge(self.key_name(key_name), unit, description)
+    }
+
+    fn describe_histogram(&self, key_name: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.recorder
+            .describe_histogram(self.key_name(key_name), unit, description)
+    }
+
+    fn register_counter(&self, key: &Key, metadata: &Metadata<'_>) -> Counter {
+        self.recorder.register_counter(&self.key(key), metadata)
+    }
+
+    fn register_gauge(&self, key: &Key, metadata: &Metadata<'_>) -> Gauge {
+        self.recorder.register_gauge(&self.key(key), metadata)
+    }
+
+    fn register_histogram(&self, key: &Key, metadata: &Metadata<'_>) -> Histogram {
+        self.recorder.register_histogram(&self.key(key), metadata)
+    }
+}
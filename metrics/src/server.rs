@@ -16,7 +16,7 @@ use {
 };
 
 pub async fn spawn_server(
-    ConfigMetrics { endpoint }: ConfigMetrics,
+    ConfigMetrics { endpoint, .. }: ConfigMetrics,
     gather_metrics: impl Fn() -> Vec<u8> + Clone + Send + 'static,
     is_health_check: impl Fn() -> bool + Clone + Send + 'static,
     is_ready_check: impl Fn() -> bool + Clone + Send + 'static,
@@ -61,7 +61,7 @@ pub async fn spawn_server(
                                             (StatusCode::OK, Bytes::from("OK"))
                                         } else {
                                             (
-                                                StatusCode::INTERNAL_SERVER_ERROR,
+                                                StatusCode::SERVICE_UNAVAILABLE,
                                                 Bytes::from("Service is unhealthy"),
                                             )
                                         }
@@ -72,7 +72,7 @@ pub async fn spawn_server(
                                             (StatusCode::OK, Bytes::from("OK"))
                                         } else {
                                             (
-                                                StatusCode::INTERNAL_SERVER_ERROR,
+                                                StatusCode::SERVICE_UNAVAILABLE,
                                                 Bytes::from("Service is not ready"),
                                             )
                                         }
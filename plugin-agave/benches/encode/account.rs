@@ -1,5 +1,5 @@
 use {
-    criterion::Criterion,
+    criterion::{Criterion, Throughput},
     prost::Message,
     prost_types::Timestamp,
     richat_plugin_agave::protobuf::{
@@ -12,7 +12,7 @@ use {
         },
         message::MessageAccount,
     },
-    std::{hint::black_box, time::SystemTime},
+    std::{hint::black_box, io::Write, time::SystemTime},
 };
 
 pub fn bench_encode_accounts(criterion: &mut Criterion) {
@@ -54,6 +54,9 @@ pub fn bench_encode_accounts(criterion: &mut Criterion) {
                         let message = ProtobufMessage::Account {
                             slot: *slot,
                             account,
+                            original_data_len: None,
+                            is_startup: false,
+                            owner_changed: false,
                         };
                         message.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
                     }
@@ -69,6 +72,9 @@ pub fn bench_encode_accounts(criterion: &mut Criterion) {
                         let message = ProtobufMessage::Account {
                             slot: *slot,
                             account,
+                            original_data_len: None,
+                            is_startup: false,
+                            owner_changed: false,
                         };
                         message.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
                     }
@@ -115,3 +121,93 @@ pub fn bench_encode_accounts(criterion: &mut Criterion) {
             },
         );
 }
+
+pub fn bench_encode_large_account(criterion: &mut Criterion) {
+    let mut account = generate_accounts()
+        .into_iter()
+        .next()
+        .expect("at least one account");
+    account.data = vec![42; 10 * 1024 * 1024];
+    let (slot, replica) = account.to_replica();
+
+    let mut group = criterion.benchmark_group("encode_large_account");
+    for encoder in [ProtobufEncoder::Prost, ProtobufEncoder::Raw] {
+        group.bench_function(format!("richat/{}", encoder.as_str()), |criterion| {
+            let created_at = SystemTime::now();
+            criterion.iter(|| {
+                let message = ProtobufMessage::Account {
+                    slot,
+                    account: &replica,
+                    original_data_len: None,
+                    is_startup: false,
+                    owner_changed: false,
+                };
+                black_box(message.encode_with_timestamp(encoder, created_at))
+            })
+        });
+    }
+    group.bench_function("data-copy-only", |criterion| {
+        criterion.iter(|| black_box(replica.data.to_vec()))
+    });
+    group.finish();
+}
+
+pub fn bench_compress_accounts(criterion: &mut Criterion) {
+    let accounts = generate_accounts();
+    let created_at = SystemTime::now();
+    let raw = accounts
+        .iter()
+        .map(|account| {
+            let (slot, account) = account.to_replica();
+            ProtobufMessage::Account {
+                slot,
+                account: &account,
+                original_data_len: None,
+                is_startup: false,
+                owner_changed: false,
+            }
+            .encode_with_timestamp(ProtobufEncoder::Raw, created_at)
+        })
+        .collect::<Vec<_>>();
+
+    // bandwidth of every encoder on the same fixture, CPU is measured by the group below
+    let raw_size = raw.iter().map(Vec::len).sum::<usize>();
+    for (name, compress) in [
+        ("zstd", compress_zstd as fn(&[u8]) -> Vec<u8>),
+        ("lz4", compress_lz4),
+    ] {
+        let size = raw.iter().map(|data| compress(data).len()).sum::<usize>();
+        println!(
+            "compress_accounts/{name}: {size} bytes of {raw_size} raw bytes, ratio {:.2}",
+            raw_size as f64 / size as f64
+        );
+    }
+
+    let mut group = criterion.benchmark_group("compress_accounts");
+    group.throughput(Throughput::Bytes(raw_size as u64));
+    group.bench_with_input("zstd", &raw, |criterion, raw| {
+        criterion.iter(|| {
+            for data in raw {
+                black_box(compress_zstd(data));
+            }
+        })
+    });
+    group.bench_with_input("lz4", &raw, |criterion, raw| {
+        criterion.iter(|| {
+            for data in raw {
+                black_box(compress_lz4(data));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn compress_zstd(data: &[u8]) -> Vec<u8> {
+    zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL).unwrap()
+}
+
+fn compress_lz4(data: &[u8]) -> Vec<u8> {
+    let mut compressor = lz4_flex::frame::FrameEncoder::new(Vec::new());
+    compressor.write_all(data).unwrap();
+    compressor.finish().unwrap()
+}
@@ -37,7 +37,10 @@ pub fn bench_encode_block_metas(criterion: &mut Criterion) {
                     #[allow(clippy::unit_arg)]
                     black_box({
                         for blockinfo in block_metas {
-                            let message = ProtobufMessage::BlockMeta { blockinfo };
+                            let message = ProtobufMessage::BlockMeta {
+                                blockinfo,
+                                counts: None,
+                            };
                             message.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
                         }
                     })
@@ -53,7 +56,10 @@ pub fn bench_encode_block_metas(criterion: &mut Criterion) {
                     #[allow(clippy::unit_arg)]
                     black_box({
                         for blockinfo in block_metas {
-                            let message = ProtobufMessage::BlockMeta { blockinfo };
+                            let message = ProtobufMessage::BlockMeta {
+                                blockinfo,
+                                counts: None,
+                            };
                             message.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
                         }
                     })
@@ -9,6 +9,8 @@ mod transaction;
 criterion_group!(
     benches,
     account::bench_encode_accounts,
+    account::bench_encode_large_account,
+    account::bench_compress_accounts,
     slot::bench_encode_slot,
     entry::bench_encode_entries,
     block_meta::bench_encode_block_metas,
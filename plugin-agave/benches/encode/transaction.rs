@@ -39,6 +39,7 @@ pub fn bench_encode_transactions(criterion: &mut Criterion) {
                             let message = ProtobufMessage::Transaction {
                                 slot: *slot,
                                 transaction,
+                                include_loaded_addresses: true,
                             };
                             message.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
                         }
@@ -58,6 +59,7 @@ pub fn bench_encode_transactions(criterion: &mut Criterion) {
                             let message = ProtobufMessage::Transaction {
                                 slot: *slot,
                                 transaction,
+                                include_loaded_addresses: true,
                             };
                             message.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
                         }
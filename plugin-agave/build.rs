@@ -10,10 +10,11 @@ fn emit_version() -> anyhow::Result<()> {
         .add_instructions(&vergen::RustcBuilder::all_rustc()?)?
         .emit()?;
 
-    // vergen git version does not looks cool
+    // vergen git version does not looks cool, fallback for builds without git (e.g. from
+    // source tarball)
     println!(
         "cargo:rustc-env=GIT_VERSION={}",
-        git_version::git_version!()
+        git_version::git_version!(fallback = "unknown")
     );
 
     // Extract packages version
@@ -30,6 +31,10 @@ fn emit_version() -> anyhow::Result<()> {
         "cargo:rustc-env=RICHAT_PROTO_VERSION={}",
         get_pkg_version(&lockfile, "richat-proto")
     );
+    println!(
+        "cargo:rustc-env=AGAVE_GEYSER_PLUGIN_INTERFACE_VERSION={}",
+        get_pkg_version(&lockfile, "agave-geyser-plugin-interface")
+    );
 
     Ok(())
 }
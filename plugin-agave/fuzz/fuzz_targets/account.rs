@@ -52,6 +52,8 @@ libfuzzer_sys::fuzz_target!(|fuzz_message: FuzzAccountMessage| {
             txn: txn.as_ref(),
         },
         slot: fuzz_message.slot,
+        original_data_len: None,
+        is_startup: false,
     };
     let created_at = SystemTime::now();
 
@@ -0,0 +1,195 @@
+use {
+    crate::config::ConfigAdmin,
+    http_body_util::{BodyExt, Full as BodyFull},
+    hyper::{
+        Request, Response, StatusCode,
+        body::{Bytes, Incoming as BodyIncoming},
+        header::CONTENT_TYPE,
+        service::service_fn,
+    },
+    hyper_util::{
+        rt::tokio::{TokioExecutor, TokioIo},
+        server::conn::auto::Builder as ServerBuilder,
+    },
+    log::{error, info},
+    richat_shared::transports::is_x_token_valid,
+    std::{future::Future, io, sync::Arc},
+    tokio::{net::TcpListener, task::JoinError},
+};
+
+/// Replace secrets in raw config, so it can be shown to the operator
+pub fn redact_config(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if key == "x_tokens" {
+                    if let serde_json::Value::Array(tokens) = value {
+                        for token in tokens {
+                            *token = serde_json::Value::from("<redacted>");
+                        }
+                    }
+                } else {
+                    redact_config(value);
+                }
+            }
+        }
+        serde_json::Value::Array(array) => array.iter_mut().for_each(redact_config),
+        _ => {}
+    }
+}
+
+/// Convert Prometheus text format to `{"name": [{"labels": {..}, "value": 1.0}]}`,
+/// summaries are kept as Prometheus exposes them (`quantile` label, `_sum` and `_count`)
+pub fn metrics_to_json(text: &str) -> serde_json::Value {
+    let mut metrics = serde_json::Map::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let name_end = line.find(['{', ' ']).unwrap_or(line.len());
+        let (name, mut rest) = line.split_at(name_end);
+        let mut labels = serde_json::Map::new();
+        if let Some(mut chars) = rest.strip_prefix('{') {
+            loop {
+                chars = chars.trim_start_matches([',', ' ']);
+                if let Some(tail) = chars.strip_prefix('}') {
+                    rest = tail;
+                    break;
+                }
+                let Some((key, tail)) = chars.split_once("=\"") else {
+                    rest = "";
+                    break;
+                };
+                let mut value = String::new();
+                let mut escaped = false;
+                let mut end = tail.len();
+                for (i, ch) in tail.char_indices() {
+                    match (escaped, ch) {
+                        (true, 'n') => value.push('\n'),
+                        (true, ch) => value.push(ch),
+                        (false, '\\') => {
+                            escaped = true;
+                            continue;
+                        }
+                        (false, '"') => {
+                            end = i + 1;
+                            break;
+                        }
+                        (false, ch) => value.push(ch),
+                    }
+                    escaped = false;
+                }
+                labels.insert(key.to_owned(), value.into());
+                chars = &tail[end..];
+            }
+        }
+
+        let Some(value) = rest
+            .split_whitespace()
+            .next()
+            .and_then(|value| value.parse::<f64>().ok())
+        else {
+            continue;
+        };
+        if let serde_json::Value::Array(values) = metrics
+            .entry(name)
+            .or_insert_with(|| serde_json::Value::Array(Vec::new()))
+        {
+            values.push(serde_json::json!({ "labels": labels, "value": value }));
+        }
+    }
+    metrics.into()
+}
+
+/// Read-only service, `/config` returns config (without filters, secrets are redacted) and
+/// filters, both as in config files, filters include hot-reloaded changes; `/metrics` returns
+/// current metrics in JSON for clients without access to Prometheus endpoint; `/backfill`
+/// returns how many slot statuses are sent to new subscriptions and range of their slots
+pub async fn spawn_server(
+    config: ConfigAdmin,
+    get_config: impl Fn() -> serde_json::Value + Clone + Send + 'static,
+    get_metrics: impl Fn() -> Option<serde_json::Value> + Clone + Send + 'static,
+    get_backfill: impl Fn() -> serde_json::Value + Clone + Send + 'static,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<impl Future<Output = Result<(), JoinError>>> {
+    let listener = TcpListener::bind(config.endpoint).await?;
+    info!("start admin server at: {}", config.endpoint);
+
+    let x_tokens = Arc::new(config.x_tokens);
+    Ok(tokio::spawn(async move {
+        tokio::pin!(shutdown);
+        loop {
+            let stream = tokio::select! {
+                maybe_conn = listener.accept() => {
+                    match maybe_conn {
+                        Ok((stream, _addr)) => stream,
+                        Err(error) => {
+                            error!("failed to accept new admin connection: {error}");
+                            break;
+                        }
+                    }
+                }
+                () = &mut shutdown => {
+                    info!("admin server shutdown");
+                    break
+                },
+            };
+            let get_config = get_config.clone();
+            let get_metrics = get_metrics.clone();
+            let get_backfill = get_backfill.clone();
+            let x_tokens = Arc::clone(&x_tokens);
+            tokio::spawn(async move {
+                if let Err(error) = ServerBuilder::new(TokioExecutor::new())
+                    .serve_connection(
+                        TokioIo::new(stream),
+                        service_fn(move |req: Request<BodyIncoming>| {
+                            let get_config = get_config.clone();
+                            let get_metrics = get_metrics.clone();
+                            let get_backfill = get_backfill.clone();
+                            let x_tokens = Arc::clone(&x_tokens);
+                            async move {
+                                let authorized =
+                                    req.headers().get("x-token").is_some_and(|token| {
+                                        is_x_token_valid(&x_tokens, token.as_bytes())
+                                    });
+                                let (status, bytes) = match req.uri().path() {
+                                    _ if !authorized => (
+                                        StatusCode::UNAUTHORIZED,
+                                        Bytes::from("No valid auth token"),
+                                    ),
+                                    "/config" => {
+                                        (StatusCode::OK, Bytes::from(get_config().to_string()))
+                                    }
+                                    "/metrics" => match get_metrics() {
+                                        Some(metrics) => {
+                                            (StatusCode::OK, Bytes::from(metrics.to_string()))
+                                        }
+                                        None => (
+                                            StatusCode::NOT_FOUND,
+                                            Bytes::from("Metrics are not recorded"),
+                                        ),
+                                    },
+                                    "/backfill" => {
+                                        (StatusCode::OK, Bytes::from(get_backfill().to_string()))
+                                    }
+                                    _ => (StatusCode::NOT_FOUND, Bytes::new()),
+                                };
+
+                                let mut response = Response::builder().status(status);
+                                if status == StatusCode::OK {
+                                    response = response.header(CONTENT_TYPE, "application/json");
+                                }
+                                response.body(BodyFull::new(bytes).boxed())
+                            }
+                        }),
+                    )
+                    .await
+                {
+                    error!("failed to handle admin request: {error}");
+                }
+            });
+        }
+    }))
+}
@@ -1,4 +1,4 @@
-use {clap::Parser, richat_plugin_agave::config::Config};
+use {clap::Parser, richat_plugin_agave::config::Config, std::process::ExitCode};
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -7,12 +7,12 @@ use {clap::Parser, richat_plugin_agave::config::Config};
     about = "Richat Agave Geyser Plugin Config Check Cli Tool"
 )]
 struct Args {
-    #[clap(short, long, default_value_t = String::from("config.json"))]
-    /// Path to config
-    config: String,
+    #[clap(short, long, default_values_t = [String::from("config.json")])]
+    /// Path to config, can be repeated to merge overrides into the base config
+    config: Vec<String>,
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<ExitCode> {
     anyhow::ensure!(
         rustls::crypto::aws_lc_rs::default_provider()
             .install_default()
@@ -21,7 +21,19 @@ fn main() -> anyhow::Result<()> {
     );
 
     let args = Args::parse();
-    let _config = Config::load_from_file(args.config)?;
-    println!("Config is OK!");
-    Ok(())
+    // Single config is loaded exactly as in `on_load` of the plugin
+    let result = match args.config.as_slice() {
+        [file] => Config::load_from_file(file),
+        files => Config::load_from_files(files),
+    };
+    match result {
+        Ok(_config) => {
+            println!("PASS {}: config is OK!", args.config.join(" + "));
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(error) => {
+            eprintln!("FAIL {}: {error}", args.config.join(" + "));
+            Ok(ExitCode::FAILURE)
+        }
+    }
 }
@@ -0,0 +1,586 @@
+use {
+    agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus,
+    anyhow::Context,
+    clap::Parser,
+    log::info,
+    prost::{Message, encoding},
+    prost_types::Timestamp,
+    richat_metrics::MaybeRecorder,
+    richat_plugin_agave::{
+        channel::{MessageMeta, Sender},
+        config::{Config, ConfigChannel, ConfigLogs},
+        logger,
+        plugin::PluginNotification,
+        protobuf::{BincodeMessage, BincodeUpdateV1, ProtobufEncoder},
+        sink::{FILE_EXTENSION, SinkReader},
+        version::VERSION,
+    },
+    richat_proto::{
+        geyser::{SubscribeUpdate, subscribe_update::UpdateOneof},
+        richat::{
+            SubscribeUpdateCompressed, SubscribeUpdateExtensions, subscribe_update_compressed::Data,
+        },
+    },
+    richat_shared::transports::{
+        expression::MessageFields, grpc::GrpcServer, quic::QuicServer, tcp::TcpServer,
+        uds::UdsServer, websocket::WebSocketServer,
+    },
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
+    std::{
+        borrow::Cow,
+        io::Read,
+        path::PathBuf,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    },
+    tokio::{fs, time::sleep},
+    tokio_util::sync::CancellationToken,
+};
+
+#[derive(Debug, Parser)]
+#[clap(author, version, about = "Richat Agave Geyser Plugin Replay Cli Tool")]
+struct Args {
+    #[clap(short, long, default_value_t = String::from("config.json"))]
+    /// Path to plugin config, channel and transports are used
+    config: String,
+
+    /// Files recorded by file sink or directories with them
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Pace messages by recorded timestamps
+    #[clap(long, default_value_t = false)]
+    pace: bool,
+
+    /// Delay in seconds before replay, to give clients time to connect
+    #[clap(long, default_value_t = 0)]
+    start_delay: u64,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    anyhow::ensure!(
+        rustls::crypto::aws_lc_rs::default_provider()
+            .install_default()
+            .is_ok(),
+        "failed to call CryptoProvider::install_default()"
+    );
+    logger::setup(&ConfigLogs::default());
+
+    let args = Args::parse();
+    let config = Config::load_from_file(&args.config)?;
+    logger::setup(&config.logs);
+    let files = collect_files(&args.files).await?;
+
+    // recorded messages keep own sequence numbers, if any
+    let channel = ConfigChannel {
+        sequence: false,
+        ..config.channel
+    };
+    let messages = Sender::new(channel, Arc::new(MaybeRecorder::Noop));
+    let shutdown = CancellationToken::new();
+    let mut tasks = Vec::new();
+    for endpoint in config.grpc {
+        tasks.push(tokio::spawn(
+            GrpcServer::spawn(
+                endpoint.server,
+                messages.with_filter(&endpoint.filter.to_richat_filter()),
+                || {},                    // on_conn_new_cb
+                |_duration| {},           // on_conn_drop_cb
+                |_reason| {},             // on_conn_rejected_cb
+                || {},                    // on_conn_idle_cb
+                |_throttled| {},          // on_conn_throttle_cb
+                |_size| {},               // on_batch_cb
+                |_encoding| {},           // on_conn_compressed_cb
+                |_client, _connected| {}, // on_conn_client_cb
+                VERSION,
+                || None, // version_server_info
+                shutdown.clone(),
+            )
+            .await?,
+        ));
+    }
+    if let Some(config) = config.quic {
+        tasks.push(tokio::spawn(
+            QuicServer::spawn(
+                config,
+                messages.clone(),
+                || {},           // on_conn_new_cb
+                |_duration| {},  // on_conn_drop_cb
+                |_reason| {},    // on_conn_rejected_cb
+                || {},           // on_conn_idle_cb
+                |_throttled| {}, // on_conn_throttle_cb
+                VERSION,
+                shutdown.clone(),
+            )
+            .await?,
+        ));
+    }
+    if let Some(config) = config.tcp {
+        tasks.push(tokio::spawn(
+            TcpServer::spawn(
+                config,
+                messages.clone(),
+                || {},          // on_conn_new_cb
+                |_duration| {}, // on_conn_drop_cb
+                |_reason| {},   // on_conn_rejected_cb
+                || {},          // on_conn_idle_cb
+                VERSION,
+                shutdown.clone(),
+            )
+            .await?,
+        ));
+    }
+    if let Some(config) = config.uds {
+        tasks.push(tokio::spawn(
+            UdsServer::spawn(
+                config,
+                messages.clone(),
+                || {},          // on_conn_new_cb
+                |_duration| {}, // on_conn_drop_cb
+                |_reason| {},   // on_conn_rejected_cb
+                || {},          // on_conn_idle_cb
+                VERSION,
+                shutdown.clone(),
+            )
+            .await?,
+        ));
+    }
+    if let Some(config) = config.websocket {
+        tasks.push(tokio::spawn(
+            WebSocketServer::spawn(
+                config,
+                messages.clone(),
+                || {},          // on_conn_new_cb
+                |_duration| {}, // on_conn_drop_cb
+                |_reason| {},   // on_conn_rejected_cb
+                || {},          // on_conn_idle_cb
+                VERSION,
+                shutdown.clone(),
+            )
+            .await?,
+        ));
+    }
+    anyhow::ensure!(
+        !tasks.is_empty(),
+        "at least one transport should be configured"
+    );
+
+    sleep(Duration::from_secs(args.start_delay)).await;
+
+    let mut total = 0;
+    let mut prev_created_at = None;
+    for path in files {
+        let mut reader = SinkReader::open(&path)
+            .await
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let encoder = reader.encoder();
+        info!("replay {} ({})", path.display(), encoder.as_str());
+
+        while let Some(data) = reader.read_message().await? {
+            let (meta, created_at) = decode_message(&data, encoder)
+                .with_context(|| format!("failed to decode message from {}", path.display()))?;
+
+            if args.pace {
+                if let (Some(prev), Some(created_at)) = (prev_created_at, created_at) {
+                    if let Ok(delay) = created_at.duration_since(prev) {
+                        sleep(delay).await;
+                    }
+                }
+                prev_created_at = created_at.or(prev_created_at);
+            }
+
+            messages.push_encoded(meta, data);
+            total += 1;
+        }
+    }
+    info!("replayed {total} messages, press Ctrl-C to exit");
+
+    tokio::signal::ctrl_c().await?;
+    messages.close();
+    shutdown.cancel();
+    for task in tasks {
+        task.await??;
+    }
+
+    Ok(())
+}
+
+async fn collect_files(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if fs::metadata(path).await?.is_dir() {
+            let mut dir_files = Vec::new();
+            let mut entries = fs::read_dir(path).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) == Some(FILE_EXTENSION) {
+                    dir_files.push(path);
+                }
+            }
+            // file names contain creation timestamp
+            dir_files.sort();
+            files.extend(dir_files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn decode_message(
+    data: &[u8],
+    encoder: ProtobufEncoder,
+) -> anyhow::Result<(MessageMeta, Option<SystemTime>)> {
+    if encoder == ProtobufEncoder::Bincode {
+        return decode_message_bincode(data);
+    }
+
+    let data = match encoder {
+        ProtobufEncoder::Zstd | ProtobufEncoder::Lz4 => {
+            match SubscribeUpdateCompressed::decode(data)?.data {
+                Some(Data::Zstd(data)) => Cow::Owned(zstd::decode_all(data.as_slice())?),
+                Some(Data::Lz4(data)) => {
+                    let mut decoded = Vec::new();
+                    lz4_flex::frame::FrameDecoder::new(data.as_slice())
+                        .read_to_end(&mut decoded)?;
+                    Cow::Owned(decoded)
+                }
+                // pushed uncompressed on compression error
+                None => Cow::Borrowed(data),
+            }
+        }
+        _ => Cow::Borrowed(data),
+    };
+    let message = SubscribeUpdate::decode(data.as_ref())?;
+    let created_at = message
+        .created_at
+        .and_then(|ts| SystemTime::try_from(ts).ok());
+
+    let meta = match message.update_oneof.context("no message")? {
+        UpdateOneof::Account(msg) => MessageMeta {
+            notification: PluginNotification::Account,
+            slot: msg.slot,
+            slot_status: None,
+            account: msg.account.as_ref().and_then(|account| {
+                Pubkey::try_from(account.pubkey.as_slice())
+                    .ok()
+                    .zip(Pubkey::try_from(account.owner.as_slice()).ok())
+            }),
+            fields: msg
+                .account
+                .map(|account| {
+                    let extensions =
+                        SubscribeUpdateExtensions::decode(data.as_ref()).unwrap_or_default();
+                    MessageFields::account(
+                        account.lamports,
+                        &account.data,
+                        extensions
+                            .original_data_len
+                            .map(|data_len| data_len as usize),
+                        account.executable,
+                    )
+                })
+                .unwrap_or_default(),
+        },
+        UpdateOneof::Slot(msg) => MessageMeta {
+            notification: PluginNotification::Slot,
+            slot: msg.slot,
+            slot_status: Some((
+                msg.parent,
+                match msg.status {
+                    0 => SlotStatus::Processed,
+                    1 => SlotStatus::Confirmed,
+                    2 => SlotStatus::Rooted,
+                    3 => SlotStatus::FirstShredReceived,
+                    4 => SlotStatus::Completed,
+                    5 => SlotStatus::CreatedBank,
+                    6 => SlotStatus::Dead(msg.dead_error.unwrap_or_default()),
+                    status => anyhow::bail!("unknown slot status: {status}"),
+                },
+            )),
+            account: None,
+            fields: MessageFields::None,
+        },
+        UpdateOneof::Transaction(msg) => MessageMeta {
+            notification: PluginNotification::Transaction,
+            slot: msg.slot,
+            slot_status: None,
+            account: None,
+            fields: msg
+                .transaction
+                .map(|tx| MessageFields::Transaction {
+                    fee: tx.meta.as_ref().map(|meta| meta.fee),
+                    is_vote: tx.is_vote,
+                    failed: tx.meta.is_some_and(|meta| meta.err.is_some()),
+                })
+                .unwrap_or_default(),
+        },
+        UpdateOneof::Entry(msg) => MessageMeta {
+            notification: PluginNotification::Entry,
+            slot: msg.slot,
+            slot_status: None,
+            account: None,
+            fields: MessageFields::None,
+        },
+        UpdateOneof::BlockMeta(msg) => MessageMeta {
+            notification: PluginNotification::BlockMeta,
+            slot: msg.slot,
+            slot_status: None,
+            account: None,
+            fields: MessageFields::None,
+        },
+        UpdateOneof::Ping(_) => MessageMeta {
+            notification: PluginNotification::Slot,
+            slot: decode_complete_slot(data.as_ref())?,
+            slot_status: None,
+            account: None,
+            fields: MessageFields::None,
+        },
+        _ => anyhow::bail!("unexpected message type"),
+    };
+
+    Ok((meta, created_at))
+}
+
+// Slot complete barrier is a ping with slot in the extension field, dropped by prost decode
+fn decode_complete_slot(mut data: &[u8]) -> anyhow::Result<Slot> {
+    while !data.is_empty() {
+        let (tag, wire_type) = encoding::decode_key(&mut data)?;
+        if tag == 103 {
+            return Ok(encoding::decode_varint(&mut data)?);
+        }
+        encoding::skip_field(
+            wire_type,
+            tag,
+            &mut data,
+            encoding::DecodeContext::default(),
+        )?;
+    }
+    anyhow::bail!("no slot in slot complete message")
+}
+
+fn decode_message_bincode(data: &[u8]) -> anyhow::Result<(MessageMeta, Option<SystemTime>)> {
+    let BincodeMessage::V1(message) = BincodeMessage::decode(data)?;
+    let created_at = SystemTime::try_from(Timestamp::from(message.created_at)).ok();
+
+    let meta = match message.update {
+        BincodeUpdateV1::Account(msg) | BincodeUpdateV1::AccountOwnerChanged(msg) => MessageMeta {
+            notification: PluginNotification::Account,
+            slot: msg.slot,
+            slot_status: None,
+            account: Pubkey::try_from(msg.pubkey.as_ref())
+                .ok()
+                .zip(Pubkey::try_from(msg.owner.as_ref()).ok()),
+            fields: MessageFields::account(
+                msg.lamports,
+                &msg.data,
+                msg.original_data_len.map(|data_len| data_len as usize),
+                msg.executable,
+            ),
+        },
+        BincodeUpdateV1::Slot(msg) => MessageMeta {
+            notification: PluginNotification::Slot,
+            slot: msg.slot,
+            slot_status: Some((msg.parent, msg.status.into())),
+            account: None,
+            fields: MessageFields::None,
+        },
+        BincodeUpdateV1::Transaction(msg) => MessageMeta {
+            notification: PluginNotification::Transaction,
+            slot: msg.slot,
+            slot_status: None,
+            account: None,
+            fields: MessageFields::Transaction {
+                fee: Some(msg.meta.fee),
+                is_vote: msg.is_vote,
+                failed: msg.meta.status.is_err(),
+            },
+        },
+        BincodeUpdateV1::Entry(msg) => MessageMeta {
+            notification: PluginNotification::Entry,
+            slot: msg.slot,
+            slot_status: None,
+            account: None,
+            fields: MessageFields::None,
+        },
+        BincodeUpdateV1::BlockMeta(msg) => MessageMeta {
+            notification: PluginNotification::BlockMeta,
+            slot: msg.slot,
+            slot_status: None,
+            account: None,
+            fields: MessageFields::None,
+        },
+        BincodeUpdateV1::SlotComplete(msg) => MessageMeta {
+            notification: PluginNotification::Slot,
+            slot: msg.slot,
+            slot_status: None,
+            account: None,
+            fields: MessageFields::None,
+        },
+    };
+
+    Ok((meta, created_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{collect_files, decode_message},
+        agave_geyser_plugin_interface::geyser_plugin_interface::{
+            ReplicaAccountInfoV3, SlotStatus,
+        },
+        prost::Message,
+        richat_plugin_agave::{
+            plugin::PluginNotification,
+            protobuf::{ProtobufEncoder, ProtobufMessage},
+            sink::{FILE_EXTENSION, SinkWriter},
+        },
+        richat_proto::richat::{SubscribeUpdateCompressed, subscribe_update_compressed::Data},
+        richat_shared::transports::expression::MessageFields,
+        solana_sdk::pubkey::Pubkey,
+        std::{
+            env,
+            io::Write,
+            process,
+            time::{Duration, SystemTime, UNIX_EPOCH},
+        },
+        tokio::fs,
+    };
+
+    fn encode(
+        message: &ProtobufMessage,
+        encoder: ProtobufEncoder,
+        created_at: SystemTime,
+    ) -> Vec<u8> {
+        let data = message.encode_with_timestamp(encoder, created_at);
+        let data = match encoder {
+            ProtobufEncoder::Zstd => Data::Zstd(zstd::encode_all(data.as_slice(), 0).unwrap()),
+            ProtobufEncoder::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(&data).unwrap();
+                Data::Lz4(encoder.finish().unwrap())
+            }
+            _ => return data,
+        };
+        SubscribeUpdateCompressed { data: Some(data) }.encode_to_vec()
+    }
+
+    #[test]
+    fn test_decode_message() {
+        let pubkey = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let data = [42; 100];
+        let account = ReplicaAccountInfoV3 {
+            pubkey: pubkey.as_ref(),
+            lamports: 1_000,
+            owner: owner.as_ref(),
+            executable: true,
+            rent_epoch: 0,
+            data: &data[..10],
+            write_version: 1,
+            txn: None,
+        };
+        let created_at = UNIX_EPOCH + Duration::from_micros(1_700_000_000_000_000);
+
+        for encoder in ProtobufEncoder::ALL {
+            for original_data_len in [None, Some(data.len())] {
+                let message = ProtobufMessage::Account {
+                    slot: 10,
+                    account: &account,
+                    original_data_len,
+                    is_startup: false,
+                    owner_changed: false,
+                };
+                let (meta, decoded_created_at) =
+                    decode_message(&encode(&message, encoder, created_at), encoder).unwrap();
+                assert_eq!(decoded_created_at, Some(created_at), "{encoder:?}");
+                assert_eq!(meta.notification, PluginNotification::Account);
+                assert_eq!(meta.slot, 10);
+                assert_eq!(meta.account, Some((pubkey, owner)));
+                let data_len = original_data_len.unwrap_or(10) as u64;
+                assert!(
+                    matches!(
+                        meta.fields,
+                        MessageFields::Account {
+                            lamports: 1_000,
+                            data_len: len,
+                            data_prefix,
+                            executable: true,
+                        } if len == data_len && data_prefix.is_some() == original_data_len.is_none()
+                    ),
+                    "{encoder:?}: {:?}",
+                    meta.fields
+                );
+            }
+
+            let status = SlotStatus::Dead("error".to_owned());
+            let message = ProtobufMessage::Slot {
+                slot: 11,
+                parent: Some(10),
+                status: &status,
+            };
+            let (meta, _) =
+                decode_message(&encode(&message, encoder, created_at), encoder).unwrap();
+            assert_eq!(meta.notification, PluginNotification::Slot);
+            assert_eq!(meta.slot, 11);
+            assert_eq!(meta.slot_status, Some((Some(10), status)), "{encoder:?}");
+
+            // slot complete barrier
+            let message = ProtobufMessage::SlotComplete { slot: 12 };
+            let (meta, _) =
+                decode_message(&encode(&message, encoder, created_at), encoder).unwrap();
+            assert_eq!(meta.notification, PluginNotification::Slot);
+            assert_eq!(meta.slot, 12, "{encoder:?}");
+            assert_eq!(meta.slot_status, None);
+
+            assert!(
+                decode_message(b"\xff\xff\xff", encoder).is_err(),
+                "{encoder:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_files() {
+        let dir = env::temp_dir().join(format!("richat-replay-files-{}", process::id()));
+        for _ in 0..3 {
+            let writer = SinkWriter::new(dir.clone(), ProtobufEncoder::Raw, usize::MAX)
+                .await
+                .unwrap();
+            writer.finish().await.unwrap();
+        }
+        // not completed file is ignored
+        let _writer = SinkWriter::new(dir.clone(), ProtobufEncoder::Raw, usize::MAX)
+            .await
+            .unwrap();
+
+        let mut files = Vec::new();
+        let mut entries = fs::read_dir(&dir).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            files.push(entry.path());
+        }
+        assert_eq!(files.len(), 4);
+        let mut completed = files
+            .into_iter()
+            .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(FILE_EXTENSION))
+            .collect::<Vec<_>>();
+        completed.sort();
+        assert_eq!(completed.len(), 3);
+
+        // directories are expanded in order of file names, files are kept as is
+        let files = collect_files(&[completed[2].clone(), dir.clone()])
+            .await
+            .unwrap();
+        assert_eq!(
+            files,
+            [completed[2].clone()]
+                .into_iter()
+                .chain(completed.iter().cloned())
+                .collect::<Vec<_>>()
+        );
+        assert!(collect_files(&[dir.join("missing")]).await.is_err());
+
+        fs::remove_dir_all(dir).await.unwrap();
+    }
+}
@@ -1,74 +1,374 @@
 // Based on https://github.com/tokio-rs/tokio/blob/master/tokio/src/sync/broadcast.rs
 use {
     crate::{
-        config::ConfigChannel,
+        config::{
+            ConfigChannel, ConfigChannelBufferPool, ConfigChannelEviction, ConfigChannelOverflow,
+        },
         metrics,
         plugin::PluginNotification,
-        protobuf::{ProtobufEncoder, ProtobufMessage},
+        protobuf::{
+            COMPRESSED_LZ4_TAG, COMPRESSED_ZSTD_TAG, ProtobufEncoder, ProtobufMessage,
+            sequence_offset,
+        },
     },
     agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus,
+    crossbeam_queue::ArrayQueue,
     futures::stream::{Stream, StreamExt},
-    log::{debug, error},
+    log::{debug, error, warn},
     metrics_exporter_prometheus::PrometheusRecorder,
-    richat_metrics::{MaybeRecorder, counter, gauge},
+    prost::encoding,
+    richat_metrics::{
+        Counter, Gauge, Histogram, MaybeRecorder, PrefixedRecorder, counter, duration_to_seconds,
+        gauge, histogram,
+    },
     richat_proto::richat::RichatFilter,
     richat_shared::{
         mutex_lock,
-        transports::{RecvError, RecvItem, RecvStream, Subscribe, SubscribeError},
+        transports::{
+            RecvError, RecvItem, RecvStream, Subscribe, SubscribeAccountsFilter, SubscribeError,
+            expression::{FilterExpression, MessageFields},
+        },
     },
+    serde::Serialize,
     smallvec::SmallVec,
-    solana_sdk::clock::Slot,
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
     std::{
-        collections::BTreeMap,
+        cell::RefCell,
+        cmp,
+        collections::{BTreeMap, VecDeque},
         fmt,
         future::Future,
+        io::{self, Write},
         pin::Pin,
-        sync::{Arc, Mutex, MutexGuard},
+        sync::{
+            Arc, Condvar, Mutex, MutexGuard, Weak,
+            atomic::{self, AtomicU64, AtomicUsize, Ordering},
+        },
         task::{Context, Poll, Waker},
+        time::{Duration, Instant},
     },
 };
 
+/// Initial size of the ring if number of messages is not limited
+const RING_INITIAL_CAPACITY: usize = 65_536;
+
+/// Shared lane and own lanes of every notification type with limits
+const LANES_MAX: usize = PluginNotification::ALL.len() + 1;
+
+/// Message properties used by channel, extracted from `ProtobufMessage` or from decoded message
+#[derive(Debug, Clone)]
+pub struct MessageMeta {
+    pub notification: PluginNotification,
+    pub slot: Slot,
+    /// Parent and status of slot message
+    pub slot_status: Option<(Option<Slot>, SlotStatus)>,
+    /// Pubkey and owner of account message
+    pub account: Option<(Pubkey, Pubkey)>,
+    /// Values of account or transaction message used by filter expressions
+    pub fields: MessageFields,
+}
+
+impl From<&ProtobufMessage<'_>> for MessageMeta {
+    fn from(message: &ProtobufMessage<'_>) -> Self {
+        Self {
+            notification: PluginNotification::from(message),
+            slot: message.get_slot(),
+            slot_status: match message {
+                ProtobufMessage::Slot { parent, status, .. } => Some((*parent, (*status).clone())),
+                _ => None,
+            },
+            account: match message {
+                ProtobufMessage::Account { account, .. } => Pubkey::try_from(account.pubkey)
+                    .ok()
+                    .zip(Pubkey::try_from(account.owner).ok()),
+                _ => None,
+            },
+            fields: match message {
+                ProtobufMessage::Account {
+                    account,
+                    original_data_len,
+                    ..
+                } => MessageFields::account(
+                    account.lamports,
+                    account.data,
+                    *original_data_len,
+                    account.executable,
+                ),
+                ProtobufMessage::Transaction { transaction, .. } => MessageFields::Transaction {
+                    fee: Some(transaction.transaction_status_meta.fee),
+                    is_vote: transaction.is_vote,
+                    failed: transaction.transaction_status_meta.status.is_err(),
+                },
+                _ => MessageFields::None,
+            },
+        }
+    }
+}
+
+/// Messages are pushed to the ring buffer under the state lock and receivers of all transports
+/// read it by position, so every client gets messages in the same order, filters only skip them
 #[derive(Debug, Clone)]
 pub struct Sender {
     shared: Arc<Shared>,
-    recorder: Arc<MaybeRecorder<PrometheusRecorder>>,
+    max_message_bytes: Option<usize>,
+    compression_level: i32,
+    sequence: bool,
+    max_messages_per_slot: Option<usize>,
+    backfill_slot_statuses: usize,
+    overflow_timeout: Duration,
+    eviction: ConfigChannelEviction,
+    recorder: Arc<MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>>,
+    metrics: Arc<SenderMetrics>,
+    transport: &'static str,
+    filter: Option<Arc<EndpointFilter>>,
 }
 
 impl Sender {
-    pub fn new(config: ConfigChannel, recorder: Arc<MaybeRecorder<PrometheusRecorder>>) -> Self {
-        let max_messages = config.max_messages.next_power_of_two();
-        let mut buffer = Vec::with_capacity(max_messages);
-        for i in 0..max_messages {
-            buffer.push(Mutex::new(Item {
-                pos: i as u64,
-                slot: 0,
-                data: None,
-                closed: false,
-            }));
+    pub fn new(
+        config: ConfigChannel,
+        recorder: Arc<MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>>,
+    ) -> Self {
+        // notification types with own limits get own lanes, other types share the first one
+        let mut lanes = vec![Lane::new(config.max_messages, config.max_bytes)];
+        let mut notification_lanes = [0; PluginNotification::ALL.len()];
+        for notification in PluginNotification::ALL {
+            if let Some(limit) = config.limits.get(notification) {
+                notification_lanes[notification as usize] = lanes.len();
+                lanes.push(Lane::new(limit.max_messages, limit.max_bytes));
+            }
         }
 
         let shared = Arc::new(Shared {
             state: Mutex::new(State {
-                head: max_messages as u64 + 1,
-                tail: max_messages as u64,
+                lanes,
+                last_lane: None,
+                index: 0,
                 slots: BTreeMap::new(),
-                bytes_total: 0,
-                bytes_max: config.max_bytes,
+                max_slot: 0,
+                bytes_pushed: 0,
+                sequence: 0,
+                usage: [Usage::default(); PluginNotification::ALL.len()],
+                evicting_since: None,
                 wakers: Vec::with_capacity(16),
+                queues: Vec::new(),
+                slot_statuses: VecDeque::with_capacity(config.backfill_slot_statuses),
+                stats: ChannelStats::default(),
             }),
-            mask: (max_messages - 1) as u64,
-            buffer: buffer.into_boxed_slice(),
+            space: Condvar::new(),
+            space_waiters: AtomicUsize::new(0),
+            buffer_pool: BufferPool::new(config.buffer_pool),
+            block_on_overflow: config.overflow == ConfigChannelOverflow::Block,
+            notification_lanes,
         });
 
-        Self { shared, recorder }
+        Self {
+            shared,
+            max_message_bytes: config.max_message_bytes,
+            compression_level: config.compression_level,
+            sequence: config.sequence,
+            max_messages_per_slot: config.max_messages_per_slot,
+            backfill_slot_statuses: config.backfill_slot_statuses,
+            overflow_timeout: config.overflow_timeout,
+            eviction: config.eviction,
+            metrics: Arc::new(SenderMetrics::new(&recorder)),
+            recorder,
+            transport: "unknown",
+            filter: None,
+        }
+    }
+
+    /// Clone of the sender, receivers of which report message age with given transport label
+    pub fn with_transport(&self, transport: &'static str) -> Self {
+        Self {
+            transport,
+            ..self.clone()
+        }
+    }
+
+    /// Clone of the sender, receivers of which get only messages matched by the filter
+    pub fn with_filter(&self, filter: &RichatFilter) -> Self {
+        self.with_endpoint_filter(
+            Self::filter_notifications(filter),
+            Some(SubscribeAccountsFilter::new(filter)),
+        )
+    }
+
+    /// Clone of the sender, receivers of which get only messages of given notification types
+    pub fn with_notifications(&self, notifications: &[PluginNotification]) -> Self {
+        let mut enabled = [false; PluginNotification::ALL.len()];
+        for notification in notifications {
+            enabled[*notification as usize] = true;
+        }
+        self.with_endpoint_filter(enabled, None)
+    }
+
+    /// Notifications are combined with the current filter, accounts filter is replaced if set
+    fn with_endpoint_filter(
+        &self,
+        mut notifications: [bool; PluginNotification::ALL.len()],
+        accounts_filter: Option<SubscribeAccountsFilter>,
+    ) -> Self {
+        if let Some(filter) = &self.filter {
+            for (enabled, allowed) in notifications.iter_mut().zip(filter.notifications) {
+                *enabled &= allowed;
+            }
+        }
+        let accounts_filter = accounts_filter
+            .or_else(|| {
+                self.filter
+                    .as_ref()
+                    .map(|filter| filter.accounts_filter.clone())
+            })
+            .unwrap_or_default();
+        Self {
+            filter: Some(Arc::new(EndpointFilter {
+                notifications,
+                accounts_filter,
+            })),
+            ..self.clone()
+        }
+    }
+
+    const fn filter_notifications(filter: &RichatFilter) -> [bool; PluginNotification::ALL.len()] {
+        let mut notifications = [true; PluginNotification::ALL.len()];
+        notifications[PluginNotification::Account as usize] = !filter.disable_accounts;
+        notifications[PluginNotification::Transaction as usize] = !filter.disable_transactions;
+        notifications[PluginNotification::Entry as usize] = !filter.disable_entries;
+        notifications
+    }
+
+    fn wait_space<'a>(
+        &self,
+        mut state: MutexGuard<'a, State>,
+        notification: PluginNotification,
+        size: usize,
+    ) -> MutexGuard<'a, State> {
+        let lane = self.shared.notification_lanes[notification as usize];
+        let mut blocked_since = None;
+        loop {
+            if !state.lanes[lane].is_overflow(size) {
+                break;
+            }
+
+            // waiter is registered before the progress check, receivers update progress before
+            // they check waiters, so either the progress is seen here or the receiver notifies
+            // under the state lock, which is released only by `wait_timeout`
+            if blocked_since.is_none() {
+                self.shared.space_waiters.fetch_add(1, Ordering::Relaxed);
+                atomic::fence(Ordering::SeqCst);
+            }
+            let since = *blocked_since.get_or_insert_with(Instant::now);
+
+            let head = state.lanes[lane].head;
+            if !state.has_unread(lane, head) {
+                break;
+            }
+            let Some(timeout) = self.overflow_timeout.checked_sub(since.elapsed()) else {
+                break;
+            };
+
+            state = match self.shared.space.wait_timeout(state, timeout) {
+                Ok((state, _timeout)) => state,
+                Err(error) => error.into_inner().0,
+            };
+        }
+
+        if let Some(since) = blocked_since {
+            self.shared.space_waiters.fetch_sub(1, Ordering::Relaxed);
+            histogram!(&self.recorder, metrics::CHANNEL_BLOCKED_SECONDS)
+                .record(duration_to_seconds(since.elapsed()));
+        }
+
+        state
+    }
+
+    pub(crate) fn encode(&self, message: &ProtobufMessage, encoder: ProtobufEncoder) -> Vec<u8> {
+        let mut data = self.buffer_get();
+        match encoder {
+            ProtobufEncoder::Zstd | ProtobufEncoder::Lz4 => {
+                let mut raw = self.buffer_get();
+                message.encode_to(encoder, &mut raw);
+                let compressed = if encoder == ProtobufEncoder::Zstd {
+                    compress_zstd(self.compression_level, &raw, self.buffer_get())
+                        .map(|compressed| (COMPRESSED_ZSTD_TAG, compressed))
+                } else {
+                    compress_lz4(&raw, self.buffer_get())
+                        .map(|compressed| (COMPRESSED_LZ4_TAG, compressed))
+                };
+                match compressed {
+                    Ok((tag, compressed)) => {
+                        encoding::bytes::encode(tag, &compressed, &mut data);
+                        self.buffer_put(compressed);
+                    }
+                    // clients decode message without compression field as is
+                    Err(error) => {
+                        error!("failed to compress message, pushed uncompressed: {error}");
+                        self.metrics.compress_errors.increment(1);
+                        std::mem::swap(&mut data, &mut raw);
+                    }
+                }
+                self.buffer_put(raw);
+            }
+            _ if self.sequence => {
+                // rejected on config load, message is pushed without sequence number
+                if message.encode_with_sequence_to(encoder, &mut data).is_err() {
+                    message.encode_to(encoder, &mut data);
+                }
+            }
+            _ => message.encode_to(encoder, &mut data),
+        }
+        data
+    }
+
+    fn buffer_get(&self) -> Vec<u8> {
+        let Some(pool) = &self.shared.buffer_pool else {
+            return Vec::new();
+        };
+
+        let buffer = pool.buffers.pop();
+        let result = if buffer.is_some() { "hit" } else { "miss" };
+        counter!(&self.recorder, metrics::CHANNEL_BUFFER_POOL_TOTAL, "result" => result)
+            .increment(1);
+        buffer.unwrap_or_default()
+    }
+
+    fn buffer_put(&self, mut buffer: Vec<u8>) {
+        if let Some(pool) = &self.shared.buffer_pool {
+            if buffer.capacity() <= pool.max_buffer_size {
+                // encoders only append, so old bytes are never visible after clear
+                buffer.clear();
+                let _ = pool.buffers.push(buffer);
+            }
+        }
+    }
+
+    /// Return buffer of removed message to the pool, if no receiver holds it
+    fn buffer_release(&self, data: Arc<Vec<u8>>) {
+        if self.shared.buffer_pool.is_some() {
+            if let Ok(buffer) = Arc::try_unwrap(data) {
+                self.buffer_put(buffer);
+            }
+        }
     }
 
     pub fn push(&self, message: ProtobufMessage, encoder: ProtobufEncoder) {
         // encode message
-        let data = message.encode(encoder);
+        let notification = PluginNotification::from(&message);
+        let data = self.encode(&message, encoder);
+        if self.is_oversized(notification, data.len(), || match &message {
+            ProtobufMessage::Account { account, .. } => bs58::encode(account.pubkey).into_string(),
+            ProtobufMessage::Transaction { transaction, .. } => transaction.signature.to_string(),
+            message => format!("slot {}", message.get_slot()),
+        }) {
+            self.buffer_put(data);
+            return;
+        }
 
         // acquire state lock
         let mut state = self.shared.state_lock();
+        if self.shared.block_on_overflow {
+            state = self.wait_space(state, notification, data.len());
+        }
 
         // In March 2023 in Triton One we noticed that sometimes we do not receive
         // slots with Confirmed status, I'm not sure that this still a case but for
@@ -102,7 +402,7 @@ impl Sender {
                         parent: entry.parent_slot,
                         status,
                     };
-                    let data = message.encode(encoder);
+                    let data = self.encode(&message, encoder);
                     messages.push((message, data));
 
                     error!("missed slot status update for {} ({:?})", parent, *status);
@@ -116,7 +416,7 @@ impl Sender {
 
         // push messages
         for (message, data) in messages.into_iter().rev() {
-            self.push_msg(&mut state, message, data);
+            self.push_msg(&mut state, MessageMeta::from(&message), data);
         }
 
         // notify receivers
@@ -125,56 +425,185 @@ impl Sender {
         }
     }
 
-    fn push_msg(&self, state: &mut MutexGuard<'_, State>, message: ProtobufMessage, data: Vec<u8>) {
+    /// Push already encoded message, used to replay recorded messages
+    pub fn push_encoded(&self, meta: MessageMeta, data: Vec<u8>) {
+        if self.is_oversized(meta.notification, data.len(), || match meta.account {
+            Some((pubkey, _owner)) => pubkey.to_string(),
+            None => format!("slot {}", meta.slot),
+        }) {
+            self.buffer_put(data);
+            return;
+        }
+
+        let mut state = self.shared.state_lock();
+        if self.shared.block_on_overflow {
+            state = self.wait_space(state, meta.notification, data.len());
+        }
+
+        self.push_msg(&mut state, meta, data);
+
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Returns `true` if message should be dropped because encoded size is over the limit
+    fn is_oversized(
+        &self,
+        notification: PluginNotification,
+        size: usize,
+        id: impl FnOnce() -> String,
+    ) -> bool {
+        if self.max_message_bytes.is_none_or(|max| size <= max) {
+            return false;
+        }
+
+        counter!(&self.recorder, metrics::MESSAGES_OVERSIZED_TOTAL, "notification" => notification.as_str())
+            .increment(1);
+        debug!(
+            "drop oversized {} message, {size} bytes: {}",
+            notification.as_str(),
+            id()
+        );
+        true
+    }
+
+    fn push_msg(&self, state: &mut MutexGuard<'_, State>, meta: MessageMeta, mut data: Vec<u8>) {
         let mut removed_max_slot = None;
 
-        // bump current tail
-        state.tail = state.tail.wrapping_add(1);
+        let notification = meta.notification;
+        self.metrics.messages[notification as usize].increment(1);
+        state.stats.pushed[notification as usize] += 1;
+
+        // drop new message if lane is full and buffered messages are not read yet
+        let lane = self.shared.notification_lanes[notification as usize];
+        if self.eviction == ConfigChannelEviction::Newest
+            && !state.has_space_newest(lane, data.len())
+        {
+            self.inc_dropped(state, notification, DropReason::Newest);
+            self.buffer_put(data);
+            return;
+        }
+
+        // drop message if slot produced too many messages, slot statuses and block meta are kept
+        let slot_limited = matches!(
+            notification,
+            PluginNotification::Account
+                | PluginNotification::Transaction
+                | PluginNotification::Entry
+        );
+        if let Some(max) = self.max_messages_per_slot.filter(|_| slot_limited) {
+            let messages = state.slots.get(&meta.slot).map_or(0, |info| info.messages);
+            if messages >= max {
+                if messages == max {
+                    // count slot once, next messages of the slot are dropped without increment
+                    counter!(&self.recorder, metrics::CHANNEL_SLOTS_CAPPED_TOTAL).increment(1);
+                    warn!("slot {} reached max messages per slot ({max})", meta.slot);
+                    if let Some(info) = state.slots.get_mut(&meta.slot) {
+                        info.messages += 1;
+                    }
+                }
+                self.inc_dropped(state, notification, DropReason::SlotLimit);
+                self.buffer_put(data);
+                return;
+            }
+        }
+
+        // set sequence under the lock, so numbers follow the order of messages in the channel,
+        // already encoded messages without sequence field are pushed as is
+        if let Some(offset) = sequence_offset(&data).filter(|_| self.sequence) {
+            data[offset..].copy_from_slice(&state.sequence.to_le_bytes());
+            state.sequence += 1;
+        }
+
+        // make space instead of eviction of the oldest message if number is not limited
+        if state.lanes[lane].grow_ring && state.lanes[lane].is_full() {
+            state.lanes[lane].grow_ring();
+            debug!(
+                "channel lane {lane} ring grown to {} messages",
+                state.lanes[lane].ring.capacity()
+            );
+        }
 
-        // update slots info
-        let slot = message.get_slot();
-        let head = state.tail;
-        let entry = state.slots.entry(slot).or_insert_with(|| SlotInfo {
-            head,
+        // update slots info, new slot starts from the next position of every lane
+        let slot = meta.slot;
+        if meta.slot_status.is_some() {
+            state.max_slot = state.max_slot.max(slot);
+        }
+        let State { slots, lanes, .. } = &mut **state;
+        let entry = slots.entry(slot).or_insert_with(|| SlotInfo {
+            heads: lanes.iter().map(|lane| lane.tail.wrapping_add(1)).collect(),
             parent_slot: None,
             confirmed: false,
             finalized: false,
+            messages: 0,
         });
-        if let ProtobufMessage::Slot { parent, status, .. } = &message {
+        if slot_limited {
+            entry.messages += 1;
+        }
+        if let Some((parent, status)) = &meta.slot_status {
             if let Some(parent) = parent {
                 entry.parent_slot = Some(*parent);
             }
-            if **status == SlotStatus::Confirmed {
+            if *status == SlotStatus::Confirmed {
                 entry.confirmed = true;
-            } else if **status == SlotStatus::Rooted {
+            } else if *status == SlotStatus::Rooted {
                 entry.finalized = true;
             }
         }
 
+        // bump current tail
+        state.lanes[lane].tail = state.lanes[lane].tail.wrapping_add(1);
+        state.last_lane = Some(lane);
+
         // lock and update item
-        state.bytes_total += data.len();
-        let idx = self.shared.get_idx(state.tail);
-        let mut item = self.shared.buffer_idx(idx);
+        state.lanes[lane].bytes_total += data.len();
+        state.usage[notification as usize].add(data.len());
+        let ring = Arc::clone(&state.lanes[lane].ring);
+        let tail = state.lanes[lane].tail;
+        let mut item = ring.item(tail);
         if let Some(message) = item.data.take() {
-            state.head = state.head.wrapping_add(1);
-            state.bytes_total -= message.1.len();
+            state.lanes[lane].remove(message.1.len());
+            state.usage[message.0 as usize].remove(message.1.len());
+            self.inc_dropped(state, message.0, DropReason::MaxMessages);
+            self.buffer_release(message.1);
             removed_max_slot = Some(item.slot);
         }
-        item.pos = state.tail;
+        let index = state.index;
+        state.index += 1;
+        item.pos = tail;
+        item.index = index;
         item.slot = slot;
-        item.data = Some((PluginNotification::from(&message), Arc::new(data)));
+        item.account = meta.account;
+        item.fields = meta.fields;
+        item.offset = state.bytes_pushed;
+        state.bytes_pushed += data.len() as u64;
+        let data = Arc::new(data);
+        item.data = Some((notification, Arc::clone(&data)));
+        item.pushed_at = Instant::now();
         drop(item);
 
+        // keep last slot statuses for new receivers
+        if meta.slot_status.is_some() && self.backfill_slot_statuses > 0 {
+            if state.slot_statuses.len() == self.backfill_slot_statuses {
+                state.slot_statuses.pop_front();
+            }
+            state.slot_statuses.push_back((index, slot, data));
+        }
+
         // drop extra messages by max bytes
-        while state.bytes_total >= state.bytes_max && state.head < state.tail {
-            let idx = self.shared.get_idx(state.head);
-            let mut item = self.shared.buffer_idx(idx);
+        while state.lanes[lane].bytes_total >= state.lanes[lane].bytes_max
+            && state.lanes[lane].head < state.lanes[lane].tail
+        {
+            let mut item = ring.item(state.lanes[lane].head);
             let Some(message) = item.data.take() else {
                 panic!("nothing to remove to keep bytes under limit")
             };
 
-            state.head = state.head.wrapping_add(1);
-            state.bytes_total -= message.1.len();
+            state.lanes[lane].remove(message.1.len());
+            state.usage[message.0 as usize].remove(message.1.len());
+            self.inc_dropped(state, message.0, DropReason::MaxBytes);
+            self.buffer_release(message.1);
             removed_max_slot = Some(match removed_max_slot {
                 Some(slot) => item.slot.max(slot),
                 None => item.slot,
@@ -183,95 +612,307 @@ impl Sender {
 
         // remove not-complete slots
         if let Some(remove_upto) = removed_max_slot {
+            let mut removed_finalized = None;
             loop {
                 match state.slots.first_key_value() {
-                    Some((slot, _)) if *slot <= remove_upto => {
+                    Some((slot, info)) if *slot <= remove_upto => {
+                        removed_finalized =
+                            Some(removed_finalized.unwrap_or(true) && info.finalized);
                         let slot = *slot;
                         state.slots.remove(&slot);
                     }
                     _ => break,
                 }
             }
+            match removed_finalized {
+                Some(true) => state.evicting_since = None,
+                Some(false) => {
+                    state.evicting_since.get_or_insert_with(Instant::now);
+                }
+                None => {}
+            }
         }
 
         // update metrics
-        if let ProtobufMessage::Slot { status, .. } = message {
+        let (messages_total, bytes_total) = state.buffered();
+        state.stats.peak_messages = state.stats.peak_messages.max(messages_total);
+        state.stats.peak_bytes = state.stats.peak_bytes.max(bytes_total);
+        self.metrics.channel_messages.set(messages_total as f64);
+        self.metrics.channel_bytes.set(bytes_total as f64);
+        if removed_max_slot.is_some() || meta.slot_status.is_some() {
+            if let Some((oldest_slot, _)) = state.slots.first_key_value() {
+                gauge!(&self.recorder, metrics::CHANNEL_SLOT_LAG)
+                    .set(state.max_slot.saturating_sub(*oldest_slot) as f64);
+            }
+        }
+        if let Some((_parent, status)) = &meta.slot_status {
             if !matches!(status, SlotStatus::Dead(_)) {
                 gauge!(&self.recorder, metrics::GEYSER_SLOT_STATUS, "status" => status.as_str())
                     .set(slot as f64);
             }
             if *status == SlotStatus::Processed {
                 debug!(
-                    "new processed {slot} / {} messages / {} slots / {} bytes",
-                    state.tail - state.head,
+                    "new processed {slot} / {messages_total} messages / {} slots / {bytes_total} bytes",
                     state.slots.len(),
-                    state.bytes_total
                 );
 
-                gauge!(&self.recorder, metrics::CHANNEL_MESSAGES_TOTAL)
-                    .set((state.tail - state.head) as f64);
                 gauge!(&self.recorder, metrics::CHANNEL_SLOTS_TOTAL).set(state.slots.len() as f64);
-                gauge!(&self.recorder, metrics::CHANNEL_BYTES_TOTAL).set(state.bytes_total as f64);
+                for notification in PluginNotification::ALL {
+                    let usage = state.usage[notification as usize];
+                    self.metrics.notification_messages[notification as usize]
+                        .set(usage.messages as f64);
+                    self.metrics.notification_bytes[notification as usize].set(usage.bytes as f64);
+                }
+                self.update_queues_metrics(state);
             }
         }
     }
 
-    pub fn close(&self) {
-        for idx in 0..self.shared.buffer.len() {
-            self.shared.buffer_idx(idx).closed = true;
+    /// Max and sum of not consumed bytes and max lag in messages by receivers of every transport
+    fn update_queues_metrics(&self, state: &mut State) {
+        let bytes_pushed = state.bytes_pushed;
+        let State { lanes, queues, .. } = state;
+        for (transport, queues) in queues.iter_mut() {
+            let (mut max, mut sum, mut lag_max) = (0, 0, 0);
+            queues.retain(|progress| match progress.upgrade() {
+                Some(progress) => {
+                    let size =
+                        bytes_pushed.saturating_sub(progress.consumed.load(Ordering::Relaxed));
+                    max = max.max(size);
+                    sum += size;
+                    let lag = lanes
+                        .iter()
+                        .zip(progress.next.iter())
+                        .map(|(lane, next)| {
+                            lane.tail
+                                .wrapping_add(1)
+                                .saturating_sub(next.load(Ordering::Relaxed))
+                        })
+                        .sum::<u64>();
+                    lag_max = lag_max.max(lag);
+                    true
+                }
+                None => false,
+            });
+            gauge!(&self.recorder, metrics::CLIENT_SEND_QUEUE, "transport" => *transport, "stat" => "max")
+                .set(max as f64);
+            gauge!(&self.recorder, metrics::CLIENT_SEND_QUEUE, "transport" => *transport, "stat" => "sum")
+                .set(sum as f64);
+            gauge!(&self.recorder, metrics::CLIENT_LAG_MESSAGES, "transport" => *transport)
+                .set(lag_max as f64);
+        }
+    }
+
+    fn inc_dropped(&self, state: &mut State, notification: PluginNotification, reason: DropReason) {
+        state.stats.dropped[notification as usize] += 1;
+        self.metrics.dropped[notification as usize][reason as usize].increment(1);
+    }
+
+    /// Counters accumulated over the channel lifetime
+    pub fn stats(&self) -> ChannelStats {
+        self.shared.state_lock().stats
+    }
+
+    /// Number and range of slots of slot statuses sent to new live receivers
+    pub fn backfill_stats(&self) -> BackfillStats {
+        let state = self.shared.state_lock();
+        BackfillStats {
+            statuses: state.slot_statuses.len(),
+            first_slot: state
+                .slot_statuses
+                .iter()
+                .map(|(_pos, slot, _data)| *slot)
+                .min(),
+            last_slot: state
+                .slot_statuses
+                .iter()
+                .map(|(_pos, slot, _data)| *slot)
+                .max(),
         }
+    }
+
+    /// Bytes pushed to the channel but not yet read by connected receivers
+    pub fn pending_bytes(&self) -> u64 {
+        let state = self.shared.state_lock();
+        state
+            .queues
+            .iter()
+            .flat_map(|(_transport, queues)| queues.iter())
+            .filter_map(Weak::upgrade)
+            .map(|progress| {
+                state
+                    .bytes_pushed
+                    .saturating_sub(progress.consumed.load(Ordering::Relaxed))
+            })
+            .sum()
+    }
 
+    /// How long channel keeps evicting not finalized slots
+    pub fn evicting_duration(&self) -> Option<Duration> {
+        self.shared
+            .state_lock()
+            .evicting_since
+            .map(|since| since.elapsed())
+    }
+
+    pub fn close(&self) {
         let mut state = self.shared.state_lock();
+        for item in state.lanes.iter().flat_map(|lane| lane.ring.items.iter()) {
+            mutex_lock(item).closed = true;
+        }
         for waker in state.wakers.drain(..) {
             waker.wake();
         }
     }
 }
 
-impl Subscribe for Sender {
-    fn subscribe(
+impl Sender {
+    /// Subscribe on live messages of given notification types only
+    pub fn subscribe_notifications(&self, notifications: &[PluginNotification]) -> Receiver {
+        let mut enabled = [false; PluginNotification::ALL.len()];
+        for notification in notifications {
+            enabled[*notification as usize] = true;
+        }
+        self.subscribe_receiver(
+            None,
+            false,
+            enabled,
+            SubscribeAccountsFilter::default(),
+            None,
+        )
+        .expect("replay is not requested")
+    }
+
+    fn subscribe_receiver(
         &self,
         replay_from_slot: Option<Slot>,
-        filter: Option<RichatFilter>,
-    ) -> Result<RecvStream, SubscribeError> {
+        backfill: bool,
+        mut notifications: [bool; PluginNotification::ALL.len()],
+        accounts_filter: SubscribeAccountsFilter,
+        expression: Option<FilterExpression>,
+    ) -> Result<Receiver, SubscribeError> {
         let shared = Arc::clone(&self.shared);
+        if let Some(filter) = &self.filter {
+            for (enabled, allowed) in notifications.iter_mut().zip(filter.notifications) {
+                *enabled &= allowed;
+            }
+        }
 
-        let state = shared.state_lock();
+        let mut state = shared.state_lock();
         let next = match replay_from_slot {
-            Some(slot) => state.slots.get(&slot).map(|s| s.head).ok_or_else(|| {
-                match state.slots.first_key_value() {
-                    Some((key, _value)) => SubscribeError::SlotNotAvailable {
-                        first_available: *key,
-                    },
-                    None => SubscribeError::NotInitialized,
-                }
-            })?,
-            None => state.tail,
+            Some(slot) => state.position_from_slot(slot)?,
+            None => state.live_positions(),
+        };
+        // slot statuses pushed before the last message, live receiver starts from it
+        let backfill = if backfill
+            && replay_from_slot.is_none()
+            && notifications[PluginNotification::Slot as usize]
+        {
+            state
+                .slot_statuses
+                .iter()
+                .filter(|(index, _slot, _data)| index + 1 < state.index)
+                .map(|(_index, _slot, data)| Arc::clone(data))
+                .collect()
+        } else {
+            VecDeque::new()
         };
+        let rings = state
+            .lanes
+            .iter()
+            .map(|lane| Arc::clone(&lane.ring))
+            .collect::<Vec<_>>();
+        let consumed = rings
+            .iter()
+            .zip(next.iter())
+            .filter_map(|(ring, pos)| {
+                let item = ring.item(*pos);
+                item.data
+                    .as_ref()
+                    .filter(|_| item.pos == *pos)
+                    .map(|_| item.offset)
+            })
+            .min()
+            .unwrap_or(state.bytes_pushed);
+        let progress = Arc::new(ReceiverProgress {
+            consumed: AtomicU64::new(consumed),
+            next: next.iter().map(|pos| AtomicU64::new(*pos)).collect(),
+        });
+        match state
+            .queues
+            .iter_mut()
+            .find(|(transport, _)| *transport == self.transport)
+        {
+            Some((_, queues)) => queues.push(Arc::downgrade(&progress)),
+            None => state
+                .queues
+                .push((self.transport, vec![Arc::downgrade(&progress)])),
+        }
         drop(state);
 
-        let filter = filter.unwrap_or_default();
-
         Ok(Receiver {
             shared,
+            rings,
             next,
+            progress,
+            backfill,
             finished: false,
-            enable_notifications_accounts: !filter.disable_accounts,
-            enable_notifications_transactions: !filter.disable_transactions,
-            enable_notifications_entries: !filter.disable_entries,
-        }
-        .boxed())
+            notifications,
+            accounts_filter,
+            expression,
+            filter: self.filter.clone(),
+            message_age: PluginNotification::ALL.map(|notification| {
+                histogram!(
+                    &self.recorder,
+                    metrics::MESSAGE_AGE_SECONDS,
+                    "transport" => self.transport,
+                    "notification" => notification.as_str()
+                )
+            }),
+        })
+    }
+}
+
+impl Subscribe for Sender {
+    fn subscribe(
+        &self,
+        replay_from_slot: Option<Slot>,
+        filter: Option<RichatFilter>,
+    ) -> Result<RecvStream, SubscribeError> {
+        let filter = filter.unwrap_or_default();
+        let expression = filter
+            .expression
+            .as_deref()
+            .map(FilterExpression::compile)
+            .transpose()?;
+        self.subscribe_receiver(
+            replay_from_slot,
+            true,
+            Self::filter_notifications(&filter),
+            SubscribeAccountsFilter::new(&filter),
+            expression,
+        )
+        .map(|receiver| receiver.boxed())
     }
 }
 
 #[derive(Debug)]
 pub struct Receiver {
     shared: Arc<Shared>,
-    next: u64,
+    /// Current ring of every lane, reloaded from the state once items are moved to a bigger one
+    rings: Vec<Arc<Ring>>,
+    /// Position of the next message in every lane
+    next: Vec<u64>,
+    progress: Arc<ReceiverProgress>,
+    /// Slot statuses sent before live messages
+    backfill: VecDeque<RecvItem>,
     finished: bool,
-    enable_notifications_accounts: bool,
-    enable_notifications_transactions: bool,
-    enable_notifications_entries: bool,
+    notifications: [bool; PluginNotification::ALL.len()],
+    accounts_filter: SubscribeAccountsFilter,
+    expression: Option<FilterExpression>,
+    filter: Option<Arc<EndpointFilter>>,
+    /// Age of delivered messages by notification type, created once on subscribe
+    message_age: [Histogram; PluginNotification::ALL.len()],
 }
 
 impl Receiver {
@@ -280,49 +921,184 @@ impl Receiver {
     }
 
     pub fn recv_ref(&mut self, waker: &Waker) -> Result<Option<RecvItem>, RecvError> {
+        if let Some(item) = self.backfill.pop_front() {
+            return Ok(Some(item));
+        }
+
         loop {
+            let Some(lane) = self.next_lane()? else {
+                if self.register_waker(waker)? {
+                    continue;
+                }
+                return Ok(None);
+            };
+
             // read item with next value
-            let idx = self.shared.get_idx(self.next);
-            let mut item = self.shared.buffer_idx(idx);
+            let pos = self.next[lane];
+            let item = self.rings[lane].item(pos);
             if item.closed {
                 return Err(RecvError::Closed);
             }
-
-            if item.pos != self.next {
+            if item.moved {
+                drop(item);
+                self.reload_ring(lane);
+                continue;
+            }
+            if item.pos != pos {
+                if item.pos > pos {
+                    return Err(RecvError::Lagged);
+                }
                 // release lock before attempting to acquire state
                 drop(item);
+                if self.register_waker(waker)? {
+                    continue;
+                }
+                return Ok(None);
+            }
 
-                // acquire state to store waker
-                let mut state = self.shared.state_lock();
-
-                // make sure that position did not changed
-                item = self.shared.buffer_idx(idx);
-                if item.closed {
-                    return Err(RecvError::Closed);
+            self.next[lane] = pos.wrapping_add(1);
+            let slot = item.slot;
+            let account = item.account;
+            let fields = item.fields;
+            let pushed_at = item.pushed_at;
+            let offset = item.offset;
+            let data = item.data.clone();
+            // release lock before attempting to acquire state
+            drop(item);
+            let (plugin_notification, item) = data.ok_or(RecvError::Lagged)?;
+            self.progress
+                .consumed
+                .store(offset + item.len() as u64, Ordering::Relaxed);
+            self.progress.next[lane].store(self.next[lane], Ordering::Relaxed);
+            if self.shared.block_on_overflow {
+                // pairs with the fence in `Sender::wait_space`
+                atomic::fence(Ordering::SeqCst);
+                if self.shared.space_waiters.load(Ordering::Relaxed) > 0 {
+                    let _state = self.shared.state_lock();
+                    self.shared.space.notify_all();
                 }
-                if item.pos != self.next {
-                    return if item.pos < self.next {
-                        state.wakers.push(waker.clone());
-                        Ok(None)
-                    } else {
-                        Err(RecvError::Lagged)
-                    };
+            }
+            if !self.notifications[plugin_notification as usize] {
+                continue;
+            }
+            if plugin_notification == PluginNotification::Account
+                && !self.accounts_filter.is_empty()
+                && !account
+                    .is_some_and(|(pubkey, owner)| self.accounts_filter.is_match(&pubkey, &owner))
+            {
+                continue;
+            }
+            if let Some(expression) = &self.expression {
+                if matches!(
+                    plugin_notification,
+                    PluginNotification::Account | PluginNotification::Transaction
+                ) && !expression.is_match(
+                    slot,
+                    account.as_ref().map(|(pubkey, owner)| (pubkey, owner)),
+                    &fields,
+                ) {
+                    continue;
                 }
             }
-
-            self.next = self.next.wrapping_add(1);
-            let (plugin_notification, item) = item.data.clone().ok_or(RecvError::Lagged)?;
-            match plugin_notification {
-                PluginNotification::Account if !self.enable_notifications_accounts => continue,
-                PluginNotification::Transaction if !self.enable_notifications_transactions => {
+            if let Some(filter) = &self.filter {
+                if plugin_notification == PluginNotification::Account
+                    && !filter.accounts_filter.is_empty()
+                    && !account.is_some_and(|(pubkey, owner)| {
+                        filter.accounts_filter.is_match(&pubkey, &owner)
+                    })
+                {
                     continue;
                 }
-                PluginNotification::Entry if !self.enable_notifications_entries => continue,
-                _ => {}
             }
+            self.message_age[plugin_notification as usize]
+                .record(duration_to_seconds(pushed_at.elapsed()));
             break Ok(Some(item));
         }
     }
+
+    /// Lane of the next message, messages are read in order of push, so it's the lane with the
+    /// lowest index of the next message
+    fn next_lane(&mut self) -> Result<Option<usize>, RecvError> {
+        if self.rings.len() == 1 {
+            return Ok(Some(0));
+        }
+
+        let mut selected = None;
+        let mut pending = SmallVec::<[usize; LANES_MAX]>::new();
+        for lane in 0..self.rings.len() {
+            match self.peek(lane)? {
+                Some(index) if selected.is_none_or(|(_lane, selected)| index < selected) => {
+                    selected = Some((lane, index));
+                }
+                Some(_index) => {}
+                None => pending.push(lane),
+            }
+        }
+
+        // message with lower index could be pushed to empty lane after the check, it's visible
+        // already once a message pushed after it is seen
+        if let Some((lane, index)) = &mut selected {
+            for pending in pending {
+                if let Some(pending_index) = self.peek(pending)? {
+                    if pending_index < *index {
+                        *lane = pending;
+                        *index = pending_index;
+                    }
+                }
+            }
+        }
+
+        Ok(selected.map(|(lane, _index)| lane))
+    }
+
+    /// Index of the next message of the lane, `None` if it's not pushed yet
+    fn peek(&mut self, lane: usize) -> Result<Option<u64>, RecvError> {
+        loop {
+            let item = self.rings[lane].item(self.next[lane]);
+            if item.closed {
+                return Err(RecvError::Closed);
+            }
+            if item.moved {
+                drop(item);
+                self.reload_ring(lane);
+                continue;
+            }
+            return match item.pos.cmp(&self.next[lane]) {
+                cmp::Ordering::Equal => Ok(Some(item.index)),
+                cmp::Ordering::Less => Ok(None),
+                cmp::Ordering::Greater => Err(RecvError::Lagged),
+            };
+        }
+    }
+
+    fn reload_ring(&mut self, lane: usize) {
+        self.rings[lane] = Arc::clone(&self.shared.state_lock().lanes[lane].ring);
+    }
+
+    /// Store waker if no lane has the next message, returns `true` if message was pushed
+    /// meanwhile, positions are checked under the state lock because messages are pushed under it
+    fn register_waker(&mut self, waker: &Waker) -> Result<bool, RecvError> {
+        let mut state = self.shared.state_lock();
+        let mut pushed = false;
+        for (lane, ring) in self.rings.iter_mut().enumerate() {
+            if !Arc::ptr_eq(ring, &state.lanes[lane].ring) {
+                *ring = Arc::clone(&state.lanes[lane].ring);
+            }
+
+            let item = ring.item(self.next[lane]);
+            if item.closed {
+                return Err(RecvError::Closed);
+            }
+            if item.pos > self.next[lane] {
+                return Err(RecvError::Lagged);
+            }
+            pushed |= item.pos == self.next[lane];
+        }
+        if !pushed {
+            state.wakers.push(waker.clone());
+        }
+        Ok(pushed)
+    }
 }
 
 struct Recv<'a> {
@@ -370,54 +1146,1175 @@ impl Stream for Receiver {
     }
 }
 
+/// Filter of the server, applied in addition to filter of subscription
+#[derive(Debug)]
+struct EndpointFilter {
+    notifications: [bool; PluginNotification::ALL.len()],
+    accounts_filter: SubscribeAccountsFilter,
+}
+
 struct Shared {
     state: Mutex<State>,
-    mask: u64,
-    buffer: Box<[Mutex<Item>]>,
+    space: Condvar,
+    /// Number of senders waiting for space, receivers notify only if someone waits
+    space_waiters: AtomicUsize,
+    buffer_pool: Option<BufferPool>,
+    block_on_overflow: bool,
+    /// Lane of every notification type
+    notification_lanes: [usize; PluginNotification::ALL.len()],
 }
 
 impl fmt::Debug for Shared {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Shared").field("mask", &self.mask).finish()
+        f.debug_struct("Shared")
+            .field("notification_lanes", &self.notification_lanes)
+            .finish()
     }
 }
 
 impl Shared {
     #[inline]
-    const fn get_idx(&self, pos: u64) -> usize {
-        (pos & self.mask) as usize
+    fn state_lock(&self) -> MutexGuard<'_, State> {
+        mutex_lock(&self.state)
+    }
+}
+
+/// Items indexed by position, size is a power of 2
+struct Ring {
+    mask: u64,
+    items: Box<[Mutex<Item>]>,
+}
+
+impl fmt::Debug for Ring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ring").field("mask", &self.mask).finish()
+    }
+}
+
+impl Ring {
+    /// Empty items, position of item should be less than any position read from it later
+    fn new(capacity: usize, pos: impl Fn(usize) -> u64) -> Self {
+        let items = (0..capacity)
+            .map(|idx| {
+                Mutex::new(Item {
+                    pos: pos(idx),
+                    index: 0,
+                    slot: 0,
+                    account: None,
+                    fields: MessageFields::None,
+                    data: None,
+                    offset: 0,
+                    pushed_at: Instant::now(),
+                    closed: false,
+                    moved: false,
+                })
+            })
+            .collect();
+        Self {
+            mask: (capacity - 1) as u64,
+            items,
+        }
     }
 
     #[inline]
-    fn state_lock(&self) -> MutexGuard<'_, State> {
-        mutex_lock(&self.state)
+    const fn capacity(&self) -> u64 {
+        self.mask + 1
     }
 
     #[inline]
-    fn buffer_idx(&self, idx: usize) -> MutexGuard<'_, Item> {
-        mutex_lock(&self.buffer[idx])
+    fn item(&self, pos: u64) -> MutexGuard<'_, Item> {
+        mutex_lock(&self.items[(pos & self.mask) as usize])
     }
 }
 
-struct State {
+/// Free buffers for encoded messages
+struct BufferPool {
+    buffers: ArrayQueue<Vec<u8>>,
+    max_buffer_size: usize,
+}
+
+impl BufferPool {
+    fn new(config: ConfigChannelBufferPool) -> Option<Self> {
+        (config.max_buffers > 0).then(|| Self {
+            buffers: ArrayQueue::new(config.max_buffers),
+            max_buffer_size: config.max_buffer_size,
+        })
+    }
+}
+
+/// Reason of dropped message, used as metric label
+#[derive(Debug, Clone, Copy)]
+enum DropReason {
+    Newest,
+    SlotLimit,
+    MaxMessages,
+    MaxBytes,
+}
+
+impl DropReason {
+    const ALL: [Self; 4] = [
+        Self::Newest,
+        Self::SlotLimit,
+        Self::MaxMessages,
+        Self::MaxBytes,
+    ];
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::SlotLimit => "slot_limit",
+            Self::MaxMessages => "max_messages",
+            Self::MaxBytes => "max_bytes",
+        }
+    }
+}
+
+thread_local! {
+    /// Compression context is reused by encoding threads, level is the same for all senders
+    static ZSTD_COMPRESSOR: RefCell<Option<(i32, zstd::bulk::Compressor<'static>)>> =
+        const { RefCell::new(None) };
+}
+
+fn compress_zstd(level: i32, raw: &[u8], mut buffer: Vec<u8>) -> io::Result<Vec<u8>> {
+    ZSTD_COMPRESSOR.with_borrow_mut(|compressor| {
+        if !matches!(compressor, Some((compressor_level, _)) if *compressor_level == level) {
+            *compressor = Some((level, zstd::bulk::Compressor::new(level)?));
+        }
+        let (_level, compressor) = compressor.as_mut().expect("initialized");
+        buffer.reserve_exact(zstd::zstd_safe::compress_bound(raw.len()));
+        compressor.compress_to_buffer(raw, &mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+fn compress_lz4(raw: &[u8], buffer: Vec<u8>) -> io::Result<Vec<u8>> {
+    let mut compressor = lz4_flex::frame::FrameEncoder::new(buffer);
+    compressor.write_all(raw)?;
+    compressor.finish().map_err(Into::into)
+}
+
+/// Metrics updated on every push, created once to not look up them by labels under the lock
+#[derive(Debug)]
+struct SenderMetrics {
+    messages: [Counter; PluginNotification::ALL.len()],
+    dropped: [[Counter; DropReason::ALL.len()]; PluginNotification::ALL.len()],
+    notification_messages: [Gauge; PluginNotification::ALL.len()],
+    notification_bytes: [Gauge; PluginNotification::ALL.len()],
+    channel_messages: Gauge,
+    channel_bytes: Gauge,
+    compress_errors: Counter,
+}
+
+impl SenderMetrics {
+    fn new(recorder: &MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>) -> Self {
+        Self {
+            messages: PluginNotification::ALL.map(|notification| {
+                counter!(recorder, metrics::MESSAGES_TOTAL, "notification" => notification.as_str())
+            }),
+            dropped: PluginNotification::ALL.map(|notification| {
+                DropReason::ALL.map(|reason| {
+                    counter!(
+                        recorder,
+                        metrics::CHANNEL_DROPPED_TOTAL,
+                        "notification" => notification.as_str(),
+                        "reason" => reason.as_str()
+                    )
+                })
+            }),
+            notification_messages: PluginNotification::ALL.map(|notification| {
+                gauge!(recorder, metrics::CHANNEL_NOTIFICATION_MESSAGES_TOTAL, "notification" => notification.as_str())
+            }),
+            notification_bytes: PluginNotification::ALL.map(|notification| {
+                gauge!(recorder, metrics::CHANNEL_NOTIFICATION_BYTES_TOTAL, "notification" => notification.as_str())
+            }),
+            channel_messages: gauge!(recorder, metrics::CHANNEL_MESSAGES_TOTAL),
+            channel_bytes: gauge!(recorder, metrics::CHANNEL_BYTES_TOTAL),
+            compress_errors: counter!(recorder, metrics::CHANNEL_COMPRESS_ERRORS_TOTAL),
+        }
+    }
+}
+
+/// Receiver position, used for queue metrics
+#[derive(Debug)]
+struct ReceiverProgress {
+    /// Total pushed bytes at the moment of the last read message
+    consumed: AtomicU64,
+    /// Position of the next message to read in every lane
+    next: Box<[AtomicU64]>,
+}
+
+/// Ring with own limits, notification types with configured limits are kept in own lanes,
+/// so bursts of other types do not evict them
+struct Lane {
+    ring: Arc<Ring>,
     head: u64,
     tail: u64,
-    slots: BTreeMap<Slot, SlotInfo>,
     bytes_total: usize,
     bytes_max: usize,
+    /// Number of messages is not limited, ring is replaced by a bigger one instead of eviction
+    grow_ring: bool,
+}
+
+impl Lane {
+    fn new(max_messages: usize, max_bytes: usize) -> Self {
+        // without limit of messages the ring grows on overflow, bounded only by bytes
+        let grow_ring = max_messages == 0;
+        let max_messages = if grow_ring {
+            RING_INITIAL_CAPACITY
+        } else {
+            max_messages.next_power_of_two()
+        };
+        Self {
+            ring: Arc::new(Ring::new(max_messages, |idx| idx as u64)),
+            head: max_messages as u64 + 1,
+            tail: max_messages as u64,
+            bytes_total: 0,
+            bytes_max: if max_bytes == 0 {
+                usize::MAX
+            } else {
+                max_bytes
+            },
+            grow_ring,
+        }
+    }
+
+    const fn len(&self) -> u64 {
+        self.tail.wrapping_add(1).wrapping_sub(self.head)
+    }
+
+    /// Ring is never full if it grows
+    #[allow(clippy::missing_const_for_fn)] // false positive, `Arc` deref is not const
+    fn is_full(&self) -> bool {
+        self.len() >= self.ring.capacity()
+    }
+
+    fn is_overflow(&self, size: usize) -> bool {
+        (!self.grow_ring && self.is_full()) || self.bytes_total + size >= self.bytes_max
+    }
+
+    /// Remove the oldest message, item is updated by caller
+    const fn remove(&mut self, size: usize) {
+        self.head = self.head.wrapping_add(1);
+        self.bytes_total -= size;
+    }
+
+    /// Replace ring by twice bigger one, buffered messages keep their positions, receivers
+    /// reload ring once they see moved item
+    fn grow_ring(&mut self) {
+        // not buffered positions are lagged for receivers behind the head and not yet pushed
+        // for receivers after the tail
+        let empty_pos = self.head.wrapping_sub(1);
+        let ring = Ring::new(self.ring.items.len() * 2, |_idx| empty_pos);
+        for item in self.ring.items.iter() {
+            let mut item = mutex_lock(item);
+            if item.data.is_some() {
+                let mut moved = ring.item(item.pos);
+                moved.pos = item.pos;
+                moved.index = item.index;
+                moved.slot = item.slot;
+                moved.account = item.account.take();
+                moved.fields = item.fields;
+                moved.data = item.data.take();
+                moved.offset = item.offset;
+                moved.pushed_at = item.pushed_at;
+            }
+            item.moved = true;
+        }
+        self.ring = Arc::new(ring);
+    }
+}
+
+struct State {
+    lanes: Vec<Lane>,
+    /// Lane of the last pushed message, live receivers start from it
+    last_lane: Option<usize>,
+    /// Index of the next message over all lanes, receivers read lanes in order of it
+    index: u64,
+    slots: BTreeMap<Slot, SlotInfo>,
+    max_slot: Slot,
+    /// Total size of all pushed messages
+    bytes_pushed: u64,
+    /// Sequence number of the next message, if sequence is enabled
+    sequence: u64,
+    usage: [Usage; PluginNotification::ALL.len()],
+    evicting_since: Option<Instant>,
     wakers: Vec<Waker>,
+    /// Progress of receivers grouped by transport
+    queues: Vec<(&'static str, Vec<Weak<ReceiverProgress>>)>,
+    /// Last slot status messages with indexes, sent to new live receivers before live messages
+    slot_statuses: VecDeque<(u64, Slot, RecvItem)>,
+    stats: ChannelStats,
+}
+
+impl State {
+    /// Number and size of buffered messages in all lanes
+    fn buffered(&self) -> (u64, usize) {
+        self.lanes.iter().fold((0, 0), |(messages, bytes), lane| {
+            (messages + lane.len(), bytes + lane.bytes_total)
+        })
+    }
+
+    /// Positions of the last pushed message and the next messages of other lanes
+    fn live_positions(&self) -> Vec<u64> {
+        self.lanes
+            .iter()
+            .enumerate()
+            .map(|(idx, lane)| {
+                if self.last_lane == Some(idx) {
+                    lane.tail
+                } else {
+                    lane.tail.wrapping_add(1)
+                }
+            })
+            .collect()
+    }
+
+    /// Positions of the first buffered message with `slot >= from_slot`, if all buffered
+    /// messages are older then replay starts from the tail (live messages)
+    fn position_from_slot(&self, from_slot: Slot) -> Result<Vec<u64>, SubscribeError> {
+        match self.slots.first_key_value() {
+            Some((first_available, _value)) if from_slot < *first_available => {
+                Err(SubscribeError::SlotNotAvailable {
+                    first_available: *first_available,
+                })
+            }
+            Some(_) => Ok(self
+                .slots
+                .range(from_slot..)
+                .next()
+                .map_or_else(|| self.live_positions(), |(_slot, info)| info.heads.clone())),
+            None => Err(SubscribeError::NotInitialized),
+        }
+    }
+
+    // used only with `newest` eviction, messages already read by all receivers can be evicted,
+    // receivers behind the head are lagged and do not hold messages
+    fn has_space_newest(&mut self, lane: usize, size: usize) -> bool {
+        let (head, tail) = (self.lanes[lane].head, self.lanes[lane].tail);
+        let mut unread = tail.wrapping_add(1);
+        for (_transport, queues) in self.queues.iter_mut() {
+            queues.retain(|progress| match progress.upgrade() {
+                Some(progress) => {
+                    let next = progress.next[lane].load(Ordering::Relaxed);
+                    if next >= head {
+                        unread = unread.min(next);
+                    }
+                    true
+                }
+                None => false,
+            });
+        }
+
+        let lane = &self.lanes[lane];
+        if !lane.grow_ring && lane.is_full() && unread == head {
+            return false;
+        }
+
+        let mut bytes_total = lane.bytes_total;
+        let mut pos = head;
+        while bytes_total + size >= lane.bytes_max {
+            if pos == unread {
+                return false;
+            }
+            if let Some((_notification, data)) = &lane.ring.item(pos).data {
+                bytes_total -= data.len();
+            }
+            pos = pos.wrapping_add(1);
+        }
+        true
+    }
+
+    // used only with `block` overflow, receivers behind the position are lagged already and
+    // do not block the sender
+    fn has_unread(&mut self, lane: usize, pos: u64) -> bool {
+        let mut unread = false;
+        for (_transport, queues) in self.queues.iter_mut() {
+            queues.retain(|progress| match progress.upgrade() {
+                Some(progress) => {
+                    unread |= progress.next[lane].load(Ordering::Relaxed) == pos;
+                    true
+                }
+                None => false,
+            });
+        }
+        unread
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ChannelStats {
+    /// Pushed messages by notification type, including dropped
+    pub pushed: [u64; PluginNotification::ALL.len()],
+    /// Dropped messages by notification type
+    pub dropped: [u64; PluginNotification::ALL.len()],
+    /// Max number of messages in channel
+    pub peak_messages: u64,
+    /// Max size of messages in channel
+    pub peak_bytes: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct BackfillStats {
+    /// Number of slot statuses kept for new receivers
+    pub statuses: usize,
+    pub first_slot: Option<Slot>,
+    pub last_slot: Option<Slot>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Usage {
+    messages: usize,
+    bytes: usize,
+}
+
+impl Usage {
+    const fn add(&mut self, bytes: usize) {
+        self.messages += 1;
+        self.bytes += bytes;
+    }
+
+    const fn remove(&mut self, bytes: usize) {
+        self.messages -= 1;
+        self.bytes -= bytes;
+    }
 }
 
 struct SlotInfo {
-    head: u64,
+    /// Position of the first message of the slot in every lane
+    heads: Vec<u64>,
     parent_slot: Option<Slot>,
     confirmed: bool,
     finalized: bool,
+    /// Number of pushed accounts, transactions and entries, limited by `max_messages_per_slot`
+    messages: usize,
 }
 
 struct Item {
     pos: u64,
+    /// Index of message over all lanes
+    index: u64,
     slot: Slot,
+    /// Account pubkey and owner, used by subscription filters
+    account: Option<(Pubkey, Pubkey)>,
+    /// Values of account or transaction, used by filter expression of subscription
+    fields: MessageFields,
     data: Option<(PluginNotification, RecvItem)>,
+    /// Total size of messages pushed before this one
+    offset: u64,
+    /// Time of push, used for message age metric
+    pushed_at: Instant,
     closed: bool,
+    /// Item is in the replaced ring, receiver should read it from the current one
+    moved: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{MessageMeta, RING_INITIAL_CAPACITY, Sender},
+        crate::{
+            config::{
+                ConfigChannel, ConfigChannelEviction, ConfigChannelLimit, ConfigChannelLimits,
+                ConfigChannelOverflow,
+            },
+            plugin::PluginNotification,
+            protobuf::{ProtobufEncoder, ProtobufMessage, fixtures::generate_accounts},
+        },
+        agave_geyser_plugin_interface::geyser_plugin_interface::{
+            ReplicaAccountInfoV3, SlotStatus,
+        },
+        futures::{TryStreamExt, executor::block_on, stream::StreamExt},
+        prost::Message,
+        richat_metrics::MaybeRecorder,
+        richat_proto::{
+            geyser::SubscribeUpdate,
+            richat::{RichatFilter, SubscribeUpdateCompressed, subscribe_update_compressed::Data},
+        },
+        richat_shared::transports::{
+            RecvStream, Subscribe, SubscribeError,
+            expression::{ExpressionError, FilterExpression, MessageFields},
+        },
+        solana_sdk::{clock::Slot, pubkey::Pubkey},
+        std::{
+            io::Read,
+            sync::Arc,
+            thread,
+            time::{Duration, Instant},
+        },
+    };
+
+    fn create_sender(
+        max_messages: usize,
+        max_bytes: usize,
+        eviction: ConfigChannelEviction,
+    ) -> Sender {
+        let config = ConfigChannel {
+            max_messages,
+            max_bytes,
+            eviction,
+            ..Default::default()
+        };
+        Sender::new(config, Arc::new(MaybeRecorder::Noop))
+    }
+
+    /// Encoded message stub, ends with zero sequence field if size is enough for it
+    fn encoded(fill: u8, size: usize) -> Vec<u8> {
+        let mut data = vec![fill; size];
+        if let Some(offset) = size.checked_sub(10) {
+            data[offset..offset + 2].copy_from_slice(&[0xc1, 0x06]);
+            data[offset + 2..].fill(0);
+        }
+        data
+    }
+
+    fn push_entries(sender: &Sender, slots: impl Iterator<Item = Slot>, size: usize) {
+        for slot in slots {
+            let meta = MessageMeta {
+                notification: PluginNotification::Entry,
+                slot,
+                slot_status: None,
+                account: None,
+                fields: MessageFields::None,
+            };
+            sender.push_encoded(meta, encoded(0, size));
+        }
+    }
+
+    fn buffered_slots(sender: &Sender) -> Vec<Slot> {
+        buffered_lane_slots(sender, 0)
+    }
+
+    fn buffered_lane_slots(sender: &Sender, lane: usize) -> Vec<Slot> {
+        let state = sender.shared.state_lock();
+        let lane = &state.lanes[lane];
+        (lane.head..=lane.tail)
+            .map(|pos| lane.ring.item(pos).slot)
+            .collect()
+    }
+
+    fn push_block_meta(sender: &Sender, slot: Slot, size: usize) {
+        let meta = MessageMeta {
+            notification: PluginNotification::BlockMeta,
+            slot,
+            slot_status: None,
+            account: None,
+            fields: MessageFields::None,
+        };
+        sender.push_encoded(meta, encoded(slot as u8, size));
+    }
+
+    #[test]
+    fn test_eviction_oldest_max_messages() {
+        let sender = create_sender(4, usize::MAX, ConfigChannelEviction::Oldest);
+        push_entries(&sender, 0..10, 1);
+        assert_eq!(buffered_slots(&sender), vec![6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_eviction_newest_max_messages() {
+        let sender = create_sender(4, usize::MAX, ConfigChannelEviction::Newest);
+        push_entries(&sender, 0..1, 1);
+        let _stream = sender.subscribe(None, None).expect("subscribe");
+        push_entries(&sender, 1..10, 1);
+        assert_eq!(buffered_slots(&sender), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_eviction_newest_read_messages() {
+        let sender = create_sender(4, usize::MAX, ConfigChannelEviction::Newest);
+        push_entries(&sender, 0..1, 1);
+        let mut stream = sender.subscribe(None, None).expect("subscribe");
+        push_entries(&sender, 1..10, 1);
+        assert_eq!(buffered_slots(&sender), vec![0, 1, 2, 3]);
+
+        // read messages are evicted for new messages
+        let received = block_on(stream.by_ref().take(2).try_collect::<Vec<_>>()).expect("received");
+        assert_eq!(received.len(), 2);
+        push_entries(&sender, 10..20, 1);
+        assert_eq!(buffered_slots(&sender), vec![2, 3, 10, 11]);
+
+        // without receivers everything can be evicted
+        drop(stream);
+        push_entries(&sender, 20..30, 1);
+        assert_eq!(buffered_slots(&sender), vec![26, 27, 28, 29]);
+    }
+
+    #[test]
+    fn test_eviction_oldest_max_bytes() {
+        let sender = create_sender(16, 35, ConfigChannelEviction::Oldest);
+        push_entries(&sender, 0..10, 10);
+        assert_eq!(buffered_slots(&sender), vec![7, 8, 9]);
+    }
+
+    #[test]
+    fn test_eviction_newest_max_bytes() {
+        let sender = create_sender(16, 35, ConfigChannelEviction::Newest);
+        push_entries(&sender, 0..1, 10);
+        let _stream = sender.subscribe(None, None).expect("subscribe");
+        push_entries(&sender, 1..10, 10);
+        assert_eq!(buffered_slots(&sender), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_eviction_unlimited_messages() {
+        let sender = create_sender(0, 35, ConfigChannelEviction::Oldest);
+        push_entries(&sender, 0..10, 10);
+        assert_eq!(buffered_slots(&sender), vec![7, 8, 9]);
+    }
+
+    #[test]
+    fn test_eviction_unlimited_bytes() {
+        let sender = create_sender(4, 0, ConfigChannelEviction::Oldest);
+        push_entries(&sender, 0..10, 1_000);
+        assert_eq!(buffered_slots(&sender), vec![6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_overflow_block() {
+        let config = ConfigChannel {
+            max_messages: 4,
+            overflow: ConfigChannelOverflow::Block,
+            overflow_timeout: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        push_entries(&sender, 0..1, 1);
+        let stream = sender.subscribe(None, None).expect("subscribe");
+
+        // sender waits for the reader instead of dropping messages
+        let received = thread::scope(|scope| {
+            let reader = scope
+                .spawn(|| block_on(stream.take(100).try_collect::<Vec<_>>()).expect("received"));
+            push_entries(&sender, 1..100, 1);
+            reader.join().expect("reader")
+        });
+        // receiver never lagged, otherwise stream would be finished with an error
+        assert_eq!(received.len(), 100);
+    }
+
+    #[test]
+    fn test_overflow_block_wakeup() {
+        let timeout = Duration::from_secs(60);
+        let config = ConfigChannel {
+            max_messages: 4,
+            overflow: ConfigChannelOverflow::Block,
+            overflow_timeout: timeout,
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        push_entries(&sender, 0..1, 1);
+        let mut stream = sender.subscribe(None, None).expect("subscribe");
+        push_entries(&sender, 1..4, 1);
+
+        // blocked sender is woken up by the receiver, not by the timeout
+        let delay = Duration::from_millis(100);
+        let started_at = Instant::now();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(delay);
+                block_on(stream.next()).expect("item").expect("received");
+            });
+            push_entries(&sender, 4..5, 1);
+        });
+        let elapsed = started_at.elapsed();
+        assert!(elapsed >= delay && elapsed < timeout / 10, "{elapsed:?}");
+        assert_eq!(buffered_slots(&sender), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_overflow_block_lagged_receiver() {
+        let timeout = Duration::from_millis(200);
+        let config = ConfigChannel {
+            max_messages: 4,
+            overflow: ConfigChannelOverflow::Block,
+            overflow_timeout: timeout,
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        push_entries(&sender, 0..1, 1);
+        let _stream = sender.subscribe(None, None).expect("subscribe");
+
+        // receiver does not read, so the first push over the limit waits for timeout
+        push_entries(&sender, 1..5, 1);
+        assert_eq!(buffered_slots(&sender), vec![1, 2, 3, 4]);
+
+        // receiver is lagged now, pushes do not wait for it anymore
+        let started_at = Instant::now();
+        push_entries(&sender, 5..10, 1);
+        assert!(started_at.elapsed() < timeout);
+        assert_eq!(buffered_slots(&sender), vec![6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_limits_own_lane() {
+        let config = ConfigChannel {
+            max_messages: 8,
+            limits: ConfigChannelLimits {
+                block_meta: Some(ConfigChannelLimit {
+                    max_messages: 2,
+                    max_bytes: 0,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        let push_entry = |slot: Slot| {
+            let meta = MessageMeta {
+                notification: PluginNotification::Entry,
+                slot,
+                slot_status: None,
+                account: None,
+                fields: MessageFields::None,
+            };
+            sender.push_encoded(meta, vec![slot as u8]);
+        };
+        push_entry(0);
+        let stream = sender.subscribe(None, None).expect("subscribe");
+
+        // messages of both lanes are received in order of push
+        push_block_meta(&sender, 1, 1);
+        for slot in 2..6 {
+            push_entry(slot);
+        }
+        push_block_meta(&sender, 6, 1);
+        let received = block_on(stream.take(7).try_collect::<Vec<_>>()).expect("received");
+        let received = received.iter().map(|data| data[0]).collect::<Vec<_>>();
+        assert_eq!(received, vec![0, 1, 2, 3, 4, 5, 6]);
+
+        // flood of entries does not evict block meta
+        for slot in 10..1_000 {
+            push_entry(slot);
+        }
+        assert_eq!(buffered_lane_slots(&sender, 1), vec![1, 6]);
+        assert_eq!(buffered_slots(&sender), (992..1_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_limits_concurrent_pushes() {
+        let config = ConfigChannel {
+            max_messages: 4_096,
+            sequence: true,
+            limits: ConfigChannelLimits {
+                block_meta: Some(ConfigChannelLimit {
+                    max_messages: 4_096,
+                    max_bytes: 0,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        push_entries(&sender, 0..1, 16);
+        let stream = sender.subscribe(None, None).expect("subscribe");
+
+        // receiver reads concurrently with pushes to both lanes, sequence follows push order
+        let received = thread::scope(|scope| {
+            let reader = scope
+                .spawn(|| block_on(stream.take(2_001).try_collect::<Vec<_>>()).expect("received"));
+            for _ in 0..2 {
+                scope.spawn(|| push_entries(&sender, 0..500, 16));
+                scope.spawn(|| {
+                    for slot in 0..500 {
+                        push_block_meta(&sender, slot, 16);
+                    }
+                });
+            }
+            reader.join().expect("reader")
+        });
+        let sequences = received
+            .iter()
+            .map(|data| u64::from_le_bytes(data[data.len() - 8..].try_into().expect("8 bytes")))
+            .collect::<Vec<_>>();
+        assert_eq!(sequences, (0..2_001).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_ring_grow() {
+        let sender = create_sender(0, usize::MAX, ConfigChannelEviction::Oldest);
+        push_entries(&sender, 0..1, 1);
+        let stream = sender.subscribe(None, None).expect("subscribe");
+
+        // receiver reads concurrently with pushes, so it sees items moved to the bigger ring
+        let total = RING_INITIAL_CAPACITY * 4;
+        let received = thread::scope(|scope| {
+            let reader = scope.spawn(|| {
+                block_on(stream.take(total + 1).try_collect::<Vec<_>>()).expect("received")
+            });
+            push_entries(&sender, 1..=total as Slot, 8);
+            reader.join().expect("reader")
+        });
+        assert_eq!(received.len(), total + 1);
+        assert_eq!(
+            buffered_slots(&sender),
+            (0..=total as Slot).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_max_message_bytes() {
+        let config = ConfigChannel {
+            max_messages: 16,
+            max_message_bytes: Some(10),
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        push_entries(&sender, 0..2, 10);
+        push_entries(&sender, 2..4, 11);
+        push_entries(&sender, 4..5, 1);
+        assert_eq!(buffered_slots(&sender), vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn test_max_messages_per_slot() {
+        let config = ConfigChannel {
+            max_messages: 16,
+            max_messages_per_slot: Some(2),
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        push_entries(&sender, [0, 0, 0, 1, 0, 1].into_iter(), 1);
+        assert_eq!(buffered_slots(&sender), vec![0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_sequence_concurrent_pushes() {
+        let config = ConfigChannel {
+            max_messages: 4_096,
+            sequence: true,
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| push_entries(&sender, 0..500, 16));
+            }
+        });
+
+        let state = sender.shared.state_lock();
+        let lane = &state.lanes[0];
+        let sequences = (lane.head..=lane.tail)
+            .map(|pos| {
+                let item = lane.ring.item(pos);
+                let (_notification, data) = item.data.as_ref().expect("pushed message");
+                let sequence = data[data.len() - 8..].try_into().expect("8 bytes");
+                u64::from_le_bytes(sequence)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sequences, (0..2_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sequence_without_field() {
+        let config = ConfigChannel {
+            max_messages: 16,
+            sequence: true,
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        push_entries(&sender, 0..1, 8);
+        push_entries(&sender, 0..1, 16);
+        push_entries(&sender, 0..1, 9);
+        push_entries(&sender, 0..1, 16);
+
+        // short and foreign messages are pushed as is and do not take sequence numbers
+        let state = sender.shared.state_lock();
+        let lane = &state.lanes[0];
+        let received = (lane.head..=lane.tail)
+            .map(|pos| {
+                let item = lane.ring.item(pos);
+                let (_notification, data) = item.data.as_ref().expect("pushed message");
+                data.to_vec()
+            })
+            .collect::<Vec<_>>();
+        let mut expected = vec![encoded(0, 8), encoded(0, 16), encoded(0, 9), encoded(0, 16)];
+        expected[3][8..].copy_from_slice(&1u64.to_le_bytes());
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_receivers_same_order() {
+        let config = ConfigChannel {
+            max_messages: 4_096,
+            sequence: true,
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        // new receiver starts from the last pushed message
+        push_entries(&sender, 0..1, 16);
+        let grpc = sender
+            .with_transport("grpc")
+            .subscribe(None, None)
+            .expect("grpc subscribe");
+        let quic = sender
+            .with_transport("quic")
+            .subscribe(None, None)
+            .expect("quic subscribe");
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| push_entries(&sender, 0..500, 16));
+            }
+        });
+
+        // receivers read the same buffer by position, so order is set on push
+        let collect = |stream: RecvStream| {
+            block_on(stream.take(2_001).try_collect::<Vec<_>>()).expect("received messages")
+        };
+        let grpc = collect(grpc);
+        assert_eq!(grpc.len(), 2_001);
+        assert_eq!(grpc, collect(quic));
+    }
+
+    #[test]
+    fn test_backfill_slot_statuses() {
+        let config = ConfigChannel {
+            max_messages: 16,
+            backfill_slot_statuses: 2,
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        let push_status = |slot: Slot| {
+            let meta = MessageMeta {
+                notification: PluginNotification::Slot,
+                slot,
+                slot_status: Some((None, SlotStatus::Processed)),
+                account: None,
+                fields: MessageFields::None,
+            };
+            sender.push_encoded(meta, vec![slot as u8]);
+        };
+        for slot in 1..=3 {
+            push_status(slot);
+        }
+
+        // statuses pushed before the last message are sent first, last message is not duplicated
+        let stream = sender.subscribe(None, None).expect("subscribe");
+        push_status(4);
+        let received = block_on(stream.take(3).try_collect::<Vec<_>>()).expect("received");
+        let received = received.iter().map(|data| data[0]).collect::<Vec<_>>();
+        assert_eq!(received, vec![2, 3, 4]);
+
+        let stats = sender.backfill_stats();
+        assert_eq!(stats.statuses, 2);
+        assert_eq!((stats.first_slot, stats.last_slot), (Some(3), Some(4)));
+    }
+
+    #[test]
+    fn test_filter_expression() {
+        let sender = create_sender(16, usize::MAX, ConfigChannelEviction::Oldest);
+        push_entries(&sender, 0..1, 1);
+        let owner = Pubkey::new_unique();
+        let subscribe = |expression: &str| {
+            sender.subscribe(
+                None,
+                Some(RichatFilter {
+                    expression: Some(expression.to_owned()),
+                    ..Default::default()
+                }),
+            )
+        };
+        let stream = subscribe(&format!(
+            "(account.owner == {owner} and account.data[0] == 3) || transaction.fee > 5_000"
+        ))
+        .expect("valid expression");
+
+        let push = |id: u8, account: Option<(Pubkey, Pubkey)>, fields: MessageFields| {
+            let notification = if account.is_some() {
+                PluginNotification::Account
+            } else {
+                PluginNotification::Transaction
+            };
+            let meta = MessageMeta {
+                notification,
+                slot: 1,
+                slot_status: None,
+                account,
+                fields,
+            };
+            sender.push_encoded(meta, vec![id]);
+        };
+        let account = |data: &[u8]| MessageFields::account(1, data, None, false);
+        let transaction = |fee| MessageFields::Transaction {
+            fee: Some(fee),
+            is_vote: false,
+            failed: false,
+        };
+        push(0, Some((Pubkey::new_unique(), owner)), account(&[3, 1]));
+        push(1, Some((Pubkey::new_unique(), owner)), account(&[4]));
+        push(2, Some((Pubkey::new_unique(), owner)), account(&[]));
+        push(
+            3,
+            Some((Pubkey::new_unique(), Pubkey::new_unique())),
+            account(&[3]),
+        );
+        push(4, None, transaction(5_000));
+        push(5, None, transaction(5_001));
+        push(6, Some((Pubkey::new_unique(), owner)), account(&[3]));
+
+        // entries are not filtered by expression
+        let received = block_on(stream.take(4).try_collect::<Vec<_>>()).expect("received");
+        let received = received.iter().map(|data| data[0]).collect::<Vec<_>>();
+        assert_eq!(received, vec![0, 0, 5, 6]);
+
+        for (expression, error) in [
+            ("", ExpressionError::Empty),
+            ("account.lamports", ExpressionError::UnexpectedEnd),
+            (
+                "account.data[8] == 1",
+                ExpressionError::UnknownField {
+                    field: "account.data[8]".to_owned(),
+                },
+            ),
+            (
+                "account.owner > 1",
+                ExpressionError::InvalidOperator {
+                    field: "account.owner".to_owned(),
+                    op: ">",
+                },
+            ),
+            (
+                "transaction.is_vote == 1",
+                ExpressionError::InvalidValue {
+                    field: "transaction.is_vote".to_owned(),
+                    value: "1".to_owned(),
+                },
+            ),
+            ("(slot > 1", ExpressionError::UnexpectedEnd),
+        ] {
+            match subscribe(expression) {
+                Err(SubscribeError::InvalidExpression(actual)) => assert_eq!(actual, error),
+                _ => panic!("expression should be invalid: {expression}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_expression_sliced_account() {
+        let data_byte = FilterExpression::compile("account.data[0] < 256").expect("valid");
+        for item in generate_accounts() {
+            let (slot, replica) = item.to_replica();
+            let original_data_len = replica.data.len() + 42;
+            let replica = ReplicaAccountInfoV3 {
+                data: &replica.data[replica.data.len().min(1)..],
+                ..replica
+            };
+            let meta = MessageMeta::from(&ProtobufMessage::Account {
+                slot,
+                account: &replica,
+                original_data_len: Some(original_data_len),
+                is_startup: false,
+                owner_changed: false,
+            });
+            let account = meta.account.as_ref().map(|(pubkey, owner)| (pubkey, owner));
+            // data bytes of the slice are not matched, length is taken before slicing
+            assert!(!data_byte.is_match(slot, account, &meta.fields), "{item:?}");
+            let data_len = format!("account.data_len == {original_data_len}");
+            let data_len = FilterExpression::compile(&data_len).expect("valid");
+            assert!(data_len.is_match(slot, account, &meta.fields), "{item:?}");
+        }
+    }
+
+    #[test]
+    fn test_encode_compressed() {
+        let sender = create_sender(1_000, 1_000_000, ConfigChannelEviction::Oldest);
+        let messages = generate_accounts();
+        let messages = messages
+            .iter()
+            .map(|item| item.to_replica())
+            .collect::<Vec<_>>();
+        for (slot, replica) in &messages {
+            let message = ProtobufMessage::Account {
+                slot: *slot,
+                account: replica,
+                original_data_len: None,
+                is_startup: false,
+                owner_changed: false,
+            };
+            // messages are encoded with current time
+            let decode = |data: &[u8]| SubscribeUpdate {
+                created_at: None,
+                ..SubscribeUpdate::decode(data).unwrap()
+            };
+            let raw = decode(&sender.encode(&message, ProtobufEncoder::Raw));
+            // compressor of the thread is reused, output does not depend on previous messages
+            for _ in 0..2 {
+                let zstd = sender.encode(&message, ProtobufEncoder::Zstd);
+                match SubscribeUpdateCompressed::decode(zstd.as_slice())
+                    .unwrap()
+                    .data
+                {
+                    Some(Data::Zstd(data)) => {
+                        assert_eq!(decode(&zstd::decode_all(data.as_slice()).unwrap()), raw)
+                    }
+                    data => panic!("unexpected data: {data:?}"),
+                }
+            }
+            let lz4 = sender.encode(&message, ProtobufEncoder::Lz4);
+            match SubscribeUpdateCompressed::decode(lz4.as_slice())
+                .unwrap()
+                .data
+            {
+                Some(Data::Lz4(data)) => {
+                    let mut decoded = Vec::new();
+                    lz4_flex::frame::FrameDecoder::new(data.as_slice())
+                        .read_to_end(&mut decoded)
+                        .unwrap();
+                    assert_eq!(decode(&decoded), raw);
+                }
+                data => panic!("unexpected data: {data:?}"),
+            }
+            // Yellowstone decoders skip compressed field
+            assert_eq!(
+                SubscribeUpdate::decode(lz4.as_slice()).unwrap(),
+                SubscribeUpdate::default()
+            );
+        }
+    }
+
+    #[test]
+    fn test_transport_notifications() {
+        let sender = create_sender(4_096, usize::MAX, ConfigChannelEviction::Oldest);
+        push_entries(&sender, 0..1, 2);
+        let quic = sender
+            .with_notifications(&[PluginNotification::Slot])
+            .subscribe(None, None)
+            .expect("quic subscribe");
+        let grpc = sender
+            .with_notifications(&[PluginNotification::Slot, PluginNotification::Entry])
+            .with_filter(&RichatFilter {
+                disable_entries: true,
+                ..Default::default()
+            })
+            .subscribe(None, None)
+            .expect("grpc subscribe");
+        let tcp = sender
+            .with_notifications(&[PluginNotification::Entry])
+            .subscribe(None, None)
+            .expect("tcp subscribe");
+
+        // slots are encoded with 1 byte, entries with 2 bytes
+        for slot in 1..=10 {
+            let meta = MessageMeta {
+                notification: PluginNotification::Slot,
+                slot,
+                slot_status: None,
+                account: None,
+                fields: MessageFields::None,
+            };
+            sender.push_encoded(meta, vec![0; 1]);
+            push_entries(&sender, slot..slot + 1, 2);
+        }
+
+        let sizes = |stream: RecvStream, count: usize| {
+            block_on(stream.take(count).try_collect::<Vec<_>>())
+                .expect("received messages")
+                .into_iter()
+                .map(|item| item.len())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sizes(quic, 10), vec![1; 10]);
+        assert_eq!(sizes(grpc, 10), vec![1; 10]);
+        assert_eq!(sizes(tcp, 11), vec![2; 11]);
+    }
 }
@@ -1,43 +1,753 @@
 use {
-    crate::protobuf::ProtobufEncoder,
+    crate::{
+        plugin::PluginNotification,
+        protobuf::{ProtobufEncoder, SequenceNotSupported},
+    },
     agave_geyser_plugin_interface::geyser_plugin_interface::{
-        GeyserPluginError, Result as PluginResult,
+        GeyserPluginError, Result as PluginResult, SlotStatus,
     },
     richat_metrics::ConfigMetrics,
+    richat_proto::richat::RichatFilter,
     richat_shared::{
-        config::{ConfigTokio, deserialize_humansize_usize, deserialize_num_str},
-        transports::{grpc::ConfigGrpcServer, quic::ConfigQuicServer},
+        config::{
+            ConfigTokio, deserialize_humansize_usize, deserialize_maybe_humansize_usize,
+            deserialize_maybe_num_str, deserialize_num_str, deserialize_x_tokens_set,
+        },
+        five8::{pubkey_decode, signature_decode},
+        transports::{
+            grpc::ConfigGrpcServer, quic::ConfigQuicServer, tcp::ConfigTcpServer,
+            uds::ConfigUdsServer, websocket::ConfigWebSocketServer,
+        },
     },
     serde::{
         Deserialize,
-        de::{self, Deserializer},
+        de::{
+            self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, MapAccess,
+            SeqAccess, VariantAccess, Visitor,
+        },
+    },
+    solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature},
+    solana_sdk_ids::sysvar,
+    std::{
+        borrow::Cow,
+        collections::HashSet,
+        env, fmt, fs,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        path::{Path, PathBuf},
+        time::Duration,
     },
-    std::{fs, path::Path},
 };
 
-#[derive(Debug, Clone, Default, Deserialize)]
+/// Sysvar accounts updated by the runtime, most of them on every slot
+const SYSVAR_ACCOUNTS: [Pubkey; 12] = [
+    sysvar::clock::ID,
+    sysvar::epoch_rewards::ID,
+    sysvar::epoch_schedule::ID,
+    sysvar::fees::ID,
+    sysvar::instructions::ID,
+    sysvar::last_restart_slot::ID,
+    sysvar::recent_blockhashes::ID,
+    sysvar::rent::ID,
+    sysvar::rewards::ID,
+    sysvar::slot_hashes::ID,
+    sysvar::slot_history::ID,
+    sysvar::stake_history::ID,
+];
+
+#[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct Config {
     pub libpath: String,
     pub logs: ConfigLogs,
     pub metrics: Option<ConfigMetrics>,
+    /// Record metrics without Prometheus server, metrics are served by health server
+    pub record_metrics: bool,
+    /// Count account updates by these owners, other owners are counted as `other`,
+    /// if None counting is disabled to keep cardinality of labels bounded
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub metrics_account_owners: Option<HashSet<Pubkey>>,
+    pub health: Option<ConfigHealth>,
+    pub admin: Option<ConfigAdmin>,
     pub tokio: ConfigTokio,
+    pub runtimes: ConfigRuntimes,
+    pub notifications: ConfigTransportNotifications,
     pub channel: ConfigChannel,
     pub filters: ConfigFilters,
     pub quic: Option<ConfigQuicServer>,
-    pub grpc: Option<ConfigGrpcServer>,
+    /// Single server or list of named servers with own filters
+    #[serde(deserialize_with = "Config::deserialize_grpc")]
+    pub grpc: Vec<ConfigGrpcEndpoint>,
+    pub tcp: Option<ConfigTcpServer>,
+    pub uds: Option<ConfigUdsServer>,
+    pub websocket: Option<ConfigWebSocketServer>,
+    pub file_sink: Option<ConfigFileSink>,
+    /// Maximum time to wait for tasks and runtime on unload
+    #[serde(with = "humantime_serde")]
+    pub shutdown_timeout: Duration,
+    /// Log and count panics of plugin threads before the validator panic hook
+    pub panic_hook: bool,
+    /// Everything except `filters`, used to reject non-reloadable changes
+    #[serde(skip)]
+    pub fixed: serde_json::Value,
+    /// `filters` as in the config file, reported by gRPC `GetVersion`
+    #[serde(skip)]
+    pub filters_raw: serde_json::Value,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            libpath: String::default(),
+            logs: ConfigLogs::default(),
+            metrics: None,
+            record_metrics: false,
+            metrics_account_owners: None,
+            health: None,
+            admin: None,
+            tokio: ConfigTokio::default(),
+            runtimes: ConfigRuntimes::default(),
+            notifications: ConfigTransportNotifications::default(),
+            channel: ConfigChannel::default(),
+            filters: ConfigFilters::default(),
+            quic: None,
+            grpc: Vec::new(),
+            tcp: None,
+            uds: None,
+            websocket: None,
+            file_sink: None,
+            shutdown_timeout: Duration::from_secs(10),
+            panic_hook: true,
+            fixed: serde_json::Value::Null,
+            filters_raw: serde_json::Value::Null,
+        }
+    }
 }
 
 impl Config {
-    fn load_from_str(config: &str) -> PluginResult<Self> {
-        serde_json::from_str(config).map_err(|error| GeyserPluginError::ConfigFileReadError {
-            msg: error.to_string(),
+    pub fn load_from_str(config: &str) -> PluginResult<Self> {
+        Self::load(config, ConfigFormat::Json)
+    }
+
+    pub fn load_from_yaml(config: &str) -> PluginResult<Self> {
+        Self::load(config, ConfigFormat::Yaml)
+    }
+
+    fn load(config: &str, format: ConfigFormat) -> PluginResult<Self> {
+        let fixed = format
+            .parse(config)
+            .map_err(|msg| GeyserPluginError::ConfigFileReadError { msg })?;
+        Self::load_parsed(fixed, config, format)
+    }
+
+    fn load_parsed(
+        fixed: serde_json::Value,
+        config: &str,
+        format: ConfigFormat,
+    ) -> PluginResult<Self> {
+        let config = Self::deserialize(&fixed).map_err(|error| {
+            // value does not keep positions, source is parsed again to report line and column
+            GeyserPluginError::ConfigFileReadError {
+                msg: format
+                    .parse::<Self>(config)
+                    .err()
+                    .unwrap_or_else(|| error.to_string()),
+            }
+        })?;
+        Self::finalize(config, fixed)
+    }
+
+    fn load_merged(merged: serde_json::Value) -> PluginResult<Self> {
+        let config =
+            Self::deserialize(&merged).map_err(|error| GeyserPluginError::ConfigFileReadError {
+                msg: error.to_string(),
+            })?;
+        Self::finalize(config, merged)
+    }
+
+    fn finalize(mut config: Self, mut fixed: serde_json::Value) -> PluginResult<Self> {
+        if let Some(object) = fixed.as_object_mut() {
+            config.filters_raw = object.remove("filters").unwrap_or_default();
+        }
+
+        config.validate()?;
+        config.filters.build_account_groups();
+        config.fixed = fixed;
+        Ok(config)
+    }
+
+    /// Checks applied to every loaded config: channel limits, transport notifications, filters,
+    /// admin server, gRPC batches, keepalive, slot datagrams and endpoint collisions (encoders and
+    /// pubkeys are checked on deserialization)
+    pub fn validate(&self) -> PluginResult<()> {
+        self.channel.validate()?;
+        self.notifications.validate()?;
+        self.filters.validate()?;
+        if let Some(admin) = &self.admin {
+            admin.validate()?;
+        }
+        for config in &self.grpc {
+            config.server.validate_batch().map_err(|error| {
+                GeyserPluginError::ConfigFileReadError {
+                    msg: format!("{}: {error}", config.name),
+                }
+            })?;
+        }
+        self.validate_keepalive()?;
+        self.validate_slot_datagrams()?;
+        self.validate_endpoints()
+    }
+
+    /// Keepalive ping is uncompressed protobuf `SubscribeUpdate` without sequence number,
+    /// clients of `zstd`, `lz4` and `bincode` encoders or with sequence can not decode it
+    fn validate_keepalive(&self) -> PluginResult<()> {
+        if self.channel.encoder.is_protobuf() && !self.channel.sequence {
+            return Ok(());
+        }
+
+        let keepalive = self
+            .grpc
+            .iter()
+            .filter(|config| config.server.keepalive.is_some())
+            .map(|config| config.name.as_str())
+            .chain(
+                self.quic
+                    .as_ref()
+                    .filter(|config| config.keepalive.is_some())
+                    .map(|_config| "quic"),
+            )
+            .next();
+        match keepalive {
+            Some(name) => Err(GeyserPluginError::ConfigFileReadError {
+                msg: if self.channel.sequence {
+                    format!("keepalive of {name} is not supported with channel sequence")
+                } else {
+                    format!(
+                        "keepalive of {name} is not supported with {} encoder",
+                        self.channel.encoder.as_str()
+                    )
+                },
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// QUIC server looks for slot updates in encoded `SubscribeUpdate`, messages of `zstd`, `lz4`
+    /// and `bincode` encoders can not be checked
+    fn validate_slot_datagrams(&self) -> PluginResult<()> {
+        if self.channel.encoder.is_protobuf()
+            || !self
+                .quic
+                .as_ref()
+                .is_some_and(|config| config.slot_datagrams)
+        {
+            return Ok(());
+        }
+
+        Err(GeyserPluginError::ConfigFileReadError {
+            msg: format!(
+                "slot_datagrams of quic is not supported with {} encoder",
+                self.channel.encoder.as_str()
+            ),
         })
     }
 
+    /// Servers can not share the same port of the same protocol, unspecified address overlaps
+    /// with any other address; QUIC binds UDP, all other servers bind TCP
+    fn validate_endpoints(&self) -> PluginResult<()> {
+        for (i, a) in self.grpc.iter().enumerate() {
+            if self.grpc[i + 1..].iter().any(|b| a.name == b.name) {
+                return Err(GeyserPluginError::ConfigFileReadError {
+                    msg: format!("gRPC endpoint name {:?} is not unique", a.name),
+                });
+            }
+        }
+
+        let endpoints = self
+            .grpc
+            .iter()
+            .map(|config| (config.name.as_str(), false, Some(config.server.endpoint)))
+            .chain([
+                (
+                    "quic",
+                    true,
+                    self.quic.as_ref().map(|config| config.endpoint),
+                ),
+                (
+                    "tcp",
+                    false,
+                    self.tcp.as_ref().map(|config| config.endpoint),
+                ),
+                (
+                    "websocket",
+                    false,
+                    self.websocket.as_ref().map(|config| config.endpoint),
+                ),
+                (
+                    "metrics",
+                    false,
+                    self.metrics.as_ref().map(|config| config.endpoint),
+                ),
+                (
+                    "health",
+                    false,
+                    self.health.as_ref().map(|config| config.endpoint),
+                ),
+                (
+                    "admin",
+                    false,
+                    self.admin.as_ref().map(|config| config.endpoint),
+                ),
+            ])
+            .filter_map(|(name, udp, endpoint)| endpoint.map(|endpoint| (name, udp, endpoint)))
+            .collect::<Vec<_>>();
+
+        for (i, (name_a, udp_a, a)) in endpoints.iter().enumerate() {
+            for (name_b, udp_b, b) in &endpoints[i + 1..] {
+                if udp_a == udp_b
+                    && a.port() != 0
+                    && a.port() == b.port()
+                    && (a.ip() == b.ip() || a.ip().is_unspecified() || b.ip().is_unspecified())
+                {
+                    return Err(GeyserPluginError::ConfigFileReadError {
+                        msg: format!(
+                            "endpoint {a} of {name_a} conflicts with endpoint {b} of {name_b}"
+                        ),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn deserialize_grpc<'de, D>(deserializer: D) -> Result<Vec<ConfigGrpcEndpoint>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // `name` and `filter` are removed before parsing of the server config, so unknown
+        // fields are still rejected (`deny_unknown_fields` does not work with `flatten`)
+        fn parse_endpoint(
+            mut value: serde_json::Value,
+            name: Option<String>,
+        ) -> Result<ConfigGrpcEndpoint, serde_json::Error> {
+            let (name_value, filter) = match value.as_object_mut() {
+                Some(object) => (
+                    object.remove("name"),
+                    object.remove("filter").unwrap_or_default(),
+                ),
+                None => (None, serde_json::Value::Null),
+            };
+            let name = match name_value {
+                Some(value) => String::deserialize(&value)?,
+                None => name.ok_or_else(|| de::Error::missing_field("name"))?,
+            };
+            Ok(ConfigGrpcEndpoint {
+                name,
+                filter: Option::deserialize(&filter)?.unwrap_or_default(),
+                server: ConfigGrpcServer::deserialize(&value)?,
+            })
+        }
+
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Null => Ok(Vec::new()),
+            serde_json::Value::Array(values) => values
+                .into_iter()
+                .map(|value| parse_endpoint(value, None))
+                .collect::<Result<_, _>>()
+                .map_err(de::Error::custom),
+            value => parse_endpoint(value, Some("grpc".to_owned()))
+                .map(|endpoint| vec![endpoint])
+                .map_err(de::Error::custom),
+        }
+    }
+
+    /// Load config, files from `overrides` (relative to the config file) are merged over it
+    /// in order as in [`Config::load_from_files`]
     pub fn load_from_file<P: AsRef<Path>>(file: P) -> PluginResult<Self> {
+        let config = fs::read_to_string(&file).map_err(GeyserPluginError::ConfigFileOpenError)?;
+        let format = ConfigFormat::from_path(file.as_ref());
+        let mut fixed = format
+            .parse(&config)
+            .map_err(|msg| GeyserPluginError::ConfigFileReadError { msg })?;
+
+        let overrides = Self::take_overrides(&mut fixed, file.as_ref())?;
+        if overrides.is_empty() {
+            return Self::load_parsed(fixed, &config, format);
+        }
+        for file in overrides {
+            let value = Self::read_value(&file)?;
+            merge_values(&mut fixed, value);
+        }
+        Self::load_merged(fixed)
+    }
+
+    /// Load base config with overrides, files are merged in order before deserialization:
+    /// objects are merged key-wise, other values (including arrays) are replaced
+    pub fn load_from_files<P: AsRef<Path>>(files: &[P]) -> PluginResult<Self> {
+        let mut merged = serde_json::Value::Null;
+        for file in files {
+            let value = Self::read_value(file.as_ref())?;
+            merge_values(&mut merged, value);
+        }
+        Self::load_merged(merged)
+    }
+
+    fn read_value(file: &Path) -> PluginResult<serde_json::Value> {
         let config = fs::read_to_string(file).map_err(GeyserPluginError::ConfigFileOpenError)?;
-        Self::load_from_str(&config)
+        let mut value = ConfigFormat::from_path(file)
+            .parse(&config)
+            .map_err(|error| GeyserPluginError::ConfigFileReadError {
+                msg: format!("{}: {error}", file.display()),
+            })?;
+        if !Self::take_overrides(&mut value, file)?.is_empty() {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: format!(
+                    "{}: overrides are supported only in the config of the plugin",
+                    file.display()
+                ),
+            });
+        }
+        Ok(value)
+    }
+
+    fn take_overrides(value: &mut serde_json::Value, file: &Path) -> PluginResult<Vec<PathBuf>> {
+        let Some(overrides) = value
+            .as_object_mut()
+            .and_then(|object| object.remove("overrides"))
+        else {
+            return Ok(Vec::new());
+        };
+
+        let dir = file.parent().unwrap_or(Path::new(""));
+        Vec::<PathBuf>::deserialize(overrides)
+            .map(|files| files.into_iter().map(|file| dir.join(file)).collect())
+            .map_err(|error| GeyserPluginError::ConfigFileReadError {
+                msg: format!("{}: invalid overrides: {error}", file.display()),
+            })
+    }
+}
+
+/// Config files with `yml` or `yaml` extension are YAML, others are JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yml") | Some("yaml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+
+    /// Parse with expanded environment variables, errors of both formats include line
+    /// and column
+    fn parse<T: DeserializeOwned>(self, config: &str) -> Result<T, String> {
+        match self {
+            Self::Json => {
+                let mut deserializer = serde_json::Deserializer::from_str(config);
+                let value = T::deserialize(EnvDeserializer(&mut deserializer))
+                    .map_err(|error| error.to_string())?;
+                deserializer.end().map_err(|error| error.to_string())?;
+                Ok(value)
+            }
+            Self::Yaml => {
+                T::deserialize(EnvDeserializer(serde_yaml::Deserializer::from_str(config)))
+                    .map_err(|error| error.to_string())
+            }
+        }
+    }
+}
+
+fn merge_values(base: &mut serde_json::Value, value: serde_json::Value) {
+    match (base, value) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(value)) => {
+            for (key, value) in value {
+                match base.get_mut(&key) {
+                    Some(base) => merge_values(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+/// Replace `${ENV_VAR}` references, `$${` is replaced with `${` without expansion
+fn expand_env_vars(value: &str) -> Result<Cow<'_, str>, String> {
+    if !value.contains("${") {
+        return Ok(Cow::Borrowed(value));
+    }
+
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        let Some(len) = rest[start + 2..].find('}') else {
+            return Err(format!(
+                "environment variable reference in `{value}` is not closed"
+            ));
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let var =
+            env::var(name).map_err(|_error| format!("environment variable `{name}` is not set"))?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&var);
+        rest = &rest[start + 3 + len..];
+    }
+    expanded.push_str(rest);
+    Ok(Cow::Owned(expanded))
+}
+
+/// Deserializer which expands environment variables in string values (but not in keys) while
+/// the source is parsed, so errors keep line and column of the source
+struct EnvDeserializer<D>(D);
+
+macro_rules! env_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error> {
+                self.0.$method($($arg,)* EnvVisitor(visitor))
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for EnvDeserializer<D> {
+    type Error = D::Error;
+
+    env_deserialize! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+struct EnvVisitor<V>(V);
+
+macro_rules! env_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, value: $ty) -> Result<Self::Value, E> {
+                self.0.$method(value)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for EnvVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    env_visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        match expand_env_vars(value).map_err(E::custom)? {
+            Cow::Borrowed(value) => self.0.visit_str(value),
+            Cow::Owned(value) => self.0.visit_string(value),
+        }
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<Self::Value, E> {
+        match expand_env_vars(value).map_err(E::custom)? {
+            Cow::Borrowed(value) => self.0.visit_borrowed_str(value),
+            Cow::Owned(value) => self.0.visit_string(value),
+        }
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        match expand_env_vars(&value).map_err(E::custom)? {
+            Cow::Borrowed(_) => self.0.visit_string(value),
+            Cow::Owned(value) => self.0.visit_string(value),
+        }
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.0.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.0.visit_some(EnvDeserializer(deserializer))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.0.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        self.0.visit_newtype_struct(EnvDeserializer(deserializer))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        self.0.visit_seq(EnvAccess(seq))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        self.0.visit_map(EnvAccess(map))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        self.0.visit_enum(EnvAccess(data))
+    }
+}
+
+struct EnvAccess<A>(A);
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for EnvAccess<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.0.next_element_seed(EnvSeed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for EnvAccess<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        self.0.next_key_seed(seed)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.next_value_seed(EnvSeed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+impl<'de, A: EnumAccess<'de>> EnumAccess<'de> for EnvAccess<A> {
+    type Error = A::Error;
+    type Variant = EnvAccess<A::Variant>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        self.0
+            .variant_seed(seed)
+            .map(|(value, variant)| (value, EnvAccess(variant)))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for EnvAccess<A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.0.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        self.0.newtype_variant_seed(EnvSeed(seed))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.tuple_variant(len, EnvVisitor(visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.struct_variant(fields, EnvVisitor(visitor))
+    }
+}
+
+struct EnvSeed<S>(S);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for EnvSeed<S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.0.deserialize(EnvDeserializer(deserializer))
     }
 }
 
@@ -46,25 +756,274 @@ impl Config {
 pub struct ConfigLogs {
     /// Log level
     pub level: String,
+    /// Format of log lines
+    pub format: ConfigLogsFormat,
 }
 
 impl Default for ConfigLogs {
     fn default() -> Self {
         Self {
             level: "info".to_owned(),
+            format: ConfigLogsFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigLogsFormat {
+    #[default]
+    Plain,
+    /// Object per line with timestamp, level, target and message
+    Json,
+}
+
+/// gRPC server with own name and filter applied to all subscriptions of the server,
+/// `name` and `filter` are set next to the server fields
+#[derive(Debug, Clone)]
+pub struct ConfigGrpcEndpoint {
+    /// Name of the endpoint, used as metrics label
+    pub name: String,
+    pub filter: ConfigGrpcEndpointFilter,
+    pub server: ConfigGrpcServer,
+}
+
+/// Filter of the endpoint, combined with filter of the subscription
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigGrpcEndpointFilter {
+    pub disable_accounts: bool,
+    pub disable_transactions: bool,
+    pub disable_entries: bool,
+    /// Send only these accounts, if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub accounts: Option<HashSet<Pubkey>>,
+    /// Send only accounts owned by these programs, if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub owners: Option<HashSet<Pubkey>>,
+}
+
+impl ConfigGrpcEndpointFilter {
+    pub fn to_richat_filter(&self) -> RichatFilter {
+        let to_vec = |keys: &Option<HashSet<Pubkey>>| {
+            keys.iter()
+                .flatten()
+                .map(|key| key.to_bytes().to_vec())
+                .collect()
+        };
+        RichatFilter {
+            disable_accounts: self.disable_accounts,
+            disable_transactions: self.disable_transactions,
+            disable_entries: self.disable_entries,
+            accounts: to_vec(&self.accounts),
+            owners: to_vec(&self.owners),
+            expression: None,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigHealth {
+    /// Endpoint of health service, serves `/health` and `/ready`
+    pub endpoint: SocketAddr,
+    /// Readiness fails if channel evicts not finalized slots longer than this window
+    #[serde(with = "humantime_serde")]
+    pub max_eviction_duration: Duration,
+    /// Readiness fails for this period after load and until the first message is pushed,
+    /// if None ready does not wait for messages
+    #[serde(with = "humantime_serde")]
+    pub warmup: Option<Duration>,
+}
+
+impl Default for ConfigHealth {
+    fn default() -> Self {
+        Self {
+            endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10124),
+            max_eviction_duration: Duration::from_secs(30),
+            warmup: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigAdmin {
+    /// Endpoint of admin service, serves `/config`
+    pub endpoint: SocketAddr,
+    /// Requests without one of tokens in `x-token` header are rejected, can not be empty
+    #[serde(deserialize_with = "deserialize_x_tokens_set")]
+    pub x_tokens: HashSet<Vec<u8>>,
+}
+
+impl Default for ConfigAdmin {
+    fn default() -> Self {
+        Self {
+            endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10125),
+            x_tokens: HashSet::new(),
+        }
+    }
+}
+
+impl ConfigAdmin {
+    fn validate(&self) -> PluginResult<()> {
+        if self.x_tokens.is_empty() {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "admin x_tokens can not be empty".to_owned(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Write encoded messages to rolling files, used for debug and offline replay
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigFileSink {
+    /// Directory for files
+    pub path: PathBuf,
+    /// File is rotated once size reaches this limit
+    #[serde(deserialize_with = "deserialize_humansize_usize")]
+    pub max_file_size: usize,
+    /// Notification types to write, by default all
+    #[serde(deserialize_with = "deserialize_notifications")]
+    pub notifications: Vec<PluginNotification>,
+}
+
+impl Default for ConfigFileSink {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("richat-sink"),
+            max_file_size: 1024 * 1024 * 1024,
+            notifications: PluginNotification::ALL.to_vec(),
+        }
+    }
+}
+
+fn deserialize_notifications<'de, D>(deserializer: D) -> Result<Vec<PluginNotification>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<&str>::deserialize(deserializer)?
+        .into_iter()
+        .map(|value| {
+            PluginNotification::ALL
+                .into_iter()
+                .find(|notification| notification.as_str() == value)
+                .ok_or_else(|| de::Error::custom(format!("Invalid notification: {value}")))
+        })
+        .collect()
+}
+
+/// Dedicated Tokio runtimes for transports, transport without own runtime uses `transports`
+/// and if it's not set `tokio`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigRuntimes {
+    /// Shared by all transports, isolates IO of clients from metrics and Geyser side tasks
+    pub transports: Option<ConfigTokio>,
+    pub grpc: Option<ConfigTokio>,
+    pub quic: Option<ConfigTokio>,
+    pub tcp: Option<ConfigTokio>,
+    pub uds: Option<ConfigTokio>,
+    pub websocket: Option<ConfigTokio>,
+}
+
+/// Notification types served by transports, other messages are skipped by transport clients,
+/// by default all; applied to all gRPC endpoints in addition to their own filters
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigTransportNotifications {
+    #[serde(deserialize_with = "deserialize_notifications")]
+    pub grpc: Vec<PluginNotification>,
+    #[serde(deserialize_with = "deserialize_notifications")]
+    pub quic: Vec<PluginNotification>,
+    #[serde(deserialize_with = "deserialize_notifications")]
+    pub tcp: Vec<PluginNotification>,
+    #[serde(deserialize_with = "deserialize_notifications")]
+    pub uds: Vec<PluginNotification>,
+    #[serde(deserialize_with = "deserialize_notifications")]
+    pub websocket: Vec<PluginNotification>,
+}
+
+impl Default for ConfigTransportNotifications {
+    fn default() -> Self {
+        Self {
+            grpc: PluginNotification::ALL.to_vec(),
+            quic: PluginNotification::ALL.to_vec(),
+            tcp: PluginNotification::ALL.to_vec(),
+            uds: PluginNotification::ALL.to_vec(),
+            websocket: PluginNotification::ALL.to_vec(),
+        }
+    }
+}
+
+impl ConfigTransportNotifications {
+    fn validate(&self) -> PluginResult<()> {
+        for (transport, notifications) in [
+            ("grpc", &self.grpc),
+            ("quic", &self.quic),
+            ("tcp", &self.tcp),
+            ("uds", &self.uds),
+            ("websocket", &self.websocket),
+        ] {
+            if notifications.is_empty() {
+                return Err(GeyserPluginError::ConfigFileReadError {
+                    msg: format!("notifications of {transport} can not be empty"),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct ConfigChannel {
     #[serde(deserialize_with = "ConfigChannel::deserialize_encoder")]
     pub encoder: ProtobufEncoder,
+    /// Zero means no limit, channel grows until `max_bytes`
     #[serde(deserialize_with = "deserialize_num_str")]
     pub max_messages: usize,
+    /// Zero means no limit, channel is bounded only by `max_messages`
     #[serde(deserialize_with = "deserialize_humansize_usize")]
     pub max_bytes: usize,
+    /// Drop messages with encoded size over the limit, protects clients with frame size limits
+    #[serde(deserialize_with = "deserialize_maybe_humansize_usize")]
+    pub max_message_bytes: Option<usize>,
+    /// Compression level, used only by `zstd` encoder
+    pub compression_level: i32,
+    /// Number of threads encoding account updates, if zero accounts are encoded on Geyser threads
+    #[serde(deserialize_with = "deserialize_num_str")]
+    pub encoder_threads: usize,
+    /// Messages queued to the encoder pool, Geyser callbacks wait once the queue is full, so
+    /// `block` overflow reaches the validator instead of growing the queue
+    #[serde(deserialize_with = "deserialize_num_str")]
+    pub encoder_queue_size: usize,
+    /// Reuse buffers of messages removed from channel for new messages
+    pub buffer_pool: ConfigChannelBufferPool,
+    /// Per notification type limits, messages of these types are kept in own buffers with the
+    /// limits, so bursts of other types do not evict them
+    pub limits: ConfigChannelLimits,
+    /// Behavior on overflow if not all clients received the oldest message
+    pub overflow: ConfigChannelOverflow,
+    /// Maximum time to block Geyser callback with `block` overflow, after that oldest messages are dropped
+    #[serde(with = "humantime_serde")]
+    pub overflow_timeout: Duration,
+    /// Which messages are dropped when channel is full
+    pub eviction: ConfigChannelEviction,
+    /// On unload keep transports running until clients read all messages or shutdown timeout
+    pub drain_on_shutdown: bool,
+    /// Add sequence number of the message in the channel, only `prost` and `raw` encoders
+    pub sequence: bool,
+    /// Drop accounts, transactions and entries of the slot over the limit, bounds bursts
+    /// of replayed slots on catch-up
+    #[serde(deserialize_with = "deserialize_maybe_num_str")]
+    pub max_messages_per_slot: Option<usize>,
+    /// Number of last slot status messages sent to new live subscriptions before live
+    /// messages, lets clients restore commitment of recent slots, zero disables
+    #[serde(deserialize_with = "deserialize_num_str")]
+    pub backfill_slot_statuses: usize,
 }
 
 impl Default for ConfigChannel {
@@ -73,34 +1032,314 @@ impl Default for ConfigChannel {
             encoder: ProtobufEncoder::Raw,
             max_messages: 2_097_152, // aligned to power of 2, ~20k/slot should give us ~100 slots
             max_bytes: 15 * 1024 * 1024 * 1024, // 15GiB with ~150MiB/slot should give us ~100 slots
+            max_message_bytes: None,
+            compression_level: zstd::DEFAULT_COMPRESSION_LEVEL,
+            encoder_threads: 0,
+            encoder_queue_size: 65_536,
+            buffer_pool: ConfigChannelBufferPool::default(),
+            limits: ConfigChannelLimits::default(),
+            overflow: ConfigChannelOverflow::default(),
+            overflow_timeout: Duration::from_millis(100),
+            eviction: ConfigChannelEviction::default(),
+            drain_on_shutdown: false,
+            sequence: false,
+            max_messages_per_slot: None,
+            backfill_slot_statuses: 0,
         }
     }
 }
 
 impl ConfigChannel {
+    fn validate(&self) -> PluginResult<()> {
+        let range = zstd::compression_level_range();
+        if self.encoder == ProtobufEncoder::Zstd && !range.contains(&self.compression_level) {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: format!(
+                    "compression_level ({}) should be in range {range:?}",
+                    self.compression_level
+                ),
+            });
+        }
+        if self.max_messages == 0 && self.max_bytes == 0 {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "at least one of max_messages and max_bytes should be set".to_owned(),
+            });
+        }
+        for notification in PluginNotification::ALL {
+            if let Some(limit) = self.limits.get(notification) {
+                if limit.max_messages == 0 && limit.max_bytes == 0 {
+                    return Err(GeyserPluginError::ConfigFileReadError {
+                        msg: format!(
+                            "at least one of max_messages and max_bytes should be set in {} limits",
+                            notification.as_str()
+                        ),
+                    });
+                }
+            }
+        }
+        if self.encoder_threads > 0 && self.encoder_queue_size == 0 {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "encoder_queue_size should be greater than zero".to_owned(),
+            });
+        }
+        if self.max_messages_per_slot == Some(0) {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "max_messages_per_slot should be greater than zero".to_owned(),
+            });
+        }
+        if self.sequence && !matches!(self.encoder, ProtobufEncoder::Prost | ProtobufEncoder::Raw) {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: SequenceNotSupported(self.encoder).to_string(),
+            });
+        }
+        Ok(())
+    }
+
     pub fn deserialize_encoder<'de, D>(deserializer: D) -> Result<ProtobufEncoder, D::Error>
     where
         D: Deserializer<'de>,
     {
-        match Deserialize::deserialize(deserializer)? {
-            "prost" => Ok(ProtobufEncoder::Prost),
-            "raw" => Ok(ProtobufEncoder::Raw),
-            value => Err(de::Error::custom(format!(
-                "failed to decode encoder: {value}"
-            ))),
+        let value: &str = Deserialize::deserialize(deserializer)?;
+        ProtobufEncoder::parse(value)
+            .ok_or_else(|| de::Error::custom(format!("failed to decode encoder: {value}")))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigChannelBufferPool {
+    /// Maximum number of free buffers, if zero pool is disabled
+    #[serde(deserialize_with = "deserialize_num_str")]
+    pub max_buffers: usize,
+    /// Buffers with larger capacity are not returned to the pool
+    #[serde(deserialize_with = "deserialize_humansize_usize")]
+    pub max_buffer_size: usize,
+}
+
+impl Default for ConfigChannelBufferPool {
+    fn default() -> Self {
+        Self {
+            max_buffers: 0,
+            max_buffer_size: 64 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigChannelOverflow {
+    /// Drop oldest messages
+    #[default]
+    Drop,
+    /// Block Geyser callback until all clients received the oldest message
+    Block,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigChannelEviction {
+    /// Remove oldest messages to push new one
+    #[default]
+    Oldest,
+    /// Drop new message while buffered messages are not read by all receivers
+    Newest,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigChannelLimits {
+    pub slot: Option<ConfigChannelLimit>,
+    pub account: Option<ConfigChannelLimit>,
+    pub transaction: Option<ConfigChannelLimit>,
+    pub entry: Option<ConfigChannelLimit>,
+    pub block_meta: Option<ConfigChannelLimit>,
+}
+
+impl ConfigChannelLimits {
+    pub const fn get(&self, notification: PluginNotification) -> Option<ConfigChannelLimit> {
+        match notification {
+            PluginNotification::Slot => self.slot,
+            PluginNotification::Account => self.account,
+            PluginNotification::Transaction => self.transaction,
+            PluginNotification::Entry => self.entry,
+            PluginNotification::BlockMeta => self.block_meta,
         }
     }
 }
 
 #[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigChannelLimit {
+    #[serde(deserialize_with = "deserialize_num_str")]
+    pub max_messages: usize,
+    #[serde(deserialize_with = "deserialize_humansize_usize")]
+    pub max_bytes: usize,
+}
+
+/// Data pattern as (offset, bytes)
+pub type AccountDataMemcmp = (usize, Vec<u8>);
+
+/// Group of account filters, account matches the group if it passes all set filters
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigAccountFilter {
+    /// Name of the group, used in errors
+    pub name: String,
+    /// Match only these accounts, if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub accounts: Option<HashSet<Pubkey>>,
+    /// Match only accounts owned by these programs, if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub owners: Option<HashSet<Pubkey>>,
+    /// Minimum account data size, if None no limit
+    pub min_data_size: Option<usize>,
+    /// Maximum account data size, if None no limit
+    pub max_data_size: Option<usize>,
+    /// Match only accounts with data matching all patterns (offset, bytes), if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_memcmp")]
+    pub memcmp: Option<Vec<AccountDataMemcmp>>,
+}
+
+impl ConfigAccountFilter {
+    fn validate(&self) -> PluginResult<()> {
+        if let (Some(min_size), Some(max_size)) = (self.min_data_size, self.max_data_size) {
+            if min_size > max_size {
+                return Err(GeyserPluginError::ConfigFileReadError {
+                    msg: format!(
+                        "account filter {:?}: min_data_size ({min_size}) should be less than or equal to max_data_size ({max_size})",
+                        self.name
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Filters are checked in order: pubkey, owner, data patterns, data size.
+    /// Returns the first failed filter.
+    pub fn is_match(&self, pubkey: &[u8], owner: &[u8], data: &[u8]) -> Result<(), &'static str> {
+        if let Some(accounts) = &self.accounts {
+            if !Pubkey::try_from(pubkey).is_ok_and(|pubkey| accounts.contains(&pubkey)) {
+                return Err("pubkey");
+            }
+        }
+        if let Some(owners) = &self.owners {
+            if !Pubkey::try_from(owner).is_ok_and(|owner| owners.contains(&owner)) {
+                return Err("owner");
+            }
+        }
+        if let Some(memcmp) = &self.memcmp {
+            if !memcmp.iter().all(|(offset, bytes)| {
+                offset
+                    .checked_add(bytes.len())
+                    .and_then(|end| data.get(*offset..end))
+                    == Some(bytes.as_slice())
+            }) {
+                return Err("memcmp");
+            }
+        }
+        if self
+            .min_data_size
+            .is_some_and(|min_size| data.len() < min_size)
+            || self
+                .max_data_size
+                .is_some_and(|max_size| data.len() > max_size)
+        {
+            return Err("data_size");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct ConfigFilters {
     /// Enable/disable account update notifications
     pub enable_account_update: bool,
     /// Enable/disable transaction update notifications
     pub enable_transaction_update: bool,
+    /// Enable/disable entry notifications
+    pub enable_entry_update: bool,
+    /// Skip entries without transactions (ticks)
+    pub skip_empty_entries: bool,
+    /// Send accounts from the startup snapshot
+    pub enable_startup_accounts: bool,
+    /// Skip updates of sysvar accounts (clock, slot hashes, etc.)
+    pub exclude_sysvar_accounts: bool,
+    /// Minimum account data size to send, if None no limit
+    pub min_account_data_size: Option<usize>,
     /// Maximum account data size to send, if None no limit
     pub max_account_data_size: Option<usize>,
+    /// Send only accounts owned by these programs, if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub owners: Option<HashSet<Pubkey>>,
+    /// Send only these accounts, if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub accounts: Option<HashSet<Pubkey>>,
+    /// Send only slice of account data as (offset, length), if None full data
+    pub account_data_slice: Option<(usize, usize)>,
+    /// Send only accounts with data matching all patterns (offset, bytes), if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_memcmp")]
+    pub account_datasize_memcmp: Option<Vec<AccountDataMemcmp>>,
+    /// Groups of account filters, account is sent if it matches any group
+    pub account_filters: Vec<ConfigAccountFilter>,
+    /// Flat account filters as the first group followed by `account_filters`
+    #[serde(skip)]
+    pub account_groups: Vec<ConfigAccountFilter>,
+    /// Skip vote transactions
+    pub exclude_vote_transactions: bool,
+    /// Skip failed transactions
+    pub exclude_failed_transactions: bool,
+    /// Send only transactions with instructions invoking these programs, if None no filter
+    ///
+    /// Requires resolving program id of every instruction, so it adds some cost
+    /// to the transaction notification path
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub transaction_programs: Option<HashSet<Pubkey>>,
+    /// Send only transactions with at least one of these account keys, if None no filter
+    ///
+    /// Keys are checked in static keys and in keys loaded from address lookup tables, loaded
+    /// keys are already resolved by the validator so only the number of keys affects the cost
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub transaction_accounts_include: Option<HashSet<Pubkey>>,
+    /// Skip transactions with any of these account keys, checked same as include
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_pubkeys")]
+    pub transaction_accounts_exclude: Option<HashSet<Pubkey>>,
+    /// Send only transactions with these signatures, if None no filter
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_signatures")]
+    pub transaction_signatures: Option<HashSet<Signature>>,
+    /// Send only slots with these statuses, if None all statuses
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_slot_statuses")]
+    pub slot_statuses: Option<Vec<SlotStatus>>,
+    /// Send messages of all types only for slots in the inclusive range, for debugging
+    pub slot_range: Option<(Slot, Slot)>,
+    /// Send slot status only first time when slot reaches it, Geyser can repeat statuses
+    /// on forks switch and restarts
+    pub dedup_slot_statuses: bool,
+    /// Number of `(slot, status)` pairs remembered for deduplication
+    #[serde(deserialize_with = "deserialize_num_str")]
+    pub dedup_slot_statuses_size: usize,
+    /// Buffer account updates per slot and send only the latest write of every account when
+    /// slot is processed or confirmed, adds latency but reduces volume for hot accounts
+    pub coalesce_accounts_per_slot: bool,
+    /// Maximum number of buffered accounts, the oldest slots are sent earlier on overflow
+    #[serde(deserialize_with = "deserialize_num_str")]
+    pub coalesce_accounts_max: usize,
+    /// Keep the last owner of accounts passed other filters and flag updates where it changed,
+    /// the first seen update of an account is never flagged
+    pub account_owner_changes: bool,
+    /// Maximum number of tracked accounts, the least recently updated are evicted
+    pub account_owners_max: usize,
+    /// Send block rewards in block meta, can be disabled to skip serialization cost
+    pub include_block_rewards: bool,
+    /// Send addresses loaded from address lookup tables in transaction meta
+    pub include_loaded_addresses: bool,
+    /// Add counts of transactions and accounts pushed for the slot to block meta, accounts
+    /// coalesced per slot are sent after block meta and not counted
+    pub block_meta_counts: bool,
+    /// Push synthetic slot complete barrier after this status, if None barrier is disabled
+    #[serde(deserialize_with = "ConfigFilters::deserialize_maybe_slot_status")]
+    pub slot_complete_status: Option<SlotStatus>,
 }
 
 impl Default for ConfigFilters {
@@ -108,7 +1347,594 @@ impl Default for ConfigFilters {
         Self {
             enable_account_update: true,
             enable_transaction_update: true,
+            enable_entry_update: true,
+            skip_empty_entries: false,
+            enable_startup_accounts: false,
+            exclude_sysvar_accounts: false,
+            min_account_data_size: None,
             max_account_data_size: None,
+            owners: None,
+            accounts: None,
+            account_data_slice: None,
+            account_datasize_memcmp: None,
+            account_filters: Vec::new(),
+            account_groups: Vec::new(),
+            exclude_vote_transactions: false,
+            exclude_failed_transactions: false,
+            transaction_programs: None,
+            transaction_accounts_include: None,
+            transaction_accounts_exclude: None,
+            transaction_signatures: None,
+            slot_statuses: None,
+            slot_range: None,
+            dedup_slot_statuses: false,
+            dedup_slot_statuses_size: 4_096,
+            coalesce_accounts_per_slot: false,
+            coalesce_accounts_max: 1_000_000,
+            account_owner_changes: false,
+            account_owners_max: 1_000_000,
+            include_block_rewards: true,
+            include_loaded_addresses: true,
+            block_meta_counts: false,
+            slot_complete_status: None,
+        }
+    }
+}
+
+impl ConfigFilters {
+    fn validate(&self) -> PluginResult<()> {
+        if let (Some(min_size), Some(max_size)) =
+            (self.min_account_data_size, self.max_account_data_size)
+        {
+            if min_size > max_size {
+                return Err(GeyserPluginError::ConfigFileReadError {
+                    msg: format!(
+                        "min_account_data_size ({min_size}) should be less than or equal to max_account_data_size ({max_size})"
+                    ),
+                });
+            }
+        }
+        for group in &self.account_filters {
+            group.validate()?;
+        }
+        if self.dedup_slot_statuses && self.dedup_slot_statuses_size == 0 {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "dedup_slot_statuses_size should be greater than zero".to_owned(),
+            });
+        }
+        if let Some((from, to)) = self.slot_range {
+            if from > to {
+                return Err(GeyserPluginError::ConfigFileReadError {
+                    msg: format!(
+                        "slot_range start ({from}) should be less than or equal to end ({to})"
+                    ),
+                });
+            }
+        }
+        if self.coalesce_accounts_per_slot && self.coalesce_accounts_max == 0 {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "coalesce_accounts_max should be greater than zero".to_owned(),
+            });
+        }
+        if self.account_owner_changes && self.account_owners_max == 0 {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "account_owners_max should be greater than zero".to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    fn build_account_groups(&mut self) {
+        self.account_groups.clear();
+        if self.accounts.is_some()
+            || self.owners.is_some()
+            || self.min_account_data_size.is_some()
+            || self.max_account_data_size.is_some()
+            || self.account_datasize_memcmp.is_some()
+        {
+            self.account_groups.push(ConfigAccountFilter {
+                name: "default".to_owned(),
+                accounts: self.accounts.clone(),
+                owners: self.owners.clone(),
+                min_data_size: self.min_account_data_size,
+                max_data_size: self.max_account_data_size,
+                memcmp: self.account_datasize_memcmp.clone(),
+            });
+        }
+        self.account_groups
+            .extend(self.account_filters.iter().cloned());
+    }
+
+    /// Sysvar account updates are skipped if `exclude_sysvar_accounts` is set
+    pub fn is_sysvar_excluded(&self, pubkey: &[u8]) -> bool {
+        self.exclude_sysvar_accounts && SYSVAR_ACCOUNTS.iter().any(|id| id.as_ref() == pubkey)
+    }
+
+    /// Groups are evaluated in order and the first matched group accepts the account, remaining
+    /// groups are not checked. Without groups all accounts are accepted. If nothing matched,
+    /// returns failed filter of the single group or `group` if there are several groups.
+    pub fn is_account_allowed(
+        &self,
+        pubkey: &[u8],
+        owner: &[u8],
+        data: &[u8],
+    ) -> Result<(), &'static str> {
+        let mut result = Ok(());
+        for group in &self.account_groups {
+            result = group.is_match(pubkey, owner, data);
+            if result.is_ok() {
+                break;
+            }
+        }
+        match result {
+            Err(_) if self.account_groups.len() > 1 => Err("group"),
+            result => result,
+        }
+    }
+
+    pub fn deserialize_maybe_pubkeys<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<HashSet<Pubkey>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<Vec<&str>>::deserialize(deserializer)?
+            .map(|vec| {
+                vec.into_iter()
+                    .map(|value| {
+                        pubkey_decode(value).map_err(|error| {
+                            de::Error::custom(format!("Invalid pubkey: {value} ({error:?})"))
+                        })
+                    })
+                    .collect::<Result<_, _>>()
+            })
+            .transpose()
+    }
+
+    pub fn deserialize_maybe_signatures<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<HashSet<Signature>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<Vec<&str>>::deserialize(deserializer)?
+            .map(|vec| {
+                vec.into_iter()
+                    .map(|value| {
+                        signature_decode(value).map_err(|error| {
+                            de::Error::custom(format!("Invalid signature: {value} ({error:?})"))
+                        })
+                    })
+                    .collect::<Result<_, _>>()
+            })
+            .transpose()
+    }
+
+    pub fn deserialize_maybe_slot_statuses<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<Vec<SlotStatus>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<Vec<&str>>::deserialize(deserializer)?
+            .map(|vec| {
+                vec.into_iter()
+                    .map(Self::parse_slot_status)
+                    .collect::<Result<_, _>>()
+            })
+            .transpose()
+    }
+
+    pub fn deserialize_maybe_slot_status<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<SlotStatus>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<&str>::deserialize(deserializer)?
+            .map(Self::parse_slot_status)
+            .transpose()
+    }
+
+    fn parse_slot_status<E: de::Error>(value: &str) -> Result<SlotStatus, E> {
+        match value {
+            "processed" => Ok(SlotStatus::Processed),
+            "rooted" => Ok(SlotStatus::Rooted),
+            "confirmed" => Ok(SlotStatus::Confirmed),
+            "first_shred_received" => Ok(SlotStatus::FirstShredReceived),
+            "completed" => Ok(SlotStatus::Completed),
+            "created_bank" => Ok(SlotStatus::CreatedBank),
+            "dead" => Ok(SlotStatus::Dead(String::new())),
+            _ => Err(de::Error::custom(format!("Invalid slot status: {value}"))),
+        }
+    }
+
+    pub fn is_slot_allowed(&self, slot: Slot) -> bool {
+        self.slot_range
+            .is_none_or(|(from, to)| (from..=to).contains(&slot))
+    }
+
+    pub fn is_slot_status_allowed(&self, status: &SlotStatus) -> bool {
+        self.slot_statuses.as_ref().is_none_or(|statuses| {
+            statuses
+                .iter()
+                .any(|allowed| allowed.as_str() == status.as_str())
+        })
+    }
+
+    pub fn is_slot_complete_status(&self, status: &SlotStatus) -> bool {
+        self.slot_complete_status
+            .as_ref()
+            .is_some_and(|complete| complete.as_str() == status.as_str())
+    }
+
+    pub fn deserialize_maybe_memcmp<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<Vec<AccountDataMemcmp>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Debug, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct ConfigMemcmp<'a> {
+            offset: usize,
+            bytes_base58: &'a str,
+        }
+
+        Option::<Vec<ConfigMemcmp>>::deserialize(deserializer)?
+            .map(|vec| {
+                vec.into_iter()
+                    .map(|config| {
+                        bs58::decode(config.bytes_base58)
+                            .into_vec()
+                            .map(|bytes| (config.offset, bytes))
+                            .map_err(|error| {
+                                de::Error::custom(format!(
+                                    "Invalid base58 bytes: {} ({error:?})",
+                                    config.bytes_base58
+                                ))
+                            })
+                    })
+                    .collect::<Result<_, _>>()
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Config, ConfigLogsFormat, merge_values},
+        serde_json::json,
+        std::{env, fs, process},
+    };
+
+    fn load(config: &str) -> Result<Config, String> {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        Config::load_from_str(config).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn test_yaml() {
+        let json = Config::load_from_str(
+            r#"{
+                "logs": {"level": "debug"},
+                "channel": {"encoder": "prost", "max_messages": "1_024"},
+                "grpc": {"endpoint": "127.0.0.1:10000", "x_tokens": ["token"]},
+                "filters": {"enable_transaction_update": false}
+            }"#,
+        )
+        .unwrap();
+        let yaml = Config::load_from_yaml(
+            r#"
+logs:
+  level: debug
+channel:
+  encoder: prost
+  max_messages: 1_024
+grpc:
+  endpoint: 127.0.0.1:10000
+  x_tokens:
+    - token
+filters:
+  enable_transaction_update: false
+"#,
+        )
+        .unwrap();
+        assert_eq!(yaml.fixed, json.fixed);
+        assert_eq!(yaml.filters_raw, json.filters_raw);
+        assert_eq!(yaml.channel.max_messages, 1_024);
+        assert_eq!(yaml.grpc[0].server.endpoint, json.grpc[0].server.endpoint);
+        assert!(!yaml.filters.enable_transaction_update);
+    }
+
+    #[test]
+    fn test_yaml_error_position() {
+        let error = Config::load_from_yaml("logs:\n  level: debug\nchannel:\n  encodr: prost\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown field `encodr`"), "{error}");
+        assert!(error.contains("line 4"), "{error}");
+
+        let error = Config::load_from_str("{\n  \"logs\": {},\n  \"channel\": {\"encodr\": 1}\n}")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown field `encodr`"), "{error}");
+        assert!(error.contains("line 3"), "{error}");
+    }
+
+    // `CARGO_PKG_NAME` is set by cargo for test binaries
+    #[test]
+    fn test_env_vars() {
+        let config = Config::load_from_str(r#"{"libpath": "/lib/${CARGO_PKG_NAME}.so"}"#).unwrap();
+        assert_eq!(
+            config.libpath,
+            format!("/lib/{}.so", env!("CARGO_PKG_NAME"))
+        );
+
+        let config = Config::load_from_yaml("libpath: ${CARGO_PKG_NAME}\n").unwrap();
+        assert_eq!(config.libpath, env!("CARGO_PKG_NAME"));
+
+        // escaped reference is not expanded
+        let config =
+            Config::load_from_str(r#"{"libpath": "$${CARGO_PKG_NAME}-${CARGO_PKG_NAME}"}"#)
+                .unwrap();
+        assert_eq!(
+            config.libpath,
+            format!("${{CARGO_PKG_NAME}}-{}", env!("CARGO_PKG_NAME"))
+        );
+
+        let config = Config::load_from_str(r#"{"libpath": "$HOME/{lib}"}"#).unwrap();
+        assert_eq!(config.libpath, "$HOME/{lib}");
+    }
+
+    #[test]
+    fn test_env_vars_error() {
+        let error = Config::load_from_str(
+            "{\n  \"logs\": {},\n  \"libpath\": \"${RICHAT_TEST_NOT_SET}\"\n}",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            error.contains("`RICHAT_TEST_NOT_SET` is not set"),
+            "{error}"
+        );
+        assert!(error.contains("line 3"), "{error}");
+
+        let error = Config::load_from_yaml("logs: {}\nlibpath: ${RICHAT_TEST_NOT_SET}\n")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("`RICHAT_TEST_NOT_SET` is not set"),
+            "{error}"
+        );
+        assert!(error.contains("line 2"), "{error}");
+
+        let error = Config::load_from_str(r#"{"libpath": "${CARGO_PKG_NAME"}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("is not closed"), "{error}");
+
+        // invalid expanded value is reported at position of the reference
+        let error = Config::load_from_str(
+            "{\n  \"logs\": {},\n  \"tcp\": {\n    \"endpoint\": \"${CARGO_PKG_NAME}\"\n  }\n}",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("line 4"), "{error}");
+    }
+
+    #[test]
+    fn test_merge_values() {
+        let mut base = json!({
+            "logs": {"level": "info", "format": "plain"},
+            "channel": {"limits": {"slot": {"max_messages": 10}}},
+            "shutdown_timeout": "10s",
+            "filters": {"accounts": ["a", "b"]},
+        });
+        merge_values(
+            &mut base,
+            json!({
+                "logs": {"level": "debug"},
+                "channel": {"limits": {"slot": {"max_bytes": 20}}},
+                "shutdown_timeout": "5s",
+                "filters": {"accounts": ["c"]},
+                "panic_hook": false,
+            }),
+        );
+        assert_eq!(
+            base,
+            json!({
+                "logs": {"level": "debug", "format": "plain"},
+                "channel": {"limits": {"slot": {"max_messages": 10, "max_bytes": 20}}},
+                "shutdown_timeout": "5s",
+                "filters": {"accounts": ["c"]},
+                "panic_hook": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_load_overrides() {
+        let dir = env::temp_dir().join(format!("richat-plugin-config-{}", process::id()));
+        fs::create_dir_all(dir.join("env")).unwrap();
+        let base = dir.join("config.json");
+        fs::write(
+            &base,
+            r#"{
+                "logs": {"level": "info", "format": "json"},
+                "shutdown_timeout": "10s",
+                "filters": {"enable_transaction_update": false},
+                "overrides": ["env/override.yml", "env/override.json"]
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("env/override.yml"),
+            "logs:\n  level: debug\nshutdown_timeout: 5s\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("env/override.json"),
+            r#"{"shutdown_timeout": "1s"}"#,
+        )
+        .unwrap();
+
+        let config = Config::load_from_file(&base).unwrap();
+        assert_eq!(config.logs.level, "debug");
+        assert_eq!(config.logs.format, ConfigLogsFormat::Json);
+        assert_eq!(config.shutdown_timeout.as_secs(), 1);
+        assert!(!config.filters.enable_transaction_update);
+        assert!(config.fixed.get("overrides").is_none());
+
+        // overrides are not nested
+        fs::write(
+            dir.join("env/override.json"),
+            r#"{"overrides": ["override.yml"]}"#,
+        )
+        .unwrap();
+        let error = Config::load_from_file(&base).unwrap_err().to_string();
+        assert!(error.contains("overrides are supported only"), "{error}");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_endpoints_conflict() {
+        load(
+            r#"{
+                "grpc": {"endpoint": "127.0.0.1:10000"},
+                "quic": {
+                    "endpoint": "127.0.0.1:10000",
+                    "tls_config": {"self_signed_alt_names": ["localhost"]}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let error = load(
+            r#"{
+                "grpc": {"endpoint": "127.0.0.1:10000"},
+                "tcp": {"endpoint": "0.0.0.0:10000"}
+            }"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("conflicts"), "{error}");
+    }
+
+    #[test]
+    fn test_keepalive_encoder() {
+        for encoder in ["prost", "raw"] {
+            load(&format!(
+                r#"{{
+                    "channel": {{"encoder": "{encoder}"}},
+                    "grpc": {{"endpoint": "127.0.0.1:10000", "keepalive": "5s"}}
+                }}"#
+            ))
+            .unwrap();
+        }
+        for encoder in ["zstd", "lz4", "bincode"] {
+            let error = load(&format!(
+                r#"{{
+                    "channel": {{"encoder": "{encoder}"}},
+                    "grpc": {{"endpoint": "127.0.0.1:10000", "keepalive": "5s"}}
+                }}"#
+            ))
+            .unwrap_err();
+            assert!(error.contains(encoder), "{error}");
+
+            let error = load(&format!(
+                r#"{{
+                    "channel": {{"encoder": "{encoder}"}},
+                    "quic": {{
+                        "tls_config": {{"self_signed_alt_names": ["localhost"]}},
+                        "keepalive": "5s"
+                    }}
+                }}"#
+            ))
+            .unwrap_err();
+            assert!(error.contains(encoder), "{error}");
+        }
+
+        let error = load(
+            r#"{
+                "channel": {"encoder": "raw", "sequence": true},
+                "grpc": {"endpoint": "127.0.0.1:10000", "keepalive": "5s"}
+            }"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("channel sequence"), "{error}");
+    }
+
+    #[test]
+    fn test_dedup_slot_statuses_size_str() {
+        let config = load(
+            r#"{"filters": {"dedup_slot_statuses": true, "dedup_slot_statuses_size": "1024"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.filters.dedup_slot_statuses_size, 1_024);
+    }
+
+    #[test]
+    fn test_coalesce_accounts_max_str() {
+        let config = load(
+            r#"{"filters": {"coalesce_accounts_per_slot": true, "coalesce_accounts_max": "1024"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.filters.coalesce_accounts_max, 1_024);
+    }
+
+    #[test]
+    fn test_slot_datagrams_encoder() {
+        for encoder in ["prost", "raw"] {
+            load(&format!(
+                r#"{{
+                    "channel": {{"encoder": "{encoder}"}},
+                    "quic": {{
+                        "tls_config": {{"self_signed_alt_names": ["localhost"]}},
+                        "slot_datagrams": true
+                    }}
+                }}"#
+            ))
+            .unwrap();
+        }
+        for encoder in ["zstd", "lz4", "bincode"] {
+            let error = load(&format!(
+                r#"{{
+                    "channel": {{"encoder": "{encoder}"}},
+                    "quic": {{
+                        "tls_config": {{"self_signed_alt_names": ["localhost"]}},
+                        "slot_datagrams": true
+                    }}
+                }}"#
+            ))
+            .unwrap_err();
+            assert!(error.contains("slot_datagrams"), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_grpc_batch() {
+        load(
+            r#"{
+                "grpc": {
+                    "endpoint": "127.0.0.1:10000",
+                    "batch": {"max_messages": 64, "max_bytes": "4MiB", "linger": "1ms"}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        for batch in [
+            r#"{"max_messages": 0, "max_bytes": "1MiB", "linger": "1ms"}"#,
+            r#"{"max_messages": 64, "max_bytes": "0B", "linger": "1ms"}"#,
+            r#"{"max_messages": 64, "max_bytes": "5MiB", "linger": "1ms"}"#,
+        ] {
+            let error = load(&format!(
+                r#"{{"grpc": {{"endpoint": "127.0.0.1:10000", "batch": {batch}}}}}"#
+            ))
+            .unwrap_err();
+            assert!(error.contains("invalid batch config"), "{error}");
         }
     }
 }
@@ -0,0 +1,286 @@
+use {
+    crate::{
+        channel::{MessageMeta, Sender},
+        metrics,
+        protobuf::{ProtobufEncoder, ProtobufMessage},
+    },
+    agave_geyser_plugin_interface::geyser_plugin_interface::{ReplicaAccountInfoV3, SlotStatus},
+    crossbeam_channel as channel,
+    log::error,
+    metrics_exporter_prometheus::PrometheusRecorder,
+    richat_metrics::{Gauge, MaybeRecorder, PrefixedRecorder, gauge},
+    solana_sdk::{clock::Slot, transaction::SanitizedTransaction},
+    std::sync::Arc,
+    tokio::{
+        runtime::Handle,
+        sync::oneshot,
+        task::{JoinError, JoinHandle},
+    },
+};
+
+/// Account update copied from the Geyser callback
+#[derive(Debug)]
+struct AccountJob {
+    slot: Slot,
+    pubkey: Vec<u8>,
+    lamports: u64,
+    owner: Vec<u8>,
+    executable: bool,
+    rent_epoch: u64,
+    data: Vec<u8>,
+    write_version: u64,
+    txn: Option<SanitizedTransaction>,
+    original_data_len: Option<usize>,
+    is_startup: bool,
+    owner_changed: bool,
+    tx: oneshot::Sender<(MessageMeta, Vec<u8>)>,
+}
+
+impl AccountJob {
+    fn encode(&self, messages: &Sender, encoder: ProtobufEncoder) -> (MessageMeta, Vec<u8>) {
+        let account = ReplicaAccountInfoV3 {
+            pubkey: &self.pubkey,
+            lamports: self.lamports,
+            owner: &self.owner,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch,
+            data: &self.data,
+            write_version: self.write_version,
+            txn: self.txn.as_ref(),
+        };
+        let message = ProtobufMessage::Account {
+            slot: self.slot,
+            account: &account,
+            original_data_len: self.original_data_len,
+            is_startup: self.is_startup,
+            owner_changed: self.owner_changed,
+        };
+        (
+            MessageMeta::from(&message),
+            messages.encode(&message, encoder),
+        )
+    }
+}
+
+/// Messages in the order of Geyser callbacks
+#[derive(Debug)]
+enum PoolItem {
+    /// Account encoded by the pool
+    Account(oneshot::Receiver<(MessageMeta, Vec<u8>)>),
+    /// Slot status, pushed as is because channel can add missed statuses of parent slots
+    Slot {
+        slot: Slot,
+        parent: Option<Slot>,
+        status: SlotStatus,
+    },
+    /// Message encoded on the Geyser thread
+    Encoded(MessageMeta, Vec<u8>),
+}
+
+/// Encodes account updates on the pool of blocking threads, so Geyser callbacks only copy data.
+///
+/// Transactions, entries and blocks meta are still encoded on the Geyser thread: copy of them
+/// costs as much as encoding. All messages are pushed to the channel in the callbacks order.
+/// Queues are bounded, so Geyser callbacks wait while the channel blocks on overflow.
+#[derive(Debug)]
+pub struct EncoderPool {
+    messages: Sender,
+    encoder: ProtobufEncoder,
+    items: channel::Sender<PoolItem>,
+    jobs: channel::Sender<AccountJob>,
+    queue_size: Gauge,
+    pusher: JoinHandle<()>,
+}
+
+impl EncoderPool {
+    pub fn spawn(
+        runtime: &Handle,
+        threads: usize,
+        queue_size: usize,
+        messages: Sender,
+        encoder: ProtobufEncoder,
+        recorder: &Arc<MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>>,
+    ) -> Self {
+        let (jobs, jobs_rx) = channel::bounded::<AccountJob>(queue_size);
+        for _ in 0..threads {
+            let jobs_rx = jobs_rx.clone();
+            let messages = messages.clone();
+            runtime.spawn_blocking(move || {
+                while let Ok(job) = jobs_rx.recv() {
+                    let encoded = job.encode(&messages, encoder);
+                    let _ = job.tx.send(encoded);
+                }
+            });
+        }
+
+        let (items, items_rx) = channel::bounded(queue_size);
+        let queue_size = gauge!(recorder, metrics::ENCODER_QUEUE_SIZE);
+        let pusher = runtime.spawn_blocking({
+            let messages = messages.clone();
+            let queue_size = queue_size.clone();
+            move || {
+                while let Ok(item) = items_rx.recv() {
+                    match item {
+                        PoolItem::Account(rx) => {
+                            match rx.blocking_recv() {
+                                Ok((meta, data)) => messages.push_encoded(meta, data),
+                                Err(_) => error!("account message lost by encoder pool"),
+                            }
+                            queue_size.decrement(1);
+                        }
+                        PoolItem::Slot {
+                            slot,
+                            parent,
+                            status,
+                        } => messages.push(
+                            ProtobufMessage::Slot {
+                                slot,
+                                parent,
+                                status: &status,
+                            },
+                            encoder,
+                        ),
+                        PoolItem::Encoded(meta, data) => messages.push_encoded(meta, data),
+                    }
+                }
+            }
+        });
+
+        Self {
+            messages,
+            encoder,
+            items,
+            jobs,
+            queue_size,
+            pusher,
+        }
+    }
+
+    pub fn push(&self, message: ProtobufMessage) {
+        let item = match message {
+            ProtobufMessage::Account {
+                slot,
+                account,
+                original_data_len,
+                is_startup,
+                owner_changed,
+            } => {
+                let (tx, rx) = oneshot::channel();
+                let job = AccountJob {
+                    slot,
+                    pubkey: account.pubkey.to_vec(),
+                    lamports: account.lamports,
+                    owner: account.owner.to_vec(),
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                    data: account.data.to_vec(),
+                    write_version: account.write_version,
+                    txn: account.txn.cloned(),
+                    original_data_len,
+                    is_startup,
+                    owner_changed,
+                    tx,
+                };
+                if self.jobs.send(job).is_err() {
+                    error!("encoder pool is closed");
+                    return;
+                }
+                self.queue_size.increment(1);
+                PoolItem::Account(rx)
+            }
+            ProtobufMessage::Slot {
+                slot,
+                parent,
+                status,
+            } => PoolItem::Slot {
+                slot,
+                parent,
+                status: status.clone(),
+            },
+            message => PoolItem::Encoded(
+                MessageMeta::from(&message),
+                self.messages.encode(&message, self.encoder),
+            ),
+        };
+        if self.items.send(item).is_err() {
+            error!("encoder pool is closed");
+        }
+    }
+
+    /// Wait until all queued messages are pushed to the channel
+    pub async fn shutdown(self) -> Result<(), JoinError> {
+        drop(self.jobs);
+        drop(self.items);
+        self.pusher.await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::EncoderPool,
+        crate::{
+            channel::Sender,
+            config::{ConfigChannel, ConfigChannelOverflow},
+            protobuf::{ProtobufEncoder, ProtobufMessage},
+        },
+        agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus,
+        futures::{TryStreamExt, executor::block_on, stream::StreamExt},
+        richat_metrics::MaybeRecorder,
+        richat_shared::transports::Subscribe,
+        std::{
+            sync::{
+                Arc,
+                atomic::{AtomicUsize, Ordering},
+            },
+            thread,
+            time::Duration,
+        },
+        tokio::runtime::Runtime,
+    };
+
+    #[test]
+    fn test_block_overflow_reaches_callbacks() {
+        let config = ConfigChannel {
+            max_messages: 4,
+            overflow: ConfigChannelOverflow::Block,
+            overflow_timeout: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let sender = Sender::new(config, Arc::new(MaybeRecorder::Noop));
+        let stream = sender.subscribe(None, None).expect("subscribe");
+        let runtime = Runtime::new().expect("runtime");
+        let pool = EncoderPool::spawn(
+            runtime.handle(),
+            1,
+            2,
+            sender,
+            ProtobufEncoder::Raw,
+            &Arc::new(MaybeRecorder::Noop),
+        );
+
+        let pushed = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for slot in 0..100 {
+                    pool.push(ProtobufMessage::Slot {
+                        slot,
+                        parent: None,
+                        status: &SlotStatus::Processed,
+                    });
+                    pushed.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+
+            // channel is full and receiver does not read, so callbacks wait on the pool queue
+            thread::sleep(Duration::from_millis(200));
+            let blocked = pushed.load(Ordering::Relaxed);
+            assert!(blocked < 10, "pushed {blocked} messages");
+
+            let received = block_on(stream.take(100).try_collect::<Vec<_>>()).expect("received");
+            assert_eq!(received.len(), 100);
+        });
+        assert_eq!(pushed.load(Ordering::Relaxed), 100);
+        runtime.block_on(pool.shutdown()).expect("shutdown");
+    }
+}
@@ -1,6 +1,10 @@
+pub mod admin;
 pub mod channel;
 pub mod config;
+pub mod encoder;
+pub mod logger;
 pub mod metrics;
 pub mod plugin;
 pub mod protobuf;
+pub mod sink;
 pub mod version;
@@ -0,0 +1,63 @@
+use {
+    crate::config::{ConfigLogs, ConfigLogsFormat},
+    log::{Log, Metadata, Record},
+    std::{
+        io::Write,
+        sync::{LazyLock, RwLock},
+    },
+};
+
+// `log` allows to set logger only once, so logger is replaceable behind the shim
+static LOGGER: LazyLock<RwLock<Option<env_logger::Logger>>> = LazyLock::new(Default::default);
+
+struct LoggerShim;
+
+impl Log for LoggerShim {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        LOGGER
+            .read()
+            .ok()
+            .and_then(|logger| logger.as_ref().map(|logger| logger.enabled(metadata)))
+            .unwrap_or_default()
+    }
+
+    fn log(&self, record: &Record) {
+        if let Ok(logger) = LOGGER.read() {
+            if let Some(logger) = logger.as_ref() {
+                logger.log(record);
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Configure logger, filter from `RUST_LOG` has priority over level from the config.
+/// Can be called multiple times, every call replaces the logger.
+pub fn setup(config: &ConfigLogs) {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::new().default_filter_or(&config.level));
+    match config.format {
+        ConfigLogsFormat::Plain => {
+            builder.format_timestamp_nanos();
+        }
+        ConfigLogsFormat::Json => {
+            builder.format(|buf, record| {
+                let line = serde_json::json!({
+                    "timestamp": buf.timestamp_nanos().to_string(),
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "message": record.args().to_string(),
+                });
+                writeln!(buf, "{line}")
+            });
+        }
+    }
+    let logger = builder.build();
+
+    log::set_max_level(logger.filter());
+    if let Ok(mut current) = LOGGER.write() {
+        *current = Some(logger);
+    }
+    let _ = log::set_boxed_logger(Box::new(LoggerShim));
+}
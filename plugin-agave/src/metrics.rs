@@ -1,7 +1,10 @@
 use {
     crate::version::VERSION as VERSION_INFO,
     metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle, PrometheusRecorder},
-    richat_metrics::{ConfigMetrics, counter, describe_counter, describe_gauge},
+    richat_metrics::{
+        ConfigMetrics, PrefixedRecorder, counter, describe_counter, describe_gauge,
+        describe_histogram,
+    },
     std::{future::Future, io},
     tokio::{
         task::JoinError,
@@ -10,20 +13,55 @@ use {
 };
 
 pub const GEYSER_SLOT_STATUS: &str = "geyser_slot_status"; // status
+pub const LAST_SLOT: &str = "last_slot";
+pub const STARTUP: &str = "startup";
+pub const PANICS_TOTAL: &str = "panics_total"; // task
 pub const GEYSER_MISSED_SLOT_STATUS: &str = "geyser_missed_slot_status_total"; // status
+pub const GEYSER_UNSUPPORTED_VERSION: &str = "geyser_unsupported_version_total"; // notification, version
+pub const GEYSER_SLOT_STATUS_DEDUPLICATED: &str = "geyser_slot_status_deduplicated_total"; // status
+pub const GEYSER_ACCOUNT_FILTERED: &str = "geyser_account_filtered_total"; // filter
+pub const GEYSER_ACCOUNT_COALESCED: &str = "geyser_account_coalesced_total"; // reason
+pub const GEYSER_ACCOUNT_OWNER_CHANGED: &str = "geyser_account_owner_changed_total";
+pub const GEYSER_ACCOUNT_OWNERS_TRACKED: &str = "geyser_account_owners_tracked";
+pub const GEYSER_ACCOUNT_UPDATES_BY_OWNER: &str = "geyser_account_updates_by_owner_total"; // owner
+pub const GEYSER_TRANSACTION_FILTERED: &str = "geyser_transaction_filtered_total"; // filter
+pub const GEYSER_ENTRY_FILTERED: &str = "geyser_entry_filtered_total"; // filter
+pub const CALLBACK_DURATION_SECONDS: &str = "callback_duration_seconds"; // callback
+pub const MESSAGES_TOTAL: &str = "messages_total"; // notification
+pub const MESSAGES_OVERSIZED_TOTAL: &str = "messages_oversized_total"; // notification
 pub const CHANNEL_MESSAGES_TOTAL: &str = "channel_messages_total";
 pub const CHANNEL_SLOTS_TOTAL: &str = "channel_slots_total";
 pub const CHANNEL_BYTES_TOTAL: &str = "channel_bytes_total";
-pub const CONNECTIONS_TOTAL: &str = "connections_total"; // transport
+pub const CHANNEL_SLOT_LAG: &str = "channel_slot_lag";
+pub const CHANNEL_DROPPED_TOTAL: &str = "channel_dropped_total"; // notification, reason
+pub const CHANNEL_SLOTS_CAPPED_TOTAL: &str = "channel_slots_capped_total";
+pub const CHANNEL_BLOCKED_SECONDS: &str = "channel_blocked_seconds";
+pub const CHANNEL_BUFFER_POOL_TOTAL: &str = "channel_buffer_pool_total"; // result
+pub const CHANNEL_COMPRESS_ERRORS_TOTAL: &str = "channel_compress_errors_total";
+pub const CHANNEL_NOTIFICATION_MESSAGES_TOTAL: &str = "channel_notification_messages_total"; // notification
+pub const CHANNEL_NOTIFICATION_BYTES_TOTAL: &str = "channel_notification_bytes_total"; // notification
+pub const ENCODER_QUEUE_SIZE: &str = "encoder_queue_size";
+pub const CLIENT_SEND_QUEUE: &str = "client_send_queue_bytes"; // transport, stat
+pub const CLIENT_LAG_MESSAGES: &str = "client_lag_messages"; // transport
+pub const MESSAGE_AGE_SECONDS: &str = "message_age_seconds"; // transport, notification
+pub const CONNECTIONS_TOTAL: &str = "connections_total"; // transport, endpoint (gRPC only)
+pub const CONNECTIONS_REJECTED_TOTAL: &str = "connections_rejected_total"; // transport, endpoint (gRPC only), reason
+pub const CONNECTIONS_IDLE_TIMEOUT_TOTAL: &str = "connections_idle_timeout_total"; // transport, endpoint (gRPC only)
+pub const CONNECTIONS_THROTTLED_TOTAL: &str = "connections_throttled_total"; // transport, endpoint (gRPC only)
+pub const CONNECTIONS_COMPRESSED_TOTAL: &str = "connections_compressed_total"; // transport, endpoint (gRPC only), encoding
+pub const CONNECTION_DURATION_SECONDS: &str = "connection_duration_seconds"; // transport, endpoint (gRPC only)
+pub const GRPC_BATCH_MESSAGES: &str = "grpc_batch_messages"; // endpoint
+pub const GRPC_CLIENT_CONNECTIONS_TOTAL: &str = "grpc_client_connections_total"; // endpoint, client
 
 #[rustfmt::skip]
-pub fn setup() -> PrometheusRecorder {
-    let recorder = PrometheusBuilder::new().build_recorder();
+pub fn setup(namespace: Option<String>) -> PrefixedRecorder<PrometheusRecorder> {
+    let recorder = PrefixedRecorder::new(PrometheusBuilder::new().build_recorder(), namespace);
 
     describe_counter!(recorder, "version", "Richat Plugin version info");
     counter!(
         recorder,
         "version",
+        "agave" => VERSION_INFO.agave.unwrap_or_default(),
         "buildts" => VERSION_INFO.buildts,
         "git" => VERSION_INFO.git,
         "package" => VERSION_INFO.package,
@@ -35,33 +73,71 @@ pub fn setup() -> PrometheusRecorder {
     .absolute(1);
 
     describe_gauge!(recorder, GEYSER_SLOT_STATUS, "Latest slot received from Geyser");
+    describe_gauge!(recorder, LAST_SLOT, "Max slot observed in Geyser slot status updates, before filters");
+    describe_gauge!(recorder, STARTUP, "Validator sends startup snapshot accounts, 1 until end of startup");
+    describe_counter!(recorder, PANICS_TOTAL, "Number of panics in plugin threads, task is thread name without index");
     describe_counter!(recorder, GEYSER_MISSED_SLOT_STATUS, "Number of missed slot status updates");
+    describe_counter!(recorder, GEYSER_UNSUPPORTED_VERSION, "Number of skipped Geyser notifications with unsupported version");
+    describe_counter!(recorder, GEYSER_SLOT_STATUS_DEDUPLICATED, "Number of repeated slot status updates skipped by deduplication");
+    describe_counter!(recorder, GEYSER_ACCOUNT_FILTERED, "Number of account updates skipped by filters");
+    describe_counter!(recorder, GEYSER_ACCOUNT_COALESCED, "Number of account updates not sent due to coalescing per slot (replaced/discarded)");
+    describe_counter!(recorder, GEYSER_ACCOUNT_OWNER_CHANGED, "Number of account updates flagged with changed owner");
+    describe_gauge!(recorder, GEYSER_ACCOUNT_OWNERS_TRACKED, "Number of accounts with tracked last owner");
+    describe_counter!(recorder, GEYSER_ACCOUNT_UPDATES_BY_OWNER, "Number of account updates by owners from the config, the rest are counted as other");
+    describe_counter!(recorder, GEYSER_TRANSACTION_FILTERED, "Number of transactions skipped by filters");
+    describe_counter!(recorder, GEYSER_ENTRY_FILTERED, "Number of entries skipped by filters");
+    describe_histogram!(recorder, CALLBACK_DURATION_SECONDS, "Duration of Geyser callbacks on validator threads");
+    describe_counter!(recorder, MESSAGES_TOTAL, "Number of messages pushed to channel by notification type");
+    describe_counter!(recorder, MESSAGES_OVERSIZED_TOTAL, "Number of messages dropped due to encoded size over max_message_bytes");
     describe_gauge!(recorder, CHANNEL_MESSAGES_TOTAL, "Total number of messages in channel");
     describe_gauge!(recorder, CHANNEL_SLOTS_TOTAL, "Total number of slots in channel");
     describe_gauge!(recorder, CHANNEL_BYTES_TOTAL, "Total size of all messages in channel");
+    describe_gauge!(recorder, CHANNEL_SLOT_LAG, "Difference between latest slot from Geyser and oldest slot in channel");
+    describe_counter!(recorder, CHANNEL_DROPPED_TOTAL, "Number of messages dropped from channel due to limits");
+    describe_counter!(recorder, CHANNEL_SLOTS_CAPPED_TOTAL, "Number of slots which reached max messages per slot");
+    describe_histogram!(recorder, CHANNEL_BLOCKED_SECONDS, "Time spent by Geyser callbacks waiting for free space in channel");
+    describe_counter!(recorder, CHANNEL_BUFFER_POOL_TOTAL, "Number of buffers requests for encoded messages by result (hit/miss)");
+    describe_counter!(recorder, CHANNEL_COMPRESS_ERRORS_TOTAL, "Number of messages pushed uncompressed due to compression errors");
+    describe_gauge!(recorder, CHANNEL_NOTIFICATION_MESSAGES_TOTAL, "Total number of messages in channel by notification type");
+    describe_gauge!(recorder, CHANNEL_NOTIFICATION_BYTES_TOTAL, "Total size of messages in channel by notification type");
+    describe_gauge!(recorder, ENCODER_QUEUE_SIZE, "Number of account messages waiting for encoding in the pool");
+    describe_gauge!(recorder, CLIENT_SEND_QUEUE, "Max and sum of bytes in channel not yet read by clients of transport, updated on processed slot");
+    describe_gauge!(recorder, CLIENT_LAG_MESSAGES, "Max number of messages in channel not yet read by clients of transport, updated on processed slot");
+    describe_histogram!(recorder, MESSAGE_AGE_SECONDS, "Time between message push to channel and read by transport");
     describe_gauge!(recorder, CONNECTIONS_TOTAL, "Total number of connections");
+    describe_counter!(recorder, CONNECTIONS_REJECTED_TOTAL, "Total number of rejected connections");
+    describe_counter!(recorder, CONNECTIONS_IDLE_TIMEOUT_TOTAL, "Total number of connections closed because client did not read within idle timeout");
+    describe_gauge!(recorder, CONNECTIONS_THROTTLED_TOTAL, "Number of connections throttled by rate limit");
+    describe_counter!(recorder, CONNECTIONS_COMPRESSED_TOTAL, "Total number of connections with negotiated compression");
+    describe_histogram!(recorder, CONNECTION_DURATION_SECONDS, "Duration of closed connections");
+    describe_histogram!(recorder, GRPC_BATCH_MESSAGES, "Number of messages in gRPC batches");
+    describe_gauge!(recorder, GRPC_CLIENT_CONNECTIONS_TOTAL, "Number of gRPC connections by client identity from TLS certificate");
 
     recorder
 }
 
-pub async fn spawn_server(
-    config: ConfigMetrics,
-    handle: PrometheusHandle,
-    shutdown: impl Future<Output = ()> + Send + 'static,
-) -> io::Result<impl Future<Output = Result<(), JoinError>>> {
-    let recorder_handle = handle.clone();
+/// Periodic upkeep of recorder, required for histograms even if metrics are not served
+pub fn spawn_upkeep(handle: PrometheusHandle) {
     tokio::spawn(async move {
         loop {
             sleep(Duration::from_secs(1)).await;
-            recorder_handle.run_upkeep();
+            handle.run_upkeep();
         }
     });
+}
 
+pub async fn spawn_server(
+    config: ConfigMetrics,
+    handle: PrometheusHandle,
+    is_health_check: impl Fn() -> bool + Clone + Send + 'static,
+    is_ready_check: impl Fn() -> bool + Clone + Send + 'static,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<impl Future<Output = Result<(), JoinError>>> {
     richat_metrics::spawn_server(
         config,
         move || handle.render().into_bytes(), // metrics
-        || true,                              // health
-        || true,                              // ready
+        is_health_check,
+        is_ready_check,
         shutdown,
     )
     .await
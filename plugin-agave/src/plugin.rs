@@ -1,23 +1,57 @@
 use {
     crate::{
+        admin,
         channel::Sender,
-        config::{Config, ConfigFilters},
-        metrics,
-        protobuf::{ProtobufEncoder, ProtobufMessage},
-        version::VERSION,
+        config::{Config, ConfigFilters, ConfigLogs},
+        encoder::EncoderPool,
+        logger, metrics,
+        protobuf::{ProtobufEncoder, ProtobufMessage, SlotCounts},
+        sink::FileSink,
+        version::{self, VERSION},
     },
     agave_geyser_plugin_interface::geyser_plugin_interface::{
-        GeyserPlugin, GeyserPluginError, ReplicaAccountInfoVersions, ReplicaBlockInfoVersions,
-        ReplicaEntryInfoVersions, ReplicaTransactionInfoVersions, Result as PluginResult,
-        SlotStatus,
+        GeyserPlugin, GeyserPluginError, ReplicaAccountInfoV3, ReplicaAccountInfoVersions,
+        ReplicaBlockInfoV4, ReplicaBlockInfoVersions, ReplicaEntryInfoVersions,
+        ReplicaTransactionInfoVersions, Result as PluginResult, SlotStatus,
+    },
+    arc_swap::ArcSwap,
+    futures::future::{BoxFuture, FutureExt},
+    log::{error, info, warn},
+    lru::LruCache,
+    metrics_exporter_prometheus::PrometheusRecorder,
+    richat_metrics::{
+        ConfigMetrics, Counter, Gauge, Histogram, MaybeRecorder, PrefixedRecorder, counter,
+        duration_to_seconds, gauge, histogram,
+    },
+    richat_shared::{
+        config::ConfigTokio,
+        transports::{
+            grpc::GrpcServer, quic::QuicServer, tcp::TcpServer, uds::UdsServer,
+            websocket::WebSocketServer,
+        },
+    },
+    solana_sdk::{clock::Slot, pubkey::Pubkey, transaction::SanitizedTransaction},
+    solana_transaction_status::RewardsAndNumPartitions,
+    std::{
+        backtrace::Backtrace,
+        collections::{BTreeMap, HashMap, HashSet, VecDeque},
+        fmt,
+        future::Future,
+        io,
+        num::NonZeroUsize,
+        panic::{self, PanicHookInfo},
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicBool, AtomicU64, Ordering},
+        },
+        thread,
+        time::Duration,
+    },
+    tokio::{
+        runtime::{Handle, Runtime},
+        task::JoinError,
+        time::{Instant, sleep, timeout_at},
     },
-    futures::future::BoxFuture,
-    log::error,
-    richat_metrics::{MaybeRecorder, gauge},
-    richat_shared::transports::{grpc::GrpcServer, quic::QuicServer},
-    solana_sdk::clock::Slot,
-    std::{fmt, sync::Arc, time::Duration},
-    tokio::{runtime::Runtime, task::JoinError},
     tokio_util::sync::CancellationToken,
 };
 
@@ -30,6 +64,50 @@ pub enum PluginNotification {
     BlockMeta,
 }
 
+impl PluginNotification {
+    pub const ALL: [Self; 5] = [
+        Self::Slot,
+        Self::Account,
+        Self::Transaction,
+        Self::Entry,
+        Self::BlockMeta,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Slot => "slot",
+            Self::Account => "account",
+            Self::Transaction => "transaction",
+            Self::Entry => "entry",
+            Self::BlockMeta => "block_meta",
+        }
+    }
+
+    /// Name of `GeyserPlugin` method with the notification
+    pub const fn callback(self) -> &'static str {
+        match self {
+            Self::Slot => "update_slot_status",
+            Self::Account => "update_account",
+            Self::Transaction => "notify_transaction",
+            Self::Entry => "notify_entry",
+            Self::BlockMeta => "notify_block_metadata",
+        }
+    }
+}
+
+/// Records duration of Geyser callback on drop, so early returns are recorded too
+struct CallbackTimer<'a> {
+    histogram: &'a Histogram,
+    start: Instant,
+}
+
+impl Drop for CallbackTimer<'_> {
+    fn drop(&mut self) {
+        self.histogram
+            .record(duration_to_seconds(self.start.elapsed()));
+    }
+}
+
 impl From<&ProtobufMessage<'_>> for PluginNotification {
     fn from(value: &ProtobufMessage<'_>) -> Self {
         match value {
@@ -38,6 +116,7 @@ impl From<&ProtobufMessage<'_>> for PluginNotification {
             ProtobufMessage::Transaction { .. } => Self::Transaction,
             ProtobufMessage::Entry { .. } => Self::Entry,
             ProtobufMessage::BlockMeta { .. } => Self::BlockMeta,
+            ProtobufMessage::SlotComplete { .. } => Self::Slot,
         }
     }
 }
@@ -46,31 +125,395 @@ struct PluginTask(BoxFuture<'static, Result<(), JoinError>>);
 
 unsafe impl Sync for PluginTask {}
 
+impl PluginTask {
+    /// Spawn task on the current runtime to report panic when it happened, not only on unload
+    fn spawn<F>(name: &'static str, fut: F) -> (&'static str, Self)
+    where
+        F: Future<Output = Result<(), JoinError>> + Send + 'static,
+    {
+        let jh = tokio::spawn(async move {
+            let result = fut.await;
+            if let Err(error) = &result {
+                if error.is_panic() {
+                    error!("`{name}` task panicked: {error:?}");
+                }
+            }
+            result
+        });
+        (
+            name,
+            Self(Box::pin(jh.map(|result| result.and_then(|result| result)))),
+        )
+    }
+}
+
 impl fmt::Debug for PluginTask {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PluginTask").finish()
     }
 }
 
+/// Run future on the dedicated runtime if any, so tasks spawned by it stay on that runtime
+async fn spawn_on<F>(runtime: Option<Handle>, fut: F) -> Result<F::Output, JoinError>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match runtime {
+        Some(handle) => handle.spawn(fut).await,
+        None => Ok(fut.await),
+    }
+}
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+/// Hook installed by the plugin, the previous hook should be restored on unload because hook
+/// code is unloaded with the library
+#[derive(Default)]
+struct PluginPanicHook {
+    previous: Arc<Mutex<Option<PanicHook>>>,
+}
+
+impl fmt::Debug for PluginPanicHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginPanicHook").finish()
+    }
+}
+
+impl PluginPanicHook {
+    /// Panics of plugin threads are logged with backtrace and counted, then previous hook is called
+    fn install(recorder: Arc<MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>>) -> Self {
+        let previous = Arc::new(Mutex::new(Some(panic::take_hook())));
+        panic::set_hook(Box::new({
+            let previous = Arc::clone(&previous);
+            move |info| {
+                let thread = thread::current();
+                if let Some(name) = thread
+                    .name()
+                    .filter(|name| name.starts_with("richatPlugin"))
+                {
+                    let task = name.trim_end_matches(|ch: char| ch.is_ascii_digit());
+                    counter!(&recorder, metrics::PANICS_TOTAL, "task" => task.to_owned())
+                        .increment(1);
+                    error!(
+                        "panic in thread `{name}`: {info}\n{}",
+                        Backtrace::force_capture()
+                    );
+                }
+
+                let previous = match previous.lock() {
+                    Ok(previous) => previous,
+                    Err(error) => error.into_inner(),
+                };
+                if let Some(hook) = previous.as_ref() {
+                    hook(info);
+                }
+            }
+        }));
+        Self { previous }
+    }
+
+    fn restore(self) {
+        let previous = match self.previous.lock() {
+            Ok(mut previous) => previous.take(),
+            Err(error) => error.into_inner().take(),
+        };
+        if let Some(hook) = previous {
+            panic::set_hook(hook);
+        }
+    }
+}
+
+/// Bounded set of sent `(slot, status)` pairs, oldest pairs are evicted first
+#[derive(Debug, Default)]
+struct SlotStatusesSeen {
+    set: HashSet<(Slot, &'static str)>,
+    queue: VecDeque<(Slot, &'static str)>,
+}
+
+impl SlotStatusesSeen {
+    /// Returns `false` if pair already exists
+    fn insert(&mut self, slot: Slot, status: &'static str, max_size: usize) -> bool {
+        if !self.set.insert((slot, status)) {
+            return false;
+        }
+        self.queue.push_back((slot, status));
+        while self.queue.len() > max_size {
+            if let Some(key) = self.queue.pop_front() {
+                self.set.remove(&key);
+            }
+        }
+        true
+    }
+}
+
+/// Last seen owner of accounts, least recently updated accounts are evicted
+#[derive(Debug, Default)]
+struct AccountOwners {
+    owners: Option<LruCache<Pubkey, Pubkey>>,
+}
+
+impl AccountOwners {
+    /// Returns `true` if owner differs from the previous update, unknown account is not changed
+    fn update(&mut self, pubkey: Pubkey, owner: Pubkey, max_size: NonZeroUsize) -> bool {
+        let owners = self.owners.get_or_insert_with(|| LruCache::new(max_size));
+        if owners.cap() != max_size {
+            owners.resize(max_size);
+        }
+        owners
+            .put(pubkey, owner)
+            .is_some_and(|previous| previous != owner)
+    }
+
+    fn len(&self) -> usize {
+        self.owners.as_ref().map_or(0, |owners| owners.len())
+    }
+}
+
+/// Counters of account updates for owners from the config, created once to keep lookup cheap
+#[derive(Debug)]
+struct AccountUpdatesByOwner {
+    owners: HashMap<Pubkey, Counter>,
+    other: Counter,
+}
+
+impl AccountUpdatesByOwner {
+    fn new(
+        owners: HashSet<Pubkey>,
+        recorder: &MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>,
+    ) -> Self {
+        Self {
+            owners: owners
+                .into_iter()
+                .map(|owner| {
+                    let counter = counter!(recorder, metrics::GEYSER_ACCOUNT_UPDATES_BY_OWNER, "owner" => owner.to_string());
+                    (owner, counter)
+                })
+                .collect(),
+            other: counter!(recorder, metrics::GEYSER_ACCOUNT_UPDATES_BY_OWNER, "owner" => "other"),
+        }
+    }
+
+    fn increment(&self, owner: &[u8]) {
+        Pubkey::try_from(owner)
+            .ok()
+            .and_then(|owner| self.owners.get(&owner))
+            .unwrap_or(&self.other)
+            .increment(1);
+    }
+}
+
+/// Counters of the metric by label, created once for labels known on load, other labels are
+/// registered on every increment
+#[derive(Debug)]
+struct LabeledCounters {
+    name: &'static str,
+    label: &'static str,
+    counters: Vec<(&'static str, Counter)>,
+}
+
+impl LabeledCounters {
+    fn new(
+        recorder: &MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>,
+        name: &'static str,
+        label: &'static str,
+        values: &[&'static str],
+    ) -> Self {
+        Self {
+            name,
+            label,
+            counters: values
+                .iter()
+                .map(|value| (*value, counter!(recorder, name, label => *value)))
+                .collect(),
+        }
+    }
+
+    fn increment(
+        &self,
+        recorder: &MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>,
+        value: &'static str,
+    ) {
+        match self
+            .counters
+            .iter()
+            .find(|(known, _counter)| *known == value)
+        {
+            Some((_value, counter)) => counter.increment(1),
+            None => counter!(recorder, self.name, self.label => value).increment(1),
+        }
+    }
+}
+
+/// Metrics updated on Geyser callbacks, created once to not build prefixed keys on every update
+#[derive(Debug)]
+struct GeyserMetrics {
+    last_slot: Gauge,
+    account_filtered: LabeledCounters,
+    account_coalesced_replaced: Counter,
+    account_coalesced_discarded: Counter,
+    account_owner_changed: Counter,
+    account_owners_tracked: Gauge,
+    transaction_filtered: LabeledCounters,
+    entry_filtered: LabeledCounters,
+}
+
+impl GeyserMetrics {
+    fn new(recorder: &MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>) -> Self {
+        Self {
+            last_slot: gauge!(recorder, metrics::LAST_SLOT),
+            account_filtered: LabeledCounters::new(
+                recorder,
+                metrics::GEYSER_ACCOUNT_FILTERED,
+                "filter",
+                &["sysvar", "pubkey", "owner", "memcmp", "data_size", "group"],
+            ),
+            account_coalesced_replaced: counter!(recorder, metrics::GEYSER_ACCOUNT_COALESCED, "reason" => "replaced"),
+            account_coalesced_discarded: counter!(recorder, metrics::GEYSER_ACCOUNT_COALESCED, "reason" => "discarded"),
+            account_owner_changed: counter!(recorder, metrics::GEYSER_ACCOUNT_OWNER_CHANGED),
+            account_owners_tracked: gauge!(recorder, metrics::GEYSER_ACCOUNT_OWNERS_TRACKED),
+            transaction_filtered: LabeledCounters::new(
+                recorder,
+                metrics::GEYSER_TRANSACTION_FILTERED,
+                "filter",
+                &[
+                    "signature",
+                    "vote",
+                    "failed",
+                    "program",
+                    "account_include",
+                    "account_exclude",
+                ],
+            ),
+            entry_filtered: LabeledCounters::new(
+                recorder,
+                metrics::GEYSER_ENTRY_FILTERED,
+                "filter",
+                &["empty"],
+            ),
+        }
+    }
+}
+
+/// Account update copied from the Geyser callback, kept until slot is processed
+#[derive(Debug)]
+struct CoalescedAccount {
+    pubkey: Pubkey,
+    lamports: u64,
+    owner: Vec<u8>,
+    executable: bool,
+    rent_epoch: u64,
+    data: Vec<u8>,
+    write_version: u64,
+    txn: Option<SanitizedTransaction>,
+    original_data_len: Option<usize>,
+}
+
+/// Latest account updates per slot by pubkey, the number of buffered updates is limited
+#[derive(Debug, Default)]
+struct AccountsCoalesced {
+    slots: BTreeMap<Slot, HashMap<Pubkey, CoalescedAccount>>,
+    total: usize,
+}
+
+impl AccountsCoalesced {
+    /// Returns `true` if update replaced the previous one
+    fn insert(&mut self, slot: Slot, account: CoalescedAccount) -> bool {
+        let accounts = self.slots.entry(slot).or_default();
+        match accounts.get_mut(&account.pubkey) {
+            Some(existed) => {
+                if existed.write_version < account.write_version {
+                    *existed = account;
+                }
+                true
+            }
+            None => {
+                accounts.insert(account.pubkey, account);
+                self.total += 1;
+                false
+            }
+        }
+    }
+
+    fn remove(&mut self, slot: Slot) -> Option<HashMap<Pubkey, CoalescedAccount>> {
+        let accounts = self.slots.remove(&slot)?;
+        self.total -= accounts.len();
+        Some(accounts)
+    }
+
+    /// Remove slots from the oldest while over the limit
+    fn remove_overflow(&mut self, max: usize) -> Vec<(Slot, HashMap<Pubkey, CoalescedAccount>)> {
+        let mut removed = Vec::new();
+        while self.total > max {
+            let Some((slot, accounts)) = self.slots.pop_first() else {
+                break;
+            };
+            self.total -= accounts.len();
+            removed.push((slot, accounts));
+        }
+        removed
+    }
+
+    /// Remove slots before the rooted slot, they are on abandoned forks if still not processed
+    fn remove_before(&mut self, slot: Slot) -> usize {
+        let kept = self.slots.split_off(&slot);
+        let removed = std::mem::replace(&mut self.slots, kept)
+            .into_values()
+            .map(|accounts| accounts.len())
+            .sum();
+        self.total -= removed;
+        removed
+    }
+}
+
 #[derive(Debug)]
 pub struct PluginInner {
     runtime: Runtime,
+    runtimes: Vec<Runtime>,
     messages: Sender,
     encoder: ProtobufEncoder,
+    encoder_pool: Option<EncoderPool>,
     shutdown: CancellationToken,
     tasks: Vec<(&'static str, PluginTask)>,
-    filters: ConfigFilters,
+    filters: ArcSwap<ConfigFilters>,
+    /// Filters on load, validator asks which notifications are enabled only once
+    filters_loaded: Arc<ConfigFilters>,
+    filters_raw: Arc<ArcSwap<serde_json::Value>>,
+    unsupported_version_logged: [AtomicBool; PluginNotification::ALL.len()],
+    slot_statuses_seen: Mutex<SlotStatusesSeen>,
+    accounts_coalesced: Mutex<AccountsCoalesced>,
+    account_owners: Mutex<AccountOwners>,
+    account_updates_by_owner: Option<AccountUpdatesByOwner>,
+    slot_counts: Mutex<BTreeMap<Slot, SlotCounts>>,
+    last_slot: Arc<AtomicU64>,
+    /// Set once the first message is pushed to the channel, used by readiness warmup
+    first_message: Arc<AtomicBool>,
+    /// Validator sends startup snapshot accounts, until `notify_end_of_startup`
+    startup: AtomicBool,
+    callback_durations: [Histogram; PluginNotification::ALL.len()],
+    geyser_metrics: GeyserMetrics,
+    config_fixed: serde_json::Value,
+    metrics_recorder: Arc<MaybeRecorder<PrefixedRecorder<PrometheusRecorder>>>,
+    shutdown_timeout: Duration,
+    drain_on_shutdown: bool,
+    panic_hook: Option<PluginPanicHook>,
 }
 
 impl PluginInner {
     fn new(config: Config) -> PluginResult<Self> {
-        let (metrics_recorder, metrics_handle) = if config.metrics.is_some() {
-            let recorder = metrics::setup();
-            let handle = recorder.handle();
-            (Arc::new(recorder.into()), Some(handle))
-        } else {
-            (Arc::new(MaybeRecorder::Noop), None)
-        };
+        let (metrics_recorder, metrics_handle) =
+            if config.metrics.is_some() || config.record_metrics {
+                let recorder = metrics::setup(
+                    config
+                        .metrics
+                        .as_ref()
+                        .and_then(|config| config.namespace.clone()),
+                );
+                let handle = recorder.inner().handle();
+                (Arc::new(recorder.into()), Some(handle))
+            } else {
+                (Arc::new(MaybeRecorder::Noop), None)
+            };
 
         // Create Tokio runtime
         let runtime = config
@@ -78,32 +521,182 @@ impl PluginInner {
             .build_runtime("richatPlugin")
             .map_err(|error| GeyserPluginError::Custom(Box::new(error)))?;
 
+        // Create dedicated runtimes for transports
+        let mut runtimes = Vec::new();
+        let mut build_runtime = |config: Option<ConfigTokio>, thread_name_prefix| {
+            config
+                .map(|config| {
+                    let runtime = config.build_runtime(thread_name_prefix)?;
+                    let handle = runtime.handle().clone();
+                    runtimes.push(runtime);
+                    Ok(handle)
+                })
+                .transpose()
+                .map_err(|error: io::Error| GeyserPluginError::Custom(Box::new(error)))
+        };
+        let transports_runtime =
+            build_runtime(config.runtimes.transports, "richatPluginTransport")?;
+        let grpc_runtime = build_runtime(config.runtimes.grpc, "richatPluginGrpc")?
+            .or_else(|| transports_runtime.clone());
+        let quic_runtime = build_runtime(config.runtimes.quic, "richatPluginQuic")?
+            .or_else(|| transports_runtime.clone());
+        let tcp_runtime = build_runtime(config.runtimes.tcp, "richatPluginTcp")?
+            .or_else(|| transports_runtime.clone());
+        let uds_runtime = build_runtime(config.runtimes.uds, "richatPluginUds")?
+            .or_else(|| transports_runtime.clone());
+        let websocket_runtime = build_runtime(config.runtimes.websocket, "richatPluginWs")?
+            .or_else(|| transports_runtime.clone());
+
         // Create messages store
         let messages = Sender::new(config.channel, Arc::clone(&metrics_recorder));
+        let recorder = Arc::clone(&metrics_recorder);
+        gauge!(&recorder, metrics::STARTUP).set(1);
+
+        // Encode accounts out of Geyser threads
+        let encoder_pool = (config.channel.encoder_threads > 0).then(|| {
+            EncoderPool::spawn(
+                runtime.handle(),
+                config.channel.encoder_threads,
+                config.channel.encoder_queue_size,
+                messages.clone(),
+                config.channel.encoder,
+                &metrics_recorder,
+            )
+        });
+
+        // Health is ok once all servers started and until shutdown, ready also requires channel
+        // to not evict not finalized slots for too long and warmup to be completed
+        let shutdown = CancellationToken::new();
+        let started = Arc::new(AtomicBool::new(false));
+        let first_message = Arc::new(AtomicBool::new(false));
+        let is_health_check = {
+            let shutdown = shutdown.clone();
+            let started = Arc::clone(&started);
+            move || started.load(Ordering::Relaxed) && !shutdown.is_cancelled()
+        };
+        let is_ready_check = {
+            let is_health_check = is_health_check.clone();
+            let messages = messages.clone();
+            let config_health = config.health.unwrap_or_default();
+            let max_eviction_duration = config_health.max_eviction_duration;
+            let warmup = config_health.warmup;
+            let loaded_at = Instant::now();
+            let first_message = Arc::clone(&first_message);
+            move || {
+                is_health_check()
+                    && warmup.is_none_or(|warmup| {
+                        loaded_at.elapsed() >= warmup && first_message.load(Ordering::Relaxed)
+                    })
+                    && messages
+                        .evicting_duration()
+                        .is_none_or(|duration| duration < max_eviction_duration)
+            }
+        };
+
+        // Build details and active transports, to know what exactly is loaded from one line
+        let mut transports = config
+            .grpc
+            .iter()
+            .map(|endpoint| format!("grpc:{}", endpoint.name))
+            .collect::<Vec<_>>();
+        for (name, enabled) in [
+            ("quic", config.quic.is_some()),
+            ("tcp", config.tcp.is_some()),
+            ("uds", config.uds.is_some()),
+            ("websocket", config.websocket.is_some()),
+            ("file_sink", config.file_sink.is_some()),
+            ("metrics", config.metrics.is_some()),
+            ("admin", config.admin.is_some()),
+        ] {
+            if enabled {
+                transports.push(name.to_owned());
+            }
+        }
+        info!(
+            "starting {}: {}",
+            version::NAME,
+            serde_json::json!({
+                "features": version::features(),
+                "agave_interface": version::AGAVE_INTERFACE_VERSION,
+                "git": VERSION.git,
+                "encoder": config.channel.encoder.as_str(),
+                "transports": transports,
+            })
+        );
+
+        // Plugin details for gRPC `GetVersion`, filters can be changed on reload
+        let filters_raw = Arc::new(ArcSwap::from_pointee(config.filters_raw.clone()));
+        let last_slot = Arc::new(AtomicU64::new(0));
+        let version_server_info = {
+            let filters_raw = Arc::clone(&filters_raw);
+            let last_slot = Arc::clone(&last_slot);
+            let encoder = config.channel.encoder;
+            move || {
+                Some(serde_json::json!({
+                    "name": version::NAME,
+                    "features": version::features(),
+                    "agave_interface": version::AGAVE_INTERFACE_VERSION,
+                    "encoder": encoder.as_str(),
+                    "filters": filters_raw.load().as_ref(),
+                    "last_slot": last_slot.load(Ordering::Relaxed),
+                }))
+            }
+        };
+
+        // Notification types served by every transport
+        let notifications = config.notifications;
+
+        // Config for admin `/config`, only filters can be changed on reload
+        let admin_config = {
+            let mut config_fixed = config.fixed.clone();
+            admin::redact_config(&mut config_fixed);
+            let filters_raw = Arc::clone(&filters_raw);
+            move || {
+                serde_json::json!({
+                    "config": config_fixed,
+                    "filters": filters_raw.load().as_ref(),
+                })
+            }
+        };
 
         // Spawn servers
         let (messages, shutdown, tasks) = runtime
             .block_on(async move {
-                let shutdown = CancellationToken::new();
-                let mut tasks = Vec::with_capacity(4);
+                let mut tasks = Vec::with_capacity(8);
 
-                // Start gRPC
-                if let Some(config) = config.grpc {
-                    let connections_inc = gauge!(&metrics_recorder, metrics::CONNECTIONS_TOTAL, "transport" => "grpc");
+                // Start gRPC, every endpoint with own filter
+                for endpoint in config.grpc {
+                    let name = endpoint.name;
+                    let connections_inc = gauge!(&metrics_recorder, metrics::CONNECTIONS_TOTAL, "transport" => "grpc", "endpoint" => name.clone());
                     let connections_dec = connections_inc.clone();
-                    tasks.push((
+                    let connection_duration = histogram!(&metrics_recorder, metrics::CONNECTION_DURATION_SECONDS, "transport" => "grpc", "endpoint" => name.clone());
+                    let rejected_recorder = Arc::clone(&metrics_recorder);
+                    let rejected_name = name.clone();
+                    let connections_idle = counter!(&metrics_recorder, metrics::CONNECTIONS_IDLE_TIMEOUT_TOTAL, "transport" => "grpc", "endpoint" => name.clone());
+                    let connections_throttled = gauge!(&metrics_recorder, metrics::CONNECTIONS_THROTTLED_TOTAL, "transport" => "grpc", "endpoint" => name.clone());
+                    let batch_messages = histogram!(&metrics_recorder, metrics::GRPC_BATCH_MESSAGES, "endpoint" => name.clone());
+                    let compressed_recorder = Arc::clone(&metrics_recorder);
+                    let compressed_name = name.clone();
+                    let client_recorder = Arc::clone(&metrics_recorder);
+                    let client_name = name.clone();
+                    tasks.push(PluginTask::spawn(
                         "gRPC Server",
-                        PluginTask(Box::pin(
-                            GrpcServer::spawn(
-                                config,
-                                messages.clone(),
-                                move || connections_inc.increment(1), // on_conn_new_cb
-                                move || connections_dec.decrement(1), // on_conn_drop_cb
-                                VERSION,
-                                shutdown.clone(),
-                            )
-                            .await?,
-                        )),
+                        spawn_on(grpc_runtime.clone(), GrpcServer::spawn(
+                            endpoint.server,
+                            messages.with_transport("grpc").with_notifications(&notifications.grpc).with_filter(&endpoint.filter.to_richat_filter()),
+                            move || connections_inc.increment(1), // on_conn_new_cb
+                            move |duration| { connections_dec.decrement(1); connection_duration.record(duration_to_seconds(duration)) }, // on_conn_drop_cb
+                            move |reason| counter!(&rejected_recorder, metrics::CONNECTIONS_REJECTED_TOTAL, "transport" => "grpc", "endpoint" => rejected_name.clone(), "reason" => reason).increment(1), // on_conn_rejected_cb
+                            move || connections_idle.increment(1), // on_conn_idle_cb
+                            move |throttled| if throttled { connections_throttled.increment(1) } else { connections_throttled.decrement(1) }, // on_conn_throttle_cb
+                            move |size| batch_messages.record(size as f64), // on_batch_cb
+                            move |encoding| counter!(&compressed_recorder, metrics::CONNECTIONS_COMPRESSED_TOTAL, "transport" => "grpc", "endpoint" => compressed_name.clone(), "encoding" => encoding).increment(1), // on_conn_compressed_cb
+                            move |client, connected| { let connections = gauge!(&client_recorder, metrics::GRPC_CLIENT_CONNECTIONS_TOTAL, "endpoint" => client_name.clone(), "client" => client.to_owned()); if connected { connections.increment(1) } else { connections.decrement(1) } }, // on_conn_client_cb
+                            VERSION,
+                            version_server_info.clone(),
+                            shutdown.clone(),
+                        ))
+                        .await??,
                     ));
                 }
 
@@ -111,45 +704,442 @@ impl PluginInner {
                 if let Some(config) = config.quic {
                     let connections_inc = gauge!(&metrics_recorder, metrics::CONNECTIONS_TOTAL, "transport" => "quic");
                     let connections_dec = connections_inc.clone();
-                    tasks.push((
+                    let connection_duration = histogram!(&metrics_recorder, metrics::CONNECTION_DURATION_SECONDS, "transport" => "quic");
+                    let rejected_recorder = Arc::clone(&metrics_recorder);
+                    let connections_idle = counter!(&metrics_recorder, metrics::CONNECTIONS_IDLE_TIMEOUT_TOTAL, "transport" => "quic");
+                    let connections_throttled = gauge!(&metrics_recorder, metrics::CONNECTIONS_THROTTLED_TOTAL, "transport" => "quic");
+                    tasks.push(PluginTask::spawn(
                         "Quic Server",
-                        PluginTask(Box::pin(
-                            QuicServer::spawn(
-                                config,
-                                messages.clone(),
-                                move || connections_inc.increment(1), // on_conn_new_cb
-                                move || connections_dec.decrement(1), // on_conn_drop_cb
-                                VERSION,
-                                shutdown.clone(),
-                            )
-                            .await?,
-                        )),
+                        spawn_on(quic_runtime, QuicServer::spawn(
+                            config,
+                            messages.with_transport("quic").with_notifications(&notifications.quic),
+                            move || connections_inc.increment(1), // on_conn_new_cb
+                            move |duration| { connections_dec.decrement(1); connection_duration.record(duration_to_seconds(duration)) }, // on_conn_drop_cb
+                            move |reason| counter!(&rejected_recorder, metrics::CONNECTIONS_REJECTED_TOTAL, "transport" => "quic", "reason" => reason).increment(1), // on_conn_rejected_cb
+                            move || connections_idle.increment(1), // on_conn_idle_cb
+                            move |throttled| if throttled { connections_throttled.increment(1) } else { connections_throttled.decrement(1) }, // on_conn_throttle_cb
+                            VERSION,
+                            shutdown.clone(),
+                        ))
+                        .await??,
                     ));
                 }
 
-                // Start prometheus server
+                // Start Tcp
+                if let Some(config) = config.tcp {
+                    let connections_inc = gauge!(&metrics_recorder, metrics::CONNECTIONS_TOTAL, "transport" => "tcp");
+                    let connections_dec = connections_inc.clone();
+                    let connection_duration = histogram!(&metrics_recorder, metrics::CONNECTION_DURATION_SECONDS, "transport" => "tcp");
+                    let rejected_recorder = Arc::clone(&metrics_recorder);
+                    let connections_idle = counter!(&metrics_recorder, metrics::CONNECTIONS_IDLE_TIMEOUT_TOTAL, "transport" => "tcp");
+                    tasks.push(PluginTask::spawn(
+                        "Tcp Server",
+                        spawn_on(tcp_runtime, TcpServer::spawn(
+                            config,
+                            messages.with_transport("tcp").with_notifications(&notifications.tcp),
+                            move || connections_inc.increment(1), // on_conn_new_cb
+                            move |duration| { connections_dec.decrement(1); connection_duration.record(duration_to_seconds(duration)) }, // on_conn_drop_cb
+                            move |reason| counter!(&rejected_recorder, metrics::CONNECTIONS_REJECTED_TOTAL, "transport" => "tcp", "reason" => reason).increment(1), // on_conn_rejected_cb
+                            move || connections_idle.increment(1), // on_conn_idle_cb
+                            VERSION,
+                            shutdown.clone(),
+                        ))
+                        .await??,
+                    ));
+                }
+
+                // Start Uds
+                if let Some(config) = config.uds {
+                    let connections_inc = gauge!(&metrics_recorder, metrics::CONNECTIONS_TOTAL, "transport" => "uds");
+                    let connections_dec = connections_inc.clone();
+                    let connection_duration = histogram!(&metrics_recorder, metrics::CONNECTION_DURATION_SECONDS, "transport" => "uds");
+                    let rejected_recorder = Arc::clone(&metrics_recorder);
+                    let connections_idle = counter!(&metrics_recorder, metrics::CONNECTIONS_IDLE_TIMEOUT_TOTAL, "transport" => "uds");
+                    tasks.push(PluginTask::spawn(
+                        "Uds Server",
+                        spawn_on(uds_runtime, UdsServer::spawn(
+                            config,
+                            messages.with_transport("uds").with_notifications(&notifications.uds),
+                            move || connections_inc.increment(1), // on_conn_new_cb
+                            move |duration| { connections_dec.decrement(1); connection_duration.record(duration_to_seconds(duration)) }, // on_conn_drop_cb
+                            move |reason| counter!(&rejected_recorder, metrics::CONNECTIONS_REJECTED_TOTAL, "transport" => "uds", "reason" => reason).increment(1), // on_conn_rejected_cb
+                            move || connections_idle.increment(1), // on_conn_idle_cb
+                            VERSION,
+                            shutdown.clone(),
+                        ))
+                        .await??,
+                    ));
+                }
+
+                // Start WebSocket
+                if let Some(config) = config.websocket {
+                    let connections_inc = gauge!(&metrics_recorder, metrics::CONNECTIONS_TOTAL, "transport" => "websocket");
+                    let connections_dec = connections_inc.clone();
+                    let connection_duration = histogram!(&metrics_recorder, metrics::CONNECTION_DURATION_SECONDS, "transport" => "websocket");
+                    let rejected_recorder = Arc::clone(&metrics_recorder);
+                    let connections_idle = counter!(&metrics_recorder, metrics::CONNECTIONS_IDLE_TIMEOUT_TOTAL, "transport" => "websocket");
+                    tasks.push(PluginTask::spawn(
+                        "WebSocket Server",
+                        spawn_on(websocket_runtime, WebSocketServer::spawn(
+                            config,
+                            messages.with_transport("websocket").with_notifications(&notifications.websocket),
+                            move || connections_inc.increment(1), // on_conn_new_cb
+                            move |duration| { connections_dec.decrement(1); connection_duration.record(duration_to_seconds(duration)) }, // on_conn_drop_cb
+                            move |reason| counter!(&rejected_recorder, metrics::CONNECTIONS_REJECTED_TOTAL, "transport" => "websocket", "reason" => reason).increment(1), // on_conn_rejected_cb
+                            move || connections_idle.increment(1), // on_conn_idle_cb
+                            VERSION,
+                            shutdown.clone(),
+                        ))
+                        .await??,
+                    ));
+                }
+
+                // Start file sink
+                if let Some(sink_config) = config.file_sink {
+                    tasks.push(PluginTask::spawn(
+                        "File Sink",
+                        FileSink::spawn(
+                            sink_config,
+                            messages.with_transport("file"),
+                            config.channel.encoder,
+                            shutdown.clone(),
+                        )
+                        .await?,
+                    ));
+                }
+
+                // Start prometheus server, or serve metrics by health server
+                if let Some(metrics_handle) = metrics_handle.clone() {
+                    metrics::spawn_upkeep(metrics_handle);
+                }
+                let health_metrics_handle = metrics_handle.clone().filter(|_| config.metrics.is_none());
+                let admin_metrics_handle = metrics_handle.clone();
                 if let (Some(config), Some(metrics_handle)) = (config.metrics, metrics_handle) {
-                    tasks.push((
+                    tasks.push(PluginTask::spawn(
                         "Prometheus Server",
-                        PluginTask(Box::pin(
-                            metrics::spawn_server(config, metrics_handle, shutdown.clone().cancelled_owned()).await?,
-                        )),
+                        metrics::spawn_server(
+                            config,
+                            metrics_handle,
+                            is_health_check.clone(),
+                            is_ready_check.clone(),
+                            shutdown.clone().cancelled_owned(),
+                        )
+                        .await?,
+                    ));
+                }
+
+                // Start health server
+                if let Some(config) = config.health {
+                    tasks.push(PluginTask::spawn(
+                        "Health Server",
+                        richat_metrics::spawn_server(
+                            ConfigMetrics { endpoint: config.endpoint, namespace: None },
+                            move || health_metrics_handle.as_ref().map(|handle| handle.render().into_bytes()).unwrap_or_default(), // metrics
+                            is_health_check,
+                            is_ready_check,
+                            shutdown.clone().cancelled_owned(),
+                        )
+                        .await?,
+                    ));
+                }
+
+                // Start admin server
+                if let Some(config) = config.admin {
+                    let backfill_messages = messages.clone();
+                    tasks.push(PluginTask::spawn(
+                        "Admin Server",
+                        admin::spawn_server(
+                            config,
+                            admin_config,
+                            move || admin_metrics_handle.as_ref().map(|handle| admin::metrics_to_json(&handle.render())), // metrics
+                            move || serde_json::json!(backfill_messages.backfill_stats()), // backfill
+                            shutdown.clone().cancelled_owned(),
+                        )
+                        .await?,
                     ));
                 }
 
                 Ok::<_, anyhow::Error>((messages, shutdown, tasks))
             })
             .map_err(|error| GeyserPluginError::Custom(format!("{error:?}").into()))?;
+        started.store(true, Ordering::Relaxed);
 
+        // Installed last, hook should not stay installed if plugin failed to load
+        let panic_hook = config
+            .panic_hook
+            .then(|| PluginPanicHook::install(Arc::clone(&recorder)));
+
+        let filters = Arc::new(config.filters);
         Ok(Self {
             runtime,
+            runtimes,
             messages,
             encoder: config.channel.encoder,
+            encoder_pool,
             shutdown,
             tasks,
-            filters: config.filters,
+            filters: ArcSwap::new(Arc::clone(&filters)),
+            filters_loaded: filters,
+            filters_raw,
+            unsupported_version_logged: Default::default(),
+            slot_statuses_seen: Mutex::default(),
+            accounts_coalesced: Mutex::default(),
+            account_owners: Mutex::default(),
+            account_updates_by_owner: config
+                .metrics_account_owners
+                .map(|owners| AccountUpdatesByOwner::new(owners, &recorder)),
+            slot_counts: Mutex::default(),
+            last_slot,
+            first_message,
+            startup: AtomicBool::new(true),
+            callback_durations: PluginNotification::ALL.map(|notification| {
+                histogram!(&recorder, metrics::CALLBACK_DURATION_SECONDS, "callback" => notification.callback())
+            }),
+            geyser_metrics: GeyserMetrics::new(&recorder),
+            config_fixed: config.fixed,
+            metrics_recorder: recorder,
+            shutdown_timeout: config.shutdown_timeout,
+            drain_on_shutdown: config.channel.drain_on_shutdown,
+            panic_hook,
         })
     }
+
+    fn reload(&self, config: Config) -> PluginResult<()> {
+        if config.fixed != self.config_fixed {
+            return Err(GeyserPluginError::ConfigFileReadError {
+                msg: "only `filters` can be changed on reload".to_owned(),
+            });
+        }
+
+        // notifications disabled on load are never sent by validator
+        for (name, loaded, enabled) in [
+            (
+                "enable_account_update",
+                self.filters_loaded.enable_account_update,
+                config.filters.enable_account_update,
+            ),
+            (
+                "enable_transaction_update",
+                self.filters_loaded.enable_transaction_update,
+                config.filters.enable_transaction_update,
+            ),
+            (
+                "enable_entry_update",
+                self.filters_loaded.enable_entry_update,
+                config.filters.enable_entry_update,
+            ),
+        ] {
+            if !loaded && enabled {
+                return Err(GeyserPluginError::ConfigFileReadError {
+                    msg: format!("`{name}` disabled on load can not be enabled on reload"),
+                });
+            }
+        }
+
+        self.filters.store(Arc::new(config.filters));
+        self.filters_raw.store(Arc::new(config.filters_raw));
+        info!("filters reloaded");
+
+        Ok(())
+    }
+
+    fn unsupported_version(
+        &self,
+        notification: PluginNotification,
+        version: &'static str,
+    ) -> PluginResult<()> {
+        counter!(
+            &self.metrics_recorder,
+            metrics::GEYSER_UNSUPPORTED_VERSION,
+            "notification" => notification.as_str(),
+            "version" => version
+        )
+        .increment(1);
+        if !self.unsupported_version_logged[notification as usize].swap(true, Ordering::Relaxed) {
+            warn!(
+                "unsupported {} notification version: {version}, notifications are skipped",
+                notification.as_str()
+            );
+        }
+        Ok(())
+    }
+
+    /// Plugin loaded after startup gets slot updates without `notify_end_of_startup`
+    fn end_startup(&self) {
+        if self.startup.swap(false, Ordering::Relaxed) {
+            gauge!(&self.metrics_recorder, metrics::STARTUP).set(0);
+            info!("end of startup");
+        }
+    }
+
+    fn callback_timer(&self, notification: PluginNotification) -> CallbackTimer<'_> {
+        CallbackTimer {
+            histogram: &self.callback_durations[notification as usize],
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns `true` if slot already reached this status, `(slot, status)` pair is remembered
+    fn is_slot_status_seen(&self, slot: Slot, status: &SlotStatus, max_size: usize) -> bool {
+        let mut seen = self.slot_statuses_seen.lock().expect("unpoisoned mutex");
+        if seen.insert(slot, status.as_str(), max_size) {
+            return false;
+        }
+        counter!(
+            &self.metrics_recorder,
+            metrics::GEYSER_SLOT_STATUS_DEDUPLICATED,
+            "status" => status.as_str()
+        )
+        .increment(1);
+        true
+    }
+
+    fn coalesce_account(&self, slot: Slot, account: CoalescedAccount, max: usize) {
+        let mut coalesced = self.accounts_coalesced.lock().expect("unpoisoned mutex");
+        if coalesced.insert(slot, account) {
+            self.geyser_metrics.account_coalesced_replaced.increment(1);
+        }
+        let overflow = coalesced.remove_overflow(max);
+        drop(coalesced);
+
+        for (slot, accounts) in overflow {
+            self.push_coalesced_accounts(slot, accounts);
+        }
+    }
+
+    /// Push buffered accounts on processed / confirmed status, discard on dead or rooted child
+    fn flush_coalesced_accounts(&self, slot: Slot, status: &SlotStatus) {
+        let mut coalesced = self.accounts_coalesced.lock().expect("unpoisoned mutex");
+        if coalesced.slots.is_empty() {
+            return;
+        }
+        match status {
+            SlotStatus::Processed | SlotStatus::Confirmed => {
+                let accounts = coalesced.remove(slot);
+                drop(coalesced);
+                if let Some(accounts) = accounts {
+                    self.push_coalesced_accounts(slot, accounts);
+                }
+            }
+            SlotStatus::Dead(_) => {
+                if let Some(accounts) = coalesced.remove(slot) {
+                    self.geyser_metrics
+                        .account_coalesced_discarded
+                        .increment(accounts.len() as u64);
+                }
+            }
+            SlotStatus::Rooted => {
+                let removed = coalesced.remove_before(slot + 1);
+                self.geyser_metrics
+                    .account_coalesced_discarded
+                    .increment(removed as u64);
+            }
+            _ => {}
+        }
+    }
+
+    fn push_coalesced_accounts(&self, slot: Slot, accounts: HashMap<Pubkey, CoalescedAccount>) {
+        let filters = self.filters.load();
+        let mut accounts = accounts.into_values().collect::<Vec<_>>();
+        accounts.sort_unstable_by_key(|account| account.write_version);
+        for account in accounts {
+            let owner_changed =
+                self.is_owner_changed(&filters, account.pubkey.as_ref(), &account.owner);
+            self.push(ProtobufMessage::Account {
+                slot,
+                account: &ReplicaAccountInfoV3 {
+                    pubkey: account.pubkey.as_ref(),
+                    lamports: account.lamports,
+                    owner: &account.owner,
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                    data: &account.data,
+                    write_version: account.write_version,
+                    txn: account.txn.as_ref(),
+                },
+                original_data_len: account.original_data_len,
+                is_startup: false,
+                owner_changed,
+            });
+        }
+    }
+
+    /// Compare owner with the last pushed update of the account if tracking is enabled
+    fn is_owner_changed(&self, filters: &ConfigFilters, pubkey: &[u8], owner: &[u8]) -> bool {
+        let Some(max_size) = filters
+            .account_owner_changes
+            .then(|| NonZeroUsize::new(filters.account_owners_max))
+            .flatten()
+        else {
+            return false;
+        };
+        let (Ok(pubkey), Ok(owner)) = (Pubkey::try_from(pubkey), Pubkey::try_from(owner)) else {
+            return false;
+        };
+
+        let mut owners = self.account_owners.lock().expect("unpoisoned mutex");
+        let changed = owners.update(pubkey, owner, max_size);
+        let size = owners.len();
+        drop(owners);
+
+        self.geyser_metrics.account_owners_tracked.set(size as f64);
+        if changed {
+            self.geyser_metrics.account_owner_changed.increment(1);
+        }
+        changed
+    }
+
+    /// Count transactions and accounts (except startup) pushed for the slot
+    fn count_slot_message(&self, message: &ProtobufMessage) {
+        let (slot, transactions, accounts) = match message {
+            ProtobufMessage::Account {
+                slot,
+                is_startup: false,
+                ..
+            } => (*slot, 0, 1),
+            ProtobufMessage::Transaction { slot, .. } => (*slot, 1, 0),
+            _ => return,
+        };
+        let mut slot_counts = self.slot_counts.lock().expect("unpoisoned mutex");
+        let counts = slot_counts.entry(slot).or_default();
+        counts.transactions += transactions;
+        counts.accounts += accounts;
+    }
+
+    fn take_slot_counts(&self, slot: Slot) -> SlotCounts {
+        let mut slot_counts = self.slot_counts.lock().expect("unpoisoned mutex");
+        slot_counts.remove(&slot).unwrap_or_default()
+    }
+
+    /// Remove counts of slots without block meta, otherwise they would stay forever
+    fn cleanup_slot_counts(&self, slot: Slot, status: &SlotStatus) {
+        let mut slot_counts = self.slot_counts.lock().expect("unpoisoned mutex");
+        match status {
+            SlotStatus::Dead(_) => {
+                slot_counts.remove(&slot);
+            }
+            SlotStatus::Rooted => *slot_counts = slot_counts.split_off(&(slot + 1)),
+            _ => {}
+        }
+    }
+
+    fn push(&self, message: ProtobufMessage) {
+        let filters = self.filters.load();
+        if !filters.is_slot_allowed(message.get_slot()) {
+            return;
+        }
+        if filters.block_meta_counts {
+            self.count_slot_message(&message);
+        }
+        drop(filters);
+        if !self.first_message.load(Ordering::Relaxed) {
+            self.first_message.store(true, Ordering::Relaxed);
+        }
+        match &self.encoder_pool {
+            Some(pool) => pool.push(message),
+            None => self.messages.push(message, self.encoder),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -159,17 +1149,26 @@ pub struct Plugin {
 
 impl GeyserPlugin for Plugin {
     fn name(&self) -> &'static str {
-        concat!(env!("CARGO_PKG_NAME"), "-", env!("CARGO_PKG_VERSION"))
+        version::NAME
     }
 
-    fn on_load(&mut self, config_file: &str, _is_reload: bool) -> PluginResult<()> {
-        solana_logger::setup_with_default("info");
+    fn on_load(&mut self, config_file: &str, is_reload: bool) -> PluginResult<()> {
+        logger::setup(&ConfigLogs::default());
         let config = Config::load_from_file(config_file).inspect_err(|error| {
             error!("failed to load config: {error:?}");
         })?;
 
+        // Swap filters only, transports and channel keep running
+        if is_reload {
+            if let Some(inner) = &self.inner {
+                return inner.reload(config).inspect_err(|error| {
+                    error!("failed to reload plugin from the config: {error:?}");
+                });
+            }
+        }
+
         // Setup logger from the config
-        solana_logger::setup_with_default(&config.logs.level);
+        logger::setup(&config.logs);
 
         // Create inner
         self.inner = Some(PluginInner::new(config).inspect_err(|error| {
@@ -181,18 +1180,93 @@ impl GeyserPlugin for Plugin {
 
     fn on_unload(&mut self) {
         if let Some(inner) = self.inner.take() {
-            inner.messages.close();
+            if let Some(pool) = inner.encoder_pool {
+                if let Err(error) = inner.runtime.block_on(pool.shutdown()) {
+                    error!("failed to flush encoder pool: {error:?}");
+                }
+            }
+
+            // Geyser callbacks are not called anymore, give clients time to read buffered messages
+            let drained = inner.drain_on_shutdown.then(|| {
+                inner.runtime.block_on(async {
+                    let deadline = Instant::now() + inner.shutdown_timeout;
+                    loop {
+                        let pending = inner.messages.pending_bytes();
+                        if pending == 0 {
+                            info!("drain on shutdown completed");
+                            break true;
+                        }
+                        if Instant::now() >= deadline {
+                            warn!(
+                                "drain on shutdown timed out, {pending} bytes not read by clients"
+                            );
+                            break false;
+                        }
+                        sleep(Duration::from_millis(10)).await;
+                    }
+                })
+            });
 
+            inner.messages.close();
             inner.shutdown.cancel();
-            inner.runtime.block_on(async {
+            let tasks_total = inner.tasks.len();
+            let tasks_failed = inner.runtime.block_on(async {
+                let deadline = Instant::now() + inner.shutdown_timeout;
+                let mut failed = 0;
                 for (name, task) in inner.tasks {
-                    if let Err(error) = task.0.await {
-                        error!("failed to join `{name}` task: {error:?}");
+                    match timeout_at(deadline, task.0).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(error)) => {
+                            error!("failed to join `{name}` task: {error:?}");
+                            failed += 1;
+                        }
+                        Err(_elapsed) => {
+                            error!("failed to join `{name}` task: timeout");
+                            failed += 1;
+                        }
                     }
                 }
+                failed
             });
+            if tasks_failed > 0 {
+                error!("{tasks_failed} of {tasks_total} tasks failed to join on shutdown");
+            }
+
+            // summary for post-mortem analysis of gaps after restart
+            let stats = inner.messages.stats();
+            let messages = PluginNotification::ALL
+                .iter()
+                .map(|notification| {
+                    let idx = *notification as usize;
+                    let value = serde_json::json!({
+                        "pushed": stats.pushed[idx],
+                        "dropped": stats.dropped[idx],
+                    });
+                    (notification.as_str().to_owned(), value)
+                })
+                .collect::<serde_json::Map<_, _>>();
+            info!(
+                "shutdown summary: {}",
+                serde_json::json!({
+                    "reason": "unload",
+                    "drained": drained,
+                    "messages": messages,
+                    "peak_messages": stats.peak_messages,
+                    "peak_bytes": stats.peak_bytes,
+                    "tasks_total": tasks_total,
+                    "tasks_failed": tasks_failed,
+                    "tasks_joined": tasks_failed == 0,
+                })
+            );
+
+            for runtime in inner.runtimes {
+                runtime.shutdown_timeout(inner.shutdown_timeout);
+            }
+            inner.runtime.shutdown_timeout(inner.shutdown_timeout);
 
-            inner.runtime.shutdown_timeout(Duration::from_secs(10));
+            if let Some(panic_hook) = inner.panic_hook {
+                panic_hook.restore();
+            }
         }
     }
 
@@ -202,35 +1276,98 @@ impl GeyserPlugin for Plugin {
         slot: u64,
         is_startup: bool,
     ) -> PluginResult<()> {
-        if !is_startup {
-            let account = match account {
-                ReplicaAccountInfoVersions::V0_0_1(_info) => {
-                    unreachable!("ReplicaAccountInfoVersions::V0_0_1 is not supported")
-                }
-                ReplicaAccountInfoVersions::V0_0_2(_info) => {
-                    unreachable!("ReplicaAccountInfoVersions::V0_0_2 is not supported")
-                }
-                ReplicaAccountInfoVersions::V0_0_3(info) => info,
-            };
+        let inner = self.inner.as_ref().expect("initialized");
+        let _timer = inner.callback_timer(PluginNotification::Account);
+        let account = match account {
+            ReplicaAccountInfoVersions::V0_0_1(_info) => {
+                return inner.unsupported_version(PluginNotification::Account, "0.0.1");
+            }
+            ReplicaAccountInfoVersions::V0_0_2(_info) => {
+                return inner.unsupported_version(PluginNotification::Account, "0.0.2");
+            }
+            ReplicaAccountInfoVersions::V0_0_3(info) => info,
+        };
 
-            let inner = self.inner.as_ref().expect("initialized");
+        // Churn by owner, counted before filters
+        if let Some(by_owner) = inner
+            .account_updates_by_owner
+            .as_ref()
+            .filter(|_| !is_startup)
+        {
+            by_owner.increment(account.owner);
+        }
 
-            // Filter by account data size
-            if let Some(max_size) = inner.filters.max_account_data_size {
-                if account.data.len() > max_size {
-                    return Ok(());
-                }
-            }
+        let filters = inner.filters.load();
+        if !filters.enable_account_update || (is_startup && !filters.enable_startup_accounts) {
+            return Ok(());
+        }
+
+        if filters.is_sysvar_excluded(account.pubkey) {
+            inner
+                .geyser_metrics
+                .account_filtered
+                .increment(&inner.metrics_recorder, "sysvar");
+            return Ok(());
+        }
 
+        // Filter by account groups: pubkey, owner, data patterns and data size
+        if let Err(filter) = filters.is_account_allowed(account.pubkey, account.owner, account.data)
+        {
             inner
-                .messages
-                .push(ProtobufMessage::Account { slot, account }, inner.encoder);
+                .geyser_metrics
+                .account_filtered
+                .increment(&inner.metrics_recorder, filter);
+            return Ok(());
         }
 
+        // Slice account data
+        let sliced;
+        let (account, original_data_len) = match filters.account_data_slice {
+            Some((offset, length)) => {
+                let data = account.data.get(offset..).unwrap_or_default();
+                sliced = ReplicaAccountInfoV3 {
+                    data: &data[..length.min(data.len())],
+                    ..*account
+                };
+                (&sliced, Some(account.data.len()))
+            }
+            None => (account, None),
+        };
+
+        // Keep only the latest update of the account in the slot
+        if filters.coalesce_accounts_per_slot && !is_startup {
+            if let Ok(pubkey) = Pubkey::try_from(account.pubkey) {
+                let account = CoalescedAccount {
+                    pubkey,
+                    lamports: account.lamports,
+                    owner: account.owner.to_vec(),
+                    executable: account.executable,
+                    rent_epoch: account.rent_epoch,
+                    data: account.data.to_vec(),
+                    write_version: account.write_version,
+                    txn: account.txn.cloned(),
+                    original_data_len,
+                };
+                inner.coalesce_account(slot, account, filters.coalesce_accounts_max);
+                return Ok(());
+            }
+        }
+
+        let owner_changed = inner.is_owner_changed(&filters, account.pubkey, account.owner);
+        inner.push(ProtobufMessage::Account {
+            slot,
+            account,
+            original_data_len,
+            is_startup,
+            owner_changed,
+        });
+
         Ok(())
     }
 
     fn notify_end_of_startup(&self) -> PluginResult<()> {
+        let inner = self.inner.as_ref().expect("initialized");
+        inner.end_startup();
         Ok(())
     }
 
@@ -241,14 +1378,45 @@ impl GeyserPlugin for Plugin {
         status: &SlotStatus,
     ) -> PluginResult<()> {
         let inner = self.inner.as_ref().expect("initialized");
-        inner.messages.push(
-            ProtobufMessage::Slot {
+        let _timer = inner.callback_timer(PluginNotification::Slot);
+
+        if inner.startup.load(Ordering::Relaxed) {
+            inner.end_startup();
+        }
+
+        // Track tip before filters, stalled value means stalled Geyser callbacks
+        if inner.last_slot.fetch_max(slot, Ordering::Relaxed) < slot {
+            inner.geyser_metrics.last_slot.set(slot as f64);
+        }
+
+        // Coalesced accounts are sent before the slot status, even if status is filtered
+        inner.flush_coalesced_accounts(slot, status);
+        inner.cleanup_slot_counts(slot, status);
+
+        let filters = inner.filters.load();
+        let allowed = filters.is_slot_status_allowed(status);
+        let complete = filters.is_slot_complete_status(status);
+        if !allowed && !complete {
+            return Ok(());
+        }
+        if filters.dedup_slot_statuses
+            && inner.is_slot_status_seen(slot, status, filters.dedup_slot_statuses_size)
+        {
+            return Ok(());
+        }
+
+        if allowed {
+            inner.push(ProtobufMessage::Slot {
                 slot,
                 parent,
                 status,
-            },
-            inner.encoder,
-        );
+            });
+        }
+        // Barrier follows the status in the same channel, so everything pushed for the slot
+        // before this status is already queued for every consumer
+        if complete {
+            inner.push(ProtobufMessage::SlotComplete { slot });
+        }
 
         Ok(())
     }
@@ -258,60 +1426,175 @@ impl GeyserPlugin for Plugin {
         transaction: ReplicaTransactionInfoVersions<'_>,
         slot: u64,
     ) -> PluginResult<()> {
+        let inner = self.inner.as_ref().expect("initialized");
+        let _timer = inner.callback_timer(PluginNotification::Transaction);
         let transaction = match transaction {
             ReplicaTransactionInfoVersions::V0_0_1(_info) => {
-                unreachable!("ReplicaAccountInfoVersions::V0_0_1 is not supported")
+                return inner.unsupported_version(PluginNotification::Transaction, "0.0.1");
             }
             ReplicaTransactionInfoVersions::V0_0_2(_info) => {
-                unreachable!("ReplicaAccountInfoVersions::V0_0_2 is not supported")
+                return inner.unsupported_version(PluginNotification::Transaction, "0.0.2");
             }
             ReplicaTransactionInfoVersions::V0_0_3(info) => info,
         };
 
-        let inner = self.inner.as_ref().expect("initialized");
-        inner.messages.push(
-            ProtobufMessage::Transaction { slot, transaction },
-            inner.encoder,
-        );
+        let filters = inner.filters.load();
+        if !filters.enable_transaction_update {
+            return Ok(());
+        }
+
+        // Filter by signatures
+        if let Some(signatures) = &filters.transaction_signatures {
+            if !signatures.contains(transaction.signature) {
+                inner
+                    .geyser_metrics
+                    .transaction_filtered
+                    .increment(&inner.metrics_recorder, "signature");
+                return Ok(());
+            }
+        }
+
+        // Filter vote transactions
+        if filters.exclude_vote_transactions && transaction.is_vote {
+            inner
+                .geyser_metrics
+                .transaction_filtered
+                .increment(&inner.metrics_recorder, "vote");
+            return Ok(());
+        }
+
+        // Filter failed transactions
+        if filters.exclude_failed_transactions
+            && transaction.transaction_status_meta.status.is_err()
+        {
+            inner
+                .geyser_metrics
+                .transaction_filtered
+                .increment(&inner.metrics_recorder, "failed");
+            return Ok(());
+        }
+
+        // Filter by invoked programs
+        if let Some(programs) = &filters.transaction_programs {
+            let message = &transaction.transaction.message;
+            let account_keys = message.static_account_keys();
+            if !message.instructions().iter().any(|ix| {
+                account_keys
+                    .get(ix.program_id_index as usize)
+                    .is_some_and(|program_id| programs.contains(program_id))
+            }) {
+                inner
+                    .geyser_metrics
+                    .transaction_filtered
+                    .increment(&inner.metrics_recorder, "program");
+                return Ok(());
+            }
+        }
+
+        // Filter by account keys, including loaded from address lookup tables
+        if filters.transaction_accounts_include.is_some()
+            || filters.transaction_accounts_exclude.is_some()
+        {
+            let loaded = &transaction.transaction_status_meta.loaded_addresses;
+            let account_keys = transaction
+                .transaction
+                .message
+                .static_account_keys()
+                .iter()
+                .chain(loaded.writable.iter())
+                .chain(loaded.readonly.iter());
+            let excluded = filters
+                .transaction_accounts_exclude
+                .as_ref()
+                .is_some_and(|exclude| account_keys.clone().any(|key| exclude.contains(key)));
+            let included = filters
+                .transaction_accounts_include
+                .as_ref()
+                .is_none_or(|include| account_keys.clone().any(|key| include.contains(key)));
+            if excluded || !included {
+                let filter = if excluded {
+                    "account_exclude"
+                } else {
+                    "account_include"
+                };
+                inner
+                    .geyser_metrics
+                    .transaction_filtered
+                    .increment(&inner.metrics_recorder, filter);
+                return Ok(());
+            }
+        }
+
+        inner.push(ProtobufMessage::Transaction {
+            slot,
+            transaction,
+            include_loaded_addresses: filters.include_loaded_addresses,
+        });
 
         Ok(())
     }
 
     fn notify_entry(&self, entry: ReplicaEntryInfoVersions) -> PluginResult<()> {
-        #[allow(clippy::infallible_destructuring_match)]
+        let inner = self.inner.as_ref().expect("initialized");
+        let _timer = inner.callback_timer(PluginNotification::Entry);
         let entry = match entry {
             ReplicaEntryInfoVersions::V0_0_1(_entry) => {
-                unreachable!("ReplicaEntryInfoVersions::V0_0_1 is not supported")
+                return inner.unsupported_version(PluginNotification::Entry, "0.0.1");
             }
             ReplicaEntryInfoVersions::V0_0_2(entry) => entry,
         };
 
-        let inner = self.inner.as_ref().expect("initialized");
-        inner
-            .messages
-            .push(ProtobufMessage::Entry { entry }, inner.encoder);
+        let filters = inner.filters.load();
+        if !filters.enable_entry_update {
+            return Ok(());
+        }
+
+        if filters.skip_empty_entries && entry.executed_transaction_count == 0 {
+            inner
+                .geyser_metrics
+                .entry_filtered
+                .increment(&inner.metrics_recorder, "empty");
+            return Ok(());
+        }
+
+        inner.push(ProtobufMessage::Entry { entry });
 
         Ok(())
     }
 
     fn notify_block_metadata(&self, blockinfo: ReplicaBlockInfoVersions<'_>) -> PluginResult<()> {
+        let inner = self.inner.as_ref().expect("initialized");
+        let _timer = inner.callback_timer(PluginNotification::BlockMeta);
         let blockinfo = match blockinfo {
             ReplicaBlockInfoVersions::V0_0_1(_info) => {
-                unreachable!("ReplicaBlockInfoVersions::V0_0_1 is not supported")
+                return inner.unsupported_version(PluginNotification::BlockMeta, "0.0.1");
             }
             ReplicaBlockInfoVersions::V0_0_2(_info) => {
-                unreachable!("ReplicaBlockInfoVersions::V0_0_2 is not supported")
+                return inner.unsupported_version(PluginNotification::BlockMeta, "0.0.2");
             }
             ReplicaBlockInfoVersions::V0_0_3(_info) => {
-                unreachable!("ReplicaBlockInfoVersions::V0_0_3 is not supported")
+                return inner.unsupported_version(PluginNotification::BlockMeta, "0.0.3");
             }
             ReplicaBlockInfoVersions::V0_0_4(info) => info,
         };
 
-        let inner = self.inner.as_ref().expect("initialized");
-        inner
-            .messages
-            .push(ProtobufMessage::BlockMeta { blockinfo }, inner.encoder);
+        let rewards = RewardsAndNumPartitions {
+            rewards: Vec::new(),
+            num_partitions: None,
+        };
+        let filters = inner.filters.load();
+        let counts = filters
+            .block_meta_counts
+            .then(|| inner.take_slot_counts(blockinfo.slot));
+        let blockinfo = if filters.include_block_rewards {
+            blockinfo
+        } else {
+            &ReplicaBlockInfoV4 {
+                rewards: &rewards,
+                ..*blockinfo
+            }
+        };
+        inner.push(ProtobufMessage::BlockMeta { blockinfo, counts });
 
         Ok(())
     }
@@ -321,11 +1604,17 @@ impl GeyserPlugin for Plugin {
             .as_ref()
             .expect("initialized")
             .filters
+            .load()
             .enable_account_update
     }
 
     fn account_data_snapshot_notifications_enabled(&self) -> bool {
-        false
+        self.inner
+            .as_ref()
+            .expect("initialized")
+            .filters
+            .load()
+            .enable_startup_accounts
     }
 
     fn transaction_notifications_enabled(&self) -> bool {
@@ -333,11 +1622,17 @@ impl GeyserPlugin for Plugin {
             .as_ref()
             .expect("initialized")
             .filters
+            .load()
             .enable_transaction_update
     }
 
     fn entry_notifications_enabled(&self) -> bool {
-        true
+        self.inner
+            .as_ref()
+            .expect("initialized")
+            .filters
+            .load()
+            .enable_entry_update
     }
 }
 
@@ -357,3 +1652,200 @@ pub unsafe extern "C" fn _create_plugin() -> *mut dyn GeyserPlugin {
     let plugin: Box<dyn GeyserPlugin> = Box::new(plugin);
     Box::into_raw(plugin)
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{LabeledCounters, Plugin},
+        crate::{metrics, protobuf::fixtures::generate_entries},
+        agave_geyser_plugin_interface::geyser_plugin_interface::{
+            GeyserPlugin, GeyserPluginError, ReplicaEntryInfoVersions,
+        },
+        futures::{StreamExt, TryStreamExt},
+        richat_client::{grpc::GrpcClient, quic::QuicClient},
+        richat_metrics::MaybeRecorder,
+        richat_proto::richat::GrpcSubscribeRequest,
+        std::{
+            env, fs,
+            net::{TcpListener, UdpSocket},
+            process, thread,
+        },
+    };
+
+    #[test]
+    fn test_labeled_counters() {
+        let recorder = metrics::setup(Some("test_".to_owned()));
+        let handle = recorder.inner().handle();
+        let recorder = MaybeRecorder::from(recorder);
+        let counters = LabeledCounters::new(
+            &recorder,
+            metrics::GEYSER_ENTRY_FILTERED,
+            "filter",
+            &["empty"],
+        );
+        counters.increment(&recorder, "empty");
+        counters.increment(&recorder, "empty");
+        counters.increment(&recorder, "other");
+
+        let rendered = handle.render();
+        assert!(
+            rendered.contains("test_geyser_entry_filtered_total{filter=\"empty\"} 2"),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains("test_geyser_entry_filtered_total{filter=\"other\"} 1"),
+            "{rendered}"
+        );
+    }
+
+    fn write_config(name: &str, config: &str) -> String {
+        let path = env::temp_dir().join(format!("richat-plugin-{name}-{}.json", process::id()));
+        fs::write(&path, config).unwrap();
+        path.to_str().expect("valid path").to_owned()
+    }
+
+    #[test]
+    fn test_reload_filters() {
+        let path = write_config(
+            "reload",
+            r#"{"filters": {"enable_transaction_update": true, "max_account_data_size": 100}}"#,
+        );
+        let mut plugin = Plugin::default();
+        plugin.on_load(&path, false).unwrap();
+        assert!(plugin.transaction_notifications_enabled());
+
+        // filters are swapped in place, transports and channel keep running
+        fs::write(
+            &path,
+            r#"{"filters": {"enable_transaction_update": false, "max_account_data_size": 10}}"#,
+        )
+        .unwrap();
+        plugin.on_load(&path, true).unwrap();
+        assert!(!plugin.transaction_notifications_enabled());
+        let inner = plugin.inner.as_ref().expect("initialized");
+        assert_eq!(inner.filters.load().max_account_data_size, Some(10));
+        assert_eq!(
+            inner.filters_raw.load()["max_account_data_size"],
+            serde_json::json!(10)
+        );
+
+        plugin.on_unload();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_reload_rejected() {
+        let path = write_config(
+            "reload-rejected",
+            r#"{"filters": {"enable_entry_update": false, "max_account_data_size": 100}}"#,
+        );
+        let mut plugin = Plugin::default();
+        plugin.on_load(&path, false).unwrap();
+
+        for (config, expected) in [
+            (
+                r#"{"logs": {"level": "debug"}, "filters": {"max_account_data_size": 10}}"#,
+                "only `filters` can be changed on reload",
+            ),
+            (
+                r#"{"filters": {"enable_entry_update": true, "max_account_data_size": 10}}"#,
+                "`enable_entry_update` disabled on load can not be enabled on reload",
+            ),
+        ] {
+            fs::write(&path, config).unwrap();
+            match plugin.on_load(&path, true) {
+                Err(GeyserPluginError::ConfigFileReadError { msg }) => assert_eq!(msg, expected),
+                result => panic!("unexpected result: {result:?}"),
+            }
+            // previous filters are kept
+            let inner = plugin.inner.as_ref().expect("initialized");
+            assert_eq!(inner.filters.load().max_account_data_size, Some(100));
+        }
+
+        plugin.on_unload();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_grpc_quic_same_order() {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let grpc = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap();
+        let quic = UdpSocket::bind("127.0.0.1:0")
+            .and_then(|socket| socket.local_addr())
+            .unwrap();
+        let path = write_config(
+            "order",
+            &format!(
+                r#"{{
+                    "grpc": {{"endpoint": "{grpc}"}},
+                    "quic": {{
+                        "endpoint": "{quic}",
+                        "tls_config": {{"self_signed_alt_names": ["localhost"]}}
+                    }}
+                }}"#
+            ),
+        );
+        let mut plugin = Plugin::default();
+        plugin.on_load(&path, false).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let entries = generate_entries();
+        let notify = |plugin: &Plugin, count: usize| {
+            for item in entries.iter().cycle().take(count) {
+                let entry = item.to_replica();
+                plugin
+                    .notify_entry(ReplicaEntryInfoVersions::V0_0_2(&entry))
+                    .unwrap();
+            }
+        };
+        // new subscription starts from the last pushed message
+        notify(&plugin, 1);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (grpc, quic) = runtime.block_on(async {
+            let grpc = GrpcClient::build_from_shared(format!("http://{grpc}"))
+                .unwrap()
+                .connect()
+                .await
+                .unwrap()
+                .subscribe_richat(GrpcSubscribeRequest::default())
+                .await
+                .unwrap();
+            let quic = QuicClient::builder()
+                .set_server_name(Some("localhost".to_owned()))
+                .insecure()
+                .connect(quic)
+                .await
+                .unwrap()
+                .subscribe(None, None)
+                .await
+                .unwrap();
+            (grpc, quic)
+        });
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| notify(&plugin, 500));
+            }
+        });
+
+        // every transport reads the same channel, so clients get messages in the push order
+        let (grpc, quic) = runtime.block_on(async {
+            tokio::try_join!(
+                grpc.take(2_001).try_collect::<Vec<_>>(),
+                quic.take(2_001).try_collect::<Vec<_>>()
+            )
+            .unwrap()
+        });
+        assert_eq!(grpc.len(), 2_001);
+        assert!(
+            grpc == quic,
+            "transports received messages in different order"
+        );
+
+        drop(runtime);
+        plugin.on_unload();
+    }
+}
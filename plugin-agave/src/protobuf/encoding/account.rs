@@ -104,11 +104,16 @@ impl Message for ReplicaWrapper<'_> {
 pub struct Account<'a> {
     account: &'a ReplicaAccountInfoV3<'a>,
     slot: Slot,
+    is_startup: bool,
 }
 
 impl<'a> Account<'a> {
-    pub const fn new(slot: Slot, account: &'a ReplicaAccountInfoV3<'a>) -> Self {
-        Self { slot, account }
+    pub const fn new(slot: Slot, account: &'a ReplicaAccountInfoV3<'a>, is_startup: bool) -> Self {
+        Self {
+            slot,
+            account,
+            is_startup,
+        }
     }
 }
 
@@ -119,6 +124,9 @@ impl Message for Account<'_> {
         if self.slot != 0 {
             encoding::uint64::encode(2, &self.slot, buf)
         }
+        if self.is_startup {
+            encoding::bool::encode(3, &self.is_startup, buf)
+        }
     }
 
     fn encoded_len(&self) -> usize {
@@ -129,6 +137,11 @@ impl Message for Account<'_> {
             } else {
                 0
             }
+            + if self.is_startup {
+                encoding::bool::encoded_len(3, &self.is_startup)
+            } else {
+                0
+            }
     }
 
     fn merge_field(
@@ -28,17 +28,26 @@ use {
 pub struct Transaction<'a> {
     slot: Slot,
     transaction: &'a ReplicaTransactionInfoV3<'a>,
+    include_loaded_addresses: bool,
 }
 
 impl<'a> Transaction<'a> {
-    pub const fn new(slot: Slot, transaction: &'a ReplicaTransactionInfoV3<'a>) -> Self {
-        Self { slot, transaction }
+    pub const fn new(
+        slot: Slot,
+        transaction: &'a ReplicaTransactionInfoV3<'a>,
+        include_loaded_addresses: bool,
+    ) -> Self {
+        Self {
+            slot,
+            transaction,
+            include_loaded_addresses,
+        }
     }
 }
 
 impl Message for Transaction<'_> {
     fn encode_raw(&self, buf: &mut impl BufMut) {
-        let tx = ReplicaWrapper(self.transaction);
+        let tx = ReplicaWrapper(self.transaction, self.include_loaded_addresses);
         encoding::message::encode(1, &tx, buf);
         if self.slot != 0 {
             encoding::uint64::encode(2, &self.slot, buf);
@@ -46,7 +55,7 @@ impl Message for Transaction<'_> {
     }
 
     fn encoded_len(&self) -> usize {
-        let tx = ReplicaWrapper(self.transaction);
+        let tx = ReplicaWrapper(self.transaction, self.include_loaded_addresses);
         encoding::message::encoded_len(1, &tx)
             + if self.slot != 0 {
                 encoding::uint64::encoded_len(2, &self.slot)
@@ -74,7 +83,7 @@ impl Message for Transaction<'_> {
 }
 
 #[derive(Debug)]
-struct ReplicaWrapper<'a>(&'a ReplicaTransactionInfoV3<'a>);
+struct ReplicaWrapper<'a>(&'a ReplicaTransactionInfoV3<'a>, bool);
 
 impl<'a> Deref for ReplicaWrapper<'a> {
     type Target = ReplicaTransactionInfoV3<'a>;
@@ -98,7 +107,7 @@ impl Message for ReplicaWrapper<'_> {
         encoding::message::encode(3, &VersionedTransactionWrapper(self.transaction), buf);
         encoding::message::encode(
             4,
-            &TransactionStatusMetaWrapper(self.transaction_status_meta),
+            &TransactionStatusMetaWrapper(self.transaction_status_meta, self.1),
             buf,
         );
         if index != 0 {
@@ -118,7 +127,7 @@ impl Message for ReplicaWrapper<'_> {
             + encoding::message::encoded_len(3, &VersionedTransactionWrapper(self.transaction))
             + encoding::message::encoded_len(
                 4,
-                &TransactionStatusMetaWrapper(self.transaction_status_meta),
+                &TransactionStatusMetaWrapper(self.transaction_status_meta, self.1),
             )
             + if index != 0 {
                 encoding::uint64::encoded_len(5, &index)
@@ -460,8 +469,9 @@ impl Message for MessageAddressTableLookupWrapper<'_> {
     }
 }
 
+/// Meta with flag to encode loaded addresses
 #[derive(Debug)]
-struct TransactionStatusMetaWrapper<'a>(&'a TransactionStatusMeta);
+struct TransactionStatusMetaWrapper<'a>(&'a TransactionStatusMeta, bool);
 
 impl Deref for TransactionStatusMetaWrapper<'_> {
     type Target = TransactionStatusMeta;
@@ -517,8 +527,10 @@ impl Message for TransactionStatusMetaWrapper<'_> {
         if self.log_messages.is_none() {
             encoding::bool::encode(11, &self.log_messages.is_none(), buf);
         }
-        pubkeys_encode(12, &self.loaded_addresses.writable, buf);
-        pubkeys_encode(13, &self.loaded_addresses.readonly, buf);
+        if self.1 {
+            pubkeys_encode(12, &self.loaded_addresses.writable, buf);
+            pubkeys_encode(13, &self.loaded_addresses.readonly, buf);
+        }
         if let Some(return_data) = &self.return_data {
             encoding::message::encode(14, &TransactionReturnDataWrapper(return_data), buf);
         }
@@ -585,8 +597,12 @@ impl Message for TransactionStatusMetaWrapper<'_> {
             } else {
                 0
             }
-            + pubkeys_encoded_len(12, &self.loaded_addresses.writable)
-            + pubkeys_encoded_len(13, &self.loaded_addresses.readonly)
+            + if self.1 {
+                pubkeys_encoded_len(12, &self.loaded_addresses.writable)
+                    + pubkeys_encoded_len(13, &self.loaded_addresses.readonly)
+            } else {
+                0
+            }
             + self.return_data.as_ref().map_or(0, |return_data| {
                 encoding::message::encoded_len(14, &TransactionReturnDataWrapper(return_data))
             })
@@ -1,19 +1,96 @@
 use {
-    crate::protobuf::encoding,
+    crate::protobuf::{
+        encoding,
+        wire::{BincodeMessage, BincodeMessageV1},
+    },
     agave_geyser_plugin_interface::geyser_plugin_interface::{
         ReplicaAccountInfoV3, ReplicaBlockInfoV4, ReplicaEntryInfoV2, ReplicaTransactionInfoV3,
         SlotStatus as GeyserSlotStatus,
     },
-    prost::encoding::message,
+    prost::encoding::{fixed64, message, uint64},
     prost_types::Timestamp,
+    richat_proto::geyser::SubscribeUpdatePing,
     solana_sdk::clock::Slot,
-    std::time::SystemTime,
+    std::{fmt, time::SystemTime},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProtobufEncoder {
     Prost,
     Raw,
+    /// Raw encoding compressed with zstd
+    Zstd,
+    /// Raw encoding compressed with lz4 frame format, cheaper than zstd but worse ratio
+    Lz4,
+    /// Rust only, not protobuf: `BincodeMessage` serialized with bincode
+    Bincode,
+}
+
+impl ProtobufEncoder {
+    pub const ALL: [Self; 5] = [Self::Prost, Self::Raw, Self::Zstd, Self::Lz4, Self::Bincode];
+
+    /// Encoder by name from [`ProtobufEncoder::as_str`], used by config and sink files
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|encoder| encoder.as_str() == name)
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Prost => "prost",
+            Self::Raw => "raw",
+            Self::Zstd => "zstd",
+            Self::Lz4 => "lz4",
+            Self::Bincode => "bincode",
+        }
+    }
+
+    /// Messages are uncompressed protobuf `SubscribeUpdate`
+    pub const fn is_protobuf(self) -> bool {
+        matches!(self, Self::Prost | Self::Raw)
+    }
+}
+
+/// Tag of the channel sequence number, `richat.SubscribeUpdateExtensions.sequence`, encoded as
+/// the last field with `fixed64` type, so the channel can set it without re-encoding
+pub const SEQUENCE_TAG: u32 = 104;
+
+/// Tags of `richat.SubscribeUpdateCompressed` fields, message of `zstd` and `lz4` encoders is the
+/// compressed raw message in the field with the tag of the compression
+pub const COMPRESSED_ZSTD_TAG: u32 = 106;
+pub const COMPRESSED_LZ4_TAG: u32 = 107;
+
+/// Encoded key of the sequence field, varint of `(SEQUENCE_TAG << 3) | 1` (`fixed64` wire type)
+const SEQUENCE_KEY: [u8; 2] = [0xc1, 0x06];
+
+/// Offset of the sequence number if message ends with the sequence field
+pub fn sequence_offset(data: &[u8]) -> Option<usize> {
+    let offset = data.len().checked_sub(8)?;
+    data[..offset].ends_with(&SEQUENCE_KEY).then_some(offset)
+}
+
+/// Sequence number is supported only by protobuf encoders without compression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceNotSupported(pub ProtobufEncoder);
+
+impl fmt::Display for SequenceNotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sequence is not supported by {} encoder",
+            self.0.as_str()
+        )
+    }
+}
+
+impl std::error::Error for SequenceNotSupported {}
+
+/// Plugin own counts of updates pushed for the slot, consumers can detect lost messages
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SlotCounts {
+    pub transactions: u64,
+    pub accounts: u64,
 }
 
 #[derive(Debug)]
@@ -21,6 +98,12 @@ pub enum ProtobufMessage<'a> {
     Account {
         slot: Slot,
         account: &'a ReplicaAccountInfoV3<'a>,
+        /// Account data length before slicing, set only if data was sliced
+        original_data_len: Option<usize>,
+        /// Account from the startup snapshot
+        is_startup: bool,
+        /// Owner differs from the previous update of the account, set only if tracking is enabled
+        owner_changed: bool,
     },
     Slot {
         slot: Slot,
@@ -30,12 +113,21 @@ pub enum ProtobufMessage<'a> {
     Transaction {
         slot: Slot,
         transaction: &'a ReplicaTransactionInfoV3<'a>,
+        /// Encode addresses loaded from address lookup tables in meta
+        include_loaded_addresses: bool,
     },
     Entry {
         entry: &'a ReplicaEntryInfoV2<'a>,
     },
     BlockMeta {
         blockinfo: &'a ReplicaBlockInfoV4<'a>,
+        /// Counts of transactions and accounts pushed before block meta, if enabled
+        counts: Option<SlotCounts>,
+    },
+    /// Synthetic barrier pushed after the configured terminal slot status, all messages of the
+    /// slot received from Geyser before that status are already in the channel
+    SlotComplete {
+        slot: Slot,
     },
 }
 
@@ -46,7 +138,8 @@ impl ProtobufMessage<'_> {
             Self::Slot { slot, .. } => *slot,
             Self::Transaction { slot, .. } => *slot,
             Self::Entry { entry } => entry.slot,
-            Self::BlockMeta { blockinfo } => blockinfo.slot,
+            Self::BlockMeta { blockinfo, .. } => blockinfo.slot,
+            Self::SlotComplete { slot } => *slot,
         }
     }
 
@@ -54,6 +147,32 @@ impl ProtobufMessage<'_> {
         self.encode_with_timestamp(encoder, SystemTime::now())
     }
 
+    /// Append encoded message to the buffer, used to encode to reused buffers
+    pub fn encode_to(&self, encoder: ProtobufEncoder, buffer: &mut Vec<u8>) {
+        match encoder {
+            ProtobufEncoder::Prost => self.encode_prost_to(SystemTime::now(), buffer, false),
+            ProtobufEncoder::Raw | ProtobufEncoder::Zstd | ProtobufEncoder::Lz4 => {
+                self.encode_raw_to(SystemTime::now(), buffer, false)
+            }
+            ProtobufEncoder::Bincode => self.encode_bincode_to(SystemTime::now(), buffer),
+        }
+    }
+
+    /// Same as `encode_to` with zero sequence number in the last 8 bytes, only for protobuf
+    /// encoders without compression
+    pub fn encode_with_sequence_to(
+        &self,
+        encoder: ProtobufEncoder,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), SequenceNotSupported> {
+        match encoder {
+            ProtobufEncoder::Prost => self.encode_prost_to(SystemTime::now(), buffer, true),
+            ProtobufEncoder::Raw => self.encode_raw_to(SystemTime::now(), buffer, true),
+            encoder => return Err(SequenceNotSupported(encoder)),
+        }
+        Ok(())
+    }
+
     pub fn encode_with_timestamp(
         &self,
         encoder: ProtobufEncoder,
@@ -61,11 +180,25 @@ impl ProtobufMessage<'_> {
     ) -> Vec<u8> {
         match encoder {
             ProtobufEncoder::Prost => self.encode_prost(created_at),
-            ProtobufEncoder::Raw => self.encode_raw(created_at),
+            ProtobufEncoder::Raw | ProtobufEncoder::Zstd | ProtobufEncoder::Lz4 => {
+                self.encode_raw(created_at)
+            }
+            ProtobufEncoder::Bincode => self.encode_bincode(created_at),
         }
     }
 
     pub fn encode_prost(&self, created_at: impl Into<Timestamp>) -> Vec<u8> {
+        let mut vec = Vec::new();
+        self.encode_prost_to(created_at, &mut vec, false);
+        vec
+    }
+
+    fn encode_prost_to(
+        &self,
+        created_at: impl Into<Timestamp>,
+        buffer: &mut Vec<u8>,
+        sequence: bool,
+    ) {
         use {
             prost::Message,
             richat_proto::{
@@ -79,10 +212,15 @@ impl ProtobufMessage<'_> {
             },
         };
 
-        SubscribeUpdate {
+        let update = SubscribeUpdate {
             filters: Vec::new(),
             update_oneof: Some(match self {
-                Self::Account { slot, account } => UpdateOneof::Account(SubscribeUpdateAccount {
+                Self::Account {
+                    slot,
+                    account,
+                    is_startup,
+                    ..
+                } => UpdateOneof::Account(SubscribeUpdateAccount {
                     account: Some(SubscribeUpdateAccountInfo {
                         pubkey: account.pubkey.to_vec(),
                         lamports: account.lamports,
@@ -97,7 +235,7 @@ impl ProtobufMessage<'_> {
                             .map(|transaction| transaction.signature().as_ref().to_vec()),
                     }),
                     slot: *slot,
-                    is_startup: false,
+                    is_startup: *is_startup,
                 }),
                 Self::Slot {
                     slot,
@@ -121,7 +259,17 @@ impl ProtobufMessage<'_> {
                         None
                     },
                 }),
-                Self::Transaction { slot, transaction } => {
+                Self::Transaction {
+                    slot,
+                    transaction,
+                    include_loaded_addresses,
+                } => {
+                    let mut meta =
+                        convert_to::create_transaction_meta(transaction.transaction_status_meta);
+                    if !include_loaded_addresses {
+                        meta.loaded_writable_addresses.clear();
+                        meta.loaded_readonly_addresses.clear();
+                    }
                     UpdateOneof::Transaction(SubscribeUpdateTransaction {
                         transaction: Some(SubscribeUpdateTransactionInfo {
                             signature: transaction.signature.as_ref().to_vec(),
@@ -129,28 +277,28 @@ impl ProtobufMessage<'_> {
                             transaction: Some(convert_to::create_transaction(
                                 transaction.transaction,
                             )),
-                            meta: Some(convert_to::create_transaction_meta(
-                                transaction.transaction_status_meta,
-                            )),
+                            meta: Some(meta),
                             index: transaction.index as u64,
                         }),
                         slot: *slot,
                     })
                 }
-                Self::BlockMeta { blockinfo } => UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
-                    slot: blockinfo.slot,
-                    blockhash: blockinfo.blockhash.to_string(),
-                    rewards: Some(convert_to::create_rewards_obj(
-                        &blockinfo.rewards.rewards,
-                        blockinfo.rewards.num_partitions,
-                    )),
-                    block_time: blockinfo.block_time.map(convert_to::create_timestamp),
-                    block_height: blockinfo.block_height.map(convert_to::create_block_height),
-                    parent_slot: blockinfo.parent_slot,
-                    parent_blockhash: blockinfo.parent_blockhash.to_string(),
-                    executed_transaction_count: blockinfo.executed_transaction_count,
-                    entries_count: blockinfo.entry_count,
-                }),
+                Self::BlockMeta { blockinfo, .. } => {
+                    UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
+                        slot: blockinfo.slot,
+                        blockhash: blockinfo.blockhash.to_string(),
+                        rewards: Some(convert_to::create_rewards_obj(
+                            &blockinfo.rewards.rewards,
+                            blockinfo.rewards.num_partitions,
+                        )),
+                        block_time: blockinfo.block_time.map(convert_to::create_timestamp),
+                        block_height: blockinfo.block_height.map(convert_to::create_block_height),
+                        parent_slot: blockinfo.parent_slot,
+                        parent_blockhash: blockinfo.parent_blockhash.to_string(),
+                        executed_transaction_count: blockinfo.executed_transaction_count,
+                        entries_count: blockinfo.entry_count,
+                    })
+                }
                 Self::Entry { entry } => UpdateOneof::Entry(SubscribeUpdateEntry {
                     slot: entry.slot,
                     index: entry.index as u64,
@@ -159,18 +307,41 @@ impl ProtobufMessage<'_> {
                     executed_transaction_count: entry.executed_transaction_count,
                     starting_transaction_index: entry.starting_transaction_index as u64,
                 }),
+                Self::SlotComplete { .. } => UpdateOneof::Ping(SubscribeUpdatePing {}),
             }),
             created_at: Some(created_at.into()),
-        }
-        .encode_to_vec()
+        };
+        // allocate once, appending original data len to exact sized vec would copy whole message
+        buffer.reserve_exact(
+            update.encoded_len() + self.extensions_encoded_len() + sequence_encoded_len(sequence),
+        );
+        update.encode(buffer).expect("vec have enough capacity");
+        self.encode_extensions(buffer);
+        encode_sequence(sequence, buffer);
     }
 
     pub fn encode_raw(&self, created_at: impl Into<Timestamp>) -> Vec<u8> {
+        let mut vec = Vec::new();
+        self.encode_raw_to(created_at, &mut vec, false);
+        vec
+    }
+
+    fn encode_raw_to(
+        &self,
+        created_at: impl Into<Timestamp>,
+        buffer: &mut Vec<u8>,
+        sequence: bool,
+    ) {
         let created_at = created_at.into();
 
         let size = match self {
-            Self::Account { slot, account } => {
-                let account = encoding::Account::new(*slot, account);
+            Self::Account {
+                slot,
+                account,
+                is_startup,
+                ..
+            } => {
+                let account = encoding::Account::new(*slot, account, *is_startup);
                 message::encoded_len(2, &account)
             }
             Self::Slot {
@@ -181,11 +352,16 @@ impl ProtobufMessage<'_> {
                 let slot = encoding::Slot::new(*slot, *parent, status);
                 message::encoded_len(3, &slot)
             }
-            Self::Transaction { slot, transaction } => {
-                let transaction = encoding::Transaction::new(*slot, transaction);
+            Self::Transaction {
+                slot,
+                transaction,
+                include_loaded_addresses,
+            } => {
+                let transaction =
+                    encoding::Transaction::new(*slot, transaction, *include_loaded_addresses);
                 message::encoded_len(4, &transaction)
             }
-            Self::BlockMeta { blockinfo } => {
+            Self::BlockMeta { blockinfo, .. } => {
                 let blockmeta = encoding::BlockMeta::new(blockinfo);
                 message::encoded_len(7, &blockmeta)
             }
@@ -193,14 +369,23 @@ impl ProtobufMessage<'_> {
                 let entry = encoding::Entry::new(entry);
                 message::encoded_len(8, &entry)
             }
-        } + message::encoded_len(11, &created_at);
+            Self::SlotComplete { .. } => message::encoded_len(6, &SubscribeUpdatePing {}),
+        } + message::encoded_len(11, &created_at)
+            + self.extensions_encoded_len()
+            + sequence_encoded_len(sequence);
 
-        let mut vec = Vec::with_capacity(size);
-        let buffer = &mut vec;
+        // account data is copied directly to the output, so the size should be exact to avoid
+        // reallocation with one more copy of data
+        buffer.reserve_exact(size);
 
         match self {
-            Self::Account { slot, account } => {
-                let account = encoding::Account::new(*slot, account);
+            Self::Account {
+                slot,
+                account,
+                is_startup,
+                ..
+            } => {
+                let account = encoding::Account::new(*slot, account, *is_startup);
                 message::encode(2, &account, buffer)
             }
             Self::Slot {
@@ -211,11 +396,16 @@ impl ProtobufMessage<'_> {
                 let slot = encoding::Slot::new(*slot, *parent, status);
                 message::encode(3, &slot, buffer)
             }
-            Self::Transaction { slot, transaction } => {
-                let transaction = encoding::Transaction::new(*slot, transaction);
+            Self::Transaction {
+                slot,
+                transaction,
+                include_loaded_addresses,
+            } => {
+                let transaction =
+                    encoding::Transaction::new(*slot, transaction, *include_loaded_addresses);
                 message::encode(4, &transaction, buffer)
             }
-            Self::BlockMeta { blockinfo } => {
+            Self::BlockMeta { blockinfo, .. } => {
                 let blockmeta = encoding::BlockMeta::new(blockinfo);
                 message::encode(7, &blockmeta, buffer)
             }
@@ -223,9 +413,140 @@ impl ProtobufMessage<'_> {
                 let entry = encoding::Entry::new(entry);
                 message::encode(8, &entry, buffer)
             }
+            Self::SlotComplete { .. } => message::encode(6, &SubscribeUpdatePing {}, buffer),
         }
         message::encode(11, &created_at, buffer);
+        self.encode_extensions(buffer);
+        encode_sequence(sequence, buffer);
+    }
 
+    pub fn encode_bincode(&self, created_at: impl Into<Timestamp>) -> Vec<u8> {
+        let mut vec = Vec::new();
+        self.encode_bincode_to(created_at, &mut vec);
         vec
     }
+
+    fn encode_bincode_to(&self, created_at: impl Into<Timestamp>, buffer: &mut Vec<u8>) {
+        let message = BincodeMessage::V1(BincodeMessageV1::new(self, created_at.into()));
+        let size = bincode::serialized_size(&message).expect("failed to get bincode size");
+        buffer.reserve_exact(size as usize);
+        bincode::serialize_into(buffer, &message).expect("failed to serialize with bincode");
+    }
+
+    // Extensions are not part of Yellowstone `SubscribeUpdate`, declared with the same tags in
+    // `richat.SubscribeUpdateExtensions`, high tags used to avoid collisions
+    const fn original_data_len(&self) -> Option<u64> {
+        match self {
+            Self::Account {
+                original_data_len: Some(len),
+                ..
+            } => Some(*len as u64),
+            _ => None,
+        }
+    }
+
+    fn original_data_len_encoded_len(&self) -> usize {
+        self.original_data_len()
+            .map_or(0, |len| uint64::encoded_len(100, &len))
+    }
+
+    fn encode_original_data_len(&self, buffer: &mut Vec<u8>) {
+        if let Some(len) = self.original_data_len() {
+            uint64::encode(100, &len, buffer);
+        }
+    }
+
+    // Encoded only if set, absent field is the same as false
+    const fn owner_changed(&self) -> bool {
+        matches!(
+            self,
+            Self::Account {
+                owner_changed: true,
+                ..
+            }
+        )
+    }
+
+    fn owner_changed_encoded_len(&self) -> usize {
+        if self.owner_changed() {
+            prost::encoding::bool::encoded_len(105, &true)
+        } else {
+            0
+        }
+    }
+
+    fn encode_owner_changed(&self, buffer: &mut Vec<u8>) {
+        if self.owner_changed() {
+            prost::encoding::bool::encode(105, &true, buffer);
+        }
+    }
+
+    // Same as original data len, encoded even if zero, presence means that counts are enabled
+    const fn counts(&self) -> Option<SlotCounts> {
+        match self {
+            Self::BlockMeta { counts, .. } => *counts,
+            _ => None,
+        }
+    }
+
+    fn counts_encoded_len(&self) -> usize {
+        self.counts().map_or(0, |counts| {
+            uint64::encoded_len(101, &counts.transactions)
+                + uint64::encoded_len(102, &counts.accounts)
+        })
+    }
+
+    fn encode_counts(&self, buffer: &mut Vec<u8>) {
+        if let Some(counts) = self.counts() {
+            uint64::encode(101, &counts.transactions, buffer);
+            uint64::encode(102, &counts.accounts, buffer);
+        }
+    }
+
+    // Slot complete is sent as Yellowstone ping, ignored by clients unaware of the barrier
+    const fn complete_slot(&self) -> Option<Slot> {
+        match self {
+            Self::SlotComplete { slot } => Some(*slot),
+            _ => None,
+        }
+    }
+
+    fn complete_slot_encoded_len(&self) -> usize {
+        self.complete_slot()
+            .map_or(0, |slot| uint64::encoded_len(103, &slot))
+    }
+
+    fn encode_complete_slot(&self, buffer: &mut Vec<u8>) {
+        if let Some(slot) = self.complete_slot() {
+            uint64::encode(103, &slot, buffer);
+        }
+    }
+
+    fn extensions_encoded_len(&self) -> usize {
+        self.original_data_len_encoded_len()
+            + self.owner_changed_encoded_len()
+            + self.counts_encoded_len()
+            + self.complete_slot_encoded_len()
+    }
+
+    fn encode_extensions(&self, buffer: &mut Vec<u8>) {
+        self.encode_original_data_len(buffer);
+        self.encode_owner_changed(buffer);
+        self.encode_counts(buffer);
+        self.encode_complete_slot(buffer);
+    }
+}
+
+fn sequence_encoded_len(sequence: bool) -> usize {
+    if sequence {
+        fixed64::encoded_len(SEQUENCE_TAG, &0)
+    } else {
+        0
+    }
+}
+
+fn encode_sequence(sequence: bool, buffer: &mut Vec<u8>) {
+    if sequence {
+        fixed64::encode(SEQUENCE_TAG, &0, buffer);
+    }
 }
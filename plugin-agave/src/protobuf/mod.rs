@@ -1,9 +1,19 @@
 mod encoding;
 mod message;
+mod wire;
 
 pub use {
     encoding::{Account, BlockMeta, Entry, Slot, Transaction, bytes_encode, bytes_encoded_len},
-    message::{ProtobufEncoder, ProtobufMessage},
+    message::{
+        COMPRESSED_LZ4_TAG, COMPRESSED_ZSTD_TAG, ProtobufEncoder, ProtobufMessage, SEQUENCE_TAG,
+        SequenceNotSupported, SlotCounts, sequence_offset,
+    },
+    wire::{
+        BincodeAccountV1, BincodeBlockMetaV1, BincodeEntryV1, BincodeMessage, BincodeMessageV1,
+        BincodeSlotCompleteV1, BincodeSlotStatusV1, BincodeSlotV1, BincodeTimestampV1,
+        BincodeTransactionStatusMetaV1, BincodeTransactionTokenBalanceV1, BincodeTransactionV1,
+        BincodeUpdateV1,
+    },
 };
 
 #[cfg(any(test, feature = "fixtures"))]
@@ -469,17 +479,49 @@ pub mod fixtures {
 mod tests {
     use {
         super::{
-            ProtobufEncoder, ProtobufMessage,
+            BincodeAccountV1, BincodeBlockMetaV1, BincodeEntryV1, BincodeMessage, BincodeMessageV1,
+            BincodeSlotCompleteV1, BincodeSlotV1, BincodeTransactionV1, BincodeUpdateV1,
+            ProtobufEncoder, ProtobufMessage, SequenceNotSupported, SlotCounts,
             fixtures::{
                 generate_accounts, generate_block_metas, generate_entries, generate_slots,
                 generate_transactions,
             },
+            sequence_offset,
+        },
+        agave_geyser_plugin_interface::geyser_plugin_interface::{
+            ReplicaBlockInfoV4, SlotStatus as GeyserSlotStatus,
         },
         prost::Message,
-        richat_proto::geyser::{SubscribeUpdate, subscribe_update::UpdateOneof},
-        std::time::SystemTime,
+        richat_proto::{
+            convert_to,
+            geyser::{SubscribeUpdate, SubscribeUpdatePing, subscribe_update::UpdateOneof},
+            richat::SubscribeUpdateExtensions,
+        },
+        solana_sdk::{
+            message::{
+                VersionedMessage,
+                v0::{self, LoadedAddresses, MessageAddressTableLookup},
+            },
+            pubkey::Pubkey,
+        },
+        solana_transaction_status::{
+            Reward, RewardType, RewardsAndNumPartitions, TransactionStatusMeta,
+        },
+        std::{borrow::Cow, time::SystemTime},
     };
 
+    fn decode_bincode(data: &[u8], created_at: SystemTime) -> BincodeUpdateV1<'static> {
+        let BincodeMessage::V1(BincodeMessageV1 {
+            update,
+            created_at: decoded_created_at,
+        }) = BincodeMessage::decode(data).expect("valid bincode message");
+        assert_eq!(
+            SystemTime::try_from(prost_types::Timestamp::from(decoded_created_at)),
+            Ok(created_at)
+        );
+        update
+    }
+
     #[test]
     pub fn test_encode_account() {
         let created_at = SystemTime::now();
@@ -488,6 +530,9 @@ mod tests {
             let msg_richat = ProtobufMessage::Account {
                 slot,
                 account: &replica,
+                original_data_len: None,
+                is_startup: false,
+                owner_changed: false,
             };
             let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
             let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
@@ -503,6 +548,154 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_encode_account_original_data_len() {
+        let created_at = SystemTime::now();
+        for item in generate_accounts() {
+            let (slot, replica) = item.to_replica();
+            let msg_richat = ProtobufMessage::Account {
+                slot,
+                account: &replica,
+                original_data_len: Some(replica.data.len() + 42),
+                is_startup: false,
+                owner_changed: false,
+            };
+            let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
+            let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
+            assert_eq!(vec_richat1, vec_richat2, "account: {item:?}");
+
+            let msg_prost = SubscribeUpdate::decode(vec_richat1.as_slice())
+                .expect("failed to decode account with original data len");
+            assert_eq!(
+                msg_prost.update_oneof,
+                Some(UpdateOneof::Account(item.to_prost())),
+                "account: {item:?}"
+            );
+            let extensions = SubscribeUpdateExtensions::decode(vec_richat1.as_slice())
+                .expect("failed to decode extensions");
+            assert_eq!(
+                extensions.original_data_len,
+                Some(replica.data.len() as u64 + 42),
+                "account: {item:?}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_encode_account_owner_changed() {
+        let created_at = SystemTime::now();
+        let mut flag = Vec::new();
+        prost::encoding::bool::encode(105, &true, &mut flag);
+        for item in generate_accounts() {
+            let (slot, replica) = item.to_replica();
+            let msg_richat = ProtobufMessage::Account {
+                slot,
+                account: &replica,
+                original_data_len: None,
+                is_startup: false,
+                owner_changed: true,
+            };
+            let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
+            let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
+            assert_eq!(vec_richat1, vec_richat2, "account: {item:?}");
+            assert!(vec_richat1.ends_with(&flag), "account: {item:?}");
+
+            let msg_prost = SubscribeUpdate::decode(vec_richat1.as_slice())
+                .expect("failed to decode account with owner changed");
+            assert_eq!(
+                msg_prost.update_oneof,
+                Some(UpdateOneof::Account(item.to_prost())),
+                "account: {item:?}"
+            );
+            let extensions = SubscribeUpdateExtensions::decode(vec_richat1.as_slice())
+                .expect("failed to decode extensions");
+            assert!(extensions.owner_changed, "account: {item:?}");
+            assert_eq!(extensions.original_data_len, None, "account: {item:?}");
+        }
+    }
+
+    #[test]
+    pub fn test_encode_account_write_version() {
+        let created_at = SystemTime::now();
+        for item in generate_accounts() {
+            let (slot, mut replica) = item.to_replica();
+            replica.write_version += 1 << 40;
+            let msg_richat = ProtobufMessage::Account {
+                slot,
+                account: &replica,
+                original_data_len: None,
+                is_startup: false,
+                owner_changed: false,
+            };
+            for encoder in [ProtobufEncoder::Prost, ProtobufEncoder::Raw] {
+                let vec_richat = msg_richat.encode_with_timestamp(encoder, created_at);
+                let msg = SubscribeUpdate::decode(vec_richat.as_slice()).expect("valid message");
+                let Some(UpdateOneof::Account(msg)) = msg.update_oneof else {
+                    panic!("expected account: {item:?}");
+                };
+                let account = msg.account.expect("account info");
+                assert_eq!(
+                    account.write_version, replica.write_version,
+                    "account: {item:?}"
+                );
+                assert_eq!(
+                    account.txn_signature,
+                    replica.txn.map(|tx| tx.signature().as_ref().to_vec()),
+                    "account: {item:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_encode_account_single_allocation() {
+        let created_at = SystemTime::now();
+        for item in generate_accounts() {
+            let (slot, replica) = item.to_replica();
+            for original_data_len in [None, Some(replica.data.len() + 42)] {
+                let msg_richat = ProtobufMessage::Account {
+                    slot,
+                    account: &replica,
+                    original_data_len,
+                    is_startup: false,
+                    owner_changed: false,
+                };
+                for encoder in [ProtobufEncoder::Prost, ProtobufEncoder::Raw] {
+                    let vec_richat = msg_richat.encode_with_timestamp(encoder, created_at);
+                    assert_eq!(vec_richat.len(), vec_richat.capacity(), "account: {item:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_encode_account_startup() {
+        let created_at = SystemTime::now();
+        for item in generate_accounts() {
+            let (slot, replica) = item.to_replica();
+            let msg_richat = ProtobufMessage::Account {
+                slot,
+                account: &replica,
+                original_data_len: None,
+                is_startup: true,
+                owner_changed: false,
+            };
+            let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
+            let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
+            assert_eq!(vec_richat1, vec_richat2, "account: {item:?}");
+
+            let mut account = item.to_prost();
+            account.is_startup = true;
+            let msg_prost = SubscribeUpdate {
+                filters: Vec::new(),
+                update_oneof: Some(UpdateOneof::Account(account)),
+                created_at: Some(created_at.into()),
+            };
+            let vec_prost = msg_prost.encode_to_vec();
+            assert_eq!(vec_richat1, vec_prost, "account: {item:?}");
+        }
+    }
+
     #[test]
     pub fn test_encode_block_meta() {
         let created_at = SystemTime::now();
@@ -510,6 +703,7 @@ mod tests {
             let replica = item.to_replica();
             let msg_richat = ProtobufMessage::BlockMeta {
                 blockinfo: &replica,
+                counts: None,
             };
             let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
             let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
@@ -525,6 +719,174 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_encode_block_meta_counts() {
+        let created_at = SystemTime::now();
+        for item in generate_block_metas() {
+            let replica = item.to_replica();
+            let msg_richat = ProtobufMessage::BlockMeta {
+                blockinfo: &replica,
+                counts: Some(SlotCounts {
+                    transactions: 42,
+                    accounts: 0,
+                }),
+            };
+            let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
+            let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
+            assert_eq!(vec_richat1, vec_richat2, "block meta: {item:?}");
+
+            let msg_prost = SubscribeUpdate::decode(vec_richat1.as_slice())
+                .expect("failed to decode block meta with counts");
+            assert_eq!(
+                msg_prost.update_oneof,
+                Some(UpdateOneof::BlockMeta(item.to_prost())),
+                "block meta: {item:?}"
+            );
+            assert!(vec_richat1.ends_with(&[0xa8, 0x06, 42, 0xb0, 0x06, 0]));
+            let extensions = SubscribeUpdateExtensions::decode(vec_richat1.as_slice())
+                .expect("failed to decode extensions");
+            assert_eq!(extensions.slot_transactions, Some(42));
+            assert_eq!(extensions.slot_accounts, Some(0));
+        }
+    }
+
+    #[test]
+    pub fn test_encode_slot_complete() {
+        let created_at = SystemTime::now();
+        let msg_richat = ProtobufMessage::SlotComplete { slot: 42 };
+        let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
+        let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
+        assert_eq!(vec_richat1, vec_richat2);
+
+        let msg_prost = SubscribeUpdate::decode(vec_richat1.as_slice())
+            .expect("failed to decode slot complete");
+        assert_eq!(
+            msg_prost.update_oneof,
+            Some(UpdateOneof::Ping(SubscribeUpdatePing {}))
+        );
+        assert!(vec_richat1.ends_with(&[0xb8, 0x06, 42]));
+        let extensions = SubscribeUpdateExtensions::decode(vec_richat1.as_slice())
+            .expect("failed to decode extensions");
+        assert_eq!(extensions.slot_complete, Some(42));
+
+        let vec_bincode = msg_richat.encode_with_timestamp(ProtobufEncoder::Bincode, created_at);
+        assert_eq!(
+            decode_bincode(&vec_bincode, created_at),
+            BincodeUpdateV1::SlotComplete(BincodeSlotCompleteV1 { slot: 42 })
+        );
+    }
+
+    #[test]
+    pub fn test_encode_sequence() {
+        for item in generate_accounts() {
+            let (slot, replica) = item.to_replica();
+            let msg_richat = ProtobufMessage::Account {
+                slot,
+                account: &replica,
+                original_data_len: Some(replica.data.len() + 42),
+                is_startup: false,
+                owner_changed: true,
+            };
+            for encoder in [ProtobufEncoder::Prost, ProtobufEncoder::Raw] {
+                let mut vec_richat = Vec::new();
+                msg_richat
+                    .encode_with_sequence_to(encoder, &mut vec_richat)
+                    .expect("sequence is supported");
+                let offset = sequence_offset(&vec_richat).expect("sequence field");
+                assert_eq!(offset, vec_richat.len() - 8);
+                vec_richat[offset..].copy_from_slice(&42u64.to_le_bytes());
+
+                let extensions = SubscribeUpdateExtensions::decode(vec_richat.as_slice())
+                    .expect("failed to decode extensions");
+                assert_eq!(extensions.sequence, Some(42), "account: {item:?}");
+                assert!(extensions.owner_changed, "account: {item:?}");
+                assert_eq!(
+                    extensions.original_data_len,
+                    Some(replica.data.len() as u64 + 42),
+                    "account: {item:?}"
+                );
+            }
+
+            for encoder in [
+                ProtobufEncoder::Zstd,
+                ProtobufEncoder::Lz4,
+                ProtobufEncoder::Bincode,
+            ] {
+                let mut vec_richat = Vec::new();
+                assert_eq!(
+                    msg_richat.encode_with_sequence_to(encoder, &mut vec_richat),
+                    Err(SequenceNotSupported(encoder))
+                );
+                assert!(vec_richat.is_empty());
+            }
+
+            // message without sequence field is not patched
+            let vec_richat =
+                msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, SystemTime::UNIX_EPOCH);
+            assert_eq!(sequence_offset(&vec_richat), None, "account: {item:?}");
+        }
+        assert_eq!(sequence_offset(&[0; 7]), None);
+    }
+
+    #[test]
+    pub fn test_encode_block_meta_rewards() {
+        let created_at = SystemTime::now();
+        let rewards = RewardsAndNumPartitions {
+            rewards: [
+                RewardType::Fee,
+                RewardType::Rent,
+                RewardType::Staking,
+                RewardType::Voting,
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(i, reward_type)| Reward {
+                pubkey: Pubkey::new_unique().to_string(),
+                lamports: 1_000 * (i as i64 + 1),
+                post_balance: 1_000_000,
+                reward_type: Some(reward_type),
+                commission: matches!(reward_type, RewardType::Staking | RewardType::Voting)
+                    .then_some(10),
+            })
+            .collect(),
+            num_partitions: Some(4),
+        };
+        for item in generate_block_metas() {
+            let replica = ReplicaBlockInfoV4 {
+                rewards: &rewards,
+                ..item.to_replica()
+            };
+            let msg_richat = ProtobufMessage::BlockMeta {
+                blockinfo: &replica,
+                counts: None,
+            };
+            let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
+            let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
+            assert_eq!(vec_richat1, vec_richat2, "block meta: {item:?}");
+
+            let mut block_meta = item.to_prost();
+            block_meta.rewards = Some(convert_to::create_rewards_obj(
+                &rewards.rewards,
+                rewards.num_partitions,
+            ));
+            let msg_prost = SubscribeUpdate {
+                filters: Vec::new(),
+                update_oneof: Some(UpdateOneof::BlockMeta(block_meta)),
+                created_at: Some(created_at.into()),
+            };
+            let vec_prost = msg_prost.encode_to_vec();
+            assert_eq!(vec_richat1, vec_prost, "block meta: {item:?}");
+
+            let msg = SubscribeUpdate::decode(vec_richat1.as_slice()).expect("valid message");
+            let Some(UpdateOneof::BlockMeta(msg)) = msg.update_oneof else {
+                panic!("expected block meta: {item:?}");
+            };
+            let decoded = msg.rewards.expect("rewards");
+            assert_eq!(decoded.rewards.len(), rewards.rewards.len());
+            assert_eq!(decoded.num_partitions.map(|p| p.num_partitions), Some(4));
+        }
+    }
+
     #[test]
     pub fn test_encode_entry() {
         let created_at = SystemTime::now();
@@ -577,6 +939,7 @@ mod tests {
             let msg_richat = ProtobufMessage::Transaction {
                 slot,
                 transaction: &replica,
+                include_loaded_addresses: true,
             };
             let vec_richat1 = msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
             let vec_richat2 = msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
@@ -632,4 +995,263 @@ mod tests {
             // assert_eq!(slice_richat, slice_prost, "transaction: {gen:?}");
         }
     }
+
+    #[test]
+    pub fn test_encode_transaction_index() {
+        let created_at = SystemTime::now();
+        let mut items = generate_transactions();
+        for item in items.iter_mut() {
+            item.index += 1_000;
+        }
+        for item in items {
+            let (slot, replica) = item.to_replica();
+            let msg_richat = ProtobufMessage::Transaction {
+                slot,
+                transaction: &replica,
+                include_loaded_addresses: true,
+            };
+            for encoder in [ProtobufEncoder::Prost, ProtobufEncoder::Raw] {
+                let vec_richat = msg_richat.encode_with_timestamp(encoder, created_at);
+                let msg = SubscribeUpdate::decode(vec_richat.as_slice()).expect("valid message");
+                let Some(UpdateOneof::Transaction(msg)) = msg.update_oneof else {
+                    panic!("expected transaction: {item:?}");
+                };
+                let index = msg.transaction.map(|tx| tx.index);
+                assert_eq!(index, Some(item.index as u64), "transaction: {item:?}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_encode_transaction_loaded_addresses() {
+        let created_at = SystemTime::now();
+        // predefined blocks have only legacy transactions, convert them to v0 with lookup table
+        let items = generate_transactions().into_iter().take(8).map(|mut item| {
+            let message = &item.versioned_transaction.message;
+            item.versioned_transaction.message = VersionedMessage::V0(v0::Message {
+                header: *message.header(),
+                account_keys: message.static_account_keys().to_vec(),
+                recent_blockhash: *message.recent_blockhash(),
+                instructions: message.instructions().to_vec(),
+                address_table_lookups: vec![MessageAddressTableLookup {
+                    account_key: Pubkey::new_unique(),
+                    writable_indexes: vec![0],
+                    readonly_indexes: vec![1, 2],
+                }],
+            });
+            item.transaction_status_meta.loaded_addresses = LoadedAddresses {
+                writable: vec![Pubkey::new_unique()],
+                readonly: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            };
+            item
+        });
+        for item in items {
+            let (slot, replica) = item.to_replica();
+            let loaded = &item.transaction_status_meta.loaded_addresses;
+            for include_loaded_addresses in [true, false] {
+                let msg_richat = ProtobufMessage::Transaction {
+                    slot,
+                    transaction: &replica,
+                    include_loaded_addresses,
+                };
+                let (writable, readonly) = if include_loaded_addresses {
+                    (loaded.writable.clone(), loaded.readonly.clone())
+                } else {
+                    (Vec::new(), Vec::new())
+                };
+
+                let vec_richat1 =
+                    msg_richat.encode_with_timestamp(ProtobufEncoder::Prost, created_at);
+                let vec_richat2 =
+                    msg_richat.encode_with_timestamp(ProtobufEncoder::Raw, created_at);
+                assert_eq!(vec_richat1, vec_richat2, "transaction: {item:?}");
+                let msg = SubscribeUpdate::decode(vec_richat1.as_slice()).expect("valid message");
+                let Some(UpdateOneof::Transaction(msg)) = msg.update_oneof else {
+                    panic!("expected transaction: {item:?}");
+                };
+                let meta = msg.transaction.and_then(|tx| tx.meta).expect("meta");
+                let to_bytes = |keys: &[Pubkey]| {
+                    keys.iter()
+                        .map(|key| key.to_bytes().to_vec())
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(meta.loaded_writable_addresses, to_bytes(&writable));
+                assert_eq!(meta.loaded_readonly_addresses, to_bytes(&readonly));
+
+                let vec_richat =
+                    msg_richat.encode_with_timestamp(ProtobufEncoder::Bincode, created_at);
+                let BincodeUpdateV1::Transaction(decoded) = decode_bincode(&vec_richat, created_at)
+                else {
+                    panic!("expected transaction: {item:?}");
+                };
+                assert_eq!(decoded.meta.loaded_addresses.writable, writable);
+                assert_eq!(decoded.meta.loaded_addresses.readonly, readonly);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_bincode_account() {
+        let created_at = SystemTime::now();
+        for item in generate_accounts() {
+            let (slot, replica) = item.to_replica();
+            for (original_data_len, is_startup) in [(None, false), (Some(1_000_000), true)] {
+                let msg_richat = ProtobufMessage::Account {
+                    slot,
+                    account: &replica,
+                    original_data_len,
+                    is_startup,
+                    owner_changed: false,
+                };
+                let vec_richat =
+                    msg_richat.encode_with_timestamp(ProtobufEncoder::Bincode, created_at);
+                assert_eq!(
+                    decode_bincode(&vec_richat, created_at),
+                    BincodeUpdateV1::Account(BincodeAccountV1 {
+                        slot: item.slot,
+                        pubkey: Cow::Owned(item.pubkey.to_bytes().to_vec()),
+                        lamports: item.lamports,
+                        owner: Cow::Owned(item.owner.to_bytes().to_vec()),
+                        executable: item.executable,
+                        rent_epoch: item.rent_epoch,
+                        data: Cow::Owned(item.data.clone()),
+                        write_version: item.write_version,
+                        txn_signature: item
+                            .transaction_signature
+                            .as_ref()
+                            .map(|tx| *tx.signature()),
+                        original_data_len: original_data_len.map(|len| len as u64),
+                        is_startup,
+                    }),
+                    "account: {item:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_bincode_block_meta() {
+        let created_at = SystemTime::now();
+        for item in generate_block_metas() {
+            let replica = item.to_replica();
+            let msg_richat = ProtobufMessage::BlockMeta {
+                blockinfo: &replica,
+                counts: Some(SlotCounts {
+                    transactions: 42,
+                    accounts: 0,
+                }),
+            };
+            let vec_richat = msg_richat.encode_with_timestamp(ProtobufEncoder::Bincode, created_at);
+            let prost = item.to_prost();
+            assert_eq!(
+                decode_bincode(&vec_richat, created_at),
+                BincodeUpdateV1::BlockMeta(BincodeBlockMetaV1 {
+                    slot: prost.slot,
+                    blockhash: prost.blockhash,
+                    rewards: replica.rewards.rewards.clone(),
+                    num_partitions: replica.rewards.num_partitions,
+                    block_time: replica.block_time,
+                    block_height: replica.block_height,
+                    parent_slot: prost.parent_slot,
+                    parent_blockhash: prost.parent_blockhash,
+                    executed_transaction_count: prost.executed_transaction_count,
+                    entries_count: prost.entries_count,
+                    pushed_transactions: Some(42),
+                    pushed_accounts: Some(0),
+                }),
+                "block meta: {item:?}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_bincode_entry() {
+        let created_at = SystemTime::now();
+        for item in generate_entries() {
+            let replica = item.to_replica();
+            let msg_richat = ProtobufMessage::Entry { entry: &replica };
+            let vec_richat = msg_richat.encode_with_timestamp(ProtobufEncoder::Bincode, created_at);
+            assert_eq!(
+                decode_bincode(&vec_richat, created_at),
+                BincodeUpdateV1::Entry(BincodeEntryV1 {
+                    slot: item.slot,
+                    index: item.index as u64,
+                    num_hashes: item.num_hashes,
+                    hash: Cow::Owned(item.hash.to_bytes().to_vec()),
+                    executed_transaction_count: item.executed_transaction_count,
+                    starting_transaction_index: item.starting_transaction_index as u64,
+                }),
+                "entry: {item:?}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_bincode_slot() {
+        let created_at = SystemTime::now();
+        for item in generate_slots() {
+            let (slot, parent, status) = item.to_replica();
+            let msg_richat = ProtobufMessage::Slot {
+                slot,
+                parent,
+                status,
+            };
+            let vec_richat = msg_richat.encode_with_timestamp(ProtobufEncoder::Bincode, created_at);
+            let BincodeUpdateV1::Slot(BincodeSlotV1 {
+                slot,
+                parent,
+                status,
+            }) = decode_bincode(&vec_richat, created_at)
+            else {
+                panic!("expected slot: {item:?}");
+            };
+            assert_eq!(slot, item.slot, "slot: {item:?}");
+            assert_eq!(parent, item.parent, "slot: {item:?}");
+            assert_eq!(
+                format!("{:?}", GeyserSlotStatus::from(status)),
+                format!("{:?}", item.status),
+                "slot: {item:?}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_bincode_transaction() {
+        let created_at = SystemTime::now();
+        for item in generate_transactions() {
+            let (slot, replica) = item.to_replica();
+            let msg_richat = ProtobufMessage::Transaction {
+                slot,
+                transaction: &replica,
+                include_loaded_addresses: true,
+            };
+            let vec_richat = msg_richat.encode_with_timestamp(ProtobufEncoder::Bincode, created_at);
+            let BincodeUpdateV1::Transaction(decoded) = decode_bincode(&vec_richat, created_at)
+            else {
+                panic!("expected transaction: {item:?}");
+            };
+            let BincodeTransactionV1 {
+                slot,
+                signature,
+                is_vote,
+                index,
+                transaction,
+                meta,
+            } = *decoded;
+            assert_eq!(slot, item.slot, "transaction: {item:?}");
+            assert_eq!(signature, item.signature, "transaction: {item:?}");
+            assert_eq!(is_vote, item.is_vote, "transaction: {item:?}");
+            assert_eq!(index, item.index as u64, "transaction: {item:?}");
+            assert_eq!(
+                transaction.as_ref(),
+                &item.versioned_transaction,
+                "transaction: {item:?}"
+            );
+            assert_eq!(
+                TransactionStatusMeta::from(meta),
+                item.transaction_status_meta,
+                "transaction: {item:?}"
+            );
+        }
+    }
 }
@@ -0,0 +1,432 @@
+//! Bincode representation of plugin messages for Rust consumers.
+//!
+//! This is not a cross-language format: it follows Rust types layout and can be decoded only with
+//! `bincode` 1.x and the structs below. Clients of gRPC / other languages should use protobuf
+//! encoders. Every struct has a version suffix, any change of layout requires a new version.
+
+use {
+    super::{ProtobufMessage, SlotCounts},
+    agave_geyser_plugin_interface::geyser_plugin_interface::{
+        ReplicaAccountInfoV3, ReplicaBlockInfoV4, ReplicaEntryInfoV2, ReplicaTransactionInfoV3,
+        SlotStatus as GeyserSlotStatus,
+    },
+    prost_types::Timestamp,
+    serde::{Deserialize, Serialize},
+    solana_account_decoder::parse_token::UiTokenAmount,
+    solana_sdk::{
+        clock::{Slot, UnixTimestamp},
+        message::v0::LoadedAddresses,
+        signature::Signature,
+        transaction::{TransactionError, VersionedTransaction},
+    },
+    solana_transaction_context::TransactionReturnData,
+    solana_transaction_status::{
+        InnerInstructions, Reward, TransactionStatusMeta, TransactionTokenBalance,
+    },
+    std::borrow::Cow,
+};
+
+/// Versioned envelope, bincode encodes variant index as `u32` prefix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BincodeMessage<'a> {
+    V1(BincodeMessageV1<'a>),
+}
+
+impl BincodeMessage<'_> {
+    pub fn decode(data: &[u8]) -> bincode::Result<BincodeMessage<'static>> {
+        bincode::deserialize(data)
+    }
+
+    pub const fn get_slot(&self) -> Slot {
+        match self {
+            Self::V1(message) => match &message.update {
+                BincodeUpdateV1::Account(account) => account.slot,
+                BincodeUpdateV1::AccountOwnerChanged(account) => account.slot,
+                BincodeUpdateV1::Slot(slot) => slot.slot,
+                BincodeUpdateV1::Transaction(transaction) => transaction.slot,
+                BincodeUpdateV1::Entry(entry) => entry.slot,
+                BincodeUpdateV1::BlockMeta(blockmeta) => blockmeta.slot,
+                BincodeUpdateV1::SlotComplete(complete) => complete.slot,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BincodeMessageV1<'a> {
+    pub update: BincodeUpdateV1<'a>,
+    pub created_at: BincodeTimestampV1,
+}
+
+impl<'a> BincodeMessageV1<'a> {
+    pub fn new(message: &ProtobufMessage<'a>, created_at: Timestamp) -> Self {
+        let update = match message {
+            ProtobufMessage::Account {
+                slot,
+                account,
+                original_data_len,
+                is_startup,
+                owner_changed,
+            } => {
+                let account = BincodeAccountV1::new(
+                    *slot,
+                    account,
+                    original_data_len.map(|len| len as u64),
+                    *is_startup,
+                );
+                if *owner_changed {
+                    BincodeUpdateV1::AccountOwnerChanged(account)
+                } else {
+                    BincodeUpdateV1::Account(account)
+                }
+            }
+            ProtobufMessage::Slot {
+                slot,
+                parent,
+                status,
+            } => BincodeUpdateV1::Slot(BincodeSlotV1 {
+                slot: *slot,
+                parent: *parent,
+                status: (*status).into(),
+            }),
+            ProtobufMessage::Transaction {
+                slot,
+                transaction,
+                include_loaded_addresses,
+            } => BincodeUpdateV1::Transaction(Box::new(BincodeTransactionV1::new(
+                *slot,
+                transaction,
+                *include_loaded_addresses,
+            ))),
+            ProtobufMessage::Entry { entry } => BincodeUpdateV1::Entry(BincodeEntryV1::new(entry)),
+            ProtobufMessage::BlockMeta { blockinfo, counts } => {
+                BincodeUpdateV1::BlockMeta(BincodeBlockMetaV1::new(blockinfo, *counts))
+            }
+            ProtobufMessage::SlotComplete { slot } => {
+                BincodeUpdateV1::SlotComplete(BincodeSlotCompleteV1 { slot: *slot })
+            }
+        };
+        Self {
+            update,
+            created_at: created_at.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BincodeTimestampV1 {
+    pub seconds: i64,
+    pub nanos: i32,
+}
+
+impl From<Timestamp> for BincodeTimestampV1 {
+    fn from(ts: Timestamp) -> Self {
+        Self {
+            seconds: ts.seconds,
+            nanos: ts.nanos,
+        }
+    }
+}
+
+impl From<BincodeTimestampV1> for Timestamp {
+    fn from(ts: BincodeTimestampV1) -> Self {
+        Self {
+            seconds: ts.seconds,
+            nanos: ts.nanos,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BincodeUpdateV1<'a> {
+    Account(BincodeAccountV1<'a>),
+    Slot(BincodeSlotV1),
+    Transaction(Box<BincodeTransactionV1<'a>>),
+    Entry(BincodeEntryV1<'a>),
+    BlockMeta(BincodeBlockMetaV1),
+    SlotComplete(BincodeSlotCompleteV1),
+    /// Same as `Account`, owner differs from the previous update of the account
+    AccountOwnerChanged(BincodeAccountV1<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BincodeAccountV1<'a> {
+    pub slot: Slot,
+    pub pubkey: Cow<'a, [u8]>,
+    pub lamports: u64,
+    pub owner: Cow<'a, [u8]>,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    pub data: Cow<'a, [u8]>,
+    pub write_version: u64,
+    pub txn_signature: Option<Signature>,
+    /// Account data length before slicing, set only if data was sliced
+    pub original_data_len: Option<u64>,
+    /// Account from the startup snapshot
+    pub is_startup: bool,
+}
+
+impl<'a> BincodeAccountV1<'a> {
+    fn new(
+        slot: Slot,
+        account: &ReplicaAccountInfoV3<'a>,
+        original_data_len: Option<u64>,
+        is_startup: bool,
+    ) -> Self {
+        Self {
+            slot,
+            pubkey: Cow::Borrowed(account.pubkey),
+            lamports: account.lamports,
+            owner: Cow::Borrowed(account.owner),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data: Cow::Borrowed(account.data),
+            write_version: account.write_version,
+            txn_signature: account.txn.map(|transaction| *transaction.signature()),
+            original_data_len,
+            is_startup,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BincodeSlotV1 {
+    pub slot: Slot,
+    pub parent: Option<Slot>,
+    pub status: BincodeSlotStatusV1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BincodeSlotCompleteV1 {
+    pub slot: Slot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BincodeSlotStatusV1 {
+    Processed,
+    Rooted,
+    Confirmed,
+    FirstShredReceived,
+    Completed,
+    CreatedBank,
+    Dead(String),
+}
+
+impl From<&GeyserSlotStatus> for BincodeSlotStatusV1 {
+    fn from(status: &GeyserSlotStatus) -> Self {
+        match status {
+            GeyserSlotStatus::Processed => Self::Processed,
+            GeyserSlotStatus::Rooted => Self::Rooted,
+            GeyserSlotStatus::Confirmed => Self::Confirmed,
+            GeyserSlotStatus::FirstShredReceived => Self::FirstShredReceived,
+            GeyserSlotStatus::Completed => Self::Completed,
+            GeyserSlotStatus::CreatedBank => Self::CreatedBank,
+            GeyserSlotStatus::Dead(error) => Self::Dead(error.clone()),
+        }
+    }
+}
+
+impl From<BincodeSlotStatusV1> for GeyserSlotStatus {
+    fn from(status: BincodeSlotStatusV1) -> Self {
+        match status {
+            BincodeSlotStatusV1::Processed => Self::Processed,
+            BincodeSlotStatusV1::Rooted => Self::Rooted,
+            BincodeSlotStatusV1::Confirmed => Self::Confirmed,
+            BincodeSlotStatusV1::FirstShredReceived => Self::FirstShredReceived,
+            BincodeSlotStatusV1::Completed => Self::Completed,
+            BincodeSlotStatusV1::CreatedBank => Self::CreatedBank,
+            BincodeSlotStatusV1::Dead(error) => Self::Dead(error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BincodeTransactionV1<'a> {
+    pub slot: Slot,
+    pub signature: Signature,
+    pub is_vote: bool,
+    pub index: u64,
+    pub transaction: Cow<'a, VersionedTransaction>,
+    pub meta: BincodeTransactionStatusMetaV1,
+}
+
+impl<'a> BincodeTransactionV1<'a> {
+    fn new(
+        slot: Slot,
+        transaction: &ReplicaTransactionInfoV3<'a>,
+        include_loaded_addresses: bool,
+    ) -> Self {
+        let mut meta = BincodeTransactionStatusMetaV1::from(transaction.transaction_status_meta);
+        if !include_loaded_addresses {
+            meta.loaded_addresses = LoadedAddresses::default();
+        }
+        Self {
+            slot,
+            signature: *transaction.signature,
+            is_vote: transaction.is_vote,
+            index: transaction.index as u64,
+            transaction: Cow::Borrowed(transaction.transaction),
+            meta,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BincodeTransactionStatusMetaV1 {
+    pub status: Result<(), TransactionError>,
+    pub fee: u64,
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+    pub inner_instructions: Option<Vec<InnerInstructions>>,
+    pub log_messages: Option<Vec<String>>,
+    pub pre_token_balances: Option<Vec<BincodeTransactionTokenBalanceV1>>,
+    pub post_token_balances: Option<Vec<BincodeTransactionTokenBalanceV1>>,
+    pub rewards: Option<Vec<Reward>>,
+    pub loaded_addresses: LoadedAddresses,
+    pub return_data: Option<TransactionReturnData>,
+    pub compute_units_consumed: Option<u64>,
+    pub cost_units: Option<u64>,
+}
+
+impl From<&TransactionStatusMeta> for BincodeTransactionStatusMetaV1 {
+    fn from(meta: &TransactionStatusMeta) -> Self {
+        Self {
+            status: meta.status.clone(),
+            fee: meta.fee,
+            pre_balances: meta.pre_balances.clone(),
+            post_balances: meta.post_balances.clone(),
+            inner_instructions: meta.inner_instructions.clone(),
+            log_messages: meta.log_messages.clone(),
+            pre_token_balances: meta
+                .pre_token_balances
+                .as_ref()
+                .map(|balances| balances.iter().map(Into::into).collect()),
+            post_token_balances: meta
+                .post_token_balances
+                .as_ref()
+                .map(|balances| balances.iter().map(Into::into).collect()),
+            rewards: meta.rewards.clone(),
+            loaded_addresses: meta.loaded_addresses.clone(),
+            return_data: meta.return_data.clone(),
+            compute_units_consumed: meta.compute_units_consumed,
+            cost_units: meta.cost_units,
+        }
+    }
+}
+
+impl From<BincodeTransactionStatusMetaV1> for TransactionStatusMeta {
+    fn from(meta: BincodeTransactionStatusMetaV1) -> Self {
+        Self {
+            status: meta.status,
+            fee: meta.fee,
+            pre_balances: meta.pre_balances,
+            post_balances: meta.post_balances,
+            inner_instructions: meta.inner_instructions,
+            log_messages: meta.log_messages,
+            pre_token_balances: meta
+                .pre_token_balances
+                .map(|balances| balances.into_iter().map(Into::into).collect()),
+            post_token_balances: meta
+                .post_token_balances
+                .map(|balances| balances.into_iter().map(Into::into).collect()),
+            rewards: meta.rewards,
+            loaded_addresses: meta.loaded_addresses,
+            return_data: meta.return_data,
+            compute_units_consumed: meta.compute_units_consumed,
+            cost_units: meta.cost_units,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BincodeTransactionTokenBalanceV1 {
+    pub account_index: u8,
+    pub mint: String,
+    pub ui_token_amount: UiTokenAmount,
+    pub owner: String,
+    pub program_id: String,
+}
+
+impl From<&TransactionTokenBalance> for BincodeTransactionTokenBalanceV1 {
+    fn from(balance: &TransactionTokenBalance) -> Self {
+        Self {
+            account_index: balance.account_index,
+            mint: balance.mint.clone(),
+            ui_token_amount: balance.ui_token_amount.clone(),
+            owner: balance.owner.clone(),
+            program_id: balance.program_id.clone(),
+        }
+    }
+}
+
+impl From<BincodeTransactionTokenBalanceV1> for TransactionTokenBalance {
+    fn from(balance: BincodeTransactionTokenBalanceV1) -> Self {
+        Self {
+            account_index: balance.account_index,
+            mint: balance.mint,
+            ui_token_amount: balance.ui_token_amount,
+            owner: balance.owner,
+            program_id: balance.program_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BincodeEntryV1<'a> {
+    pub slot: Slot,
+    pub index: u64,
+    pub num_hashes: u64,
+    pub hash: Cow<'a, [u8]>,
+    pub executed_transaction_count: u64,
+    pub starting_transaction_index: u64,
+}
+
+impl<'a> BincodeEntryV1<'a> {
+    const fn new(entry: &ReplicaEntryInfoV2<'a>) -> Self {
+        Self {
+            slot: entry.slot,
+            index: entry.index as u64,
+            num_hashes: entry.num_hashes,
+            hash: Cow::Borrowed(entry.hash),
+            executed_transaction_count: entry.executed_transaction_count,
+            starting_transaction_index: entry.starting_transaction_index as u64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BincodeBlockMetaV1 {
+    pub slot: Slot,
+    pub blockhash: String,
+    pub rewards: Vec<Reward>,
+    pub num_partitions: Option<u64>,
+    pub block_time: Option<UnixTimestamp>,
+    pub block_height: Option<u64>,
+    pub parent_slot: Slot,
+    pub parent_blockhash: String,
+    pub executed_transaction_count: u64,
+    pub entries_count: u64,
+    /// Transactions pushed by the plugin for the slot, if counts are enabled
+    pub pushed_transactions: Option<u64>,
+    /// Accounts pushed by the plugin for the slot, if counts are enabled
+    pub pushed_accounts: Option<u64>,
+}
+
+impl BincodeBlockMetaV1 {
+    fn new(blockinfo: &ReplicaBlockInfoV4<'_>, counts: Option<SlotCounts>) -> Self {
+        Self {
+            slot: blockinfo.slot,
+            blockhash: blockinfo.blockhash.to_owned(),
+            rewards: blockinfo.rewards.rewards.clone(),
+            num_partitions: blockinfo.rewards.num_partitions,
+            block_time: blockinfo.block_time,
+            block_height: blockinfo.block_height,
+            parent_slot: blockinfo.parent_slot,
+            parent_blockhash: blockinfo.parent_blockhash.to_owned(),
+            executed_transaction_count: blockinfo.executed_transaction_count,
+            entries_count: blockinfo.entry_count,
+            pushed_transactions: counts.map(|counts| counts.transactions),
+            pushed_accounts: counts.map(|counts| counts.accounts),
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use {
+    crate::{channel::Sender, config::ConfigFileSink, protobuf::ProtobufEncoder},
+    futures::stream::StreamExt,
+    log::{error, info},
+    richat_shared::transports::RecvError,
+    std::{
+        future::Future,
+        io,
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    },
+    tokio::{
+        fs::{self, File},
+        io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+        task::JoinError,
+    },
+    tokio_util::sync::CancellationToken,
+};
+
+/// First bytes of every sink file
+pub const FILE_MAGIC: &[u8; 8] = b"RICHATFS";
+/// Version of the file format
+pub const FILE_VERSION: u8 = 1;
+/// Extension of completed files, file in progress has additional `.tmp`
+pub const FILE_EXTENSION: &str = "bin";
+
+#[derive(Debug)]
+pub struct FileSink;
+
+impl FileSink {
+    /// Append encoded messages to rolling files, see [`SinkWriter`]
+    pub async fn spawn(
+        config: ConfigFileSink,
+        messages: Sender,
+        encoder: ProtobufEncoder,
+        shutdown: CancellationToken,
+    ) -> io::Result<impl Future<Output = Result<(), JoinError>>> {
+        let mut writer =
+            SinkWriter::new(config.path.clone(), encoder, config.max_file_size).await?;
+        let mut rx = messages.subscribe_notifications(&config.notifications);
+        info!("start file sink at {}", config.path.display());
+
+        Ok(tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    () = shutdown.cancelled() => break,
+                    message = rx.next() => message,
+                };
+
+                let result = match message {
+                    Some(Ok(message)) => writer.write(&message).await,
+                    Some(Err(RecvError::Lagged)) => {
+                        error!("file sink lagged, messages are lost");
+                        rx = messages.subscribe_notifications(&config.notifications);
+                        Ok(())
+                    }
+                    Some(Err(RecvError::Closed)) | None => break,
+                };
+                if let Err(error) = result {
+                    error!("failed to write file sink: {error}");
+                    return;
+                }
+            }
+
+            if let Err(error) = writer.finish().await {
+                error!("failed to finish file sink: {error}");
+            }
+            info!("file sink finished");
+        }))
+    }
+}
+
+/// Writer of rolling files with length-prefixed encoded messages
+///
+/// File starts with the header: magic, format version, length of encoder name and name
+/// itself. Every message prefixed with size as `u64` in big-endian. File is written with
+/// `.tmp` suffix and renamed on rotation, so only completed files are visible for readers.
+/// File names contain creation timestamp in microseconds, sorted names are in write order.
+#[derive(Debug)]
+pub struct SinkWriter {
+    dir: PathBuf,
+    encoder: ProtobufEncoder,
+    max_file_size: usize,
+    file: SinkFile,
+}
+
+impl SinkWriter {
+    pub async fn new(
+        dir: PathBuf,
+        encoder: ProtobufEncoder,
+        max_file_size: usize,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&dir).await?;
+        let file = SinkFile::create(&dir, encoder, 0).await?;
+        Ok(Self {
+            dir,
+            encoder,
+            max_file_size,
+            file,
+        })
+    }
+
+    /// Write message, file is rotated before the write once its size reaches the limit
+    pub async fn write(&mut self, message: &[u8]) -> io::Result<()> {
+        if self.file.size >= self.max_file_size && self.file.messages > 0 {
+            let next = SinkFile::create(&self.dir, self.encoder, self.file.created_at).await?;
+            std::mem::replace(&mut self.file, next).finish().await?;
+        }
+        self.file.write(message).await
+    }
+
+    pub async fn finish(self) -> io::Result<()> {
+        self.file.finish().await
+    }
+}
+
+#[derive(Debug)]
+struct SinkFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    created_at: u128,
+    size: usize,
+    messages: usize,
+}
+
+impl SinkFile {
+    /// Timestamp is increased if needed, so names of rotated files are unique and ordered
+    async fn create(
+        dir: &Path,
+        encoder: ProtobufEncoder,
+        prev_created_at: u128,
+    ) -> io::Result<Self> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros()
+            .max(prev_created_at + 1);
+        let path = dir.join(format!("richat-{created_at}.{FILE_EXTENSION}"));
+        let file = File::create(path.with_extension(format!("{FILE_EXTENSION}.tmp"))).await?;
+
+        let mut writer = BufWriter::new(file);
+        let encoder = encoder.as_str().as_bytes();
+        writer.write_all(FILE_MAGIC).await?;
+        writer.write_u8(FILE_VERSION).await?;
+        writer.write_u8(encoder.len() as u8).await?;
+        writer.write_all(encoder).await?;
+
+        Ok(Self {
+            path,
+            writer,
+            created_at,
+            size: FILE_MAGIC.len() + 2 + encoder.len(),
+            messages: 0,
+        })
+    }
+
+    async fn write(&mut self, message: &[u8]) -> io::Result<()> {
+        self.writer.write_u64(message.len() as u64).await?;
+        self.writer.write_all(message).await?;
+        self.size += 8 + message.len();
+        self.messages += 1;
+        Ok(())
+    }
+
+    async fn finish(mut self) -> io::Result<()> {
+        self.writer.flush().await?;
+        self.writer.get_ref().sync_all().await?;
+        fs::rename(
+            self.path.with_extension(format!("{FILE_EXTENSION}.tmp")),
+            &self.path,
+        )
+        .await
+    }
+}
+
+/// Reader of files written by [`SinkWriter`]
+#[derive(Debug)]
+pub struct SinkReader<R> {
+    reader: R,
+    encoder: ProtobufEncoder,
+}
+
+impl SinkReader<BufReader<File>> {
+    pub async fn open(path: &Path) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path).await?)).await
+    }
+}
+
+impl<R: AsyncRead + Unpin> SinkReader<R> {
+    /// Read and verify the header
+    pub async fn new(mut reader: R) -> io::Result<Self> {
+        let invalid_data = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut magic = [0; FILE_MAGIC.len()];
+        reader.read_exact(&mut magic).await?;
+        if &magic != FILE_MAGIC {
+            return Err(invalid_data("invalid file magic".to_owned()));
+        }
+
+        let version = reader.read_u8().await?;
+        if version != FILE_VERSION {
+            return Err(invalid_data(format!("unsupported file version: {version}")));
+        }
+
+        let mut encoder = vec![0; reader.read_u8().await? as usize];
+        reader.read_exact(&mut encoder).await?;
+        let encoder = std::str::from_utf8(&encoder)
+            .ok()
+            .and_then(ProtobufEncoder::parse)
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "unknown encoder: {}",
+                    String::from_utf8_lossy(&encoder)
+                ))
+            })?;
+
+        Ok(Self { reader, encoder })
+    }
+
+    /// Encoder of messages from the header
+    pub const fn encoder(&self) -> ProtobufEncoder {
+        self.encoder
+    }
+
+    /// Read the next message, `None` at the end of file
+    pub async fn read_message(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let size = match self.reader.read_u64().await {
+            Ok(size) => size,
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let mut data = vec![0; size as usize];
+        self.reader.read_exact(&mut data).await?;
+        Ok(Some(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{FILE_EXTENSION, SinkReader, SinkWriter},
+        crate::protobuf::ProtobufEncoder,
+        std::{
+            env, io,
+            path::{Path, PathBuf},
+            process,
+        },
+        tokio::fs,
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("richat-sink-{name}-{}", process::id()))
+    }
+
+    async fn read_dir(dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut entries = fs::read_dir(dir).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            files.push(entry.path());
+        }
+        files.sort();
+        files
+    }
+
+    async fn read_file(path: &Path) -> (ProtobufEncoder, Vec<Vec<u8>>) {
+        let mut reader = SinkReader::open(path).await.unwrap();
+        let mut messages = Vec::new();
+        while let Some(message) = reader.read_message().await.unwrap() {
+            messages.push(message);
+        }
+        (reader.encoder(), messages)
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let dir = temp_dir("round-trip");
+        let messages = vec![vec![], b"message".to_vec(), vec![42; 10_000]];
+
+        let mut writer = SinkWriter::new(dir.clone(), ProtobufEncoder::Zstd, usize::MAX)
+            .await
+            .unwrap();
+        for message in &messages {
+            writer.write(message).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+
+        let files = read_dir(&dir).await;
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            files[0].extension().and_then(|e| e.to_str()),
+            Some(FILE_EXTENSION)
+        );
+        assert_eq!(
+            read_file(&files[0]).await,
+            (ProtobufEncoder::Zstd, messages)
+        );
+
+        // truncated message
+        let data = fs::read(&files[0]).await.unwrap();
+        let mut reader = SinkReader::new(&data[..data.len() - 1]).await.unwrap();
+        assert_eq!(reader.read_message().await.unwrap(), Some(vec![]));
+        assert_eq!(
+            reader.read_message().await.unwrap(),
+            Some(b"message".to_vec())
+        );
+        let error = reader.read_message().await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // invalid header
+        let error = SinkReader::new(&b"RICHATFX\x01\x03raw"[..])
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = SinkReader::new(&b"RICHATFS\x02\x03raw"[..])
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = SinkReader::new(&b"RICHATFS\x01\x03xyz"[..])
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_dir_all(dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rotation() {
+        let dir = temp_dir("rotation");
+        let messages = (0..10u8).map(|i| vec![i; 40]).collect::<Vec<_>>();
+
+        // header is 13 bytes and message with size is 48 bytes, so 2 messages per file
+        let mut writer = SinkWriter::new(dir.clone(), ProtobufEncoder::Raw, 100)
+            .await
+            .unwrap();
+        for (i, message) in messages.iter().enumerate() {
+            writer.write(message).await.unwrap();
+            // only completed files are visible
+            let completed = read_dir(&dir)
+                .await
+                .into_iter()
+                .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(FILE_EXTENSION))
+                .count();
+            assert_eq!(completed, i / 2);
+        }
+        writer.finish().await.unwrap();
+
+        let files = read_dir(&dir).await;
+        assert_eq!(files.len(), 5);
+        let mut read = Vec::new();
+        for file in &files {
+            let (encoder, file_messages) = read_file(file).await;
+            assert_eq!(encoder, ProtobufEncoder::Raw);
+            assert_eq!(file_messages.len(), 2);
+            read.extend(file_messages);
+        }
+        assert_eq!(read, messages);
+
+        fs::remove_dir_all(dir).await.unwrap();
+    }
+}
@@ -1,12 +1,40 @@
 use {richat_shared::version::Version, std::env};
 
+/// Reported by `Plugin::name`, git version allows to match running library with the source
+pub const NAME: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "-",
+    env!("CARGO_PKG_VERSION"),
+    "+",
+    env!("GIT_VERSION")
+);
+
 pub const VERSION: Version = Version {
     package: env!("CARGO_PKG_NAME"),
     version: env!("CARGO_PKG_VERSION"),
     proto: env!("YELLOWSTONE_GRPC_PROTO_VERSION"),
     proto_richat: env!("RICHAT_PROTO_VERSION"),
     solana: env!("SOLANA_SDK_VERSION"),
+    agave: Some(AGAVE_INTERFACE_VERSION),
     git: env!("GIT_VERSION"),
     rustc: env!("VERGEN_RUSTC_SEMVER"),
     buildts: env!("VERGEN_BUILD_TIMESTAMP"),
 };
+
+/// Version of `agave-geyser-plugin-interface` the plugin was built against
+pub const AGAVE_INTERFACE_VERSION: &str = env!("AGAVE_GEYSER_PLUGIN_INTERFACE_VERSION");
+
+/// Enabled crate features
+pub fn features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "plugin") {
+        features.push("plugin");
+    }
+    if cfg!(feature = "fixtures") {
+        features.push("fixtures");
+    }
+    if cfg!(feature = "rustls-install-default-provider") {
+        features.push("rustls-install-default-provider");
+    }
+    features
+}
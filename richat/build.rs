@@ -15,10 +15,11 @@ fn emit_version() -> anyhow::Result<()> {
         .add_instructions(&vergen::RustcBuilder::all_rustc()?)?
         .emit()?;
 
-    // vergen git version does not looks cool
+    // vergen git version does not looks cool, fallback for builds without git (e.g. from
+    // source tarball)
     println!(
         "cargo:rustc-env=GIT_VERSION={}",
-        git_version::git_version!()
+        git_version::git_version!(fallback = "unknown")
     );
 
     // Extract packages version
@@ -50,8 +50,8 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let metrics_handle = if config.metrics.is_some() {
-        Some(richat::metrics::setup().context("failed to setup metrics")?)
+    let metrics_handle = if let Some(config) = &config.metrics {
+        Some(richat::metrics::setup(config.namespace.clone()).context("failed to setup metrics")?)
     } else {
         None
     };
@@ -20,7 +20,10 @@ use {
     richat_proto::{geyser::SlotStatus, richat::RichatFilter},
     richat_shared::{
         mutex_lock,
-        transports::{RecvError, RecvItem, RecvStream, Subscribe, SubscribeError},
+        transports::{
+            RecvError, RecvItem, RecvStream, Subscribe, SubscribeAccountsFilter, SubscribeError,
+            expression::{FilterExpression, MessageFields},
+        },
     },
     smallvec::SmallVec,
     solana_account::ReadableAccount,
@@ -454,6 +457,11 @@ impl Subscribe for Messages {
         };
 
         let filter = filter.unwrap_or_default();
+        let expression = filter
+            .expression
+            .as_deref()
+            .map(FilterExpression::compile)
+            .transpose()?;
 
         Ok(ReceiverAsync {
             shared: Arc::clone(&self.shared_processed),
@@ -462,6 +470,8 @@ impl Subscribe for Messages {
             enable_notifications_accounts: !filter.disable_accounts,
             enable_notifications_transactions: !filter.disable_transactions,
             enable_notifications_entries: !filter.disable_entries,
+            accounts_filter: SubscribeAccountsFilter::new(&filter),
+            expression,
         }
         .boxed())
     }
@@ -853,6 +863,8 @@ pub struct ReceiverAsync {
     enable_notifications_accounts: bool,
     enable_notifications_transactions: bool,
     enable_notifications_entries: bool,
+    accounts_filter: SubscribeAccountsFilter,
+    expression: Option<FilterExpression>,
 }
 
 impl ReceiverAsync {
@@ -869,9 +881,40 @@ impl ReceiverAsync {
             let item = item.data.as_ref().ok_or(RecvError::Lagged)?;
             match item {
                 ParsedMessage::Account(_) if !self.enable_notifications_accounts => continue,
+                ParsedMessage::Account(msg)
+                    if !self.accounts_filter.is_empty()
+                        && !self.accounts_filter.is_match(msg.pubkey(), msg.owner()) =>
+                {
+                    continue;
+                }
                 ParsedMessage::Transaction(_) if !self.enable_notifications_transactions => {
                     continue;
                 }
+                ParsedMessage::Account(msg)
+                    if self.expression.as_ref().is_some_and(|expression| {
+                        let fields = MessageFields::account(
+                            msg.lamports(),
+                            msg.data(),
+                            msg.original_data_len().map(|len| len as usize),
+                            msg.executable(),
+                        );
+                        !expression.is_match(msg.slot(), Some((msg.pubkey(), msg.owner())), &fields)
+                    }) =>
+                {
+                    continue;
+                }
+                ParsedMessage::Transaction(msg)
+                    if self.expression.as_ref().is_some_and(|expression| {
+                        let fields = MessageFields::Transaction {
+                            fee: msg.transaction_meta().ok().map(|meta| meta.fee),
+                            is_vote: msg.vote(),
+                            failed: msg.failed(),
+                        };
+                        !expression.is_match(msg.slot(), None, &fields)
+                    }) =>
+                {
+                    continue;
+                }
                 ParsedMessage::Entry(_) if !self.enable_notifications_entries => continue,
                 ParsedMessage::Block(_) => continue,
                 _ => {}
@@ -1459,6 +1502,7 @@ mod test {
     use {
         super::update_write_version,
         maplit::hashmap,
+        prost::encoding,
         richat_filter::{
             config::{ConfigFilter, ConfigFilterAccounts, ConfigFilterAccountsDataSlice},
             filter::Filter,
@@ -1630,4 +1674,27 @@ mod test {
         let msg2 = parse(encode(&msg, None), MessageParserEncoding::Prost);
         assert_eq!(msg, msg2, "write version update failed");
     }
+
+    #[test]
+    fn test_original_data_len() {
+        let data = const_hex::decode(MESSAGE).expect("valid hex");
+        let mut data_ext = data.clone();
+        encoding::uint64::encode(100, &42, &mut data_ext); // original_data_len
+        encoding::bool::encode(105, &true, &mut data_ext); // owner_changed
+        encoding::fixed64::encode(104, &7, &mut data_ext); // sequence
+
+        for parser in [MessageParserEncoding::Limited, MessageParserEncoding::Prost] {
+            let msg = parse(data.clone(), parser);
+            assert_eq!(msg.original_data_len(), None, "parser: {parser:?}");
+
+            let msg_ext = parse(data_ext.clone(), parser);
+            assert_eq!(msg_ext.original_data_len(), Some(42), "parser: {parser:?}");
+            assert_eq!(msg_ext.data(), msg.data(), "parser: {parser:?}");
+            assert_eq!(
+                msg_ext.write_version(),
+                msg.write_version(),
+                "parser: {parser:?}"
+            );
+        }
+    }
 }
@@ -32,7 +32,7 @@ use {
             SubscribeReplayInfoResponse, SubscribeRequest, SubscribeUpdate, SubscribeUpdatePing,
             SubscribeUpdatePong, subscribe_update::UpdateOneof,
         },
-        richat::SubscribeAccountsRequest,
+        richat::{SubscribeAccountsRequest, SubscribeUpdateExtensions},
     },
     richat_shared::{jsonrpc::helpers::X_SUBSCRIPTION_ID, mutex_lock, transports::RecvError},
     smallvec::SmallVec,
@@ -470,10 +470,9 @@ impl GrpcServer {
                 loop {
                     match stream.message().await {
                         Ok(Some(message)) => {
+                            // filters of the ping request are ignored, same as in Yellowstone
                             if let Some(id) = get_ping(&message) {
-                                let message = SubscribeClientState::create_pong(id);
-                                let mut state = client.state_lock();
-                                state.push_message(GrpcSubscribeMessage::Pong, message);
+                                client.state_lock().push_pong(id);
                                 continue;
                             }
 
@@ -508,7 +507,7 @@ impl GrpcServer {
                                             .map_err(Status::internal)?;
                                     }
                                 }
-                                state.filter = Some(filter);
+                                state.set_filter(filter);
                                 Ok::<(), Status>(())
                             }) {
                                 warn!(id, %error, "failed to handle request");
@@ -543,7 +542,7 @@ impl geyser_gen::geyser_server::Geyser for GrpcServer {
         self.subscribe2(
             request,
             "subscribe",
-            |message| message.ping.map(|msg| msg.id),
+            |message| message.ping.as_ref().map(|msg| msg.id),
             |limits, message| {
                 let subscribe_from_slot = message.from_slot;
                 let new_filter = ConfigFilter::try_from(message)
@@ -784,6 +783,8 @@ pub struct SubscribeClientState {
     commitment: CommitmentLevel,
     pub head: IndexLocation,
     pub filter: Option<Filter>,
+    /// Number of accepted filters, sent in acknowledgement of the filter update
+    filter_updates: u64,
     messages_error: Option<Status>,
     messages_len_total: usize,
     messages_len_max: usize,
@@ -832,6 +833,7 @@ impl SubscribeClientState {
             commitment: CommitmentLevel::default(),
             head: IndexLocation::Unknown,
             filter: None,
+            filter_updates: 0,
             messages_error: None,
             messages_len_total: 0,
             messages_len_max,
@@ -862,6 +864,30 @@ impl SubscribeClientState {
         Self::serialize_ping_pong(UpdateOneof::Pong(SubscribeUpdatePong { id }))
     }
 
+    #[inline]
+    fn create_filter_update(filter_updates: u64) -> Vec<u8> {
+        let mut message = Self::create_pong(0);
+        let extensions = SubscribeUpdateExtensions {
+            filter_update: Some(filter_updates),
+            ..Default::default()
+        };
+        message.extend_from_slice(&extensions.encode_to_vec());
+        message
+    }
+
+    fn push_pong(&mut self, id: i32) {
+        self.push_message(GrpcSubscribeMessage::Pong, Self::create_pong(id));
+    }
+
+    /// Filter is replaced under the lock, so workers apply it from the next message and all
+    /// messages after the acknowledgement use it
+    fn set_filter(&mut self, filter: Filter) {
+        self.filter = Some(filter);
+        self.filter_updates += 1;
+        let message = Self::create_filter_update(self.filter_updates);
+        self.push_message(GrpcSubscribeMessage::Pong, message);
+    }
+
     const fn is_full(&self) -> bool {
         self.messages_len_total > self.messages_len_max
     }
@@ -1037,3 +1063,54 @@ struct MessagesCacheItem {
     pos: u64,
     msg: Option<ParsedMessage>,
 }
+
+#[cfg(test)]
+mod test {
+    use {
+        super::SubscribeClientState,
+        crate::metrics::GrpcSubscribeMessage,
+        prost::Message,
+        richat_filter::{config::ConfigFilter, filter::Filter},
+        richat_proto::{
+            geyser::{SubscribeUpdate, SubscribeUpdatePong, subscribe_update::UpdateOneof},
+            richat::SubscribeUpdateExtensions,
+        },
+        std::sync::Arc,
+    };
+
+    fn create_state() -> SubscribeClientState {
+        SubscribeClientState::new(0, usize::MAX, usize::MAX, Arc::from(""))
+    }
+
+    fn pop_pong(state: &mut SubscribeClientState) -> (i32, Option<u64>) {
+        let (message, data) = state.messages.pop_front().expect("pushed message");
+        assert_eq!(message, GrpcSubscribeMessage::Pong);
+        let update = SubscribeUpdate::decode(data.as_slice()).expect("valid update");
+        let Some(UpdateOneof::Pong(SubscribeUpdatePong { id })) = update.update_oneof else {
+            panic!("expected pong: {update:?}");
+        };
+        let extensions =
+            SubscribeUpdateExtensions::decode(data.as_slice()).expect("valid extensions");
+        (id, extensions.filter_update)
+    }
+
+    #[test]
+    fn test_ping() {
+        let mut state = create_state();
+        state.push_pong(42);
+        assert_eq!(pop_pong(&mut state), (42, None));
+        assert!(state.filter.is_none());
+        assert!(state.messages.is_empty());
+    }
+
+    #[test]
+    fn test_filter_update() {
+        let mut state = create_state();
+        for filter_updates in 1..=2 {
+            state.set_filter(Filter::new(&ConfigFilter::default()));
+            assert!(state.filter.is_some());
+            assert_eq!(pop_pong(&mut state), (0, Some(filter_updates)));
+        }
+        assert!(state.messages.is_empty());
+    }
+}
@@ -1,9 +1,9 @@
 use {
     crate::version::VERSION as VERSION_INFO,
-    ::metrics::{counter, describe_counter, describe_gauge},
-    metrics_exporter_prometheus::{BuildError, PrometheusBuilder, PrometheusHandle},
+    ::metrics::{SetRecorderError, counter, describe_counter, describe_gauge, describe_histogram},
+    metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle, PrometheusRecorder},
     richat_filter::filter::FilteredUpdateType,
-    richat_metrics::ConfigMetrics,
+    richat_metrics::{ConfigMetrics, PrefixedRecorder},
     solana_sdk::clock::Slot,
     std::{borrow::Cow, future::Future},
     tokio::{
@@ -40,10 +40,21 @@ pub const PUBSUB_SUBSCRIPTIONS_TOTAL: &str = "pubsub_subscriptions_total"; // x_
 pub const PUBSUB_MESSAGES_SENT_COUNT_TOTAL: &str = "pubsub_messages_sent_count_total"; // x_subscription_id, subscription
 pub const PUBSUB_MESSAGES_SENT_BYTES_TOTAL: &str = "pubsub_messages_sent_bytes_total"; // x_subscription_id, subscription
 pub const RICHAT_CONNECTIONS_TOTAL: &str = "richat_connections_total"; // transport
+pub const RICHAT_CONNECTIONS_REJECTED_TOTAL: &str = "richat_connections_rejected_total"; // transport, reason
+pub const RICHAT_CONNECTIONS_IDLE_TIMEOUT_TOTAL: &str = "richat_connections_idle_timeout_total"; // transport
+pub const RICHAT_CONNECTIONS_THROTTLED_TOTAL: &str = "richat_connections_throttled_total"; // transport
+pub const RICHAT_CONNECTIONS_COMPRESSED_TOTAL: &str = "richat_connections_compressed_total"; // transport, encoding
+pub const RICHAT_CONNECTION_DURATION_SECONDS: &str = "richat_connection_duration_seconds"; // transport
+pub const RICHAT_GRPC_BATCH_MESSAGES: &str = "richat_grpc_batch_messages";
+pub const RICHAT_GRPC_CLIENT_CONNECTIONS_TOTAL: &str = "richat_grpc_client_connections_total"; // client
 
 #[rustfmt::skip]
-pub fn setup() -> Result<PrometheusHandle, BuildError> {
-    let handle = PrometheusBuilder::new().install_recorder()?;
+pub fn setup(
+    namespace: Option<String>,
+) -> Result<PrometheusHandle, SetRecorderError<PrefixedRecorder<PrometheusRecorder>>> {
+    let recorder = PrometheusBuilder::new().build_recorder();
+    let handle = recorder.handle();
+    ::metrics::set_global_recorder(PrefixedRecorder::new(recorder, namespace))?;
 
     describe_counter!("version", "Richat App version info");
     counter!(
@@ -90,6 +101,13 @@ pub fn setup() -> Result<PrometheusHandle, BuildError> {
     describe_counter!(PUBSUB_MESSAGES_SENT_COUNT_TOTAL, "Number of sent filtered messages by type");
     describe_counter!(PUBSUB_MESSAGES_SENT_BYTES_TOTAL, "Total size of sent filtered messages by type");
     describe_gauge!(RICHAT_CONNECTIONS_TOTAL, "Total number of connections to Richat");
+    describe_counter!(RICHAT_CONNECTIONS_REJECTED_TOTAL, "Total number of rejected connections to Richat");
+    describe_counter!(RICHAT_CONNECTIONS_IDLE_TIMEOUT_TOTAL, "Total number of connections to Richat closed because client did not read within idle timeout");
+    describe_gauge!(RICHAT_CONNECTIONS_THROTTLED_TOTAL, "Number of connections to Richat throttled by rate limit");
+    describe_counter!(RICHAT_CONNECTIONS_COMPRESSED_TOTAL, "Total number of connections to Richat with negotiated compression");
+    describe_histogram!(RICHAT_CONNECTION_DURATION_SECONDS, "Duration of closed connections to Richat");
+    describe_histogram!(RICHAT_GRPC_BATCH_MESSAGES, "Number of messages in gRPC batches sent to Richat clients");
+    describe_gauge!(RICHAT_GRPC_CLIENT_CONNECTIONS_TOTAL, "Number of gRPC connections to Richat by client identity from TLS certificate");
 
     Ok(handle)
 }
@@ -1,7 +1,8 @@
 use {
     crate::{channel::Messages, metrics, richat::config::ConfigAppsRichat, version::VERSION},
-    ::metrics::gauge,
+    ::metrics::{counter, gauge, histogram},
     futures::future::{FutureExt, TryFutureExt, try_join_all},
+    richat_metrics::duration_to_seconds,
     richat_shared::transports::{grpc::GrpcServer, quic::QuicServer},
     std::future::Future,
     tokio_util::sync::CancellationToken,
@@ -22,12 +23,21 @@ impl RichatServer {
         if let Some(config) = config.quic {
             let connections_inc = gauge!(metrics::RICHAT_CONNECTIONS_TOTAL, "transport" => "quic");
             let connections_dec = connections_inc.clone();
+            let connection_duration =
+                histogram!(metrics::RICHAT_CONNECTION_DURATION_SECONDS, "transport" => "quic");
+            let connections_throttled =
+                gauge!(metrics::RICHAT_CONNECTIONS_THROTTLED_TOTAL, "transport" => "quic");
+            let connections_idle =
+                counter!(metrics::RICHAT_CONNECTIONS_IDLE_TIMEOUT_TOTAL, "transport" => "quic");
             tasks.push(
                 QuicServer::spawn(
                     config,
                     messages.clone(),
                     move || connections_inc.increment(1), // on_conn_new_cb
-                    move || connections_dec.decrement(1), // on_conn_drop_cb
+                    move |duration| { connections_dec.decrement(1); connection_duration.record(duration_to_seconds(duration)) }, // on_conn_drop_cb
+                    |reason| counter!(metrics::RICHAT_CONNECTIONS_REJECTED_TOTAL, "transport" => "quic", "reason" => reason).increment(1), // on_conn_rejected_cb
+                    move || connections_idle.increment(1), // on_conn_idle_cb
+                    move |throttled| if throttled { connections_throttled.increment(1) } else { connections_throttled.decrement(1) }, // on_conn_throttle_cb
                     VERSION,
                     shutdown.clone(),
                 )
@@ -40,13 +50,27 @@ impl RichatServer {
         if let Some(config) = config.grpc {
             let connections_inc = gauge!(metrics::RICHAT_CONNECTIONS_TOTAL, "transport" => "grpc");
             let connections_dec = connections_inc.clone();
+            let connection_duration =
+                histogram!(metrics::RICHAT_CONNECTION_DURATION_SECONDS, "transport" => "grpc");
+            let connections_throttled =
+                gauge!(metrics::RICHAT_CONNECTIONS_THROTTLED_TOTAL, "transport" => "grpc");
+            let connections_idle =
+                counter!(metrics::RICHAT_CONNECTIONS_IDLE_TIMEOUT_TOTAL, "transport" => "grpc");
+            let batch_messages = histogram!(metrics::RICHAT_GRPC_BATCH_MESSAGES);
             tasks.push(
                 GrpcServer::spawn(
                     config,
                     messages.clone(),
                     move || connections_inc.increment(1), // on_conn_new_cb
-                    move || connections_dec.decrement(1), // on_conn_drop_cb
+                    move |duration| { connections_dec.decrement(1); connection_duration.record(duration_to_seconds(duration)) }, // on_conn_drop_cb
+                    |reason| counter!(metrics::RICHAT_CONNECTIONS_REJECTED_TOTAL, "transport" => "grpc", "reason" => reason).increment(1), // on_conn_rejected_cb
+                    move || connections_idle.increment(1), // on_conn_idle_cb
+                    move |throttled| if throttled { connections_throttled.increment(1) } else { connections_throttled.decrement(1) }, // on_conn_throttle_cb
+                    move |size| batch_messages.record(size as f64), // on_batch_cb
+                    |encoding| counter!(metrics::RICHAT_CONNECTIONS_COMPRESSED_TOTAL, "transport" => "grpc", "encoding" => encoding).increment(1), // on_conn_compressed_cb
+                    |client, connected| { let connections = gauge!(metrics::RICHAT_GRPC_CLIENT_CONNECTIONS_TOTAL, "client" => client.to_owned()); if connected { connections.increment(1) } else { connections.decrement(1) } }, // on_conn_client_cb
                     VERSION,
+                    || None, // version_server_info
                     shutdown.clone(),
                 )
                 .await?
@@ -159,6 +159,7 @@ impl Subscription {
                         .subscribe_richat(GrpcSubscribeRequest {
                             replay_from_slot,
                             filter: Self::create_richat_filter(disable_accounts),
+                            batch: false,
                         })
                         .await?
                         .boxed(),
@@ -180,6 +181,9 @@ impl Subscription {
             disable_accounts,
             disable_transactions: false,
             disable_entries: false,
+            accounts: Vec::new(),
+            owners: Vec::new(),
+            expression: None,
         })
     }
 
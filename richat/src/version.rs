@@ -6,6 +6,7 @@ pub const VERSION: Version = Version {
     proto: env!("YELLOWSTONE_GRPC_PROTO_VERSION"),
     proto_richat: env!("RICHAT_PROTO_VERSION"),
     solana: env!("SOLANA_SDK_VERSION"),
+    agave: None,
     git: env!("GIT_VERSION"),
     rustc: env!("VERGEN_RUSTC_SEMVER"),
     buildts: env!("VERGEN_BUILD_TIMESTAMP"),
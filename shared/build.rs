@@ -10,7 +10,8 @@ fn main() -> anyhow::Result<()> {
     }
 
     // build protos
-    generate_grpc_geyser()
+    generate_grpc_geyser()?;
+    generate_grpc_geyser_descriptor()
 }
 
 #[cfg(feature = "transports")]
@@ -48,3 +49,82 @@ fn generate_grpc_geyser() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Descriptors for gRPC reflection, only implemented methods are kept in the `Geyser` service
+/// and `Subscribe` accepts `richat.GrpcSubscribeRequest`, as decoded by the server
+#[cfg(feature = "transports")]
+fn generate_grpc_geyser_descriptor() -> anyhow::Result<()> {
+    use {
+        prost::Message,
+        prost_types::FileDescriptorSet,
+        std::{env, fs, path::PathBuf},
+    };
+
+    let yellowstone_dir = locate_package_protos("yellowstone-grpc-proto")?;
+    let richat_dir = locate_package_protos("richat-proto")?;
+
+    let descriptor_path = PathBuf::from(env::var("OUT_DIR")?).join("geyser_descriptor.bin");
+    tonic_prost_build::configure()
+        .build_client(false)
+        .build_server(false)
+        .file_descriptor_set_path(&descriptor_path)
+        .compile_protos(
+            &[
+                yellowstone_dir.join("geyser.proto"),
+                richat_dir.join("richat.proto"),
+            ],
+            &[yellowstone_dir, richat_dir],
+        )?;
+
+    let mut descriptor = FileDescriptorSet::decode(fs::read(&descriptor_path)?.as_slice())?;
+    for file in descriptor.file.iter_mut() {
+        if file.package() == "geyser" && !file.service.is_empty() {
+            for service in file.service.iter_mut() {
+                service
+                    .method
+                    .retain(|method| matches!(method.name(), "Subscribe" | "GetVersion"));
+                for method in service.method.iter_mut() {
+                    if method.name() == "Subscribe" {
+                        method.input_type = Some(".richat.GrpcSubscribeRequest".to_owned());
+                    }
+                }
+            }
+            if !file.dependency.iter().any(|name| name == "richat.proto") {
+                file.dependency.push("richat.proto".to_owned());
+            }
+        }
+    }
+    fs::write(descriptor_path, descriptor.encode_to_vec())?;
+
+    Ok(())
+}
+
+/// Proto directory of the package from the dependency graph, protos are not exported by crates
+#[cfg(feature = "transports")]
+fn locate_package_protos(name: &str) -> anyhow::Result<std::path::PathBuf> {
+    use std::{env, path::Path, process::Command};
+
+    // all packages of the target are already downloaded, network is not required
+    let output = Command::new(env::var("CARGO")?)
+        .args(["metadata", "--format-version", "1", "--offline"])
+        .args(["--filter-platform", &env::var("TARGET")?])
+        .current_dir(env::var("CARGO_MANIFEST_DIR")?)
+        .output()?;
+    anyhow::ensure!(
+        output.status.success(),
+        "failed to get cargo metadata: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let manifest_path = metadata["packages"]
+        .as_array()
+        .and_then(|packages| packages.iter().find(|package| package["name"] == name))
+        .and_then(|package| package["manifest_path"].as_str())
+        .ok_or_else(|| anyhow::anyhow!("package {name} is not found in cargo metadata"))?;
+    let proto_dir = Path::new(manifest_path)
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("invalid manifest path: {manifest_path}"))?
+        .join("proto");
+    println!("cargo:rerun-if-changed={}", proto_dir.display());
+    Ok(proto_dir)
+}
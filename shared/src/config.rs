@@ -147,6 +147,22 @@ where
         .map_err(|_| de::Error::custom("size value exceeds usize maximum"))
 }
 
+pub fn deserialize_maybe_humansize_usize<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(size) = Option::<&str>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    Size::from_str(size)
+        .map_err(|error| de::Error::custom(format!("failed to parse size {size:?}: {error}")))?
+        .to_bytes()
+        .try_into()
+        .map(Some)
+        .map_err(|_| de::Error::custom("size value exceeds usize maximum"))
+}
+
 #[derive(Debug, Error)]
 enum DecodeXTokenError {
     #[error(transparent)]
@@ -156,10 +172,12 @@ enum DecodeXTokenError {
 }
 
 fn decode_x_token(x_token: &str) -> Result<Vec<u8>, DecodeXTokenError> {
-    Ok(match &x_token[0..7] {
-        "base64:" => base64_engine.decode(x_token)?,
-        "base58:" => bs58::decode(x_token).into_vec()?,
-        _ => x_token.as_bytes().to_vec(),
+    Ok(if let Some(x_token) = x_token.strip_prefix("base64:") {
+        base64_engine.decode(x_token)?
+    } else if let Some(x_token) = x_token.strip_prefix("base58:") {
+        bs58::decode(x_token).into_vec()?
+    } else {
+        x_token.as_bytes().to_vec()
     })
 }
 
@@ -0,0 +1,747 @@
+//! Filter expressions evaluated by the server for every account and transaction message
+//! of the subscription
+//!
+//! ```text
+//! expression = or
+//! or         = and { ("||" | "or") and }
+//! and        = unary { ("&&" | "and") unary }
+//! unary      = ("!" | "not") unary | "(" or ")" | field op value
+//! op         = "==" | "!=" | "<" | "<=" | ">" | ">="
+//! ```
+//!
+//! | field                  | type   | message     |
+//! |------------------------|--------|-------------|
+//! | `slot`                 | number | all         |
+//! | `account.pubkey`       | pubkey | account     |
+//! | `account.owner`        | pubkey | account     |
+//! | `account.lamports`     | number | account     |
+//! | `account.data_len`     | number | account     |
+//! | `account.data[N]`      | number | account     |
+//! | `account.executable`   | bool   | account     |
+//! | `transaction.fee`      | number | transaction |
+//! | `transaction.is_vote`  | bool   | transaction |
+//! | `transaction.failed`   | bool   | transaction |
+//!
+//! Numbers are decimal and can contain `_`, pubkeys are base58 (optionally in double
+//! quotes), bools are `true` / `false`; pubkeys and bools support only `==` and `!=`.
+//! `account.data[N]` is a byte of account data, only first [`DATA_PREFIX_LEN`] bytes are
+//! available. Bytes of accounts with sliced data are not available, because position of the
+//! slice in the original data is unknown, `account.data_len` is length before slicing.
+//! Comparison with a field which the message does not have (or with data byte after the end
+//! of data) is `false`, e.g. `account.owner == X` never matches transactions.
+//! Slots, entries and block meta are not filtered by expression.
+
+use {
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
+    std::{fmt, iter::Peekable, vec},
+    thiserror::Error,
+};
+
+/// Number of the first bytes of account data available in expressions
+pub const DATA_PREFIX_LEN: usize = 8;
+
+/// Max length of the expression source
+pub const MAX_EXPRESSION_LEN: usize = 1_024;
+
+/// Max number of comparisons in the expression
+pub const MAX_COMPARISONS: usize = 32;
+
+/// Max nesting of parentheses and negations
+pub const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExpressionError {
+    #[error("expression is empty")]
+    Empty,
+    #[error("expression is longer than {MAX_EXPRESSION_LEN} bytes")]
+    TooLong,
+    #[error("expression has more than {MAX_COMPARISONS} comparisons")]
+    TooManyComparisons,
+    #[error("expression nesting is deeper than {MAX_DEPTH}")]
+    TooDeep,
+    #[error("unexpected character `{ch}` at {pos}")]
+    UnexpectedChar { ch: char, pos: usize },
+    #[error("unterminated string at {pos}")]
+    UnterminatedString { pos: usize },
+    #[error("unexpected `{token}` at {pos}")]
+    UnexpectedToken { token: String, pos: usize },
+    #[error("unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("unknown field `{field}`")]
+    UnknownField { field: String },
+    #[error("invalid value `{value}` for field `{field}`")]
+    InvalidValue { field: String, value: String },
+    #[error("operator `{op}` is not supported by field `{field}`")]
+    InvalidOperator { field: String, op: &'static str },
+}
+
+/// Values of the message used by expression, pubkey and owner of account are passed separately
+#[derive(Debug, Default, Clone, Copy)]
+pub enum MessageFields {
+    #[default]
+    None,
+    Account {
+        lamports: u64,
+        data_len: u64,
+        /// First bytes of data, not available if data was sliced
+        data_prefix: Option<[u8; DATA_PREFIX_LEN]>,
+        executable: bool,
+    },
+    Transaction {
+        /// Fee from transaction meta, not available if meta was not parsed
+        fee: Option<u64>,
+        is_vote: bool,
+        failed: bool,
+    },
+}
+
+impl MessageFields {
+    /// `original_data_len` is set if `data` is a slice of account data
+    pub fn account(
+        lamports: u64,
+        data: &[u8],
+        original_data_len: Option<usize>,
+        executable: bool,
+    ) -> Self {
+        let data_prefix = original_data_len.is_none().then(|| {
+            let mut data_prefix = [0; DATA_PREFIX_LEN];
+            let len = data.len().min(DATA_PREFIX_LEN);
+            data_prefix[..len].copy_from_slice(&data[..len]);
+            data_prefix
+        });
+        Self::Account {
+            lamports,
+            data_len: original_data_len.unwrap_or(data.len()) as u64,
+            data_prefix,
+            executable,
+        }
+    }
+}
+
+/// Compiled expression, field names and values are resolved on subscribe
+#[derive(Debug, Clone)]
+pub struct FilterExpression {
+    root: Node,
+}
+
+impl FilterExpression {
+    pub fn compile(source: &str) -> Result<Self, ExpressionError> {
+        if source.len() > MAX_EXPRESSION_LEN {
+            return Err(ExpressionError::TooLong);
+        }
+        let tokens = tokenize(source)?;
+        if tokens.is_empty() {
+            return Err(ExpressionError::Empty);
+        }
+
+        let mut parser = Parser {
+            tokens: tokens.into_iter().peekable(),
+            comparisons: 0,
+        };
+        let root = parser.parse_or(0)?;
+        match parser.tokens.next() {
+            Some((pos, token)) => Err(ExpressionError::UnexpectedToken {
+                token: token.to_string(),
+                pos,
+            }),
+            None => Ok(Self { root }),
+        }
+    }
+
+    pub fn is_match(
+        &self,
+        slot: Slot,
+        account: Option<(&Pubkey, &Pubkey)>,
+        fields: &MessageFields,
+    ) -> bool {
+        self.root.eval(&Input {
+            slot,
+            account,
+            fields,
+        })
+    }
+}
+
+struct Input<'a> {
+    slot: Slot,
+    account: Option<(&'a Pubkey, &'a Pubkey)>,
+    fields: &'a MessageFields,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Number {
+        field: NumberField,
+        op: Op,
+        value: u64,
+    },
+    Bool {
+        field: BoolField,
+        eq: bool,
+        value: bool,
+    },
+    Pubkey {
+        field: PubkeyField,
+        eq: bool,
+        value: Pubkey,
+    },
+}
+
+impl Node {
+    fn eval(&self, input: &Input<'_>) -> bool {
+        match self {
+            Self::And(left, right) => left.eval(input) && right.eval(input),
+            Self::Or(left, right) => left.eval(input) || right.eval(input),
+            Self::Not(node) => !node.eval(input),
+            Self::Number { field, op, value } => field
+                .get(input)
+                .is_some_and(|actual| op.compare(actual, *value)),
+            Self::Bool { field, eq, value } => field
+                .get(input)
+                .is_some_and(|actual| (actual == *value) == *eq),
+            Self::Pubkey { field, eq, value } => field
+                .get(input)
+                .is_some_and(|actual| (actual == value) == *eq),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+
+    const fn compare(self, actual: u64, value: u64) -> bool {
+        match self {
+            Self::Eq => actual == value,
+            Self::Ne => actual != value,
+            Self::Lt => actual < value,
+            Self::Le => actual <= value,
+            Self::Gt => actual > value,
+            Self::Ge => actual >= value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NumberField {
+    Slot,
+    AccountLamports,
+    AccountDataLen,
+    AccountData(usize),
+    TransactionFee,
+}
+
+impl NumberField {
+    const fn get(self, input: &Input<'_>) -> Option<u64> {
+        match (self, input.fields) {
+            (Self::Slot, _) => Some(input.slot),
+            (Self::AccountLamports, MessageFields::Account { lamports, .. }) => Some(*lamports),
+            (Self::AccountDataLen, MessageFields::Account { data_len, .. }) => Some(*data_len),
+            (
+                Self::AccountData(index),
+                MessageFields::Account {
+                    data_len,
+                    data_prefix: Some(data_prefix),
+                    ..
+                },
+            ) if (index as u64) < *data_len => Some(data_prefix[index] as u64),
+            (Self::TransactionFee, MessageFields::Transaction { fee, .. }) => *fee,
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BoolField {
+    AccountExecutable,
+    TransactionIsVote,
+    TransactionFailed,
+}
+
+impl BoolField {
+    const fn get(self, input: &Input<'_>) -> Option<bool> {
+        match (self, input.fields) {
+            (Self::AccountExecutable, MessageFields::Account { executable, .. }) => {
+                Some(*executable)
+            }
+            (Self::TransactionIsVote, MessageFields::Transaction { is_vote, .. }) => Some(*is_vote),
+            (Self::TransactionFailed, MessageFields::Transaction { failed, .. }) => Some(*failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PubkeyField {
+    AccountPubkey,
+    AccountOwner,
+}
+
+impl PubkeyField {
+    fn get<'a>(self, input: &Input<'a>) -> Option<&'a Pubkey> {
+        input.account.map(|(pubkey, owner)| match self {
+            Self::AccountPubkey => pubkey,
+            Self::AccountOwner => owner,
+        })
+    }
+}
+
+enum Field {
+    Number(NumberField),
+    Bool(BoolField),
+    Pubkey(PubkeyField),
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "slot" => Self::Number(NumberField::Slot),
+            "account.pubkey" => Self::Pubkey(PubkeyField::AccountPubkey),
+            "account.owner" => Self::Pubkey(PubkeyField::AccountOwner),
+            "account.lamports" => Self::Number(NumberField::AccountLamports),
+            "account.data_len" => Self::Number(NumberField::AccountDataLen),
+            "account.executable" => Self::Bool(BoolField::AccountExecutable),
+            "transaction.fee" => Self::Number(NumberField::TransactionFee),
+            "transaction.is_vote" => Self::Bool(BoolField::TransactionIsVote),
+            "transaction.failed" => Self::Bool(BoolField::TransactionFailed),
+            name => {
+                let index = name
+                    .strip_prefix("account.data[")?
+                    .strip_suffix(']')?
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index < DATA_PREFIX_LEN)?;
+                Self::Number(NumberField::AccountData(index))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Op(Op),
+    Word(&'a str),
+    String(&'a str),
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LParen => f.write_str("("),
+            Self::RParen => f.write_str(")"),
+            Self::And => f.write_str("&&"),
+            Self::Or => f.write_str("||"),
+            Self::Not => f.write_str("!"),
+            Self::Op(op) => f.write_str(op.as_str()),
+            Self::Word(word) => f.write_str(word),
+            Self::String(value) => write!(f, "\"{value}\""),
+        }
+    }
+}
+
+const fn is_word_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.' | '[' | ']')
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token<'_>)>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((pos, ch)) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if(|(_, ch)| *ch == expected).is_some();
+        let token = match ch {
+            ch if ch.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '=' if next_is('=') => Token::Op(Op::Eq),
+            '!' if next_is('=') => Token::Op(Op::Ne),
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if next_is('=') => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '"' => {
+                let start = pos + 1;
+                let end = loop {
+                    match chars.next() {
+                        Some((end, '"')) => break end,
+                        Some(_) => {}
+                        None => return Err(ExpressionError::UnterminatedString { pos }),
+                    }
+                };
+                Token::String(&source[start..end])
+            }
+            ch if is_word_char(ch) => {
+                let mut end = pos + ch.len_utf8();
+                while let Some((idx, ch)) = chars.next_if(|(_, ch)| is_word_char(*ch)) {
+                    end = idx + ch.len_utf8();
+                }
+                match &source[pos..end] {
+                    word if word.eq_ignore_ascii_case("and") => Token::And,
+                    word if word.eq_ignore_ascii_case("or") => Token::Or,
+                    word if word.eq_ignore_ascii_case("not") => Token::Not,
+                    word => Token::Word(word),
+                }
+            }
+            ch => return Err(ExpressionError::UnexpectedChar { ch, pos }),
+        };
+        tokens.push((pos, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Peekable<vec::IntoIter<(usize, Token<'a>)>>,
+    comparisons: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Result<(usize, Token<'a>), ExpressionError> {
+        self.tokens.next().ok_or(ExpressionError::UnexpectedEnd)
+    }
+
+    fn next_if(&mut self, expected: &Token<'_>) -> bool {
+        self.tokens
+            .next_if(|(_pos, token)| token == expected)
+            .is_some()
+    }
+
+    fn parse_or(&mut self, depth: usize) -> Result<Node, ExpressionError> {
+        let mut node = self.parse_and(depth)?;
+        while self.next_if(&Token::Or) {
+            node = Node::Or(Box::new(node), Box::new(self.parse_and(depth)?));
+        }
+        Ok(node)
+    }
+
+    fn parse_and(&mut self, depth: usize) -> Result<Node, ExpressionError> {
+        let mut node = self.parse_unary(depth)?;
+        while self.next_if(&Token::And) {
+            node = Node::And(Box::new(node), Box::new(self.parse_unary(depth)?));
+        }
+        Ok(node)
+    }
+
+    fn parse_unary(&mut self, depth: usize) -> Result<Node, ExpressionError> {
+        if depth >= MAX_DEPTH {
+            return Err(ExpressionError::TooDeep);
+        }
+
+        match self.next()? {
+            (_pos, Token::Not) => Ok(Node::Not(Box::new(self.parse_unary(depth + 1)?))),
+            (_pos, Token::LParen) => {
+                let node = self.parse_or(depth + 1)?;
+                match self.next()? {
+                    (_pos, Token::RParen) => Ok(node),
+                    (pos, token) => Err(ExpressionError::UnexpectedToken {
+                        token: token.to_string(),
+                        pos,
+                    }),
+                }
+            }
+            (_pos, Token::Word(name)) => self.parse_comparison(name),
+            (pos, token) => Err(ExpressionError::UnexpectedToken {
+                token: token.to_string(),
+                pos,
+            }),
+        }
+    }
+
+    fn parse_comparison(&mut self, name: &str) -> Result<Node, ExpressionError> {
+        self.comparisons += 1;
+        if self.comparisons > MAX_COMPARISONS {
+            return Err(ExpressionError::TooManyComparisons);
+        }
+
+        let field = Field::parse(name).ok_or_else(|| ExpressionError::UnknownField {
+            field: name.to_owned(),
+        })?;
+        let op = match self.next()? {
+            (_pos, Token::Op(op)) => op,
+            (pos, token) => {
+                return Err(ExpressionError::UnexpectedToken {
+                    token: token.to_string(),
+                    pos,
+                });
+            }
+        };
+        let value = match self.next()? {
+            (_pos, Token::Word(value) | Token::String(value)) => value,
+            (pos, token) => {
+                return Err(ExpressionError::UnexpectedToken {
+                    token: token.to_string(),
+                    pos,
+                });
+            }
+        };
+
+        let invalid_value = || ExpressionError::InvalidValue {
+            field: name.to_owned(),
+            value: value.to_owned(),
+        };
+        let eq = match (&field, op) {
+            (Field::Number(_), _) | (_, Op::Eq) => true,
+            (_, Op::Ne) => false,
+            (_, op) => {
+                return Err(ExpressionError::InvalidOperator {
+                    field: name.to_owned(),
+                    op: op.as_str(),
+                });
+            }
+        };
+        Ok(match field {
+            Field::Number(field) => Node::Number {
+                field,
+                op,
+                value: value
+                    .replace('_', "")
+                    .parse()
+                    .map_err(|_error| invalid_value())?,
+            },
+            Field::Bool(field) => Node::Bool {
+                field,
+                eq,
+                value: match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(invalid_value()),
+                },
+            },
+            Field::Pubkey(field) => Node::Pubkey {
+                field,
+                eq,
+                value: value.parse().map_err(|_error| invalid_value())?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            DATA_PREFIX_LEN, ExpressionError, FilterExpression, MAX_COMPARISONS, MAX_DEPTH,
+            MAX_EXPRESSION_LEN, MessageFields,
+        },
+        solana_sdk::pubkey::Pubkey,
+    };
+
+    fn account(lamports: u64, data: &[u8]) -> MessageFields {
+        MessageFields::account(lamports, data, None, false)
+    }
+
+    fn is_match(source: &str, slot: u64, fields: &MessageFields) -> bool {
+        let pubkey = Pubkey::new_from_array([1; 32]);
+        let owner = Pubkey::new_from_array([2; 32]);
+        FilterExpression::compile(source)
+            .expect("valid expression")
+            .is_match(slot, Some((&pubkey, &owner)), fields)
+    }
+
+    fn compile_err(source: &str) -> ExpressionError {
+        FilterExpression::compile(source).expect_err("invalid expression")
+    }
+
+    #[test]
+    fn test_precedence() {
+        let fields = account(10, &[]);
+        // `and` binds tighter than `or`: true || (false && false)
+        assert!(is_match("slot == 1 || slot == 2 && slot == 3", 1, &fields));
+        // parentheses change grouping: (true || false) && false
+        assert!(!is_match(
+            "(slot == 1 || slot == 2) && slot == 3",
+            1,
+            &fields
+        ));
+        assert!(is_match(
+            "slot == 2 or slot == 1 and account.lamports > 5",
+            1,
+            &fields
+        ));
+        assert!(!is_match(
+            "slot == 2 OR slot == 1 AND account.lamports > 50",
+            1,
+            &fields
+        ));
+    }
+
+    #[test]
+    fn test_not() {
+        let fields = account(10, &[]);
+        assert!(is_match("!slot == 2", 1, &fields));
+        assert!(!is_match("not slot == 1", 1, &fields));
+        assert!(is_match("not not slot == 1", 1, &fields));
+        // negation applies to the next comparison only
+        assert!(is_match("!slot == 1 || account.lamports == 10", 1, &fields));
+        assert!(!is_match(
+            "!(slot == 1 || account.lamports == 10)",
+            1,
+            &fields
+        ));
+    }
+
+    #[test]
+    fn test_fields() {
+        let owner = Pubkey::new_from_array([2; 32]);
+        let fields = account(1_000_000, &[7, 8, 9]);
+        assert!(is_match(
+            &format!("account.owner == {owner} && account.pubkey != \"{owner}\""),
+            1,
+            &fields
+        ));
+        assert!(is_match(
+            "account.lamports >= 1_000_000 && account.data_len < 4",
+            1,
+            &fields
+        ));
+        assert!(is_match(
+            "account.data[0] == 7 && account.data[2] > 8",
+            1,
+            &fields
+        ));
+        assert!(is_match("account.executable == false", 1, &fields));
+
+        let transaction = MessageFields::Transaction {
+            fee: Some(5_000),
+            is_vote: true,
+            failed: false,
+        };
+        assert!(is_match(
+            "transaction.fee == 5000 && transaction.is_vote == true",
+            1,
+            &transaction
+        ));
+        // fields of other message type never match
+        assert!(!is_match("account.lamports > 0", 1, &transaction));
+        assert!(!is_match("transaction.failed != true", 1, &fields));
+    }
+
+    #[test]
+    fn test_data_out_of_range() {
+        // byte after the end of data does not match
+        let fields = account(1, &[7]);
+        assert!(is_match("account.data[0] == 7", 1, &fields));
+        assert!(!is_match("account.data[1] == 0", 1, &fields));
+        assert!(!is_match("account.data[1] != 0", 1, &fields));
+
+        // only prefix of data is available
+        let field = format!("account.data[{DATA_PREFIX_LEN}]");
+        assert_eq!(
+            compile_err(&format!("{field} == 0")),
+            ExpressionError::UnknownField { field }
+        );
+    }
+
+    #[test]
+    fn test_data_sliced() {
+        // bytes of sliced data are at unknown offset, never match
+        let fields = MessageFields::account(1, &[7, 8], Some(100), false);
+        assert!(!is_match("account.data[0] == 7", 1, &fields));
+        assert!(!is_match("account.data[0] != 7", 1, &fields));
+        assert!(!is_match("account.data[5] == 0", 1, &fields));
+        assert!(is_match("account.data_len == 100", 1, &fields));
+
+        // empty slice, bytes inside of the original data do not match
+        let fields = MessageFields::account(1, &[], Some(100), false);
+        assert!(!is_match("account.data[0] == 0", 1, &fields));
+        assert!(is_match("account.data_len == 100", 1, &fields));
+    }
+
+    #[test]
+    fn test_limits() {
+        let source = format!("slot == {}", "1".repeat(MAX_EXPRESSION_LEN));
+        assert_eq!(compile_err(&source), ExpressionError::TooLong);
+
+        let source = vec!["slot == 1"; MAX_COMPARISONS].join(" || ");
+        assert!(FilterExpression::compile(&source).is_ok());
+        let source = vec!["slot == 1"; MAX_COMPARISONS + 1].join(" || ");
+        assert_eq!(compile_err(&source), ExpressionError::TooManyComparisons);
+
+        let source = format!("{}slot == 1", "!".repeat(MAX_DEPTH - 1));
+        assert!(FilterExpression::compile(&source).is_ok());
+        let source = format!("{}slot == 1", "!".repeat(MAX_DEPTH));
+        assert_eq!(compile_err(&source), ExpressionError::TooDeep);
+        let source = format!(
+            "{}slot == 1{}",
+            "(".repeat(MAX_DEPTH),
+            ")".repeat(MAX_DEPTH)
+        );
+        assert_eq!(compile_err(&source), ExpressionError::TooDeep);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(compile_err(" "), ExpressionError::Empty);
+        assert_eq!(
+            compile_err("account.owner == \"abc"),
+            ExpressionError::UnterminatedString { pos: 17 }
+        );
+        assert_eq!(
+            compile_err("account.size > 1"),
+            ExpressionError::UnknownField {
+                field: "account.size".to_owned()
+            }
+        );
+        assert_eq!(
+            compile_err("account.executable < true"),
+            ExpressionError::InvalidOperator {
+                field: "account.executable".to_owned(),
+                op: "<"
+            }
+        );
+        assert_eq!(
+            compile_err(&format!("account.owner < {}", Pubkey::default())),
+            ExpressionError::InvalidOperator {
+                field: "account.owner".to_owned(),
+                op: "<"
+            }
+        );
+        assert_eq!(
+            compile_err("slot == abc"),
+            ExpressionError::InvalidValue {
+                field: "slot".to_owned(),
+                value: "abc".to_owned()
+            }
+        );
+        assert_eq!(compile_err("slot == 1 &&"), ExpressionError::UnexpectedEnd);
+        assert_eq!(compile_err("(slot == 1"), ExpressionError::UnexpectedEnd);
+        assert_eq!(
+            compile_err("slot == 1)"),
+            ExpressionError::UnexpectedToken {
+                token: ")".to_owned(),
+                pos: 9
+            }
+        );
+        assert_eq!(
+            compile_err("slot == 1 # 2"),
+            ExpressionError::UnexpectedChar { ch: '#', pos: 10 }
+        );
+    }
+}
@@ -1,24 +1,41 @@
 use {
     crate::{
-        config::{deserialize_humansize_usize, deserialize_x_tokens_set},
-        transports::{RecvError, RecvStream, Subscribe, SubscribeError},
+        config::{
+            deserialize_humansize_usize, deserialize_maybe_num_str, deserialize_num_str,
+            deserialize_x_tokens_set,
+        },
+        transports::{
+            ConfigBindRetry, ConnectionsLimit, ConnectionsLimitGuard, DISCONNECT_REASON_KEY,
+            DisconnectReason, KeepaliveStream, RateLimiter, RecvError, RecvItem, RecvStream,
+            Subscribe, SubscribeError, ThrottleCallback, is_x_token_valid,
+        },
         version::Version,
     },
-    futures::stream::{Stream, StreamExt},
-    prost::{Message, bytes::BufMut},
+    futures::{
+        future::{BoxFuture, FutureExt},
+        stream::{FuturesUnordered, Stream, StreamExt},
+    },
+    prost::{
+        Message,
+        bytes::BufMut,
+        encoding::{self, WireType},
+    },
     richat_proto::{
         geyser::{GetVersionRequest, GetVersionResponse},
         richat::GrpcSubscribeRequest,
     },
+    rustls::{RootCertStore, pki_types::CertificateDer, server::WebPkiClientVerifier},
     serde::{
         Deserialize,
         de::{self, Deserializer},
     },
+    sha2::{Digest, Sha256},
     std::{
         borrow::Cow,
-        collections::HashSet,
+        collections::{HashMap, HashSet},
         fmt, fs,
         future::Future,
+        io::{self, IoSlice},
         marker::PhantomData,
         net::{IpAddr, Ipv4Addr, SocketAddr},
         pin::Pin,
@@ -27,19 +44,23 @@ use {
             atomic::{AtomicU64, Ordering},
         },
         task::{Context, Poll, ready},
-        time::Duration,
+        time::{Duration, Instant},
     },
     thiserror::Error,
-    tokio::task::JoinError,
-    tokio_util::sync::CancellationToken,
+    tokio::{
+        io::{AsyncRead, AsyncWrite, ReadBuf},
+        net::TcpStream,
+        task::JoinError,
+        time::{Sleep, sleep, timeout},
+    },
+    tokio_rustls::{TlsAcceptor, server::TlsStream},
+    tokio_util::sync::{CancellationToken, DropGuard, WaitForCancellationFutureOwned},
     tonic::{
-        Request, Response, Status, Streaming,
+        Code, Request, Response, Status, Streaming,
         codec::{Codec, CompressionEncoding, DecodeBuf, Decoder, EncodeBuf, Encoder},
+        metadata::{MetadataMap, MetadataValue},
         service::interceptor::InterceptorLayer,
-        transport::{
-            Identity, ServerTlsConfig,
-            server::{Server, TcpIncoming},
-        },
+        transport::server::{Connected, Server, TcpConnectInfo, TcpIncoming},
     },
     tracing::{error, info},
 };
@@ -51,6 +72,10 @@ pub mod geyser_gen {
     include!(concat!(env!("OUT_DIR"), "/geyser.Geyser.rs"));
 }
 
+/// Encoded descriptors of `Geyser` service with implemented methods only
+const GEYSER_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/geyser_descriptor.bin"));
+
 #[derive(Debug, Default, Clone, Deserialize)]
 #[serde(deny_unknown_fields, default)]
 pub struct ConfigGrpcCompression {
@@ -78,6 +103,20 @@ impl ConfigGrpcCompression {
             })
             .collect::<Result<_, _>>()
     }
+
+    /// Encoding of responses negotiated same as tonic, first supported value from
+    /// `grpc-accept-encoding` header which is enabled for sending
+    fn negotiate_send(&self, accept_encoding: Option<&str>) -> Option<&'static str> {
+        accept_encoding?
+            .split(',')
+            .map(|value| value.trim())
+            .find_map(|value| match value {
+                "gzip" => Some(("gzip", CompressionEncoding::Gzip)),
+                "zstd" => Some(("zstd", CompressionEncoding::Zstd)),
+                _ => None,
+            })
+            .and_then(|(name, encoding)| self.send.contains(&encoding).then_some(name))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -85,7 +124,13 @@ impl ConfigGrpcCompression {
 pub struct ConfigGrpcServer {
     pub endpoint: SocketAddr,
     #[serde(deserialize_with = "ConfigGrpcServer::deserialize_tls_config")]
-    pub tls_config: Option<ServerTlsConfig>,
+    pub tls_config: Option<Arc<rustls::ServerConfig>>,
+    /// Close connection if TLS handshake is not finished within the interval
+    #[serde(with = "humantime_serde")]
+    pub tls_handshake_timeout: Duration,
+    /// Maximum number of concurrent TLS handshakes, new connections are not accepted over the
+    /// limit and wait in the listen backlog
+    pub max_tls_handshakes: usize,
     pub compression: ConfigGrpcCompression,
     /// Limits the maximum size of a decoded message, default is 4MiB
     #[serde(deserialize_with = "deserialize_humansize_usize")]
@@ -102,6 +147,26 @@ pub struct ConfigGrpcServer {
     pub server_initial_stream_window_size: Option<u32>,
     #[serde(deserialize_with = "deserialize_x_tokens_set")]
     pub x_tokens: HashSet<Vec<u8>>,
+    /// Limit of sent bytes per second for every stream, writes are delayed over the limit
+    #[serde(deserialize_with = "deserialize_maybe_num_str")]
+    pub max_bytes_per_second: Option<u64>,
+    /// Maximum number of concurrent streams, new streams over the limit are rejected
+    pub max_connections: Option<usize>,
+    /// Batching for streams which requested it, if None every batch has one message
+    pub batch: Option<ConfigGrpcServerBatch>,
+    /// Send ping if there were no messages for the interval, disabled by default
+    #[serde(with = "humantime_serde")]
+    pub keepalive: Option<Duration>,
+    /// Close connection if a stream is not polled for the interval after returned message,
+    /// i.e. client does not read and HTTP/2 flow control window is full, all streams of the
+    /// connection are closed, disabled by default
+    #[serde(with = "humantime_serde")]
+    pub idle_timeout: Option<Duration>,
+    /// Serve gRPC reflection for `Subscribe` and `GetVersion`, e.g. for `grpcurl`, disabled by default
+    pub enable_reflection: bool,
+    /// Clients identified by TLS client certificate, requires `client_ca` in `tls_config`
+    pub clients: Vec<ConfigGrpcClient>,
+    pub bind_retry: ConfigBindRetry,
 }
 
 impl Default for ConfigGrpcServer {
@@ -109,6 +174,8 @@ impl Default for ConfigGrpcServer {
         Self {
             endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10100),
             tls_config: None,
+            tls_handshake_timeout: Duration::from_secs(10),
+            max_tls_handshakes: 1024,
             compression: ConfigGrpcCompression::default(),
             max_decoding_message_size: 4 * 1024 * 1024, // 4MiB
             server_tcp_keepalive: Some(Duration::from_secs(15)),
@@ -119,14 +186,78 @@ impl Default for ConfigGrpcServer {
             server_initial_connection_window_size: None,
             server_initial_stream_window_size: None,
             x_tokens: HashSet::new(),
+            max_bytes_per_second: None,
+            max_connections: None,
+            batch: None,
+            keepalive: None,
+            idle_timeout: None,
+            enable_reflection: false,
+            clients: Vec::new(),
+            bind_retry: ConfigBindRetry::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigGrpcServerBatch {
+    /// Maximum number of messages in the batch
+    #[serde(deserialize_with = "deserialize_num_str")]
+    pub max_messages: usize,
+    /// Maximum encoded size of the batch, batch is sent before a message would exceed it, message
+    /// over the limit is sent alone; should not exceed `max_decoding_message_size` of clients
+    #[serde(deserialize_with = "deserialize_humansize_usize")]
+    pub max_bytes: usize,
+    /// Maximum time to wait for more messages after the first message in the batch
+    #[serde(with = "humantime_serde")]
+    pub linger: Duration,
+}
+
+impl Default for ConfigGrpcServerBatch {
+    fn default() -> Self {
+        Self {
+            max_messages: 64,
+            max_bytes: 1024 * 1024, // 1MiB
+            linger: Duration::from_millis(1),
         }
     }
 }
 
+/// SHA-256 of DER-encoded certificate
+pub type CertFingerprint = [u8; 32];
+
+pub fn cert_fingerprint(cert: &CertificateDer<'_>) -> CertFingerprint {
+    Sha256::digest(cert).into()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigGrpcClient {
+    /// Client identity in logs and metrics
+    pub name: String,
+    /// Fingerprint of client certificate as hex, `:` separators are allowed
+    #[serde(deserialize_with = "ConfigGrpcClient::deserialize_fingerprint")]
+    pub fingerprint: CertFingerprint,
+    /// Overrides `max_bytes_per_second` of the server for the client
+    #[serde(default, deserialize_with = "deserialize_maybe_num_str")]
+    pub max_bytes_per_second: Option<u64>,
+}
+
+impl ConfigGrpcClient {
+    fn deserialize_fingerprint<'de, D>(deserializer: D) -> Result<CertFingerprint, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = <&str>::deserialize(deserializer)?;
+        const_hex::decode_to_array(value.replace(':', ""))
+            .map_err(|error| de::Error::custom(format!("invalid fingerprint {value}: {error}")))
+    }
+}
+
 impl ConfigGrpcServer {
     pub fn deserialize_tls_config<'de, D>(
         deserializer: D,
-    ) -> Result<Option<ServerTlsConfig>, D::Error>
+    ) -> Result<Option<Arc<rustls::ServerConfig>>, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -135,23 +266,83 @@ impl ConfigGrpcServer {
         struct ConfigTls<'a> {
             cert: &'a str,
             key: &'a str,
+            /// CA used to verify client certificates, clients without valid cert are rejected
+            client_ca: Option<&'a str>,
+        }
+
+        fn read_certs<E: de::Error>(path: &str) -> Result<Vec<CertificateDer<'static>>, E> {
+            let bytes = fs::read(path)
+                .map_err(|error| de::Error::custom(format!("failed to read {path}: {error:?}")))?;
+            rustls_pemfile::certs(&mut &*bytes)
+                .collect::<Result<_, _>>()
+                .map_err(|error| {
+                    de::Error::custom(format!("invalid PEM-encoded certificate {path}: {error:?}"))
+                })
         }
 
         Option::<ConfigTls>::deserialize(deserializer)?
             .map(|config| {
-                let cert = fs::read(config.cert).map_err(|error| {
-                    de::Error::custom(format!("failed to read cert {}: {error:?}", config.cert))
-                })?;
+                let cert = read_certs(config.cert)?;
                 let key = fs::read(config.key).map_err(|error| {
                     de::Error::custom(format!("failed to read key {}: {error:?}", config.key))
                 })?;
+                let key = rustls_pemfile::private_key(&mut &*key)
+                    .map_err(|error| {
+                        de::Error::custom(format!("malformed private key: {error:?}"))
+                    })?
+                    .ok_or_else(|| de::Error::custom("no private keys found"))?;
 
-                Ok(ServerTlsConfig::new().identity(Identity::from_pem(cert, key)))
+                let builder = rustls::ServerConfig::builder();
+                let builder = match config.client_ca {
+                    Some(client_ca) => {
+                        let mut roots = RootCertStore::empty();
+                        for cert in read_certs(client_ca)? {
+                            roots.add(cert).map_err(|error| {
+                                de::Error::custom(format!("failed to add client CA: {error:?}"))
+                            })?;
+                        }
+                        let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                            .build()
+                            .map_err(|error| {
+                                de::Error::custom(format!(
+                                    "failed to create client verifier: {error:?}"
+                                ))
+                            })?;
+                        builder.with_client_cert_verifier(verifier)
+                    }
+                    None => builder.with_no_client_auth(),
+                };
+
+                let mut tls_config = builder
+                    .with_single_cert(cert, key)
+                    .map_err(|error| de::Error::custom(format!("failed to use cert: {error:?}")))?;
+                tls_config.alpn_protocols.push(b"h2".to_vec());
+                Ok(Arc::new(tls_config))
             })
             .transpose()
     }
 
-    pub fn create_server_builder(&self) -> Result<(TcpIncoming, Server), CreateServerError> {
+    /// Clients decode messages up to 4MiB by default, same as server `max_decoding_message_size`,
+    /// so batches should not be larger than the limit of the server
+    pub fn validate_batch(&self) -> Result<(), CreateServerError> {
+        let Some(batch) = &self.batch else {
+            return Ok(());
+        };
+        if batch.max_messages == 0 {
+            return Err(CreateServerError::Batch(
+                "max_messages should be greater than zero".to_owned(),
+            ));
+        }
+        if batch.max_bytes == 0 || batch.max_bytes > self.max_decoding_message_size {
+            return Err(CreateServerError::Batch(format!(
+                "max_bytes should be greater than zero and not exceed max_decoding_message_size ({})",
+                self.max_decoding_message_size
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn create_server_builder(&self) -> Result<(GrpcIncoming, Server), CreateServerError> {
         // Bind service address
         let incoming = TcpIncoming::bind(self.endpoint)
             .map_err(|error| CreateServerError::Bind {
@@ -160,12 +351,18 @@ impl ConfigGrpcServer {
             })?
             .with_nodelay(Some(self.server_tcp_nodelay))
             .with_keepalive(self.server_tcp_keepalive);
+        let incoming = GrpcIncoming::new(
+            incoming,
+            self.tls_config.clone().map(|tls_config| GrpcTlsAcceptor {
+                acceptor: TlsAcceptor::from(tls_config),
+                handshake_timeout: self.tls_handshake_timeout,
+                max_handshakes: self.max_tls_handshakes,
+            }),
+            self.idle_timeout.is_some(),
+        );
 
         // Create service
         let mut server_builder = Server::builder();
-        if let Some(tls_config) = self.tls_config.clone() {
-            server_builder = server_builder.tls_config(tls_config)?;
-        }
         if let Some(enabled) = self.server_http2_adaptive_window {
             server_builder = server_builder.http2_adaptive_window(Some(enabled));
         }
@@ -194,8 +391,269 @@ pub enum CreateServerError {
         error: std::io::Error,
         endpoint: SocketAddr,
     },
-    #[error("failed to apply tls_config: {0}")]
-    Tls(#[from] tonic::transport::Error),
+    #[error("failed to build reflection service: {0}")]
+    Reflection(#[from] tonic_reflection::server::Error),
+    #[error("invalid batch config: {0}")]
+    Batch(String),
+}
+
+/// Accepted TCP connections, TLS handshakes are done concurrently when TLS is enabled
+pub struct GrpcIncoming {
+    incoming: Option<TcpIncoming>,
+    acceptor: Option<GrpcTlsAcceptor>,
+    handshakes: FuturesUnordered<BoxFuture<'static, io::Result<TlsStream<TcpStream>>>>,
+    closable: bool,
+    on_tls_rejected_cb: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl fmt::Debug for GrpcIncoming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcIncoming")
+            .field("tls", &self.acceptor.is_some())
+            .field("handshakes", &self.handshakes.len())
+            .finish()
+    }
+}
+
+struct GrpcTlsAcceptor {
+    acceptor: TlsAcceptor,
+    handshake_timeout: Duration,
+    max_handshakes: usize,
+}
+
+impl GrpcTlsAcceptor {
+    fn accept(&self, stream: TcpStream) -> BoxFuture<'static, io::Result<TlsStream<TcpStream>>> {
+        let handshake_timeout = self.handshake_timeout;
+        timeout(handshake_timeout, self.acceptor.accept(stream))
+            .map(move |result| {
+                result.unwrap_or_else(|_elapsed| {
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("not finished within {handshake_timeout:?}"),
+                    ))
+                })
+            })
+            .boxed()
+    }
+}
+
+impl GrpcIncoming {
+    fn new(incoming: TcpIncoming, acceptor: Option<GrpcTlsAcceptor>, closable: bool) -> Self {
+        Self {
+            incoming: Some(incoming),
+            acceptor,
+            handshakes: FuturesUnordered::new(),
+            closable,
+            on_tls_rejected_cb: None,
+        }
+    }
+
+    pub fn with_tls_rejected_cb(mut self, cb: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_tls_rejected_cb = Some(Arc::new(cb));
+        self
+    }
+}
+
+impl Stream for GrpcIncoming {
+    type Item = io::Result<GrpcIncomingStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = self.as_mut().get_mut();
+
+        loop {
+            while let Some(incoming) = me.incoming.as_mut() {
+                // over the limit new connections are accepted once in-flight handshakes are
+                // finished, waker is registered by `handshakes` below
+                if let Some(acceptor) = &me.acceptor {
+                    if !me.handshakes.is_empty() && me.handshakes.len() >= acceptor.max_handshakes {
+                        break;
+                    }
+                }
+
+                match incoming.poll_next_unpin(cx) {
+                    Poll::Ready(Some(Ok(stream))) => match &me.acceptor {
+                        Some(acceptor) => me.handshakes.push(acceptor.accept(stream)),
+                        None => {
+                            let io = GrpcIncomingIo::Tcp(stream);
+                            return Poll::Ready(Some(Ok(GrpcIncomingStream::new(io, me.closable))));
+                        }
+                    },
+                    Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+                    Poll::Ready(None) => me.incoming = None,
+                    Poll::Pending => break,
+                }
+            }
+
+            match ready!(me.handshakes.poll_next_unpin(cx)) {
+                Some(Ok(stream)) => {
+                    let io = GrpcIncomingIo::Tls(Box::new(stream));
+                    return Poll::Ready(Some(Ok(GrpcIncomingStream::new(io, me.closable))));
+                }
+                // slot for a new handshake is released, accept pending connections
+                Some(Err(error)) => {
+                    info!("rejected, TLS handshake failed: {error}");
+                    if let Some(cb) = &me.on_tls_rejected_cb {
+                        cb();
+                    }
+                }
+                None if me.incoming.is_none() => return Poll::Ready(None),
+                None => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Accepted connection, if closable then reads and writes fail once `close` is cancelled
+pub struct GrpcIncomingStream {
+    io: GrpcIncomingIo,
+    close: Option<(CancellationToken, Pin<Box<WaitForCancellationFutureOwned>>)>,
+}
+
+#[derive(Debug)]
+enum GrpcIncomingIo {
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl fmt::Debug for GrpcIncomingStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcIncomingStream")
+            .field("io", &self.io)
+            .field("closable", &self.close.is_some())
+            .finish()
+    }
+}
+
+impl GrpcIncomingStream {
+    fn new(io: GrpcIncomingIo, closable: bool) -> Self {
+        let close = closable.then(|| {
+            let token = CancellationToken::new();
+            let closed = Box::pin(token.clone().cancelled_owned());
+            (token, closed)
+        });
+        Self { io, close }
+    }
+
+    /// Registers waker, so blocked reads and writes are woken up once connection is closed
+    fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<io::Error> {
+        match self.close.as_mut() {
+            Some((_token, closed)) => closed
+                .as_mut()
+                .poll(cx)
+                .map(|()| io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed")),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Connection details, available in request extensions
+#[derive(Debug, Clone)]
+pub struct GrpcConnectInfo {
+    pub tcp: TcpConnectInfo,
+    /// Fingerprint of client certificate verified on TLS handshake
+    pub client_fingerprint: Option<CertFingerprint>,
+    /// Cancel to close the connection, set if idle timeout is enabled
+    pub close: Option<CancellationToken>,
+}
+
+impl Connected for GrpcIncomingStream {
+    type ConnectInfo = GrpcConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        let close = self.close.as_ref().map(|(token, _closed)| token.clone());
+        match &self.io {
+            GrpcIncomingIo::Tcp(stream) => GrpcConnectInfo {
+                tcp: stream.connect_info(),
+                client_fingerprint: None,
+                close,
+            },
+            GrpcIncomingIo::Tls(stream) => {
+                let (stream, session) = stream.get_ref();
+                GrpcConnectInfo {
+                    tcp: stream.connect_info(),
+                    client_fingerprint: session
+                        .peer_certificates()
+                        .and_then(|certs| certs.first())
+                        .map(cert_fingerprint),
+                    close,
+                }
+            }
+        }
+    }
+}
+
+impl AsyncRead for GrpcIncomingStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let me = self.get_mut();
+        if let Poll::Ready(error) = me.poll_closed(cx) {
+            return Poll::Ready(Err(error));
+        }
+        match &mut me.io {
+            GrpcIncomingIo::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            GrpcIncomingIo::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for GrpcIncomingStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let me = self.get_mut();
+        if let Poll::Ready(error) = me.poll_closed(cx) {
+            return Poll::Ready(Err(error));
+        }
+        match &mut me.io {
+            GrpcIncomingIo::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            GrpcIncomingIo::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let me = self.get_mut();
+        if let Poll::Ready(error) = me.poll_closed(cx) {
+            return Poll::Ready(Err(error));
+        }
+        match &mut me.io {
+            GrpcIncomingIo::Tcp(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            GrpcIncomingIo::Tls(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match &self.io {
+            GrpcIncomingIo::Tcp(stream) => stream.is_write_vectored(),
+            GrpcIncomingIo::Tls(stream) => stream.is_write_vectored(),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let me = self.get_mut();
+        if let Poll::Ready(error) = me.poll_closed(cx) {
+            return Poll::Ready(Err(error));
+        }
+        match &mut me.io {
+            GrpcIncomingIo::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            GrpcIncomingIo::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            GrpcIncomingIo::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            GrpcIncomingIo::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
 }
 
 pub struct GrpcServer<S, F1, F2> {
@@ -203,7 +661,21 @@ pub struct GrpcServer<S, F1, F2> {
     subscribe_id: AtomicU64,
     on_conn_new_cb: F1,
     on_conn_drop_cb: F2,
+    on_conn_rejected_cb: Arc<dyn Fn(&'static str) + Send + Sync>,
+    on_conn_idle_cb: Arc<dyn Fn() + Send + Sync>,
+    on_conn_throttle_cb: ThrottleCallback,
+    on_batch_cb: Arc<dyn Fn(usize) + Send + Sync>,
+    on_conn_compressed_cb: Box<dyn Fn(&'static str) + Send + Sync>,
+    on_conn_client_cb: ClientCallback,
+    compression: ConfigGrpcCompression,
+    max_bytes_per_second: Option<u64>,
+    clients: HashMap<CertFingerprint, ConfigGrpcClient>,
+    batch: ConfigGrpcServerBatch,
+    keepalive: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    connections: ConnectionsLimit,
     version: Version<'static>,
+    version_server_info: Arc<dyn Fn() -> Option<serde_json::Value> + Send + Sync>,
 }
 
 impl<S, F1, F2> fmt::Debug for GrpcServer<S, F1, F2> {
@@ -219,25 +691,75 @@ impl<S, F1, F2> GrpcServer<S, F1, F2>
 where
     S: Subscribe + Send + Sync + 'static,
     F1: Fn() + Clone + Unpin + Send + Sync + 'static,
-    F2: Fn() + Clone + Unpin + Send + Sync + 'static,
+    F2: Fn(Duration) + Clone + Unpin + Send + Sync + 'static,
 {
+    #[allow(clippy::too_many_arguments)]
     pub async fn spawn(
         config: ConfigGrpcServer,
         messages: S,
         on_conn_new_cb: F1,
         on_conn_drop_cb: F2,
+        on_conn_rejected_cb: impl Fn(&'static str) + Send + Sync + 'static,
+        on_conn_idle_cb: impl Fn() + Send + Sync + 'static,
+        on_conn_throttle_cb: impl Fn(bool) + Send + Sync + 'static,
+        on_batch_cb: impl Fn(usize) + Send + Sync + 'static,
+        on_conn_compressed_cb: impl Fn(&'static str) + Send + Sync + 'static,
+        on_conn_client_cb: impl Fn(&str, bool) + Send + Sync + 'static,
         version: Version<'static>,
+        version_server_info: impl Fn() -> Option<serde_json::Value> + Send + Sync + 'static,
         shutdown: CancellationToken,
     ) -> Result<impl Future<Output = Result<(), JoinError>>, CreateServerError> {
-        let (incoming, server_builder) = config.create_server_builder()?;
+        config.validate_batch()?;
+        let on_conn_rejected_cb: Arc<dyn Fn(&'static str) + Send + Sync> =
+            Arc::new(on_conn_rejected_cb);
+        let (incoming, server_builder) = config
+            .bind_retry
+            .bind(|| config.create_server_builder())
+            .await?;
+        let incoming = incoming.with_tls_rejected_cb({
+            let on_conn_rejected_cb = Arc::clone(&on_conn_rejected_cb);
+            move || on_conn_rejected_cb("tls")
+        });
         info!("start server at {}", config.endpoint);
 
+        let reflection = if config.enable_reflection {
+            Some(
+                tonic_reflection::server::Builder::configure()
+                    .register_encoded_file_descriptor_set(GEYSER_DESCRIPTOR_SET)
+                    .build_v1()?,
+            )
+        } else {
+            None
+        };
+
         let mut service = geyser_gen::geyser_server::GeyserServer::new(Self {
             messages,
             subscribe_id: AtomicU64::new(0),
             on_conn_new_cb,
             on_conn_drop_cb,
+            on_conn_rejected_cb: Arc::clone(&on_conn_rejected_cb),
+            on_conn_idle_cb: Arc::new(on_conn_idle_cb),
+            on_conn_throttle_cb: Arc::new(on_conn_throttle_cb),
+            on_batch_cb: Arc::new(on_batch_cb),
+            on_conn_compressed_cb: Box::new(on_conn_compressed_cb),
+            on_conn_client_cb: Arc::new(on_conn_client_cb),
+            compression: config.compression.clone(),
+            max_bytes_per_second: config.max_bytes_per_second,
+            clients: config
+                .clients
+                .iter()
+                .map(|client| (client.fingerprint, client.clone()))
+                .collect(),
+            batch: config.batch.unwrap_or(ConfigGrpcServerBatch {
+                max_messages: 1,
+                max_bytes: usize::MAX,
+                linger: Duration::ZERO,
+            }),
+            keepalive: config.keepalive,
+            idle_timeout: config.idle_timeout,
+            connections: ConnectionsLimit::new(config.max_connections),
             version,
+            version_server_info: Arc::new(version_server_info),
         })
         .max_decoding_message_size(config.max_decoding_message_size);
         for encoding in config.compression.accept {
@@ -255,14 +777,19 @@ where
                         Ok(request)
                     } else {
                         match request.metadata().get("x-token") {
-                            Some(token) if config.x_tokens.contains(token.as_bytes()) => {
+                            Some(token) if is_x_token_valid(&config.x_tokens, token.as_bytes()) => {
                                 Ok(request)
                             }
-                            _ => Err(Status::unauthenticated("No valid auth token")),
+                            _ => {
+                                on_conn_rejected_cb("x_token");
+                                Err(DisconnectReason::Unauthenticated
+                                    .into_status("No valid auth token"))
+                            }
                         }
                     }
                 }))
                 .add_service(service)
+                .add_optional_service(reflection)
                 .serve_with_incoming_shutdown(incoming, shutdown.cancelled())
                 .await
             {
@@ -278,7 +805,7 @@ where
 impl<S, F1, F2> geyser_gen::geyser_server::Geyser for GrpcServer<S, F1, F2>
 where
     S: Subscribe + Send + Sync + 'static,
-    F2: Fn() + Clone + Unpin + Send + Sync + 'static,
+    F2: Fn(Duration) + Clone + Unpin + Send + Sync + 'static,
     F1: Fn() + Clone + Unpin + Send + Sync + 'static,
 {
     type SubscribeStream = ReceiverStream<F2>;
@@ -288,20 +815,57 @@ where
         mut request: Request<Streaming<GrpcSubscribeRequest>>,
     ) -> Result<Response<Self::SubscribeStream>, Status> {
         let id = self.subscribe_id.fetch_add(1, Ordering::Relaxed);
-        info!("#{id}: new connection from {:?}", request.remote_addr());
+        let connect_info = request.extensions().get::<GrpcConnectInfo>();
+        info!(
+            "#{id}: new connection from {:?}",
+            connect_info.and_then(|info| info.tcp.remote_addr())
+        );
+        let close = connect_info.and_then(|info| info.close.clone());
 
-        let (replay_from_slot, filter) = match request.get_mut().message().await {
+        // Unknown certificates share one identity, so metric labels are bounded by config
+        let client = connect_info
+            .and_then(|info| info.client_fingerprint)
+            .map(|fingerprint| match self.clients.get(&fingerprint) {
+                Some(client) => {
+                    info!("#{id}: client {}", client.name);
+                    (client.name.clone(), client.max_bytes_per_second)
+                }
+                None => {
+                    info!(
+                        "#{id}: unknown client certificate {}",
+                        const_hex::encode(fingerprint)
+                    );
+                    ("unknown".to_owned(), None)
+                }
+            });
+
+        let Some(connection) = self.connections.try_acquire() else {
+            info!("#{id}: rejected, max connections reached");
+            (self.on_conn_rejected_cb)("max_connections");
+            return Err(DisconnectReason::ConnectionsLimit.into_status("max connections reached"));
+        };
+
+        let compression = self.compression.negotiate_send(
+            request
+                .metadata()
+                .get("grpc-accept-encoding")
+                .and_then(|value| value.to_str().ok()),
+        );
+
+        let (replay_from_slot, filter, batch) = match request.get_mut().message().await {
             Ok(Some(GrpcSubscribeRequest {
                 replay_from_slot,
                 filter,
-            })) => (replay_from_slot, filter),
+                batch,
+            })) => (replay_from_slot, filter, batch),
             Ok(None) => {
                 info!("#{id}: connection closed before receiving request");
-                return Err(Status::aborted("stream closed before request received"));
+                return Err(DisconnectReason::BadRequest
+                    .into_status("stream closed before request received"));
             }
             Err(error) => {
                 error!("#{id}: error receiving request {error}");
-                return Err(Status::aborted("recv error"));
+                return Err(DisconnectReason::BadRequest.into_status("recv error"));
             }
         };
 
@@ -311,17 +875,44 @@ where
                     .map(|slot| format!("slot {slot}").into())
                     .unwrap_or(Cow::Borrowed("latest"));
                 info!("#{id}: subscribed from {pos}");
+                if let Some(encoding) = compression {
+                    info!("#{id}: responses compressed with {encoding}");
+                    (self.on_conn_compressed_cb)(encoding);
+                }
+                let limiter = client
+                    .as_ref()
+                    .and_then(|(_name, max_bytes_per_second)| *max_bytes_per_second)
+                    .or(self.max_bytes_per_second)
+                    .map(|bytes_per_second| {
+                        RateLimiter::new(bytes_per_second, Arc::clone(&self.on_conn_throttle_cb))
+                    });
+                let batch = batch.then(|| Batch::new(self.batch, Arc::clone(&self.on_batch_cb)));
                 Ok(Response::new(ReceiverStream::new(
-                    rx.boxed(),
+                    KeepaliveStream::wrap(rx, self.keepalive),
                     id,
+                    connection,
+                    limiter,
+                    batch,
+                    client.map(|(name, _max_bytes_per_second)| {
+                        (name, Arc::clone(&self.on_conn_client_cb))
+                    }),
+                    self.idle_timeout.zip(close).map(|(timeout, close)| {
+                        IdleWatch::spawn(id, timeout, close, Arc::clone(&self.on_conn_idle_cb))
+                    }),
                     self.on_conn_new_cb.clone(),  // on new conn
                     self.on_conn_drop_cb.clone(), // on drop conn
                 )))
             }
-            Err(SubscribeError::NotInitialized) => Err(Status::internal("not initialized")),
-            Err(SubscribeError::SlotNotAvailable { first_available }) => Err(
-                Status::invalid_argument(format!("first available slot: {first_available}")),
-            ),
+            Err(error) => {
+                let message = match &error {
+                    SubscribeError::NotInitialized => Cow::Borrowed("not initialized"),
+                    SubscribeError::SlotNotAvailable { first_available } => {
+                        Cow::Owned(format!("first available slot: {first_available}"))
+                    }
+                    SubscribeError::InvalidExpression(error) => Cow::Owned(error.to_string()),
+                };
+                Err(DisconnectReason::from(&error).into_status(message))
+            }
         }
     }
 
@@ -330,59 +921,292 @@ where
         _request: Request<GetVersionRequest>,
     ) -> Result<Response<GetVersionResponse>, Status> {
         Ok(Response::new(GetVersionResponse {
-            version: self.version.create_grpc_version_info().json(),
+            version: self
+                .version
+                .create_grpc_version_info()
+                .with_server((self.version_server_info)())
+                .json(),
         }))
     }
 }
 
-pub struct ReceiverStream<F2: Fn()> {
+/// Called with client identity and `true` on subscribe, `false` once the stream is closed
+type ClientCallback = Arc<dyn Fn(&str, bool) + Send + Sync>;
+
+pub struct ReceiverStream<F2: Fn(Duration)> {
     rx: RecvStream,
     id: u64,
+    _connection: ConnectionsLimitGuard,
+    limiter: Option<RateLimiter>,
+    delay: Option<Pin<Box<Sleep>>>,
+    batch: Option<Batch>,
+    client: Option<(String, ClientCallback)>,
+    idle: Option<IdleWatch>,
+    connected_at: Instant,
     on_conn_drop_cb: F2,
 }
 
-impl<F2: Fn()> fmt::Debug for ReceiverStream<F2> {
+impl<F2: Fn(Duration)> fmt::Debug for ReceiverStream<F2> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ReceiverStream").finish()
     }
 }
 
-impl<F2: Fn()> ReceiverStream<F2> {
-    fn new<F1: Fn()>(rx: RecvStream, id: u64, on_conn_new_cb: F1, on_conn_drop_cb: F2) -> Self {
+impl<F2: Fn(Duration)> ReceiverStream<F2> {
+    #[allow(clippy::too_many_arguments)]
+    fn new<F1: Fn()>(
+        rx: RecvStream,
+        id: u64,
+        connection: ConnectionsLimitGuard,
+        limiter: Option<RateLimiter>,
+        batch: Option<Batch>,
+        client: Option<(String, ClientCallback)>,
+        idle: Option<IdleWatch>,
+        on_conn_new_cb: F1,
+        on_conn_drop_cb: F2,
+    ) -> Self {
         on_conn_new_cb();
+        if let Some((name, on_conn_client_cb)) = &client {
+            on_conn_client_cb(name, true);
+        }
         Self {
             rx,
             id,
+            _connection: connection,
+            limiter,
+            delay: None,
+            batch,
+            client,
+            idle,
+            connected_at: Instant::now(),
             on_conn_drop_cb,
         }
     }
 }
 
-impl<F2: Fn()> Drop for ReceiverStream<F2> {
+impl<F2: Fn(Duration)> Drop for ReceiverStream<F2> {
     fn drop(&mut self) {
         info!("#{}: send stream closed", self.id);
-        (self.on_conn_drop_cb)();
+        if let Some((name, on_conn_client_cb)) = &self.client {
+            on_conn_client_cb(name, false);
+        }
+        (self.on_conn_drop_cb)(self.connected_at.elapsed());
     }
 }
 
-impl<F2: Fn() + Unpin> Stream for ReceiverStream<F2> {
+impl<F2: Fn(Duration) + Unpin> Stream for ReceiverStream<F2> {
     type Item = Result<Arc<Vec<u8>>, Status>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match ready!(self.rx.poll_next_unpin(cx)) {
-            Some(Ok(value)) => Poll::Ready(Some(Ok(value))),
+        if let Some(idle) = &self.idle {
+            idle.polled();
+        }
+
+        if let Some(delay) = self.delay.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+            self.delay = None;
+        }
+
+        let me = &mut *self;
+        let item = match me.batch.as_mut() {
+            Some(batch) => ready!(batch.poll_next(&mut me.rx, cx)),
+            None => ready!(me.rx.poll_next_unpin(cx)),
+        };
+        match item {
+            Some(Ok(value)) => {
+                if let Some(delay) = self
+                    .limiter
+                    .as_mut()
+                    .and_then(|limiter| limiter.consume(value.len()))
+                {
+                    self.delay = Some(Box::pin(sleep(delay)));
+                }
+                if let Some(idle) = &self.idle {
+                    idle.handed_off();
+                }
+                Poll::Ready(Some(Ok(value)))
+            }
             Some(Err(error)) => {
                 error!("#{}: failed to get message: {error}", self.id);
-                match error {
-                    RecvError::Lagged => Poll::Ready(Some(Err(Status::out_of_range("lagged")))),
-                    RecvError::Closed => Poll::Ready(Some(Err(Status::out_of_range("closed")))),
-                }
+                let status = DisconnectReason::from(error).into_status(match error {
+                    RecvError::Lagged => "lagged",
+                    RecvError::Closed => "closed",
+                });
+                Poll::Ready(Some(Err(status)))
             }
             None => Poll::Ready(None),
         }
     }
 }
 
+/// Stream is not polled while HTTP/2 flow control window is full, so watchdog task closes
+/// connection if stream was not polled again within the timeout after returned message
+struct IdleWatch {
+    state: Arc<IdleState>,
+    _stop: DropGuard,
+}
+
+struct IdleState {
+    started: Instant,
+    /// Nanoseconds since `started` when message was returned, `u64::MAX` once polled again
+    handed_off_at: AtomicU64,
+}
+
+impl IdleWatch {
+    fn spawn(
+        id: u64,
+        timeout: Duration,
+        close: CancellationToken,
+        on_conn_idle_cb: Arc<dyn Fn() + Send + Sync>,
+    ) -> Self {
+        let state = Arc::new(IdleState {
+            started: Instant::now(),
+            handed_off_at: AtomicU64::new(u64::MAX),
+        });
+        let stop = CancellationToken::new();
+        tokio::spawn({
+            let state = Arc::clone(&state);
+            let stop = stop.clone();
+            async move {
+                let sleep = sleep(timeout);
+                tokio::pin!(sleep);
+                loop {
+                    tokio::select! {
+                        () = &mut sleep => {}
+                        () = stop.cancelled() => return,
+                        () = close.cancelled() => return,
+                    }
+                    let deadline = match state.handed_off_at.load(Ordering::Relaxed) {
+                        u64::MAX => Instant::now() + timeout,
+                        nanos => state.started + Duration::from_nanos(nanos) + timeout,
+                    };
+                    if deadline <= Instant::now() {
+                        info!(
+                            "#{id}: connection closed, idle timeout: no read progress for {timeout:?}"
+                        );
+                        on_conn_idle_cb();
+                        close.cancel();
+                        return;
+                    }
+                    sleep.as_mut().reset(deadline.into());
+                }
+            }
+        });
+        Self {
+            state,
+            _stop: stop.drop_guard(),
+        }
+    }
+
+    fn polled(&self) {
+        self.state.handed_off_at.store(u64::MAX, Ordering::Relaxed);
+    }
+
+    fn handed_off(&self) {
+        let nanos = self.state.started.elapsed().as_nanos() as u64;
+        self.state.handed_off_at.store(nanos, Ordering::Relaxed);
+    }
+}
+
+/// Collects messages to `GrpcSubscribeBatch`, batch is sent once it is full, next message would
+/// exceed its size limit or linger elapsed
+struct Batch {
+    config: ConfigGrpcServerBatch,
+    messages: Vec<RecvItem>,
+    /// Encoded size of the batch
+    bytes: usize,
+    /// Message received when the batch was full, it starts the next batch
+    pending: Option<RecvItem>,
+    linger: Option<Pin<Box<Sleep>>>,
+    error: Option<RecvError>,
+    on_batch_cb: Arc<dyn Fn(usize) + Send + Sync>,
+}
+
+impl Batch {
+    fn new(config: ConfigGrpcServerBatch, on_batch_cb: Arc<dyn Fn(usize) + Send + Sync>) -> Self {
+        Self {
+            config,
+            messages: Vec::with_capacity(config.max_messages),
+            bytes: 0,
+            pending: None,
+            linger: None,
+            error: None,
+            on_batch_cb,
+        }
+    }
+
+    fn poll_next(
+        &mut self,
+        rx: &mut RecvStream,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<RecvItem, RecvError>>> {
+        if let Some(error) = self.error.take() {
+            return Poll::Ready(Some(Err(error)));
+        }
+
+        loop {
+            if self.messages.len() >= self.config.max_messages
+                || self.bytes >= self.config.max_bytes
+            {
+                return Poll::Ready(Some(Ok(self.flush())));
+            }
+
+            let message = match self.pending.take() {
+                Some(message) => message,
+                None => match rx.poll_next_unpin(cx) {
+                    Poll::Ready(Some(Ok(message))) => message,
+                    Poll::Ready(Some(Err(error))) if !self.messages.is_empty() => {
+                        self.error = Some(error);
+                        return Poll::Ready(Some(Ok(self.flush())));
+                    }
+                    Poll::Ready(None) if !self.messages.is_empty() => {
+                        return Poll::Ready(Some(Ok(self.flush())));
+                    }
+                    Poll::Ready(item) => return Poll::Ready(item),
+                    Poll::Pending => {
+                        if self.messages.is_empty() {
+                            return Poll::Pending;
+                        }
+                        if let Some(linger) = self.linger.as_mut() {
+                            ready!(linger.as_mut().poll(cx));
+                        }
+                        return Poll::Ready(Some(Ok(self.flush())));
+                    }
+                },
+            };
+
+            let size = Self::encoded_len(&message);
+            if !self.messages.is_empty() && self.bytes + size > self.config.max_bytes {
+                self.pending = Some(message);
+                return Poll::Ready(Some(Ok(self.flush())));
+            }
+            if self.messages.is_empty() && !self.config.linger.is_zero() {
+                self.linger = Some(Box::pin(sleep(self.config.linger)));
+            }
+            self.bytes += size;
+            self.messages.push(message);
+        }
+    }
+
+    const fn encoded_len(message: &[u8]) -> usize {
+        encoding::key_len(1) + encoding::encoded_len_varint(message.len() as u64) + message.len()
+    }
+
+    fn flush(&mut self) -> RecvItem {
+        self.linger = None;
+        (self.on_batch_cb)(self.messages.len());
+
+        let mut buf = Vec::with_capacity(self.bytes);
+        self.bytes = 0;
+        for message in self.messages.drain(..) {
+            encoding::encode_key(1, WireType::LengthDelimited, &mut buf);
+            encoding::encode_varint(message.len() as u64, &mut buf);
+            buf.extend_from_slice(&message);
+        }
+        Arc::new(buf)
+    }
+}
+
 trait SubscribeMessage {
     fn encode(self, buf: &mut EncodeBuf<'_>);
 }
@@ -471,8 +1295,612 @@ impl<U: Message + Default> Decoder for ProstDecoder<U> {
     }
 }
 
+impl DisconnectReason {
+    const fn code(self) -> Code {
+        match self {
+            Self::GapDetected => Code::OutOfRange,
+            Self::ServerShutdown | Self::NotInitialized => Code::Unavailable,
+            Self::ConnectionsLimit => Code::ResourceExhausted,
+            Self::Unauthenticated => Code::Unauthenticated,
+            Self::SlotNotAvailable => Code::InvalidArgument,
+            Self::BadRequest => Code::Aborted,
+        }
+    }
+
+    pub fn into_status(self, message: impl Into<String>) -> Status {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            DISCONNECT_REASON_KEY,
+            MetadataValue::from_static(self.as_str()),
+        );
+        Status::with_metadata(self.code(), message, metadata)
+    }
+
+    pub fn from_status(status: &Status) -> Option<Self> {
+        status
+            .metadata()
+            .get(DISCONNECT_REASON_KEY)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::parse)
+    }
+}
+
 fn from_decode_error(error: prost::DecodeError) -> Status {
     // Map Protobuf parse errors to an INTERNAL status code, as per
     // https://github.com/grpc/grpc/blob/master/doc/statuscodes.md
     Status::new(tonic::Code::Internal, error.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            Batch, ConfigGrpcClient, ConfigGrpcServer, ConfigGrpcServerBatch, GrpcServer,
+            cert_fingerprint,
+        },
+        crate::{
+            transports::{RecvItem, RecvStream, Subscribe, SubscribeError},
+            version::Version,
+        },
+        futures::{
+            channel::mpsc,
+            future::poll_fn,
+            stream::{self, StreamExt},
+        },
+        prost::Message,
+        prost_types::FileDescriptorProto,
+        rcgen::{BasicConstraints, Certificate, CertificateParams, CertifiedIssuer, IsCa, KeyPair},
+        richat_proto::{
+            geyser::GetVersionResponse,
+            richat::{GrpcSubscribeBatch, GrpcSubscribeRequest, RichatFilter},
+        },
+        rustls::{
+            RootCertStore,
+            pki_types::{CertificateDer, PrivatePkcs8KeyDer},
+            server::WebPkiClientVerifier,
+        },
+        solana_sdk::clock::Slot,
+        std::{
+            net::{SocketAddr, TcpListener},
+            sync::{
+                Arc, Mutex,
+                atomic::{AtomicUsize, Ordering},
+            },
+            time::Duration,
+        },
+        tokio::{
+            net::TcpStream,
+            time::{Instant, sleep},
+        },
+        tokio_util::sync::CancellationToken,
+        tonic::{
+            Request, Streaming,
+            client::Grpc,
+            codegen::http::uri::PathAndQuery,
+            transport::{
+                Certificate as TlsCertificate, Channel, ClientTlsConfig, Endpoint, Identity,
+            },
+        },
+        tonic_prost::ProstCodec,
+        tonic_reflection::pb::v1::{
+            ServerReflectionRequest, server_reflection_client::ServerReflectionClient,
+            server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+        },
+    };
+
+    const VERSION: Version<'static> = Version {
+        package: "richat-shared",
+        version: "test",
+        proto: "test",
+        proto_richat: "test",
+        solana: "test",
+        agave: None,
+        git: "test",
+        rustc: "test",
+        buildts: "test",
+    };
+
+    /// Endless stream of 16KiB messages
+    struct Flood;
+
+    fn flood_message() -> Vec<u8> {
+        GetVersionResponse {
+            version: "0".repeat(16 * 1024),
+        }
+        .encode_to_vec()
+    }
+
+    impl Subscribe for Flood {
+        fn subscribe(
+            &self,
+            _replay_from_slot: Option<Slot>,
+            _filter: Option<RichatFilter>,
+        ) -> Result<RecvStream, SubscribeError> {
+            Ok(stream::repeat_with(|| Ok(Arc::new(flood_message()))).boxed())
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Counters {
+        idle: AtomicUsize,
+        dropped: AtomicUsize,
+        clients: Mutex<Vec<(String, bool)>>,
+    }
+
+    fn free_endpoint() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port")
+    }
+
+    async fn spawn_server(config: ConfigGrpcServer, shutdown: CancellationToken) -> Arc<Counters> {
+        let counters = Arc::new(Counters::default());
+        let idle = Arc::clone(&counters);
+        let dropped = Arc::clone(&counters);
+        let clients = Arc::clone(&counters);
+        let server = GrpcServer::spawn(
+            config,
+            Flood,
+            || {},
+            move |_duration| {
+                dropped.dropped.fetch_add(1, Ordering::Relaxed);
+            },
+            |_reason| {},
+            move || {
+                idle.idle.fetch_add(1, Ordering::Relaxed);
+            },
+            |_throttled| {},
+            |_size| {},
+            |_encoding| {},
+            move |client, connected| {
+                let mut clients = clients.clients.lock().unwrap();
+                clients.push((client.to_owned(), connected));
+            },
+            VERSION,
+            || None,
+            shutdown,
+        )
+        .await
+        .expect("spawn server");
+        tokio::spawn(server);
+        counters
+    }
+
+    async fn subscribe(endpoint: SocketAddr) -> Streaming<GetVersionResponse> {
+        let channel: Channel = Endpoint::from_shared(format!("http://{endpoint}"))
+            .expect("valid endpoint")
+            .connect()
+            .await
+            .expect("connect");
+        subscribe_channel(channel).await
+    }
+
+    async fn subscribe_channel(channel: Channel) -> Streaming<GetVersionResponse> {
+        let mut client = Grpc::new(channel);
+        client.ready().await.expect("ready");
+        client
+            .streaming(
+                Request::new(stream::iter([GrpcSubscribeRequest::default()])),
+                PathAndQuery::from_static("/geyser.Geyser/Subscribe"),
+                ProstCodec::<GrpcSubscribeRequest, GetVersionResponse>::default(),
+            )
+            .await
+            .expect("subscribe")
+            .into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_not_reading_client() {
+        let endpoint = free_endpoint();
+        let shutdown = CancellationToken::new();
+        let counters = spawn_server(
+            ConfigGrpcServer {
+                endpoint,
+                idle_timeout: Some(Duration::from_millis(200)),
+                ..Default::default()
+            },
+            shutdown.clone(),
+        )
+        .await;
+
+        // keep stream without reading, flow control window is filled by the server
+        let _stream = subscribe(endpoint).await;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while counters.dropped.load(Ordering::Relaxed) == 0 && Instant::now() < deadline {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(counters.idle.load(Ordering::Relaxed), 1);
+        assert_eq!(counters.dropped.load(Ordering::Relaxed), 1);
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_reading_client() {
+        let endpoint = free_endpoint();
+        let shutdown = CancellationToken::new();
+        let counters = spawn_server(
+            ConfigGrpcServer {
+                endpoint,
+                idle_timeout: Some(Duration::from_millis(200)),
+                ..Default::default()
+            },
+            shutdown.clone(),
+        )
+        .await;
+
+        let mut stream = subscribe(endpoint).await;
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut received = 0;
+        while Instant::now() < deadline {
+            stream
+                .message()
+                .await
+                .expect("message")
+                .expect("not closed");
+            received += 1;
+        }
+        assert!(received > 0);
+        assert_eq!(counters.idle.load(Ordering::Relaxed), 0);
+        assert_eq!(counters.dropped.load(Ordering::Relaxed), 0);
+        shutdown.cancel();
+    }
+
+    fn self_signed_tls_config() -> Arc<rustls::ServerConfig> {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let key = PrivatePkcs8KeyDer::from(cert.signing_key.serialize_der());
+        let tls_config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![CertificateDer::from(cert.cert)], key.into())
+            .unwrap();
+        Arc::new(tls_config)
+    }
+
+    #[tokio::test]
+    async fn test_tls_handshake_timeout() {
+        let endpoint = free_endpoint();
+        let config = ConfigGrpcServer {
+            endpoint,
+            tls_config: Some(self_signed_tls_config()),
+            tls_handshake_timeout: Duration::from_millis(200),
+            max_tls_handshakes: 1,
+            ..Default::default()
+        };
+        let rejected = Arc::new(AtomicUsize::new(0));
+        let (incoming, _server_builder) = config.create_server_builder().expect("bind");
+        let mut incoming = incoming.with_tls_rejected_cb({
+            let rejected = Arc::clone(&rejected);
+            move || {
+                rejected.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        tokio::spawn(async move { while incoming.next().await.is_some() {} });
+
+        // clients never start handshake, second one waits for the first one
+        let started_at = Instant::now();
+        let _client1 = TcpStream::connect(endpoint).await.expect("connect");
+        let _client2 = TcpStream::connect(endpoint).await.expect("connect");
+        while rejected.load(Ordering::Relaxed) < 2 && started_at.elapsed() < Duration::from_secs(5)
+        {
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(rejected.load(Ordering::Relaxed), 2);
+        assert!(started_at.elapsed() >= Duration::from_millis(400));
+    }
+
+    /// Server certificate for `localhost` and client certificates are signed by the same CA,
+    /// clients without certificate are allowed
+    struct Mtls {
+        ca: CertifiedIssuer<'static, KeyPair>,
+        server_config: Arc<rustls::ServerConfig>,
+    }
+
+    impl Mtls {
+        fn new() -> Self {
+            let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+
+            let mut roots = RootCertStore::empty();
+            roots.add(ca.der().clone()).unwrap();
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .allow_unauthenticated()
+                .build()
+                .unwrap();
+            let (cert, key) = Self::issue(&ca, "localhost");
+            let mut server_config = rustls::ServerConfig::builder()
+                .with_client_cert_verifier(verifier)
+                .with_single_cert(
+                    vec![cert.der().clone()],
+                    PrivatePkcs8KeyDer::from(key.serialize_der()).into(),
+                )
+                .unwrap();
+            server_config.alpn_protocols.push(b"h2".to_vec());
+
+            Self {
+                ca,
+                server_config: Arc::new(server_config),
+            }
+        }
+
+        fn issue(ca: &CertifiedIssuer<'static, KeyPair>, name: &str) -> (Certificate, KeyPair) {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec![name.to_owned()])
+                .unwrap()
+                .signed_by(&key, ca)
+                .unwrap();
+            (cert, key)
+        }
+
+        async fn subscribe(
+            &self,
+            endpoint: SocketAddr,
+            identity: Option<&(Certificate, KeyPair)>,
+        ) -> Streaming<GetVersionResponse> {
+            let mut tls_config = ClientTlsConfig::new()
+                .ca_certificate(TlsCertificate::from_pem(self.ca.pem()))
+                .domain_name("localhost");
+            if let Some((cert, key)) = identity {
+                tls_config =
+                    tls_config.identity(Identity::from_pem(cert.pem(), key.serialize_pem()));
+            }
+            let channel = Endpoint::from_shared(format!("https://{endpoint}"))
+                .expect("valid endpoint")
+                .tls_config(tls_config)
+                .expect("valid tls config")
+                .connect()
+                .await
+                .expect("connect");
+            subscribe_channel(channel).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_identity() {
+        let mtls = Mtls::new();
+        let known = Mtls::issue(&mtls.ca, "known");
+        let unknown = Mtls::issue(&mtls.ca, "unknown");
+
+        let endpoint = free_endpoint();
+        let shutdown = CancellationToken::new();
+        let counters = spawn_server(
+            ConfigGrpcServer {
+                endpoint,
+                tls_config: Some(Arc::clone(&mtls.server_config)),
+                clients: vec![ConfigGrpcClient {
+                    name: "client1".to_owned(),
+                    fingerprint: cert_fingerprint(known.0.der()),
+                    max_bytes_per_second: None,
+                }],
+                ..Default::default()
+            },
+            shutdown.clone(),
+        )
+        .await;
+
+        // client is reported on subscribe and on disconnect, connections without certificate
+        // do not have identity
+        for (identity, expected) in [
+            (None, None),
+            (Some(&known), Some("client1")),
+            (Some(&unknown), Some("unknown")),
+        ] {
+            let mut stream = mtls.subscribe(endpoint, identity).await;
+            stream
+                .message()
+                .await
+                .expect("message")
+                .expect("not closed");
+            drop(stream);
+
+            let expected = expected
+                .map(|name| vec![(name.to_owned(), true), (name.to_owned(), false)])
+                .unwrap_or_default();
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while counters.clients.lock().unwrap().len() < expected.len()
+                && Instant::now() < deadline
+            {
+                sleep(Duration::from_millis(10)).await;
+            }
+            let clients = std::mem::take(&mut *counters.clients.lock().unwrap());
+            assert_eq!(clients, expected);
+        }
+        shutdown.cancel();
+    }
+
+    async fn reflection_file(endpoint: SocketAddr, symbol: &str) -> Vec<FileDescriptorProto> {
+        let channel: Channel = Endpoint::from_shared(format!("http://{endpoint}"))
+            .expect("valid endpoint")
+            .connect()
+            .await
+            .expect("connect");
+        let mut client = ServerReflectionClient::new(channel);
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::FileContainingSymbol(symbol.to_owned())),
+        };
+        let response = client
+            .server_reflection_info(stream::iter([request]))
+            .await
+            .expect("reflection")
+            .into_inner()
+            .message()
+            .await
+            .expect("message")
+            .expect("not closed");
+        match response.message_response {
+            Some(MessageResponse::FileDescriptorResponse(response)) => response
+                .file_descriptor_proto
+                .iter()
+                .map(|file| FileDescriptorProto::decode(file.as_slice()).expect("descriptor"))
+                .collect(),
+            response => panic!("unexpected response: {response:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reflection_subscribe_request() {
+        let endpoint = free_endpoint();
+        let shutdown = CancellationToken::new();
+        spawn_server(
+            ConfigGrpcServer {
+                endpoint,
+                enable_reflection: true,
+                ..Default::default()
+            },
+            shutdown.clone(),
+        )
+        .await;
+
+        let files = reflection_file(endpoint, "geyser.Geyser").await;
+        let service = files
+            .iter()
+            .flat_map(|file| file.service.iter())
+            .find(|service| service.name() == "Geyser")
+            .expect("service");
+        let methods = service
+            .method
+            .iter()
+            .map(|method| (method.name(), method.input_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            methods,
+            [
+                ("Subscribe", ".richat.GrpcSubscribeRequest"),
+                ("GetVersion", ".geyser.GetVersionRequest"),
+            ]
+        );
+
+        // input type is resolved with fields decoded by the server
+        let files = reflection_file(endpoint, "richat.GrpcSubscribeRequest").await;
+        let message = files
+            .iter()
+            .flat_map(|file| file.message_type.iter())
+            .find(|message| message.name() == "GrpcSubscribeRequest")
+            .expect("message");
+        let fields = message
+            .field
+            .iter()
+            .map(|field| (field.name(), field.number()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [("replay_from_slot", 11), ("filter", 100), ("batch", 101)]
+        );
+
+        shutdown.cancel();
+    }
+
+    struct TestBatch {
+        rx: RecvStream,
+        batch: Batch,
+    }
+
+    impl TestBatch {
+        fn new(config: ConfigGrpcServerBatch) -> (mpsc::UnboundedSender<RecvItem>, Self) {
+            let (tx, rx) = mpsc::unbounded();
+            let batch = Self {
+                rx: rx.map(Ok).boxed(),
+                batch: Batch::new(config, Arc::new(|_size| {})),
+            };
+            (tx, batch)
+        }
+
+        async fn next(&mut self) -> Option<Vec<Vec<u8>>> {
+            let item = poll_fn(|cx| self.batch.poll_next(&mut self.rx, cx)).await?;
+            let batch = GrpcSubscribeBatch::decode(item.expect("message").as_slice());
+            Some(batch.expect("batch").messages)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_max_messages() {
+        let (tx, mut batch) = TestBatch::new(ConfigGrpcServerBatch {
+            max_messages: 3,
+            max_bytes: usize::MAX,
+            linger: Duration::from_secs(3600),
+        });
+        for i in 0..7u8 {
+            tx.unbounded_send(Arc::new(vec![i])).unwrap();
+        }
+        assert_eq!(batch.next().await, Some(vec![vec![0], vec![1], vec![2]]));
+        assert_eq!(batch.next().await, Some(vec![vec![3], vec![4], vec![5]]));
+        // not full batch is sent on close without waiting for linger
+        drop(tx);
+        assert_eq!(batch.next().await, Some(vec![vec![6]]));
+        assert_eq!(batch.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_linger() {
+        let (tx, mut batch) = TestBatch::new(ConfigGrpcServerBatch {
+            max_messages: 64,
+            max_bytes: usize::MAX,
+            linger: Duration::from_millis(100),
+        });
+        tx.unbounded_send(Arc::new(vec![0])).unwrap();
+        let started_at = Instant::now();
+        tokio::spawn({
+            let tx = tx.clone();
+            async move {
+                sleep(Duration::from_millis(20)).await;
+                tx.unbounded_send(Arc::new(vec![1])).unwrap();
+            }
+        });
+        // linger starts with the first message, the second one joins the batch
+        assert_eq!(batch.next().await, Some(vec![vec![0], vec![1]]));
+        assert!(started_at.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_batch_max_bytes() {
+        // encoded message of 100 bytes is 102 bytes in the batch: key, length and data
+        let (tx, mut batch) = TestBatch::new(ConfigGrpcServerBatch {
+            max_messages: 64,
+            max_bytes: 250,
+            linger: Duration::from_secs(3600),
+        });
+        for size in [100, 100, 100, 300, 100, 145, 1] {
+            tx.unbounded_send(Arc::new(vec![0; size])).unwrap();
+        }
+        drop(tx);
+        let mut batches = Vec::new();
+        while let Some(batch) = batch.next().await {
+            let size = GrpcSubscribeBatch {
+                messages: batch.clone(),
+            }
+            .encoded_len();
+            assert!(size <= 250 || batch.len() == 1, "batch of {size} bytes");
+            batches.push(batch.iter().map(Vec::len).collect::<Vec<_>>());
+        }
+        // message over the limit is sent alone, batch is flushed once it reaches the limit
+        assert_eq!(
+            batches,
+            [
+                vec![100, 100],
+                vec![100],
+                vec![300],
+                vec![100, 145],
+                vec![1]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_validate() {
+        let config = |max_messages, max_bytes| ConfigGrpcServer {
+            batch: Some(ConfigGrpcServerBatch {
+                max_messages,
+                max_bytes,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(ConfigGrpcServer::default().validate_batch().is_ok());
+        assert!(config(64, 4 * 1024 * 1024).validate_batch().is_ok());
+        assert!(config(0, 1024).validate_batch().is_err());
+        assert!(config(64, 0).validate_batch().is_err());
+        assert!(config(64, 4 * 1024 * 1024 + 1).validate_batch().is_err());
+    }
+}
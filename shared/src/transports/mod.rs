@@ -1,19 +1,40 @@
+pub mod expression;
 pub mod grpc;
 pub mod quic;
+pub mod tcp;
+pub mod uds;
+pub mod websocket;
 
 use {
-    futures::stream::BoxStream,
-    richat_proto::richat::RichatFilter,
-    solana_sdk::clock::Slot,
+    crate::transports::expression::ExpressionError,
+    futures::stream::{BoxStream, Stream, StreamExt},
+    prost::Message,
+    richat_proto::{
+        geyser::{SubscribeUpdate, SubscribeUpdatePing, subscribe_update::UpdateOneof},
+        richat::RichatFilter,
+    },
+    serde::Deserialize,
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
     std::{
+        collections::HashSet,
+        fmt,
         future::Future,
         io::{self, IoSlice},
         pin::Pin,
-        sync::Arc,
+        sync::{
+            Arc, LazyLock,
+            atomic::{AtomicUsize, Ordering},
+        },
         task::{Context, Poll, ready},
+        time::{Duration, Instant},
     },
+    subtle::{Choice, ConstantTimeEq},
     thiserror::Error,
-    tokio::io::AsyncWrite,
+    tokio::{
+        io::AsyncWrite,
+        time::{Sleep, sleep, timeout},
+    },
+    tracing::warn,
 };
 
 pub type RecvItem = Arc<Vec<u8>>;
@@ -28,12 +49,150 @@ pub enum RecvError {
     Closed,
 }
 
+/// gRPC metadata key with [`DisconnectReason`] of the failed subscription
+pub const DISCONNECT_REASON_KEY: &str = "x-richat-disconnect-reason";
+
+/// Why the server closed the stream; gRPC sends it as status with metadata:
+///
+/// | reason               | gRPC code            | client action                          |
+/// |----------------------|----------------------|----------------------------------------|
+/// | `gap-detected`       | `OUT_OF_RANGE`       | resync, messages were evicted          |
+/// | `server-shutdown`    | `UNAVAILABLE`        | reconnect, to the same or other server |
+/// | `not-initialized`    | `UNAVAILABLE`        | retry later                            |
+/// | `connections-limit`  | `RESOURCE_EXHAUSTED` | back off                               |
+/// | `unauthenticated`    | `UNAUTHENTICATED`    | fix `x-token`, do not retry            |
+/// | `slot-not-available` | `INVALID_ARGUMENT`   | subscribe from later slot              |
+/// | `bad-request`        | `ABORTED`            | fix request                            |
+///
+/// Quic and Tcp report `gap-detected` and `server-shutdown` with `QuicSubscribeClose`,
+/// WebSocket with close frame reason (`lagged` / `closed`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    GapDetected,
+    ServerShutdown,
+    NotInitialized,
+    ConnectionsLimit,
+    Unauthenticated,
+    SlotNotAvailable,
+    BadRequest,
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<RecvError> for DisconnectReason {
+    fn from(error: RecvError) -> Self {
+        match error {
+            RecvError::Lagged => Self::GapDetected,
+            RecvError::Closed => Self::ServerShutdown,
+        }
+    }
+}
+
+impl From<&SubscribeError> for DisconnectReason {
+    fn from(error: &SubscribeError) -> Self {
+        match error {
+            SubscribeError::NotInitialized => Self::NotInitialized,
+            SubscribeError::SlotNotAvailable { .. } => Self::SlotNotAvailable,
+            SubscribeError::InvalidExpression(_) => Self::BadRequest,
+        }
+    }
+}
+
+impl DisconnectReason {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::GapDetected => "gap-detected",
+            Self::ServerShutdown => "server-shutdown",
+            Self::NotInitialized => "not-initialized",
+            Self::ConnectionsLimit => "connections-limit",
+            Self::Unauthenticated => "unauthenticated",
+            Self::SlotNotAvailable => "slot-not-available",
+            Self::BadRequest => "bad-request",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            Self::GapDetected,
+            Self::ServerShutdown,
+            Self::NotInitialized,
+            Self::ConnectionsLimit,
+            Self::Unauthenticated,
+            Self::SlotNotAvailable,
+            Self::BadRequest,
+        ]
+        .into_iter()
+        .find(|reason| reason.as_str() == value)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum SubscribeError {
     #[error("channel is not initialized yet")]
     NotInitialized,
     #[error("only available from slot {first_available}")]
     SlotNotAvailable { first_available: Slot },
+    #[error("invalid filter expression: {0}")]
+    InvalidExpression(#[from] ExpressionError),
+}
+
+/// Compare access token with every allowed token in constant time
+pub fn is_x_token_valid(x_tokens: &HashSet<Vec<u8>>, x_token: &[u8]) -> bool {
+    x_tokens
+        .iter()
+        .fold(Choice::from(0), |valid, expected| {
+            valid | expected.as_slice().ct_eq(x_token)
+        })
+        .into()
+}
+
+/// Retry policy for binding server socket, the port can be still held by the previous process
+/// during the restart
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ConfigBindRetry {
+    /// Total number of bind attempts, 1 means no retries
+    pub attempts: usize,
+    /// Delay between attempts
+    #[serde(with = "humantime_serde")]
+    pub backoff: Duration,
+}
+
+impl Default for ConfigBindRetry {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ConfigBindRetry {
+    pub async fn bind<T, E: fmt::Display>(
+        &self,
+        mut bind: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let attempts = self.attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match bind() {
+                Ok(value) => return Ok(value),
+                Err(error) if attempt < attempts => {
+                    warn!(
+                        "{error}, attempt {attempt}/{attempts}, retry in {:?}",
+                        self.backoff
+                    );
+                    sleep(self.backoff).await;
+                    attempt += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
 }
 
 pub trait Subscribe {
@@ -44,6 +203,208 @@ pub trait Subscribe {
     ) -> Result<RecvStream, SubscribeError>;
 }
 
+/// Accounts filter from `RichatFilter`, keys with invalid length never match
+#[derive(Debug, Default, Clone)]
+pub struct SubscribeAccountsFilter {
+    accounts: Option<HashSet<Pubkey>>,
+    owners: Option<HashSet<Pubkey>>,
+}
+
+impl SubscribeAccountsFilter {
+    pub fn new(filter: &RichatFilter) -> Self {
+        let create = |keys: &[Vec<u8>]| {
+            (!keys.is_empty()).then(|| {
+                keys.iter()
+                    .filter_map(|key| Pubkey::try_from(key.as_slice()).ok())
+                    .collect()
+            })
+        };
+
+        Self {
+            accounts: create(&filter.accounts),
+            owners: create(&filter.owners),
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.accounts.is_none() && self.owners.is_none()
+    }
+
+    pub fn is_match(&self, pubkey: &Pubkey, owner: &Pubkey) -> bool {
+        self.accounts
+            .as_ref()
+            .is_none_or(|accounts| accounts.contains(pubkey))
+            && self
+                .owners
+                .as_ref()
+                .is_none_or(|owners| owners.contains(owner))
+    }
+}
+
+pub type ThrottleCallback = Arc<dyn Fn(bool) + Send + Sync>;
+
+static PING_MESSAGE: LazyLock<RecvItem> = LazyLock::new(|| {
+    let message = SubscribeUpdate {
+        filters: Vec::new(),
+        update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+        created_at: None,
+    };
+    Arc::new(message.encode_to_vec())
+});
+
+/// Emits `SubscribeUpdate` with ping if no messages were received for the interval,
+/// so clients with read timeouts can see that idle connection is alive
+pub struct KeepaliveStream {
+    inner: RecvStream,
+    interval: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl fmt::Debug for KeepaliveStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeepaliveStream")
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+impl KeepaliveStream {
+    /// Wrap stream if keepalive interval is set
+    pub fn wrap(inner: RecvStream, interval: Option<Duration>) -> RecvStream {
+        match interval {
+            Some(interval) => Self {
+                inner,
+                interval,
+                sleep: Box::pin(sleep(interval)),
+            }
+            .boxed(),
+            None => inner,
+        }
+    }
+
+    fn reset(&mut self) {
+        let deadline = tokio::time::Instant::now() + self.interval;
+        self.sleep.as_mut().reset(deadline);
+    }
+}
+
+impl Stream for KeepaliveStream {
+    type Item = Result<RecvItem, RecvError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Poll::Ready(item) = self.inner.poll_next_unpin(cx) {
+            self.reset();
+            return Poll::Ready(item);
+        }
+
+        ready!(self.sleep.as_mut().poll(cx));
+        self.reset();
+        Poll::Ready(Some(Ok(Arc::clone(&PING_MESSAGE))))
+    }
+}
+
+/// Limit of concurrent connections, counter is updated atomically
+#[derive(Debug, Clone)]
+pub struct ConnectionsLimit {
+    max: Option<usize>,
+    total: Arc<AtomicUsize>,
+}
+
+impl ConnectionsLimit {
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            max,
+            total: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Reserve connection, returns `None` if limit is reached
+    pub fn try_acquire(&self) -> Option<ConnectionsLimitGuard> {
+        self.total
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |total| {
+                self.max.is_none_or(|max| total < max).then_some(total + 1)
+            })
+            .ok()
+            .map(|_| ConnectionsLimitGuard {
+                total: Arc::clone(&self.total),
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct ConnectionsLimitGuard {
+    total: Arc<AtomicUsize>,
+}
+
+impl Drop for ConnectionsLimitGuard {
+    fn drop(&mut self) {
+        self.total.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Token bucket limiter of sent bytes, budget is refilled every second
+pub struct RateLimiter {
+    bytes_per_second: f64,
+    available: f64,
+    updated_at: Instant,
+    throttled: bool,
+    on_throttle_cb: ThrottleCallback,
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("bytes_per_second", &self.bytes_per_second)
+            .field("available", &self.available)
+            .field("throttled", &self.throttled)
+            .finish()
+    }
+}
+
+impl Drop for RateLimiter {
+    fn drop(&mut self) {
+        if self.throttled {
+            (self.on_throttle_cb)(false);
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_second: u64, on_throttle_cb: ThrottleCallback) -> Self {
+        Self {
+            bytes_per_second: bytes_per_second as f64,
+            available: bytes_per_second as f64,
+            updated_at: Instant::now(),
+            throttled: false,
+            on_throttle_cb,
+        }
+    }
+
+    /// Consume bytes from the budget, returns delay before next write if budget is exhausted
+    pub fn consume(&mut self, bytes: usize) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.available =
+            (self.available + elapsed * self.bytes_per_second).min(self.bytes_per_second);
+        self.updated_at = now;
+        self.available -= bytes as f64;
+
+        let delay = (self.available < 0.0)
+            .then(|| Duration::from_secs_f64(-self.available / self.bytes_per_second));
+        if self.throttled != delay.is_some() {
+            self.throttled = delay.is_some();
+            (self.on_throttle_cb)(self.throttled);
+        }
+        delay
+    }
+
+    pub async fn wait(&mut self, bytes: usize) {
+        if let Some(delay) = self.consume(bytes) {
+            sleep(delay).await;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct WriteVectored<'a, W: ?Sized> {
     writer: &'a mut W,
@@ -90,3 +451,165 @@ where
         Poll::Ready(Ok(()))
     }
 }
+
+/// Write of a message not completed within `idle_timeout` means that client stopped reading,
+/// exceeded timeout is returned as error in this case
+pub async fn write_with_idle_timeout<F: Future>(
+    idle_timeout: Option<Duration>,
+    fut: F,
+) -> Result<F::Output, Duration> {
+    match idle_timeout {
+        Some(duration) => timeout(duration, fut).await.map_err(|_| duration),
+        None => Ok(fut.await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            ConfigBindRetry, KeepaliveStream, PING_MESSAGE, RateLimiter, RecvError,
+            is_x_token_valid,
+        },
+        futures::stream::{self, StreamExt},
+        std::{
+            collections::HashSet,
+            io,
+            net::TcpListener,
+            sync::{Arc, Mutex},
+            thread,
+            time::Duration,
+        },
+        tokio::time::{Instant, sleep, timeout},
+    };
+
+    #[test]
+    fn test_x_token() {
+        assert!(!is_x_token_valid(&HashSet::new(), b""));
+        assert!(!is_x_token_valid(&HashSet::new(), b"token"));
+
+        let x_tokens = HashSet::from([b"token1".to_vec(), b"token2".to_vec()]);
+        assert!(is_x_token_valid(&x_tokens, b"token1"));
+        assert!(is_x_token_valid(&x_tokens, b"token2"));
+        assert!(!is_x_token_valid(&x_tokens, b"token3"));
+        assert!(!is_x_token_valid(&x_tokens, b"token"));
+        assert!(!is_x_token_valid(&x_tokens, b"token12"));
+        assert!(!is_x_token_valid(&x_tokens, b""));
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let throttled = Arc::new(Mutex::new(Vec::new()));
+        let mut limiter = RateLimiter::new(1_000, {
+            let throttled = Arc::clone(&throttled);
+            Arc::new(move |value| throttled.lock().unwrap().push(value))
+        });
+
+        // burst up to the budget
+        assert_eq!(limiter.consume(600), None);
+        assert_eq!(limiter.consume(400), None);
+        assert!(throttled.lock().unwrap().is_empty());
+
+        // over budget, delay is deficit at the rate
+        let delay = limiter.consume(500).expect("throttled");
+        assert!(delay > Duration::from_millis(400) && delay <= Duration::from_millis(500));
+        assert_eq!(*throttled.lock().unwrap(), [true]);
+
+        // refill
+        thread::sleep(Duration::from_millis(700));
+        assert_eq!(limiter.consume(100), None);
+        assert_eq!(*throttled.lock().unwrap(), [true, false]);
+
+        // budget is not refilled over the limit
+        thread::sleep(Duration::from_millis(1_200));
+        assert!(limiter.consume(1_100).is_some());
+        assert_eq!(*throttled.lock().unwrap(), [true, false, true]);
+
+        drop(limiter);
+        assert_eq!(*throttled.lock().unwrap(), [true, false, true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_idle() {
+        let mut rx =
+            KeepaliveStream::wrap(stream::pending().boxed(), Some(Duration::from_millis(50)));
+        for _ in 0..2 {
+            let started = Instant::now();
+            let item = timeout(Duration::from_secs(1), rx.next())
+                .await
+                .expect("ping before timeout")
+                .expect("stream is not finished")
+                .expect("valid item");
+            assert_eq!(item, *PING_MESSAGE);
+            assert!(started.elapsed() >= Duration::from_millis(50));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_data() {
+        let message = Arc::new(vec![42]);
+        let inner = stream::unfold(Arc::clone(&message), |message| async move {
+            sleep(Duration::from_millis(10)).await;
+            Some((Ok::<_, RecvError>(Arc::clone(&message)), message))
+        })
+        .take(30)
+        .boxed();
+        let items = KeepaliveStream::wrap(inner, Some(Duration::from_millis(100)))
+            .take(30)
+            .collect::<Vec<_>>()
+            .await;
+        assert!(
+            items
+                .into_iter()
+                .all(|item| item == Ok(Arc::clone(&message)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bind_retry() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let retry = ConfigBindRetry {
+            attempts: 10,
+            backoff: Duration::from_millis(50),
+        };
+
+        let release = tokio::spawn(async move {
+            sleep(Duration::from_millis(120)).await;
+            drop(listener);
+        });
+        let mut attempts = 0;
+        let rebound = retry
+            .bind(|| {
+                attempts += 1;
+                TcpListener::bind(addr)
+            })
+            .await
+            .expect("bind after release");
+        release.await.unwrap();
+        assert_eq!(rebound.local_addr().unwrap(), addr);
+        assert!(attempts > 1 && attempts < 10, "{attempts}");
+    }
+
+    #[tokio::test]
+    async fn test_bind_retry_attempts() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let retry = ConfigBindRetry {
+            attempts: 3,
+            backoff: Duration::from_millis(10),
+        };
+
+        let mut attempts = 0;
+        let error = retry
+            .bind(|| {
+                attempts += 1;
+                TcpListener::bind(addr)
+            })
+            .await
+            .expect_err("address is in use");
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(attempts, 3);
+        drop(listener);
+    }
+}
@@ -1,14 +1,25 @@
 use {
     crate::{
-        config::{deserialize_num_str, deserialize_rustls_server_config, deserialize_x_tokens_set},
-        transports::{RecvError, RecvItem, RecvStream, Subscribe, SubscribeError, WriteVectored},
+        config::{
+            deserialize_maybe_num_str, deserialize_num_str, deserialize_rustls_server_config,
+            deserialize_x_tokens_set,
+        },
+        transports::{
+            ConfigBindRetry, ConnectionsLimit, KeepaliveStream, RateLimiter, RecvError, RecvItem,
+            RecvStream, Subscribe, SubscribeError, ThrottleCallback, WriteVectored,
+            is_x_token_valid, write_with_idle_timeout,
+        },
         version::Version,
     },
     futures::{
         future::{FutureExt, pending},
         stream::StreamExt,
     },
-    prost::Message,
+    prost::{
+        Message,
+        bytes::Bytes,
+        encoding::{DecodeContext, WireType, decode_key, skip_field},
+    },
     quinn::{
         Connection, Endpoint, Incoming, SendStream, VarInt,
         crypto::rustls::{NoInitialCipherSuite, QuicServerConfig},
@@ -25,6 +36,7 @@ use {
         io::{self, IoSlice},
         net::{IpAddr, Ipv4Addr, SocketAddr},
         sync::Arc,
+        time::{Duration, Instant},
     },
     thiserror::Error,
     tokio::{
@@ -51,7 +63,8 @@ pub struct ConfigQuicServer {
         deserialize_with = "deserialize_num_str"
     )]
     pub max_stream_bandwidth: u32,
-    /// Maximum duration of inactivity to accept before timing out the connection
+    /// Maximum duration of inactivity to accept before timing out the connection, transport
+    /// level: client which acknowledges packets but does not read is not idle, see `idle_timeout`
     #[serde(default = "ConfigQuicServer::default_max_idle_timeout")]
     pub max_idle_timeout: Option<u32>,
     /// Max number of outgoing streams
@@ -62,6 +75,26 @@ pub struct ConfigQuicServer {
     pub max_request_size: usize,
     #[serde(default, deserialize_with = "deserialize_x_tokens_set")]
     pub x_tokens: HashSet<Vec<u8>>,
+    /// Limit of sent bytes per second for every connection, writes are delayed over the limit
+    #[serde(default, deserialize_with = "deserialize_maybe_num_str")]
+    pub max_bytes_per_second: Option<u64>,
+    /// Maximum number of concurrent connections, new connections over the limit are refused
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+    /// Send ping if there were no messages for the interval, disabled by default
+    #[serde(default, with = "humantime_serde")]
+    pub keepalive: Option<Duration>,
+    /// Allow clients to receive slot updates as unreliable datagrams, slot updates bigger than
+    /// max datagram size are sent over streams; slot updates are found in messages encoded as
+    /// protobuf `SubscribeUpdate`, so compressed or non-protobuf messages are not supported
+    #[serde(default)]
+    pub slot_datagrams: bool,
+    /// Close connection if a message can not be written for the interval because client does
+    /// not read, disabled by default
+    #[serde(default, with = "humantime_serde")]
+    pub idle_timeout: Option<Duration>,
+    #[serde(default)]
+    pub bind_retry: ConfigBindRetry,
 }
 
 impl ConfigQuicServer {
@@ -148,27 +181,39 @@ enum ConnectionError {
     Join(#[from] JoinError),
     #[error("stream is not available")]
     StreamNotAvailable,
+    #[error("no read progress for {0:?}")]
+    IdleTimeout(Duration),
 }
 
 #[derive(Debug)]
 pub struct QuicServer;
 
 impl QuicServer {
+    #[allow(clippy::too_many_arguments)]
     pub async fn spawn(
         config: ConfigQuicServer,
         messages: impl Subscribe + Clone + Send + 'static,
         on_conn_new_cb: impl Fn() + Clone + Send + 'static,
-        on_conn_drop_cb: impl Fn() + Clone + Send + 'static,
+        on_conn_drop_cb: impl Fn(Duration) + Clone + Send + 'static,
+        on_conn_rejected_cb: impl Fn(&'static str) + Clone + Send + 'static,
+        on_conn_idle_cb: impl Fn() + Clone + Send + 'static,
+        on_conn_throttle_cb: impl Fn(bool) + Send + Sync + 'static,
         version: Version<'static>,
         shutdown: CancellationToken,
     ) -> Result<impl Future<Output = Result<(), JoinError>>, CreateEndpointError> {
-        let endpoint = config.create_endpoint()?;
+        let endpoint = config.bind_retry.bind(|| config.create_endpoint()).await?;
         info!("start server at {}", config.endpoint);
 
         Ok(tokio::spawn(async move {
             let max_recv_streams = config.max_recv_streams;
             let max_request_size = config.max_request_size as u64;
             let x_tokens = Arc::new(config.x_tokens);
+            let max_bytes_per_second = config.max_bytes_per_second;
+            let keepalive = config.keepalive;
+            let slot_datagrams = config.slot_datagrams;
+            let idle_timeout = config.idle_timeout;
+            let on_conn_throttle_cb: ThrottleCallback = Arc::new(on_conn_throttle_cb);
+            let connections = ConnectionsLimit::new(config.max_connections);
 
             let mut id = 0;
             loop {
@@ -179,26 +224,49 @@ impl QuicServer {
                             break;
                         };
 
+                        let Some(connection) = connections.try_acquire() else {
+                            info!("#{id}: refused, max connections reached");
+                            on_conn_rejected_cb("max_connections");
+                            incoming.refuse();
+                            id += 1;
+                            continue;
+                        };
+
                         let messages = messages.clone();
                         let on_conn_new_cb = on_conn_new_cb.clone();
                         let on_conn_drop_cb = on_conn_drop_cb.clone();
+                        let on_conn_rejected_cb = on_conn_rejected_cb.clone();
+                        let on_conn_idle_cb = on_conn_idle_cb.clone();
                         let x_tokens = Arc::clone(&x_tokens);
+                        let limiter = max_bytes_per_second.map(|bytes_per_second| {
+                            RateLimiter::new(bytes_per_second, Arc::clone(&on_conn_throttle_cb))
+                        });
                         tokio::spawn(async move {
+                            let connected_at = Instant::now();
                             on_conn_new_cb();
-                            if let Err(error) = Self::handle_incoming(
+                            match Self::handle_incoming(
                                 id,
                                 incoming,
                                 messages,
                                 max_recv_streams,
                                 max_request_size,
                                 x_tokens,
+                                on_conn_rejected_cb,
+                                limiter,
+                                keepalive,
+                                slot_datagrams,
+                                idle_timeout,
                                 version.create_grpc_version_info().json(),
                             ).await {
-                                error!("#{id}: connection failed: {error}");
-                            } else {
-                                info!("#{id}: connection closed");
+                                Ok(()) => info!("#{id}: connection closed"),
+                                Err(ConnectionError::IdleTimeout(duration)) => {
+                                    info!("#{id}: connection closed, idle timeout: no read progress for {duration:?}");
+                                    on_conn_idle_cb();
+                                }
+                                Err(error) => error!("#{id}: connection failed: {error}"),
                             }
-                            on_conn_drop_cb();
+                            on_conn_drop_cb(connected_at.elapsed());
+                            drop(connection);
                         });
                         id += 1;
                     }
@@ -212,6 +280,7 @@ impl QuicServer {
         }))
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_incoming(
         id: u64,
         incoming: Incoming,
@@ -219,6 +288,11 @@ impl QuicServer {
         max_recv_streams: u32,
         max_request_size: u64,
         x_tokens: Arc<HashSet<Vec<u8>>>,
+        on_conn_rejected_cb: impl Fn(&'static str),
+        mut limiter: Option<RateLimiter>,
+        keepalive: Option<Duration>,
+        slot_datagrams: bool,
+        idle_timeout: Option<Duration>,
         version: String,
     ) -> Result<(), ConnectionError> {
         let conn = incoming.await?;
@@ -232,6 +306,8 @@ impl QuicServer {
             max_recv_streams,
             max_request_size,
             x_tokens,
+            on_conn_rejected_cb,
+            slot_datagrams,
             version,
         )
         .await?;
@@ -242,9 +318,10 @@ impl QuicServer {
         send.write_all(&buf).await?;
         send.flush().await?;
 
-        let Some((recv_streams, max_backlog, mut rx)) = maybe_rx else {
+        let Some((recv_streams, max_backlog, slot_datagrams, rx)) = maybe_rx else {
             return Ok(());
         };
+        let mut rx = KeepaliveStream::wrap(rx, keepalive);
 
         // Open connections
         let mut streams = VecDeque::with_capacity(recv_streams as usize);
@@ -263,15 +340,19 @@ impl QuicServer {
                     if let Some(mut stream) = streams.pop_front() {
                         msg_ids.insert(msg_id);
                         set.spawn(async move {
-                            WriteVectored::new(
-                                &mut stream,
-                                &mut [
-                                    IoSlice::new(&msg_id.to_be_bytes()),
-                                    IoSlice::new(&(message.len() as u64).to_be_bytes()),
-                                    IoSlice::new(&message),
-                                ],
+                            write_with_idle_timeout(
+                                idle_timeout,
+                                WriteVectored::new(
+                                    &mut stream,
+                                    &mut [
+                                        IoSlice::new(&msg_id.to_be_bytes()),
+                                        IoSlice::new(&(message.len() as u64).to_be_bytes()),
+                                        IoSlice::new(&message),
+                                    ],
+                                ),
                             )
-                            .await?;
+                            .await
+                            .map_err(ConnectionError::IdleTimeout)??;
                             Ok::<_, ConnectionError>((msg_id, stream))
                         });
                         msg_id += 1;
@@ -295,7 +376,14 @@ impl QuicServer {
             tokio::select! {
                 message = rx_recv => {
                     match message {
-                        Some(Ok(message)) => next_message = Some(message),
+                        Some(Ok(message)) => {
+                            if let Some(limiter) = limiter.as_mut() {
+                                limiter.wait(message.len()).await;
+                            }
+                            if !(slot_datagrams && Self::try_send_datagram(&conn, &message)) {
+                                next_message = Some(message);
+                            }
+                        }
                         Some(Err(error)) => {
                             error!("#{id}: failed to get message: {error}");
                             if streams.is_empty() {
@@ -344,6 +432,32 @@ impl QuicServer {
         Ok(())
     }
 
+    /// Send slot update as datagram, returns `false` if message should be sent over stream
+    fn try_send_datagram(conn: &Connection, message: &[u8]) -> bool {
+        conn.max_datagram_size()
+            .is_some_and(|max_size| message.len() <= max_size)
+            && Self::is_slot_message(message)
+            && conn.send_datagram(Bytes::copy_from_slice(message)).is_ok()
+    }
+
+    /// Check that encoded `SubscribeUpdate` contains slot update, message should be uncompressed
+    /// protobuf (checked by the plugin config)
+    fn is_slot_message(mut buf: &[u8]) -> bool {
+        while !buf.is_empty() {
+            let Ok((tag, wire_type)) = decode_key(&mut buf) else {
+                return false;
+            };
+            if tag == 3 {
+                return wire_type == WireType::LengthDelimited;
+            }
+            if skip_field(wire_type, tag, &mut buf, DecodeContext::default()).is_err() {
+                return false;
+            }
+        }
+        false
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn handle_request(
         id: u64,
         conn: &Connection,
@@ -351,12 +465,14 @@ impl QuicServer {
         max_recv_streams: u32,
         max_request_size: u64,
         x_tokens: Arc<HashSet<Vec<u8>>>,
+        on_conn_rejected_cb: impl Fn(&'static str),
+        slot_datagrams: bool,
         version: String,
     ) -> Result<
         (
             SendStream,
             QuicSubscribeResponse,
-            Option<(u32, u64, RecvStream)>,
+            Option<(u32, u64, bool, RecvStream)>,
         ),
         ConnectionError,
     > {
@@ -382,17 +498,20 @@ impl QuicServer {
             max_backlog,
             replay_from_slot,
             filter,
+            slot_datagrams: slot_datagrams_requested,
         } = Message::decode(buf.as_slice())?;
+        let slot_datagrams = slot_datagrams && slot_datagrams_requested;
 
         // verify access token
         if !x_tokens.is_empty() {
             if let Some(error) = match x_token {
-                Some(x_token) if !x_tokens.contains(&x_token) => {
+                Some(x_token) if !is_x_token_valid(&x_tokens, &x_token) => {
                     Some(QuicSubscribeResponseError::XTokenInvalid as i32)
                 }
                 None => Some(QuicSubscribeResponseError::XTokenRequired as i32),
                 _ => None,
             } {
+                on_conn_rejected_cb("x_token");
                 let msg = QuicSubscribeResponse {
                     error: Some(error),
                     version,
@@ -428,11 +547,13 @@ impl QuicServer {
                     send,
                     QuicSubscribeResponse {
                         version,
+                        slot_datagrams,
                         ..Default::default()
                     },
                     Some((
                         recv_streams,
                         max_backlog.map(|x| x as u64).unwrap_or(u64::MAX),
+                        slot_datagrams,
                         rx,
                     )),
                 )
@@ -454,6 +575,15 @@ impl QuicServer {
                 };
                 (send, msg, None)
             }
+            Err(SubscribeError::InvalidExpression(error)) => {
+                let msg = QuicSubscribeResponse {
+                    error: Some(QuicSubscribeResponseError::InvalidExpression as i32),
+                    error_message: Some(error.to_string()),
+                    version,
+                    ..Default::default()
+                };
+                (send, msg, None)
+            }
         })
     }
 }
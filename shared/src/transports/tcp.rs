@@ -0,0 +1,606 @@
+use {
+    crate::{
+        config::deserialize_x_tokens_set,
+        transports::{
+            ConnectionsLimit, RecvError, RecvStream, Subscribe, SubscribeError, WriteVectored,
+            is_x_token_valid, write_with_idle_timeout,
+        },
+        version::Version,
+    },
+    futures::stream::StreamExt,
+    prost::Message,
+    richat_proto::richat::{
+        QuicSubscribeClose, QuicSubscribeCloseError, QuicSubscribeResponse,
+        QuicSubscribeResponseError, TcpSubscribeRequest,
+    },
+    serde::Deserialize,
+    std::{
+        borrow::Cow,
+        collections::HashSet,
+        future::Future,
+        io::{self, IoSlice},
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    thiserror::Error,
+    tokio::{
+        io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        task::JoinError,
+        time::timeout,
+    },
+    tokio_util::sync::CancellationToken,
+    tracing::{error, info},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigTcpServer {
+    #[serde(default = "ConfigTcpServer::default_endpoint")]
+    pub endpoint: SocketAddr,
+    /// Disable Nagle algorithm on accepted connections
+    #[serde(default = "ConfigTcpServer::default_nodelay")]
+    pub nodelay: bool,
+    /// Max request size in bytes
+    #[serde(default = "ConfigTcpServer::default_max_request_size")]
+    pub max_request_size: usize,
+    /// Close connection if subscribe request is not received within the interval
+    #[serde(
+        default = "ConfigTcpServer::default_request_timeout",
+        with = "humantime_serde"
+    )]
+    pub request_timeout: Duration,
+    #[serde(default, deserialize_with = "deserialize_x_tokens_set")]
+    pub x_tokens: HashSet<Vec<u8>>,
+    /// Maximum number of concurrent connections, new connections over the limit are closed
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+    /// Close connection if a message can not be written for the interval because client does
+    /// not read, disabled by default
+    #[serde(default, with = "humantime_serde")]
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for ConfigTcpServer {
+    fn default() -> Self {
+        Self {
+            endpoint: Self::default_endpoint(),
+            nodelay: Self::default_nodelay(),
+            max_request_size: Self::default_max_request_size(),
+            request_timeout: Self::default_request_timeout(),
+            x_tokens: HashSet::new(),
+            max_connections: None,
+            idle_timeout: None,
+        }
+    }
+}
+
+impl ConfigTcpServer {
+    pub const fn default_endpoint() -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10102)
+    }
+
+    const fn default_nodelay() -> bool {
+        true
+    }
+
+    const fn default_max_request_size() -> usize {
+        1024
+    }
+
+    pub(crate) const fn default_request_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum ConnectionError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Prost(#[from] prost::DecodeError),
+    #[error("no read progress for {0:?}")]
+    IdleTimeout(Duration),
+    #[error("no subscribe request within {0:?}")]
+    RequestTimeout(Duration),
+}
+
+/// Streams length-prefixed messages over plain TCP
+///
+/// Client sends `TcpSubscribeRequest` and receives `QuicSubscribeResponse`, both prefixed
+/// with big-endian `u64` size. After successful subscription every message is sent as
+/// big-endian `u64` size followed by the message itself. On error `u64::MAX` is sent,
+/// followed by size and `QuicSubscribeClose`.
+#[derive(Debug)]
+pub struct TcpServer;
+
+impl TcpServer {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn spawn(
+        config: ConfigTcpServer,
+        messages: impl Subscribe + Clone + Send + 'static,
+        on_conn_new_cb: impl Fn() + Clone + Send + 'static,
+        on_conn_drop_cb: impl Fn(Duration) + Clone + Send + 'static,
+        on_conn_rejected_cb: impl Fn(&'static str) + Clone + Send + 'static,
+        on_conn_idle_cb: impl Fn() + Clone + Send + 'static,
+        version: Version<'static>,
+        shutdown: CancellationToken,
+    ) -> io::Result<impl Future<Output = Result<(), JoinError>>> {
+        let listener = TcpListener::bind(config.endpoint).await?;
+        info!("start server at {}", config.endpoint);
+
+        Ok(tokio::spawn(async move {
+            let nodelay = config.nodelay;
+            let max_request_size = config.max_request_size as u64;
+            let request_timeout = config.request_timeout;
+            let x_tokens = Arc::new(config.x_tokens);
+            let idle_timeout = config.idle_timeout;
+            let connections = ConnectionsLimit::new(config.max_connections);
+
+            let mut id = 0;
+            loop {
+                tokio::select! {
+                    incoming = listener.accept() => {
+                        let (stream, addr) = match incoming {
+                            Ok(value) => value,
+                            Err(error) => {
+                                error!("failed to accept new connection: {error}");
+                                break;
+                            }
+                        };
+
+                        let Some(connection) = connections.try_acquire() else {
+                            info!("#{id}: refused {addr:?}, max connections reached");
+                            on_conn_rejected_cb("max_connections");
+                            drop(stream);
+                            id += 1;
+                            continue;
+                        };
+
+                        let messages = messages.clone();
+                        let on_conn_new_cb = on_conn_new_cb.clone();
+                        let on_conn_drop_cb = on_conn_drop_cb.clone();
+                        let on_conn_rejected_cb = on_conn_rejected_cb.clone();
+                        let on_conn_idle_cb = on_conn_idle_cb.clone();
+                        let x_tokens = Arc::clone(&x_tokens);
+                        let shutdown = shutdown.clone();
+                        tokio::spawn(async move {
+                            let connected_at = Instant::now();
+                            on_conn_new_cb();
+                            info!("#{id}: new connection from {addr:?}");
+                            let result = tokio::select! {
+                                result = Self::handle_incoming(
+                                    id,
+                                    stream,
+                                    messages,
+                                    nodelay,
+                                    max_request_size,
+                                    request_timeout,
+                                    x_tokens,
+                                    idle_timeout,
+                                    on_conn_rejected_cb,
+                                    version.create_grpc_version_info().json(),
+                                ) => result,
+                                () = shutdown.cancelled() => Ok(()),
+                            };
+                            Self::log_result(id, result, on_conn_idle_cb);
+                            on_conn_drop_cb(connected_at.elapsed());
+                            drop(connection);
+                        });
+                        id += 1;
+                    }
+                    () = shutdown.cancelled() => {
+                        info!("shutdown");
+                        break
+                    },
+                };
+            }
+        }))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_incoming(
+        id: u64,
+        stream: TcpStream,
+        messages: impl Subscribe,
+        nodelay: bool,
+        max_request_size: u64,
+        request_timeout: Duration,
+        x_tokens: Arc<HashSet<Vec<u8>>>,
+        idle_timeout: Option<Duration>,
+        on_conn_rejected_cb: impl Fn(&'static str),
+        version: String,
+    ) -> Result<(), ConnectionError> {
+        stream.set_nodelay(nodelay)?;
+        Self::handle_stream(
+            id,
+            stream,
+            messages,
+            max_request_size,
+            request_timeout,
+            x_tokens,
+            idle_timeout,
+            on_conn_rejected_cb,
+            version,
+        )
+        .await
+    }
+
+    /// Connection closed on idle timeout is not a failure, it is counted separately
+    pub(crate) fn log_result(
+        id: u64,
+        result: Result<(), ConnectionError>,
+        on_conn_idle_cb: impl Fn(),
+    ) {
+        match result {
+            Ok(()) => info!("#{id}: connection closed"),
+            Err(ConnectionError::IdleTimeout(duration)) => {
+                info!("#{id}: connection closed, idle timeout: no read progress for {duration:?}");
+                on_conn_idle_cb();
+            }
+            Err(error) => error!("#{id}: connection failed: {error}"),
+        }
+    }
+
+    /// Protocol is independent of the stream type, used by Unix domain socket server too
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn handle_stream<S>(
+        id: u64,
+        mut stream: S,
+        messages: impl Subscribe,
+        max_request_size: u64,
+        request_timeout: Duration,
+        x_tokens: Arc<HashSet<Vec<u8>>>,
+        idle_timeout: Option<Duration>,
+        on_conn_rejected_cb: impl Fn(&'static str),
+        version: String,
+    ) -> Result<(), ConnectionError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        // Read request and subscribe, peer which does not send request holds the connection
+        let (response, maybe_rx) = timeout(
+            request_timeout,
+            Self::handle_request(
+                id,
+                &mut stream,
+                messages,
+                max_request_size,
+                x_tokens,
+                on_conn_rejected_cb,
+                version,
+            ),
+        )
+        .await
+        .map_err(|_| ConnectionError::RequestTimeout(request_timeout))??;
+
+        // Send response
+        let buf = response.encode_to_vec();
+        stream.write_u64(buf.len() as u64).await?;
+        stream.write_all(&buf).await?;
+        stream.flush().await?;
+
+        let Some(mut rx) = maybe_rx else {
+            return Ok(());
+        };
+
+        // Send loop
+        while let Some(message) = rx.next().await {
+            match message {
+                Ok(message) => {
+                    write_with_idle_timeout(
+                        idle_timeout,
+                        WriteVectored::new(
+                            &mut stream,
+                            &mut [
+                                IoSlice::new(&(message.len() as u64).to_be_bytes()),
+                                IoSlice::new(&message),
+                            ],
+                        ),
+                    )
+                    .await
+                    .map_err(ConnectionError::IdleTimeout)??;
+                }
+                Err(error) => {
+                    error!("#{id}: failed to get message: {error}");
+                    let msg = QuicSubscribeClose {
+                        error: match error {
+                            RecvError::Lagged => QuicSubscribeCloseError::Lagged,
+                            RecvError::Closed => QuicSubscribeCloseError::Closed,
+                        } as i32,
+                    };
+                    let message = msg.encode_to_vec();
+
+                    stream.write_u64(u64::MAX).await?;
+                    stream.write_u64(message.len() as u64).await?;
+                    stream.write_all(&message).await?;
+                    break;
+                }
+            }
+        }
+
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    async fn handle_request(
+        id: u64,
+        stream: &mut (impl AsyncRead + Unpin),
+        messages: impl Subscribe,
+        max_request_size: u64,
+        x_tokens: Arc<HashSet<Vec<u8>>>,
+        on_conn_rejected_cb: impl Fn(&'static str),
+        version: String,
+    ) -> Result<(QuicSubscribeResponse, Option<RecvStream>), ConnectionError> {
+        // Read request
+        let size = stream.read_u64().await?;
+        if size > max_request_size {
+            let msg = QuicSubscribeResponse {
+                error: Some(QuicSubscribeResponseError::RequestSizeTooLarge as i32),
+                version,
+                ..Default::default()
+            };
+            return Ok((msg, None));
+        }
+        let mut buf = vec![0; size as usize];
+        stream.read_exact(buf.as_mut_slice()).await?;
+
+        // Decode request
+        let TcpSubscribeRequest {
+            x_token,
+            replay_from_slot,
+            filter,
+        } = Message::decode(buf.as_slice())?;
+
+        // verify access token
+        if !x_tokens.is_empty() {
+            if let Some(error) = match x_token {
+                Some(x_token) if !is_x_token_valid(&x_tokens, &x_token) => {
+                    Some(QuicSubscribeResponseError::XTokenInvalid as i32)
+                }
+                None => Some(QuicSubscribeResponseError::XTokenRequired as i32),
+                _ => None,
+            } {
+                on_conn_rejected_cb("x_token");
+                let msg = QuicSubscribeResponse {
+                    error: Some(error),
+                    version,
+                    ..Default::default()
+                };
+                return Ok((msg, None));
+            }
+        }
+
+        Ok(match messages.subscribe(replay_from_slot, filter) {
+            Ok(rx) => {
+                let pos = replay_from_slot
+                    .map(|slot| format!("slot {slot}").into())
+                    .unwrap_or(Cow::Borrowed("latest"));
+                info!("#{id}: subscribed from {pos}");
+                (
+                    QuicSubscribeResponse {
+                        version,
+                        ..Default::default()
+                    },
+                    Some(rx),
+                )
+            }
+            Err(SubscribeError::NotInitialized) => {
+                let msg = QuicSubscribeResponse {
+                    error: Some(QuicSubscribeResponseError::NotInitialized as i32),
+                    version,
+                    ..Default::default()
+                };
+                (msg, None)
+            }
+            Err(SubscribeError::SlotNotAvailable { first_available }) => {
+                let msg = QuicSubscribeResponse {
+                    error: Some(QuicSubscribeResponseError::SlotNotAvailable as i32),
+                    first_available_slot: Some(first_available),
+                    version,
+                    ..Default::default()
+                };
+                (msg, None)
+            }
+            Err(SubscribeError::InvalidExpression(error)) => {
+                let msg = QuicSubscribeResponse {
+                    error: Some(QuicSubscribeResponseError::InvalidExpression as i32),
+                    error_message: Some(error.to_string()),
+                    version,
+                    ..Default::default()
+                };
+                (msg, None)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ConfigTcpServer, TcpServer},
+        crate::{
+            transports::{RecvStream, Subscribe, SubscribeError},
+            version::Version,
+        },
+        futures::{
+            future,
+            stream::{self, StreamExt},
+        },
+        prost::Message,
+        richat_proto::richat::{QuicSubscribeResponse, RichatFilter, TcpSubscribeRequest},
+        solana_sdk::clock::Slot,
+        std::{
+            net::SocketAddr,
+            sync::{
+                Arc,
+                atomic::{AtomicUsize, Ordering},
+            },
+            time::Duration,
+        },
+        tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpStream,
+            time::{sleep, timeout},
+        },
+        tokio_util::sync::CancellationToken,
+    };
+
+    const VERSION: Version<'static> = Version {
+        package: "richat-shared",
+        version: "test",
+        proto: "test",
+        proto_richat: "test",
+        solana: "test",
+        agave: None,
+        git: "test",
+        rustc: "test",
+        buildts: "test",
+    };
+
+    /// Two messages, stream ends once `close` is cancelled
+    #[derive(Clone, Default)]
+    struct Messages {
+        close: CancellationToken,
+    }
+
+    impl Subscribe for Messages {
+        fn subscribe(
+            &self,
+            _replay_from_slot: Option<Slot>,
+            _filter: Option<RichatFilter>,
+        ) -> Result<RecvStream, SubscribeError> {
+            let close = self.close.clone().cancelled_owned();
+            Ok(
+                stream::iter([Ok(Arc::new(vec![1])), Ok(Arc::new(vec![2, 3]))])
+                    .chain(stream::once(close).filter_map(|()| future::ready(None)))
+                    .boxed(),
+            )
+        }
+    }
+
+    async fn spawn_server(
+        config: ConfigTcpServer,
+        messages: Messages,
+        shutdown: CancellationToken,
+    ) -> (SocketAddr, Arc<AtomicUsize>) {
+        let endpoint = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port");
+        let rejected = Arc::new(AtomicUsize::new(0));
+        let rejected_cb = Arc::clone(&rejected);
+        let server = TcpServer::spawn(
+            ConfigTcpServer { endpoint, ..config },
+            messages,
+            || {},
+            |_duration| {},
+            move |_reason| {
+                rejected_cb.fetch_add(1, Ordering::Relaxed);
+            },
+            || {},
+            VERSION,
+            shutdown,
+        )
+        .await
+        .expect("spawn server");
+        tokio::spawn(server);
+        (endpoint, rejected)
+    }
+
+    async fn read_message(stream: &mut TcpStream) -> Vec<u8> {
+        let size = stream.read_u64().await.expect("size");
+        let mut buf = vec![0; size as usize];
+        stream.read_exact(&mut buf).await.expect("message");
+        buf
+    }
+
+    async fn try_subscribe(endpoint: SocketAddr) -> Option<TcpStream> {
+        let mut stream = TcpStream::connect(endpoint).await.expect("connect");
+        let request = TcpSubscribeRequest::default().encode_to_vec();
+        stream.write_u64(request.len() as u64).await.ok()?;
+        stream.write_all(&request).await.ok()?;
+        let size = stream.read_u64().await.ok()?;
+        let mut response = vec![0; size as usize];
+        stream.read_exact(&mut response).await.ok()?;
+        let response = QuicSubscribeResponse::decode(response.as_slice()).expect("response");
+        assert_eq!(response.error, None);
+        Some(stream)
+    }
+
+    async fn subscribe(endpoint: SocketAddr) -> TcpStream {
+        try_subscribe(endpoint).await.expect("subscribe")
+    }
+
+    async fn is_closed(stream: &mut TcpStream) -> bool {
+        let read = timeout(Duration::from_secs(5), stream.read(&mut [0; 1])).await;
+        matches!(read, Ok(Ok(0) | Err(_)))
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let shutdown = CancellationToken::new();
+        let messages = Messages::default();
+        let config = ConfigTcpServer::default();
+        let (endpoint, _rejected) = spawn_server(config, messages.clone(), shutdown.clone()).await;
+
+        let mut stream = subscribe(endpoint).await;
+        assert_eq!(read_message(&mut stream).await, vec![1]);
+        assert_eq!(read_message(&mut stream).await, vec![2, 3]);
+
+        // server closes connection once stream is finished
+        messages.close.cancel();
+        assert!(is_closed(&mut stream).await);
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let shutdown = CancellationToken::new();
+        let config = ConfigTcpServer {
+            request_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let (endpoint, _rejected) =
+            spawn_server(config, Messages::default(), shutdown.clone()).await;
+
+        // peer which never sends request is disconnected
+        let mut stream = TcpStream::connect(endpoint).await.expect("connect");
+        assert!(is_closed(&mut stream).await);
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_max_connections() {
+        let shutdown = CancellationToken::new();
+        let messages = Messages::default();
+        let config = ConfigTcpServer {
+            max_connections: Some(1),
+            ..Default::default()
+        };
+        let (endpoint, rejected) = spawn_server(config, messages.clone(), shutdown.clone()).await;
+
+        let mut first = subscribe(endpoint).await;
+        let mut second = TcpStream::connect(endpoint).await.expect("connect");
+        assert!(is_closed(&mut second).await);
+        assert_eq!(rejected.load(Ordering::Relaxed), 1);
+
+        // slot is released when connection is closed
+        messages.close.cancel();
+        assert_eq!(read_message(&mut first).await, vec![1]);
+        assert_eq!(read_message(&mut first).await, vec![2, 3]);
+        assert!(is_closed(&mut first).await);
+        let mut stream = timeout(Duration::from_secs(5), async {
+            loop {
+                match try_subscribe(endpoint).await {
+                    Some(stream) => break stream,
+                    None => sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await
+        .expect("subscribe after release");
+        assert_eq!(read_message(&mut stream).await, vec![1]);
+        shutdown.cancel();
+    }
+}
@@ -0,0 +1,401 @@
+use {
+    crate::{
+        config::deserialize_x_tokens_set,
+        transports::{
+            Subscribe,
+            tcp::{ConfigTcpServer, TcpServer},
+        },
+        version::Version,
+    },
+    serde::{
+        Deserialize,
+        de::{self, Deserializer},
+    },
+    std::{
+        collections::HashSet,
+        fs::{self, DirBuilder, Permissions},
+        future::Future,
+        io,
+        os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    tokio::{
+        net::{UnixListener, UnixStream},
+        task::JoinError,
+    },
+    tokio_util::sync::CancellationToken,
+    tracing::{error, info, warn},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigUdsServer {
+    #[serde(default = "ConfigUdsServer::default_path")]
+    pub path: PathBuf,
+    /// Permission bits of the socket file as octal string (e.g. "660"), if None umask is used
+    #[serde(default, deserialize_with = "ConfigUdsServer::deserialize_maybe_mode")]
+    pub mode: Option<u32>,
+    /// Max request size in bytes
+    #[serde(default = "ConfigUdsServer::default_max_request_size")]
+    pub max_request_size: usize,
+    /// Close connection if subscribe request is not received within the interval
+    #[serde(
+        default = "ConfigTcpServer::default_request_timeout",
+        with = "humantime_serde"
+    )]
+    pub request_timeout: Duration,
+    #[serde(default, deserialize_with = "deserialize_x_tokens_set")]
+    pub x_tokens: HashSet<Vec<u8>>,
+    /// Close connection if a message can not be written for the interval because client does
+    /// not read, disabled by default
+    #[serde(default, with = "humantime_serde")]
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for ConfigUdsServer {
+    fn default() -> Self {
+        Self {
+            path: Self::default_path(),
+            mode: None,
+            max_request_size: Self::default_max_request_size(),
+            request_timeout: ConfigTcpServer::default_request_timeout(),
+            x_tokens: HashSet::new(),
+            idle_timeout: None,
+        }
+    }
+}
+
+impl ConfigUdsServer {
+    pub fn default_path() -> PathBuf {
+        PathBuf::from("/tmp/richat-plugin.sock")
+    }
+
+    const fn default_max_request_size() -> usize {
+        1024
+    }
+
+    fn deserialize_maybe_mode<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<&str>::deserialize(deserializer)?
+            .map(|value| {
+                u32::from_str_radix(value.trim_start_matches("0o"), 8)
+                    .ok()
+                    .filter(|mode| *mode <= 0o777)
+                    .ok_or_else(|| de::Error::custom(format!("invalid socket mode: {value}")))
+            })
+            .transpose()
+    }
+}
+
+/// Streams length-prefixed messages over Unix domain socket, same protocol as `TcpServer`
+#[derive(Debug)]
+pub struct UdsServer;
+
+impl UdsServer {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn spawn(
+        config: ConfigUdsServer,
+        messages: impl Subscribe + Clone + Send + 'static,
+        on_conn_new_cb: impl Fn() + Clone + Send + 'static,
+        on_conn_drop_cb: impl Fn(Duration) + Clone + Send + 'static,
+        on_conn_rejected_cb: impl Fn(&'static str) + Clone + Send + 'static,
+        on_conn_idle_cb: impl Fn() + Clone + Send + 'static,
+        version: Version<'static>,
+        shutdown: CancellationToken,
+    ) -> io::Result<impl Future<Output = Result<(), JoinError>>> {
+        Self::remove_stale_socket(&config.path).await?;
+        let listener = match config.mode {
+            Some(mode) => Self::bind_with_mode(&config.path, mode)?,
+            None => UnixListener::bind(&config.path)?,
+        };
+        info!("start server at {}", config.path.display());
+
+        Ok(tokio::spawn(async move {
+            let max_request_size = config.max_request_size as u64;
+            let request_timeout = config.request_timeout;
+            let x_tokens = Arc::new(config.x_tokens);
+            let idle_timeout = config.idle_timeout;
+
+            let mut id = 0;
+            loop {
+                tokio::select! {
+                    incoming = listener.accept() => {
+                        let stream = match incoming {
+                            Ok((stream, _addr)) => stream,
+                            Err(error) => {
+                                error!("failed to accept new connection: {error}");
+                                break;
+                            }
+                        };
+
+                        let messages = messages.clone();
+                        let on_conn_new_cb = on_conn_new_cb.clone();
+                        let on_conn_drop_cb = on_conn_drop_cb.clone();
+                        let on_conn_rejected_cb = on_conn_rejected_cb.clone();
+                        let on_conn_idle_cb = on_conn_idle_cb.clone();
+                        let x_tokens = Arc::clone(&x_tokens);
+                        let shutdown = shutdown.clone();
+                        tokio::spawn(async move {
+                            let connected_at = Instant::now();
+                            on_conn_new_cb();
+                            info!("#{id}: new connection");
+                            let result = tokio::select! {
+                                result = TcpServer::handle_stream(
+                                    id,
+                                    stream,
+                                    messages,
+                                    max_request_size,
+                                    request_timeout,
+                                    x_tokens,
+                                    idle_timeout,
+                                    on_conn_rejected_cb,
+                                    version.create_grpc_version_info().json(),
+                                ) => result,
+                                () = shutdown.cancelled() => Ok(()),
+                            };
+                            TcpServer::log_result(id, result, on_conn_idle_cb);
+                            on_conn_drop_cb(connected_at.elapsed());
+                        });
+                        id += 1;
+                    }
+                    () = shutdown.cancelled() => {
+                        info!("shutdown");
+                        break
+                    },
+                };
+            }
+
+            if let Err(error) = fs::remove_file(&config.path) {
+                warn!("failed to remove socket {}: {error}", config.path.display());
+            }
+        }))
+    }
+
+    /// Socket is bound in a private (0700) directory, permissions are changed and then it's
+    /// moved to `path`, so the socket is never reachable with permissions from umask
+    fn bind_with_mode(path: &Path, mode: u32) -> io::Result<UnixListener> {
+        let file_name = path.file_name().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid socket path: {}", path.display()),
+            )
+        })?;
+        let mut dir_name = file_name.to_os_string();
+        dir_name.push(format!(".{}.tmp", std::process::id()));
+        let dir = path.with_file_name(dir_name);
+        DirBuilder::new().mode(0o700).create(&dir)?;
+
+        let tmp_path = dir.join("sock");
+        let result = UnixListener::bind(&tmp_path).and_then(|listener| {
+            fs::set_permissions(&tmp_path, Permissions::from_mode(mode))?;
+            fs::rename(&tmp_path, path)?;
+            Ok(listener)
+        });
+        if let Err(error) = fs::remove_dir_all(&dir) {
+            warn!("failed to remove directory {}: {error}", dir.display());
+        }
+        result
+    }
+
+    /// Socket file is left on crash, remove it if nobody accepts connections on it
+    async fn remove_stale_socket(path: &Path) -> io::Result<()> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                if UnixStream::connect(path).await.is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("socket {} is used by another server", path.display()),
+                    ));
+                }
+                info!("remove stale socket {}", path.display());
+                fs::remove_file(path)
+            }
+            Ok(_) => Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            )),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ConfigUdsServer, UdsServer},
+        crate::{
+            transports::{RecvStream, Subscribe, SubscribeError},
+            version::Version,
+        },
+        futures::stream::{self, StreamExt},
+        prost::Message,
+        richat_proto::richat::{QuicSubscribeResponse, RichatFilter, TcpSubscribeRequest},
+        solana_sdk::clock::Slot,
+        std::{
+            fs, io,
+            os::unix::fs::{FileTypeExt, PermissionsExt},
+            path::PathBuf,
+            sync::Arc,
+        },
+        tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::UnixStream,
+        },
+        tokio_util::sync::CancellationToken,
+    };
+
+    const VERSION: Version<'static> = Version {
+        package: "richat-shared",
+        version: "test",
+        proto: "test",
+        proto_richat: "test",
+        solana: "test",
+        agave: None,
+        git: "test",
+        rustc: "test",
+        buildts: "test",
+    };
+
+    /// Two messages and then stream stays open
+    #[derive(Clone)]
+    struct Messages;
+
+    impl Subscribe for Messages {
+        fn subscribe(
+            &self,
+            _replay_from_slot: Option<Slot>,
+            _filter: Option<RichatFilter>,
+        ) -> Result<RecvStream, SubscribeError> {
+            Ok(
+                stream::iter([Ok(Arc::new(vec![1])), Ok(Arc::new(vec![2, 3]))])
+                    .chain(stream::pending())
+                    .boxed(),
+            )
+        }
+    }
+
+    /// Empty directory unique for the test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("richat-uds-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create dir");
+        dir
+    }
+
+    async fn spawn_server(config: ConfigUdsServer, shutdown: CancellationToken) -> io::Result<()> {
+        let server = UdsServer::spawn(
+            config,
+            Messages,
+            || {},
+            |_duration| {},
+            |_reason| {},
+            || {},
+            VERSION,
+            shutdown,
+        )
+        .await?;
+        tokio::spawn(server);
+        Ok(())
+    }
+
+    async fn read_message(stream: &mut UnixStream) -> Vec<u8> {
+        let size = stream.read_u64().await.expect("size");
+        let mut buf = vec![0; size as usize];
+        stream.read_exact(&mut buf).await.expect("message");
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_with_mode() {
+        let dir = test_dir("round-trip");
+        let path = dir.join("richat.sock");
+        let shutdown = CancellationToken::new();
+        let config = ConfigUdsServer {
+            path: path.clone(),
+            mode: Some(0o660),
+            ..Default::default()
+        };
+        spawn_server(config, shutdown.clone())
+            .await
+            .expect("spawn server");
+
+        let metadata = fs::symlink_metadata(&path).expect("socket");
+        assert!(metadata.file_type().is_socket());
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o660);
+        // only socket is left in the directory, temporary one is removed
+        assert_eq!(fs::read_dir(&dir).expect("read dir").count(), 1);
+
+        let mut stream = UnixStream::connect(&path).await.expect("connect");
+        let request = TcpSubscribeRequest::default().encode_to_vec();
+        stream.write_u64(request.len() as u64).await.unwrap();
+        stream.write_all(&request).await.unwrap();
+        let response = read_message(&mut stream).await;
+        let response = QuicSubscribeResponse::decode(response.as_slice()).expect("response");
+        assert_eq!(response.error, None);
+        assert_eq!(read_message(&mut stream).await, vec![1]);
+        assert_eq!(read_message(&mut stream).await, vec![2, 3]);
+
+        shutdown.cancel();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_socket_in_use() {
+        let dir = test_dir("in-use");
+        let path = dir.join("richat.sock");
+
+        // stale socket is removed
+        drop(std::os::unix::net::UnixListener::bind(&path).expect("bind"));
+        let shutdown = CancellationToken::new();
+        let config = ConfigUdsServer {
+            path: path.clone(),
+            ..Default::default()
+        };
+        spawn_server(config.clone(), shutdown.clone())
+            .await
+            .expect("spawn server");
+
+        // socket with server is not removed
+        let error = spawn_server(config, CancellationToken::new())
+            .await
+            .expect_err("socket in use");
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+
+        shutdown.cancel();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_not_socket() {
+        let dir = test_dir("not-socket");
+        let path = dir.join("richat.sock");
+        fs::write(&path, b"data").unwrap();
+
+        let config = ConfigUdsServer {
+            path: path.clone(),
+            ..Default::default()
+        };
+        let error = spawn_server(config, CancellationToken::new())
+            .await
+            .expect_err("not a socket");
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&path).unwrap(), b"data");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_mode() {
+        let config: ConfigUdsServer = serde_json::from_str(r#"{"mode": "0o640"}"#).unwrap();
+        assert_eq!(config.mode, Some(0o640));
+        let config: ConfigUdsServer = serde_json::from_str(r#"{"mode": "660"}"#).unwrap();
+        assert_eq!(config.mode, Some(0o660));
+        assert!(serde_json::from_str::<ConfigUdsServer>(r#"{"mode": "1777"}"#).is_err());
+        assert!(serde_json::from_str::<ConfigUdsServer>(r#"{"mode": "abc"}"#).is_err());
+    }
+}
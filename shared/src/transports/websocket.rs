@@ -0,0 +1,622 @@
+use {
+    crate::{
+        config::deserialize_x_tokens_set,
+        five8::pubkey_decode,
+        transports::{
+            RecvError, RecvStream, Subscribe, SubscribeError, is_x_token_valid,
+            write_with_idle_timeout,
+        },
+        version::Version,
+    },
+    fastwebsockets::{
+        CloseCode, FragmentCollectorRead, Frame, OpCode, Payload, WebSocketError,
+        upgrade::{UpgradeFut, is_upgrade_request, upgrade},
+    },
+    futures::stream::StreamExt,
+    http_body_util::{BodyExt, Empty as BodyEmpty},
+    hyper::{Request, Response, StatusCode, body::Incoming as BodyIncoming, service::service_fn},
+    hyper_util::{
+        rt::tokio::{TokioExecutor, TokioIo},
+        server::conn::auto::Builder as ServerBuilder,
+    },
+    richat_proto::richat::RichatFilter,
+    serde::{Deserialize, Serialize},
+    solana_sdk::clock::Slot,
+    std::{
+        borrow::Cow,
+        collections::HashSet,
+        future::Future,
+        io,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    thiserror::Error,
+    tokio::{net::TcpListener, sync::mpsc, task::JoinError, time::timeout},
+    tokio_util::sync::CancellationToken,
+    tracing::{error, info},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigWebSocketServer {
+    #[serde(default = "ConfigWebSocketServer::default_endpoint")]
+    pub endpoint: SocketAddr,
+    /// Max request size in bytes
+    #[serde(default = "ConfigWebSocketServer::default_max_request_size")]
+    pub max_request_size: usize,
+    /// Close connection if subscribe request is not received within the interval
+    #[serde(
+        default = "ConfigWebSocketServer::default_request_timeout",
+        with = "humantime_serde"
+    )]
+    pub request_timeout: Duration,
+    #[serde(default, deserialize_with = "deserialize_x_tokens_set")]
+    pub x_tokens: HashSet<Vec<u8>>,
+    /// Close connection if a message can not be written for the interval because client does
+    /// not read, disabled by default
+    #[serde(default, with = "humantime_serde")]
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for ConfigWebSocketServer {
+    fn default() -> Self {
+        Self {
+            endpoint: Self::default_endpoint(),
+            max_request_size: Self::default_max_request_size(),
+            request_timeout: Self::default_request_timeout(),
+            x_tokens: HashSet::new(),
+            idle_timeout: None,
+        }
+    }
+}
+
+impl ConfigWebSocketServer {
+    pub const fn default_endpoint() -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10103)
+    }
+
+    const fn default_max_request_size() -> usize {
+        1024
+    }
+
+    const fn default_request_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct WebSocketSubscribeRequest {
+    x_token: Option<String>,
+    replay_from_slot: Option<Slot>,
+    disable_accounts: bool,
+    disable_transactions: bool,
+    disable_entries: bool,
+    accounts: Vec<String>,
+    owners: Vec<String>,
+    expression: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct WebSocketSubscribeResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_available_slot: Option<Slot>,
+    version: String,
+}
+
+#[derive(Debug, Error)]
+enum ConnectionError {
+    #[error(transparent)]
+    WebSocket(#[from] WebSocketError),
+    #[error(transparent)]
+    Join(#[from] JoinError),
+    #[error("no read progress for {0:?}")]
+    IdleTimeout(Duration),
+    #[error("no subscribe request within {0:?}")]
+    RequestTimeout(Duration),
+}
+
+/// Streams messages over WebSocket
+///
+/// Client sends JSON subscribe request as the first message and receives JSON response,
+/// after successful subscription every message is sent as binary frame with encoded bytes
+/// as they are in the channel. JSON mode of messages is not provided: the server does not
+/// know the encoder of the channel, decode frames the same way as Quic or Tcp messages.
+#[derive(Debug)]
+pub struct WebSocketServer;
+
+impl WebSocketServer {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn spawn(
+        config: ConfigWebSocketServer,
+        messages: impl Subscribe + Clone + Send + Sync + 'static,
+        on_conn_new_cb: impl Fn() + Clone + Send + Sync + 'static,
+        on_conn_drop_cb: impl Fn(Duration) + Clone + Send + Sync + 'static,
+        on_conn_rejected_cb: impl Fn(&'static str) + Clone + Send + Sync + 'static,
+        on_conn_idle_cb: impl Fn() + Clone + Send + Sync + 'static,
+        version: Version<'static>,
+        shutdown: CancellationToken,
+    ) -> io::Result<impl Future<Output = Result<(), JoinError>>> {
+        let listener = TcpListener::bind(config.endpoint).await?;
+        info!("start server at {}", config.endpoint);
+
+        Ok(tokio::spawn(async move {
+            let max_request_size = config.max_request_size;
+            let request_timeout = config.request_timeout;
+            let x_tokens = Arc::new(config.x_tokens);
+            let idle_timeout = config.idle_timeout;
+
+            let mut id = 0;
+            loop {
+                let stream = tokio::select! {
+                    incoming = listener.accept() => match incoming {
+                        Ok((stream, addr)) => {
+                            info!("#{id}: new connection from {addr:?}");
+                            stream
+                        }
+                        Err(error) => {
+                            error!("failed to accept new connection: {error}");
+                            break;
+                        }
+                    },
+                    () = shutdown.cancelled() => {
+                        info!("shutdown");
+                        break
+                    },
+                };
+
+                let service = service_fn({
+                    let messages = messages.clone();
+                    let on_conn_new_cb = on_conn_new_cb.clone();
+                    let on_conn_drop_cb = on_conn_drop_cb.clone();
+                    let on_conn_rejected_cb = on_conn_rejected_cb.clone();
+                    let on_conn_idle_cb = on_conn_idle_cb.clone();
+                    let x_tokens = Arc::clone(&x_tokens);
+                    let shutdown = shutdown.clone();
+                    move |req: Request<BodyIncoming>| {
+                        let messages = messages.clone();
+                        let on_conn_new_cb = on_conn_new_cb.clone();
+                        let on_conn_drop_cb = on_conn_drop_cb.clone();
+                        let on_conn_rejected_cb = on_conn_rejected_cb.clone();
+                        let on_conn_idle_cb = on_conn_idle_cb.clone();
+                        let x_tokens = Arc::clone(&x_tokens);
+                        let shutdown = shutdown.clone();
+                        async move {
+                            match (req.uri().path(), is_upgrade_request(&req)) {
+                                ("/", true) => match upgrade(req) {
+                                    Ok((response, ws_fut)) => {
+                                        tokio::spawn(async move {
+                                            let connected_at = Instant::now();
+                                            on_conn_new_cb();
+                                            match Self::handle_client(
+                                                id,
+                                                ws_fut,
+                                                messages,
+                                                max_request_size,
+                                                request_timeout,
+                                                x_tokens,
+                                                idle_timeout,
+                                                on_conn_rejected_cb,
+                                                version.create_grpc_version_info().json(),
+                                                shutdown,
+                                            )
+                                            .await
+                                            {
+                                                Ok(()) => info!("#{id}: connection closed"),
+                                                Err(ConnectionError::IdleTimeout(duration)) => {
+                                                    info!(
+                                                        "#{id}: connection closed, idle timeout: no read progress for {duration:?}"
+                                                    );
+                                                    on_conn_idle_cb();
+                                                }
+                                                Err(error) => {
+                                                    error!("#{id}: connection failed: {error}")
+                                                }
+                                            }
+                                            on_conn_drop_cb(connected_at.elapsed());
+                                        });
+
+                                        let (parts, body) = response.into_parts();
+                                        Ok(Response::from_parts(parts, body.boxed()))
+                                    }
+                                    Err(error) => Response::builder()
+                                        .status(StatusCode::BAD_REQUEST)
+                                        .body(format!("upgrade error: {error:?}").boxed()),
+                                },
+                                _ => Response::builder()
+                                    .status(StatusCode::NOT_FOUND)
+                                    .body(BodyEmpty::new().boxed()),
+                            }
+                        }
+                    }
+                });
+
+                tokio::spawn(async move {
+                    if let Err(error) = ServerBuilder::new(TokioExecutor::new())
+                        .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                        .await
+                    {
+                        error!("#{id}: failed to serve HTTP connection: {error:?}");
+                    }
+                });
+
+                id += 1;
+            }
+        }))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_client(
+        id: u64,
+        ws_fut: UpgradeFut,
+        messages: impl Subscribe,
+        max_request_size: usize,
+        request_timeout: Duration,
+        x_tokens: Arc<HashSet<Vec<u8>>>,
+        idle_timeout: Option<Duration>,
+        on_conn_rejected_cb: impl Fn(&'static str),
+        version: String,
+        shutdown: CancellationToken,
+    ) -> Result<(), ConnectionError> {
+        let mut ws = ws_fut.await?;
+        ws.set_max_message_size(max_request_size);
+        ws.set_auto_pong(false);
+        ws.set_auto_close(false);
+        let (ws_rx, mut ws_tx) = ws.split(tokio::io::split);
+        let mut ws_rx = FragmentCollectorRead::new(ws_rx);
+        let mut send_fn = |_| async { Ok::<(), String>(()) };
+
+        // Read request, peer which does not send request holds the connection
+        let request = timeout(request_timeout, async {
+            loop {
+                let frame = ws_rx.read_frame(&mut send_fn).await?;
+                match frame.opcode {
+                    OpCode::Close => {
+                        ws_tx
+                            .write_frame(Frame::close(CloseCode::Normal.into(), b""))
+                            .await?;
+                        return Ok(None);
+                    }
+                    OpCode::Ping => ws_tx.write_frame(Frame::pong(frame.payload)).await?,
+                    OpCode::Text | OpCode::Binary => return Ok(Some(frame.payload)),
+                    OpCode::Continuation | OpCode::Pong => {}
+                }
+            }
+        })
+        .await;
+        let payload = match request {
+            Ok(Ok(Some(payload))) => payload,
+            Ok(Ok(None)) => return Ok(()),
+            Ok(Err(error)) => return Err(ConnectionError::WebSocket(error)),
+            Err(_) => {
+                ws_tx
+                    .write_frame(Frame::close(CloseCode::Policy.into(), b"request timeout"))
+                    .await?;
+                return Err(ConnectionError::RequestTimeout(request_timeout));
+            }
+        };
+
+        // Subscribe and send response
+        let (response, maybe_rx) = Self::handle_request(
+            id,
+            &payload,
+            messages,
+            x_tokens,
+            on_conn_rejected_cb,
+            version,
+        );
+        let vec = serde_json::to_vec(&response).expect("json serialization never fail");
+        ws_tx.write_frame(Frame::text(Payload::Owned(vec))).await?;
+        let Some(mut rx) = maybe_rx else {
+            ws_tx
+                .write_frame(Frame::close(CloseCode::Policy.into(), b""))
+                .await?;
+            return Ok(());
+        };
+
+        // Read control frames
+        let (frames_tx, mut frames_rx) = mpsc::channel::<Frame<'static>>(1);
+        let read_fut = tokio::spawn(async move {
+            loop {
+                let frame = tokio::select! {
+                    frame = ws_rx.read_frame(&mut send_fn) => frame?,
+                    () = shutdown.cancelled() => break,
+                };
+                let (frame, last_frame) = match frame.opcode {
+                    OpCode::Close => (Frame::close(CloseCode::Normal.into(), b""), true),
+                    OpCode::Ping => (Frame::pong(frame.payload), false),
+                    OpCode::Text | OpCode::Binary | OpCode::Continuation | OpCode::Pong => {
+                        continue;
+                    }
+                };
+                if frames_tx.send(frame).await.is_err() || last_frame {
+                    break;
+                }
+            }
+            Ok::<(), WebSocketError>(())
+        });
+
+        // Send loop
+        loop {
+            tokio::select! {
+                frame = frames_rx.recv() => match frame {
+                    Some(frame) => {
+                        let last_frame = frame.opcode == OpCode::Close;
+                        ws_tx.write_frame(frame).await?;
+                        if last_frame {
+                            break;
+                        }
+                    }
+                    None => break,
+                },
+                message = rx.next() => match message {
+                    Some(Ok(message)) => {
+                        let frame = Frame::binary(Payload::Borrowed(&message));
+                        match write_with_idle_timeout(idle_timeout, ws_tx.write_frame(frame)).await {
+                            Ok(result) => result?,
+                            Err(duration) => {
+                                read_fut.abort();
+                                return Err(ConnectionError::IdleTimeout(duration));
+                            }
+                        }
+                    }
+                    Some(Err(error)) => {
+                        error!("#{id}: failed to get message: {error}");
+                        let reason = match error {
+                            RecvError::Lagged => b"lagged".as_slice(),
+                            RecvError::Closed => b"closed".as_slice(),
+                        };
+                        ws_tx
+                            .write_frame(Frame::close(CloseCode::Error.into(), reason))
+                            .await?;
+                        break;
+                    }
+                    None => break,
+                },
+            }
+        }
+
+        read_fut.abort();
+        match read_fut.await {
+            Ok(result) => result?,
+            Err(error) if error.is_cancelled() => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(
+        id: u64,
+        payload: &[u8],
+        messages: impl Subscribe,
+        x_tokens: Arc<HashSet<Vec<u8>>>,
+        on_conn_rejected_cb: impl Fn(&'static str),
+        version: String,
+    ) -> (WebSocketSubscribeResponse, Option<RecvStream>) {
+        let error = |error, first_available_slot| WebSocketSubscribeResponse {
+            error: Some(error),
+            error_message: None,
+            first_available_slot,
+            version: version.clone(),
+        };
+
+        // Decode request
+        let Ok(WebSocketSubscribeRequest {
+            x_token,
+            replay_from_slot,
+            disable_accounts,
+            disable_transactions,
+            disable_entries,
+            accounts,
+            owners,
+            expression,
+        }) = serde_json::from_slice(payload)
+        else {
+            return (error("invalid request", None), None);
+        };
+        let decode = |keys: Vec<String>| {
+            keys.iter()
+                .map(|key| pubkey_decode(key).map(|pubkey| pubkey.to_bytes().to_vec()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let (Ok(accounts), Ok(owners)) = (decode(accounts), decode(owners)) else {
+            return (error("invalid pubkey", None), None);
+        };
+
+        // verify access token
+        if !x_tokens.is_empty() {
+            match x_token {
+                Some(x_token) if !is_x_token_valid(&x_tokens, x_token.as_bytes()) => {
+                    on_conn_rejected_cb("x_token");
+                    return (error("x-token invalid", None), None);
+                }
+                None => {
+                    on_conn_rejected_cb("x_token");
+                    return (error("x-token required", None), None);
+                }
+                _ => {}
+            }
+        }
+
+        let filter = RichatFilter {
+            disable_accounts,
+            disable_transactions,
+            disable_entries,
+            accounts,
+            owners,
+            expression,
+        };
+        match messages.subscribe(replay_from_slot, Some(filter)) {
+            Ok(rx) => {
+                let pos = replay_from_slot
+                    .map(|slot| format!("slot {slot}").into())
+                    .unwrap_or(Cow::Borrowed("latest"));
+                info!("#{id}: subscribed from {pos}");
+                let response = WebSocketSubscribeResponse {
+                    error: None,
+                    error_message: None,
+                    first_available_slot: None,
+                    version,
+                };
+                (response, Some(rx))
+            }
+            Err(SubscribeError::NotInitialized) => (error("not initialized", None), None),
+            Err(SubscribeError::SlotNotAvailable { first_available }) => {
+                (error("slot not available", Some(first_available)), None)
+            }
+            Err(SubscribeError::InvalidExpression(expression_error)) => {
+                let mut response = error("invalid expression", None);
+                response.error_message = Some(expression_error.to_string());
+                (response, None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ConfigWebSocketServer, WebSocketServer},
+        crate::{
+            transports::{RecvStream, Subscribe, SubscribeError},
+            version::Version,
+        },
+        futures::{
+            sink::SinkExt,
+            stream::{self, StreamExt},
+        },
+        richat_proto::richat::RichatFilter,
+        solana_sdk::clock::Slot,
+        std::{net::SocketAddr, sync::Arc, time::Duration},
+        tokio::time::timeout,
+        tokio_tungstenite::{connect_async, tungstenite::Message},
+        tokio_util::sync::CancellationToken,
+    };
+
+    const VERSION: Version<'static> = Version {
+        package: "richat-shared",
+        version: "test",
+        proto: "test",
+        proto_richat: "test",
+        solana: "test",
+        agave: None,
+        git: "test",
+        rustc: "test",
+        buildts: "test",
+    };
+
+    /// Two messages and then stream stays open
+    #[derive(Clone)]
+    struct Messages;
+
+    impl Subscribe for Messages {
+        fn subscribe(
+            &self,
+            _replay_from_slot: Option<Slot>,
+            _filter: Option<RichatFilter>,
+        ) -> Result<RecvStream, SubscribeError> {
+            Ok(
+                stream::iter([Ok(Arc::new(vec![1])), Ok(Arc::new(vec![2, 3]))])
+                    .chain(stream::pending())
+                    .boxed(),
+            )
+        }
+    }
+
+    async fn spawn_server(config: ConfigWebSocketServer, shutdown: CancellationToken) -> String {
+        let endpoint: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port");
+        let server = WebSocketServer::spawn(
+            ConfigWebSocketServer { endpoint, ..config },
+            Messages,
+            || {},
+            |_duration| {},
+            |_reason| {},
+            || {},
+            VERSION,
+            shutdown,
+        )
+        .await
+        .expect("spawn server");
+        tokio::spawn(server);
+        format!("ws://{endpoint}/")
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let shutdown = CancellationToken::new();
+        let url = spawn_server(ConfigWebSocketServer::default(), shutdown.clone()).await;
+
+        let (mut ws, _response) = connect_async(url).await.expect("connect");
+        ws.send(Message::text(r#"{"disable_entries": true}"#))
+            .await
+            .unwrap();
+        let Some(Ok(Message::Text(response))) = ws.next().await else {
+            panic!("expected JSON response");
+        };
+        let response: serde_json::Value = serde_json::from_str(&response).expect("json");
+        assert_eq!(response.get("error"), None);
+        let version = response["version"].as_str().expect("version");
+        assert!(version.contains("richat-shared"), "{version}");
+
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            match ws.next().await {
+                Some(Ok(Message::Binary(data))) => received.push(data.to_vec()),
+                message => panic!("unexpected message: {message:?}"),
+            }
+        }
+        assert_eq!(received, vec![vec![1], vec![2, 3]]);
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_request() {
+        let shutdown = CancellationToken::new();
+        let url = spawn_server(ConfigWebSocketServer::default(), shutdown.clone()).await;
+
+        let (mut ws, _response) = connect_async(url).await.expect("connect");
+        ws.send(Message::text(r#"{"unknown": 1}"#)).await.unwrap();
+        let Some(Ok(Message::Text(response))) = ws.next().await else {
+            panic!("expected JSON response");
+        };
+        let response: serde_json::Value = serde_json::from_str(&response).expect("json");
+        assert_eq!(response["error"], "invalid request");
+        assert!(matches!(ws.next().await, Some(Ok(Message::Close(_)))));
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let shutdown = CancellationToken::new();
+        let config = ConfigWebSocketServer {
+            request_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let url = spawn_server(config, shutdown.clone()).await;
+
+        // peer which never sends request is disconnected
+        let (mut ws, _response) = connect_async(url).await.expect("connect");
+        let message = timeout(Duration::from_secs(5), async {
+            loop {
+                match ws.next().await {
+                    Some(Ok(Message::Ping(_))) => continue,
+                    message => break message,
+                }
+            }
+        });
+        let message = message.await.expect("closed within timeout");
+        assert!(
+            matches!(message, Some(Ok(Message::Close(_))) | Some(Err(_)) | None),
+            "unexpected message: {message:?}"
+        );
+        shutdown.cancel();
+    }
+}
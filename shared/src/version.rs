@@ -7,6 +7,9 @@ pub struct Version<'a> {
     pub proto: &'a str,
     pub proto_richat: &'a str,
     pub solana: &'a str,
+    /// Version of `agave-geyser-plugin-interface`, only for the plugin
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub agave: Option<&'a str>,
     pub git: &'a str,
     pub rustc: &'a str,
     pub buildts: &'a str,
@@ -21,6 +24,9 @@ impl<'a> Version<'a> {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GrpcVersionInfoExtra {
     pub hostname: Option<String>,
+    /// Server specific details, like enabled features and active settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -38,10 +44,16 @@ impl<'a> GrpcVersionInfo<'a> {
                 hostname: hostname::get()
                     .ok()
                     .and_then(|name| name.into_string().ok()),
+                server: None,
             },
         }
     }
 
+    pub fn with_server(mut self, server: Option<serde_json::Value>) -> Self {
+        self.extra.server = server;
+        self
+    }
+
     pub fn json(&self) -> String {
         serde_json::to_string(self).expect("json serialization never fail")
     }